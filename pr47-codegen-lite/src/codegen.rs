@@ -0,0 +1,330 @@
+use crate::concrete::{ConcreteFn, ConcreteType};
+
+/// Generate the source of a `FunctionBase` implementation for `func`, named
+/// `Pr47Binder_<function name>`, together with a `PR47BINDER_<FUNCTION NAME>` constant. The
+/// generated code is the same as what `pr47-codegen` generates.
+pub(crate) fn generate_function_bind(func: &ConcreteFn, krate: &str) -> String {
+    let ConcreteFn { vis, name, params, ret } = func;
+    let binder_name: String = format!("Pr47Binder_{}", name);
+    let binder_const_name: String = format!("PR47BINDER_{}", name.to_uppercase());
+
+    format!(r#"
+#[allow(non_camel_case_types)]
+{vis} struct {binder_name}();
+
+impl {krate}::ffi::sync_fn::FunctionBase for {binder_name} {{
+    fn signature(
+        tyck_info_pool: &mut {krate}::data::tyck::TyckInfoPool
+    ) -> {krate}::ffi::Signature {{
+        {signature}
+    }}
+
+    #[allow(unused_variables)]
+    unsafe fn call_rtlc<CTX: {krate}::ffi::sync_fn::VMContext>(
+        context: &mut CTX,
+        args: &[{krate}::data::Value],
+        rets: &[*mut {krate}::data::Value]
+    ) -> Result<(), {krate}::ffi::FFIException> {{
+        {call_rtlc}
+    }}
+
+    #[allow(unused_variables)]
+    unsafe fn call_unchecked<CTX: {krate}::ffi::sync_fn::VMContext>(
+        context: &mut CTX,
+        args: &[{krate}::data::Value],
+        rets: &[*mut {krate}::data::Value]
+    ) -> Result<(), {krate}::ffi::FFIException> {{
+        {call_unchecked}
+    }}
+}}
+
+{vis} const {binder_const_name}: &{binder_name} = &{binder_name}();
+"#,
+        signature = generate_signature(params, ret, krate),
+        call_rtlc = generate_call(name, params, ret, krate, true),
+        call_unchecked = generate_call(name, params, ret, krate, false)
+    )
+}
+
+fn generate_signature(params: &[ConcreteType], ret: &ConcreteType, krate: &str) -> String {
+    let (ret, exception): (&ConcreteType, Option<&String>) = match ret {
+        ConcreteType::Result(ok_type, err_type) => (ok_type, Some(err_type)),
+        ret => (ret, None)
+    };
+    let rets: Vec<&ConcreteType> = match ret {
+        ConcreteType::Unit => vec![],
+        ret => vec![ret]
+    };
+
+    let param_types: Vec<String> = params.iter()
+        .map(|param: &ConcreteType| tyck_info(param, krate))
+        .collect();
+    let param_options: Vec<String> = params.iter()
+        .map(|param: &ConcreteType| data_option(param, krate))
+        .collect();
+    let ret_types: Vec<String> = rets.iter()
+        .map(|ret: &&ConcreteType| tyck_info(ret, krate))
+        .collect();
+    let ret_options: Vec<String> = rets.iter()
+        .map(|ret: &&ConcreteType| data_option(ret, krate))
+        .collect();
+    let exception_types: Vec<String> = exception.into_iter()
+        .map(|exception: &String| plain_type(exception))
+        .collect();
+
+    format!(r#"
+        let param_types: Vec<std::ptr::NonNull<{krate}::data::tyck::TyckInfo>> = vec![{}];
+        let ret_types: Vec<std::ptr::NonNull<{krate}::data::tyck::TyckInfo>> = vec![{}];
+        let exception_types: Vec<std::ptr::NonNull<{krate}::data::tyck::TyckInfo>> = vec![{}];
+
+        {krate}::ffi::Signature {{
+            func_type: tyck_info_pool.create_function_type(
+                &param_types, &ret_types, &exception_types
+            ),
+            param_options: vec![{}].into_boxed_slice(),
+            ret_option: vec![{}].into_boxed_slice()
+        }}
+    "#,
+        param_types.join(", "),
+        ret_types.join(", "),
+        exception_types.join(", "),
+        param_options.join(", "),
+        ret_options.join(", ")
+    )
+}
+
+/// Generate the body of `call_rtlc` if `checked` is set, or `call_unchecked` otherwise
+///
+/// Arguments are borrowed before any argument gets moved out, and each move-out check is done
+/// right before the corresponding move, so an object passed both by reference and by value fails
+/// the move-out check.
+fn generate_call(
+    name: &str,
+    params: &[ConcreteType],
+    ret: &ConcreteType,
+    krate: &str,
+    checked: bool
+) -> String {
+    let mut checks: String = String::new();
+    let mut borrows: String = String::new();
+    let mut moves: String = String::new();
+    let mut arg_names: Vec<String> = vec![];
+
+    for (i, param) in params.iter().enumerate() {
+        let (check, binding): (String, String) = param_conversion(param, i, krate, checked);
+        let is_move: bool = match param {
+            ConcreteType::Object(_) => true,
+            ConcreteType::Option(inner) => matches!(**inner, ConcreteType::Object(_)),
+            _ => false
+        };
+        if is_move {
+            moves.push_str(&check);
+            moves.push_str(&binding);
+        } else {
+            checks.push_str(&check);
+            borrows.push_str(&binding);
+        }
+        arg_names.push(format!("arg{}", i));
+    }
+
+    let call: String = format!("{}({})", name, arg_names.join(", "));
+    format!("{}{}{}{}\nOk(())", checks, borrows, moves, ret_conversion(ret, &call, krate))
+}
+
+/// Returns the ownership checks of the `idx`-th argument and the statement binding the converted
+/// argument to `arg<idx>`
+fn param_conversion(
+    param: &ConcreteType,
+    idx: usize,
+    krate: &str,
+    checked: bool
+) -> (String, String) {
+    let value: String = format!("(*args.get_unchecked({}))", idx);
+    let null_check: String = if checked {
+        format!(r#"
+            if {value}.is_null() {{
+                return Err({krate}::ffi::FFIException::Unchecked(
+                    {krate}::data::exception::UncheckedException::UnexpectedNull {{
+                        value: {value}
+                    }}
+                ));
+            }}
+        "#)
+    } else {
+        String::new()
+    };
+    let has_guard = |param: &ConcreteType| checked && matches!(
+        param,
+        ConcreteType::Ref { .. } | ConcreteType::ContainerRef(_)
+    );
+
+    match param {
+        ConcreteType::Option(inner) => {
+            let (inner_check, inner_binding): (String, String) =
+                param_conversion_inner(inner, &value, krate, checked);
+            let binding: String = if has_guard(inner) {
+                format!(r#"
+                    let (arg{idx}, _guard{idx}) = if {value}.is_null() {{
+                        (None, None)
+                    }} else {{
+                        let (inner, guard) = {inner_binding};
+                        (Some(inner), Some(guard))
+                    }};
+                "#)
+            } else {
+                format!(r#"
+                    let arg{idx} = if {value}.is_null() {{ None }} else {{ Some({inner_binding}) }};
+                "#)
+            };
+            (format!("if !{value}.is_null() {{ {inner_check} }}"), binding)
+        },
+        ConcreteType::Ref { .. } | ConcreteType::ContainerRef(_) | ConcreteType::Object(_) => {
+            let (check, binding): (String, String) =
+                param_conversion_inner(param, &value, krate, checked);
+            let binding: String = if has_guard(param) {
+                format!("let (arg{idx}, _guard{idx}) = {binding};")
+            } else {
+                format!("let arg{idx} = {binding};")
+            };
+            (format!("{null_check}{check}"), binding)
+        },
+        _ => {
+            let (_, binding): (String, String) =
+                param_conversion_inner(param, &value, krate, checked);
+            (String::new(), format!("let arg{idx} = {binding};"))
+        }
+    }
+}
+
+/// Returns the ownership checks and the conversion expression of a non-null argument. For
+/// references in `call_rtlc`, the expression evaluates to a `(reference, guard)` pair.
+fn param_conversion_inner(
+    param: &ConcreteType,
+    value: &str,
+    krate: &str,
+    checked: bool
+) -> (String, String) {
+    let ffi: String = format!("{}::ffi::sync_fn", krate);
+    match param {
+        ConcreteType::Bool => (String::new(), format!("{}.vt_data.inner.bool_value", value)),
+        ConcreteType::Char => (String::new(), format!("{}.vt_data.inner.char_value", value)),
+        ConcreteType::Float => (String::new(), format!("{}.vt_data.inner.float_value", value)),
+        ConcreteType::Int => (String::new(), format!("{}.vt_data.inner.int_value", value)),
+        ConcreteType::Value => (String::new(), value.to_string()),
+        ConcreteType::Object(ty) => {
+            let check: String = if checked {
+                format!("{}::value_move_out_check_norm_noalias({})?;", ffi, value)
+            } else {
+                String::new()
+            };
+            (check, format!("{}::value_move_out_norm::<{}>({})", ffi, ty, value))
+        },
+        ConcreteType::Ref { mutable: false, inner } => if checked {
+            (String::new(), format!("{}::value_into_ref::<{}>({})?", ffi, inner, value))
+        } else {
+            (String::new(), format!("{}::value_into_ref_noalias::<{}>({})?", ffi, inner, value))
+        },
+        ConcreteType::Ref { mutable: true, inner } => if checked {
+            (String::new(), format!(r#"{{
+                let (inner, guard) = {}::value_into_mut_ref::<{}>({})?;
+                (inner, Some(guard))
+            }}"#, ffi, inner, value))
+        } else {
+            (String::new(), format!("{}::value_into_mut_ref_noalias::<{}>({})?", ffi, inner, value))
+        },
+        ConcreteType::ContainerRef(ty) => if checked {
+            (String::new(), format!("{}::container_into_ref::<{}>({})?", ffi, ty, value))
+        } else {
+            (String::new(), format!("{}::container_into_ref_noalias::<{}>({})?", ffi, ty, value))
+        },
+        ConcreteType::Option(_) | ConcreteType::Result(..) | ConcreteType::Unit => unreachable!()
+    }
+}
+
+/// Returns the statements calling the bound function with `call` and storing its return value
+/// into `rets`
+fn ret_conversion(ret: &ConcreteType, call: &str, krate: &str) -> String {
+    match ret {
+        ConcreteType::Unit => format!("{};", call),
+        ConcreteType::Result(ok_type, err_type) => {
+            let err_conversion: String = format!(r#"
+                {krate}::util::type_assert::assert_static_base::<{err_type}>();
+                let err_value: {krate}::data::Value = {krate}::data::Value::new_owned(err);
+                context.add_heap_managed(err_value);
+                return Err({krate}::ffi::FFIException::Checked(err_value));
+            "#);
+            if let ConcreteType::Unit = **ok_type {
+                format!("if let Err(err) = {} {{ {} }}", call, err_conversion)
+            } else {
+                format!(r#"
+                    let ret = match {call} {{
+                        Ok(ret) => ret,
+                        Err(err) => {{ {err_conversion} }}
+                    }};
+                    {}
+                "#, ret_conversion(ok_type, "ret", krate))
+            }
+        },
+        ret => format!("**rets.get_unchecked(0) = {};", to_value(ret, call, krate))
+    }
+}
+
+/// Expression converting `ret`, a value of type `ty`, into a VM `Value`
+fn to_value(ty: &ConcreteType, ret: &str, krate: &str) -> String {
+    match ty {
+        ConcreteType::Bool => format!("{}::data::Value::new_bool({})", krate, ret),
+        ConcreteType::Char => format!("{}::data::Value::new_char({})", krate, ret),
+        ConcreteType::Float => format!("{}::data::Value::new_float({})", krate, ret),
+        ConcreteType::Int => format!("{}::data::Value::new_int({})", krate, ret),
+        ConcreteType::Value => ret.to_string(),
+        ConcreteType::Object(ty) => format!(r#"{{
+            {krate}::util::type_assert::assert_static_base::<{ty}>();
+            let value: {krate}::data::Value = {krate}::data::Value::new_owned({ret});
+            context.add_heap_managed(value);
+            value
+        }}"#),
+        ConcreteType::Option(inner) => format!(
+            "match {} {{ Some(inner) => {}, None => {}::data::Value::new_null() }}",
+            ret, to_value(inner, "inner", krate), krate
+        ),
+        ConcreteType::Ref { .. } | ConcreteType::ContainerRef(_) | ConcreteType::Unit
+            | ConcreteType::Result(..) => unreachable!()
+    }
+}
+
+/// Expression creating the `TyckInfo` of `ty` in `tyck_info_pool`
+fn tyck_info(ty: &ConcreteType, krate: &str) -> String {
+    match ty {
+        ConcreteType::Bool => plain_type("bool"),
+        ConcreteType::Char => plain_type("char"),
+        ConcreteType::Float => plain_type("f64"),
+        ConcreteType::Int => plain_type("i64"),
+        ConcreteType::Value => "tyck_info_pool.get_any_type()".into(),
+        ConcreteType::Object(ty) | ConcreteType::Ref { inner: ty, .. } => plain_type(ty),
+        ConcreteType::ContainerRef(_) => "tyck_info_pool.get_object_type()".into(),
+        ConcreteType::Option(inner) => format!(r#"{{
+            let inner: std::ptr::NonNull<{}::data::tyck::TyckInfo> = {};
+            tyck_info_pool.create_nullable_type(inner)
+        }}"#, krate, tyck_info(inner, krate)),
+        ConcreteType::Unit | ConcreteType::Result(..) => unreachable!()
+    }
+}
+
+/// How values of type `ty` are passed between VM and Rust
+fn data_option(ty: &ConcreteType, krate: &str) -> String {
+    let option: &str = match ty {
+        ConcreteType::Bool | ConcreteType::Char | ConcreteType::Float | ConcreteType::Int =>
+            "Copy",
+        ConcreteType::Value => "Raw",
+        ConcreteType::Object(_) => "Move",
+        ConcreteType::Ref { mutable: false, .. } | ConcreteType::ContainerRef(_) => "Share",
+        ConcreteType::Ref { mutable: true, .. } => "MutShare",
+        ConcreteType::Option(inner) => return data_option(inner, krate),
+        ConcreteType::Unit | ConcreteType::Result(..) => unreachable!()
+    };
+    format!("{}::ffi::DataOption::{}", krate, option)
+}
+
+fn plain_type(ty: &str) -> String {
+    format!("tyck_info_pool.create_plain_type(std::any::TypeId::of::<{}>())", ty)
+}
@@ -0,0 +1,30 @@
+/// Concrete representation of a Rust function signature accepted by codegen-lite
+pub(crate) struct ConcreteFn {
+    /// Visibility of the function, in source form (e.g. `pub(crate)`)
+    pub vis: String,
+    pub name: String,
+    pub params: Vec<ConcreteType>,
+    pub ret: ConcreteType
+}
+
+/// Types accepted by codegen-lite. Type names are kept in source form, and are spliced into the
+/// generated code as-is.
+pub(crate) enum ConcreteType {
+    Bool,
+    Char,
+    Float,
+    Int,
+    Unit,
+    /// `Value`, passed through without any conversion
+    Value,
+    /// Any other type, living on VM heap as an object and moved between VM and Rust
+    Object(String),
+    /// `&T` or `&mut T` where `T` is an object type
+    Ref { mutable: bool, inner: String },
+    /// Reference to a VM container, e.g. `ObjectRef`, created by `GenericTypeRef::create_ref`
+    ContainerRef(String),
+    /// `Option<T>`, where `None` is converted to or from `null`
+    Option(Box<ConcreteType>),
+    /// `Result<T, E>`, where `Err` is converted to a checked exception. Only allowed as return type
+    Result(Box<ConcreteType>, String)
+}
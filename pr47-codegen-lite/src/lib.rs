@@ -1,3 +1,84 @@
-pub mod codegen;
-pub mod concrete;
-pub mod parse;
+//! A dependency-free alternative of `pr47-codegen`, for builds that cannot afford `syn`
+//!
+//! Function signatures are parsed by hand, so only a restricted subset of Rust is accepted. See
+//! `pr47_function_bind` for details.
+
+mod codegen;
+mod concrete;
+mod parse;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+use crate::codegen::generate_function_bind;
+use crate::concrete::ConcreteFn;
+use crate::parse::{ParseError, parse_fn};
+
+/// Generate a `FunctionBase` implementation for an ordinary Rust function, the same way
+/// `pr47_codegen::pr47_function_bind` does
+///
+/// For a function `foo`, a unit struct `Pr47Binder_foo` implementing `FunctionBase` and a constant
+/// `PR47BINDER_FOO` referring to it are generated next to the function. Accepted parameter and
+/// return types are:
+///
+/// - `i64`, `f64`, `char` and `bool`, copied from or into value registers
+/// - `Value`, passed through as-is
+/// - `&T` and `&mut T`, sharing a VM object with the function
+/// - `ObjectRef`, sharing a VM container with the function
+/// - any other path type `T`, moved out of a VM object, or moved into a new one when returned
+/// - `Option<T>`, mapping `None` to `null`
+/// - `Result<T, E>` as return type, throwing `E` as a checked exception on `Err`
+///
+/// Generic, `async` and `extern` functions, methods and other kinds of types (tuples, slices,
+/// trait objects etc.) are rejected.
+///
+/// Pass `local` (i.e. `#[pr47_function_bind(local)]`) when using the macro inside `pr47` itself.
+#[proc_macro_attribute]
+pub fn pr47_function_bind(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut krate: &str = "pr47";
+    for token in attr {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "local" => krate = "crate",
+            TokenTree::Punct(punct) if punct.as_char() == ',' => {},
+            token => {
+                let message: String = format!("unknown function binder parameter: {}", token);
+                return compile_error(token.span(), &message);
+            }
+        }
+    }
+
+    let func: ConcreteFn = match parse_fn(item.clone()) {
+        Ok(func) => func,
+        Err(ParseError { span, message }) => {
+            let message: String = format!("cannot bind function: {}", message);
+            let mut ret: TokenStream = compile_error(span, &message);
+            ret.extend(item);
+            return ret;
+        }
+    };
+
+    let binder: TokenStream = generate_function_bind(&func, krate)
+        .parse()
+        .expect("generated code should be valid tokens");
+    let mut ret: TokenStream = item;
+    ret.extend(binder);
+    ret
+}
+
+/// `compile_error!("<message>");` reported at `span`
+fn compile_error(span: Span, message: &str) -> TokenStream {
+    let mut literal: Literal = Literal::string(message);
+    literal.set_span(span);
+    let mut bang: Punct = Punct::new('!', Spacing::Alone);
+    bang.set_span(span);
+    let mut args: Group = Group::new(Delimiter::Parenthesis, TokenTree::from(literal).into());
+    args.set_span(span);
+    let mut semi: Punct = Punct::new(';', Spacing::Alone);
+    semi.set_span(span);
+
+    [
+        TokenTree::from(Ident::new("compile_error", span)),
+        TokenTree::from(bang),
+        TokenTree::from(args),
+        TokenTree::from(semi)
+    ].into_iter().collect()
+}
@@ -0,0 +1,293 @@
+use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
+
+use crate::concrete::{ConcreteFn, ConcreteType};
+
+pub(crate) struct ParseError {
+    pub span: Span,
+    pub message: String
+}
+
+impl ParseError {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Self { span, message: message.into() }
+    }
+}
+
+/// Parse the signature of a function item. Only a restricted subset of Rust is accepted: no
+/// generics, no `where` clauses, no receivers and no `async` or `extern` functions.
+pub(crate) fn parse_fn(item: TokenStream) -> Result<ConcreteFn, ParseError> {
+    let tokens: Vec<TokenTree> = item.into_iter().collect();
+    let mut idx: usize = 0;
+
+    // outer attributes, including doc comments
+    while is_punct(tokens.get(idx), '#') {
+        match tokens.get(idx + 1) {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => idx += 2,
+            _ => return Err(ParseError::new(span_of(&tokens, idx), "malformed attribute"))
+        }
+    }
+
+    let mut vis: String = String::new();
+    if is_ident(tokens.get(idx), "pub") {
+        vis.push_str("pub");
+        idx += 1;
+        if let Some(TokenTree::Group(group)) = tokens.get(idx) {
+            if group.delimiter() == Delimiter::Parenthesis {
+                vis.push_str(&group.to_string());
+                idx += 1;
+            }
+        }
+    }
+
+    loop {
+        match tokens.get(idx) {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "fn" => {
+                idx += 1;
+                break;
+            },
+            Some(TokenTree::Ident(ident)) if ["const", "unsafe"].contains(&&*ident.to_string()) =>
+                idx += 1,
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "async" =>
+                return Err(ParseError::new(
+                    ident.span(),
+                    "async functions are not supported by pr47-codegen-lite, use pr47-codegen"
+                )),
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "extern" =>
+                return Err(ParseError::new(ident.span(), "extern functions cannot be bound")),
+            _ => return Err(ParseError::new(span_of(&tokens, idx), "expected function item"))
+        }
+    }
+
+    let name: String = match tokens.get(idx) {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err(ParseError::new(span_of(&tokens, idx), "expected function name"))
+    };
+    idx += 1;
+
+    if is_punct(tokens.get(idx), '<') {
+        return Err(ParseError::new(span_of(&tokens, idx), "generic functions cannot be bound"));
+    }
+
+    let params: Vec<ConcreteType> = match tokens.get(idx) {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            let param_tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+            let mut params: Vec<ConcreteType> = vec![];
+            for param in split_top_level(&param_tokens, ',') {
+                params.push(parse_param(param, group.span())?);
+            }
+            params
+        },
+        _ => return Err(ParseError::new(span_of(&tokens, idx), "expected parameter list"))
+    };
+    idx += 1;
+
+    let ret: ConcreteType = if is_punct(tokens.get(idx), '-')
+        && is_punct(tokens.get(idx + 1), '>')
+    {
+        idx += 2;
+        let start: usize = idx;
+        while idx < tokens.len() && !is_body_or_where(&tokens[idx]) {
+            idx += 1;
+        }
+        parse_ret(&tokens[start..idx], span_of(&tokens, start))?
+    } else {
+        ConcreteType::Unit
+    };
+
+    if is_ident(tokens.get(idx), "where") {
+        return Err(ParseError::new(span_of(&tokens, idx), "`where` clauses are not supported"));
+    }
+
+    Ok(ConcreteFn { vis, name, params, ret })
+}
+
+fn parse_param(tokens: &[TokenTree], list_span: Span) -> Result<ConcreteType, ParseError> {
+    if tokens.iter().any(|token: &TokenTree| is_ident(Some(token), "self")) {
+        return Err(ParseError::new(
+            span_of(tokens, 0),
+            "methods cannot be bound, use a free function instead"
+        ));
+    }
+
+    let colon: usize = tokens.iter()
+        .position(|token: &TokenTree| is_punct(Some(token), ':'))
+        .ok_or_else(|| ParseError::new(list_span, "expected `name: Type` parameter"))?;
+    let ty: &[TokenTree] = &tokens[colon + 1..];
+    match parse_type(ty, span_of(tokens, colon))? {
+        ConcreteType::Unit =>
+            Err(ParseError::new(span_of(ty, 0), "unit parameters are not supported")),
+        ConcreteType::Result(..) =>
+            Err(ParseError::new(span_of(ty, 0), "`Result` parameters are not supported")),
+        ty => Ok(ty)
+    }
+}
+
+fn parse_ret(tokens: &[TokenTree], span: Span) -> Result<ConcreteType, ParseError> {
+    let ret: ConcreteType = parse_type(tokens, span)?;
+    let ok_type: &ConcreteType = match &ret {
+        ConcreteType::Result(ok_type, _) => ok_type,
+        ret => ret
+    };
+    let returns_ref: bool = match ok_type {
+        ConcreteType::Ref { .. } | ConcreteType::ContainerRef(_) => true,
+        ConcreteType::Option(inner) =>
+            matches!(**inner, ConcreteType::Ref { .. } | ConcreteType::ContainerRef(_)),
+        _ => false
+    };
+    if returns_ref {
+        return Err(ParseError::new(span, "returning references is not supported"));
+    }
+    Ok(ret)
+}
+
+fn parse_type(tokens: &[TokenTree], span: Span) -> Result<ConcreteType, ParseError> {
+    let span: Span = tokens.first().map(TokenTree::span).unwrap_or(span);
+    match tokens {
+        [] => Err(ParseError::new(span, "expected type")),
+        [TokenTree::Group(group)] if group.delimiter() == Delimiter::Parenthesis
+            && group.stream().is_empty() => Ok(ConcreteType::Unit),
+        [TokenTree::Group(group)] if group.delimiter() == Delimiter::None => {
+            let inner: Vec<TokenTree> = group.stream().into_iter().collect();
+            parse_type(&inner, span)
+        },
+        [TokenTree::Punct(punct), rest @ ..] if punct.as_char() == '&' => {
+            let mut rest: &[TokenTree] = rest;
+            // lifetime
+            if is_punct(rest.first(), '\'') {
+                rest = rest.get(2..).unwrap_or(&[]);
+            }
+            let mutable: bool = is_ident(rest.first(), "mut");
+            if mutable {
+                rest = &rest[1..];
+            }
+            match parse_type(rest, span)? {
+                ConcreteType::Object(inner) => Ok(ConcreteType::Ref { mutable, inner }),
+                _ => Err(ParseError::new(span, "only references to object types are supported"))
+            }
+        },
+        _ => parse_path_type(tokens, span)
+    }
+}
+
+fn parse_path_type(tokens: &[TokenTree], span: Span) -> Result<ConcreteType, ParseError> {
+    let unsupported = || ParseError::new(
+        span,
+        format!("unsupported type `{}`", to_source(tokens))
+    );
+
+    // `::`-separated identifiers, optionally followed by generic arguments
+    let mut idx: usize = 0;
+    if is_punct(tokens.first(), ':') {
+        idx += 2;
+    }
+    let mut last_ident: String = loop {
+        match tokens.get(idx) {
+            Some(TokenTree::Ident(ident)) => {
+                idx += 1;
+                if is_punct(tokens.get(idx), ':') && is_punct(tokens.get(idx + 1), ':') {
+                    idx += 2;
+                } else {
+                    break ident.to_string();
+                }
+            },
+            _ => return Err(unsupported())
+        }
+    };
+    if ["dyn", "impl", "fn"].contains(&&*last_ident) {
+        return Err(unsupported());
+    }
+    if let Some(stripped) = last_ident.strip_prefix("r#") {
+        last_ident = stripped.to_string();
+    }
+
+    let args: Vec<&[TokenTree]> = if idx == tokens.len() {
+        vec![]
+    } else if is_punct(tokens.get(idx), '<') && is_punct(tokens.last(), '>') {
+        split_top_level(&tokens[idx + 1..tokens.len() - 1], ',')
+    } else {
+        return Err(unsupported());
+    };
+
+    match (last_ident.as_str(), args.len()) {
+        ("bool", 0) => Ok(ConcreteType::Bool),
+        ("char", 0) => Ok(ConcreteType::Char),
+        ("f64", 0) => Ok(ConcreteType::Float),
+        ("i64", 0) => Ok(ConcreteType::Int),
+        ("Value", 0) => Ok(ConcreteType::Value),
+        ("ObjectRef", 0) => Ok(ConcreteType::ContainerRef(to_source(tokens))),
+        ("f32", 0) => Err(ParseError::new(span, "`f32` is not supported, use `f64` instead")),
+        ("i8" | "i16" | "i32" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" |
+         "usize", 0) =>
+            Err(ParseError::new(
+                span,
+                format!("`{}` is not supported, use `i64` instead", last_ident)
+            )),
+        ("str", 0) => Err(ParseError::new(span, "`str` is not supported, use `String` instead")),
+        ("Option", 1) => match parse_type(args[0], span)? {
+            ConcreteType::Option(_) | ConcreteType::Result(..) | ConcreteType::Unit =>
+                Err(ParseError::new(span, "`Option` may only wrap plain or object types")),
+            inner => Ok(ConcreteType::Option(Box::new(inner)))
+        },
+        ("Option", _) =>
+            Err(ParseError::new(span, "expected exactly one type argument for `Option`")),
+        ("Result", 2) => match parse_type(args[0], span)? {
+            ConcreteType::Result(..) =>
+                Err(ParseError::new(span, "nested `Result` is not supported")),
+            ok_type => Ok(ConcreteType::Result(Box::new(ok_type), to_source(args[1])))
+        },
+        ("Result", _) =>
+            Err(ParseError::new(span, "expected exactly two type arguments for `Result`")),
+        _ => Ok(ConcreteType::Object(to_source(tokens)))
+    }
+}
+
+/// Split `tokens` by `separator`, ignoring separators inside angle brackets. Groups are single
+/// token trees, so only angle brackets need tracking.
+fn split_top_level(tokens: &[TokenTree], separator: char) -> Vec<&[TokenTree]> {
+    let mut ret: Vec<&[TokenTree]> = vec![];
+    let mut depth: usize = 0;
+    let mut start: usize = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if let TokenTree::Punct(punct) = token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                // `->` in `fn` types is not an angle bracket
+                '>' if i > 0 && is_punct(tokens.get(i - 1), '-') => {},
+                '>' => depth = depth.saturating_sub(1),
+                c if c == separator && depth == 0 => {
+                    ret.push(&tokens[start..i]);
+                    start = i + 1;
+                },
+                _ => {}
+            }
+        }
+    }
+    if start < tokens.len() {
+        ret.push(&tokens[start..]);
+    }
+    ret
+}
+
+fn is_body_or_where(token: &TokenTree) -> bool {
+    match token {
+        TokenTree::Group(group) => group.delimiter() == Delimiter::Brace,
+        TokenTree::Ident(ident) => ident.to_string() == "where",
+        _ => false
+    }
+}
+
+fn is_punct(token: Option<&TokenTree>, c: char) -> bool {
+    matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == c)
+}
+
+fn is_ident(token: Option<&TokenTree>, name: &str) -> bool {
+    matches!(token, Some(TokenTree::Ident(ident)) if ident.to_string() == name)
+}
+
+fn span_of(tokens: &[TokenTree], idx: usize) -> Span {
+    tokens.get(idx).or_else(|| tokens.last()).map(TokenTree::span).unwrap_or_else(Span::call_site)
+}
+
+fn to_source(tokens: &[TokenTree]) -> String {
+    tokens.iter().cloned().collect::<TokenStream>().to_string()
+}
@@ -0,0 +1,180 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::{ItemFn, ReturnType};
+
+use crate::sync_fn::{
+    CallKind,
+    binder_const_name,
+    generate_signature,
+    param_conversions,
+    param_types,
+    ret_type
+};
+use crate::types::TypeInfo;
+
+/// Generate an `AsyncFunctionBase` implementation for the Rust `async fn` `item`, named
+/// `Pr47Binder_<function name>`, together with a `PR47BINDER_<FUNCTION NAME>` constant which can
+/// be put into `CompiledProgram::async_ffi_funcs` directly.
+///
+/// Guards of shared arguments are moved into the promise, and released only after the promise
+/// gets resolved.
+pub(crate) fn generate_async_function_bind(
+    item: &ItemFn,
+    krate: &TokenStream
+) -> Result<TokenStream, String> {
+    if item.sig.generics.type_params().next().is_some()
+        || item.sig.generics.const_params().next().is_some() {
+        return Err("generic functions cannot be bound".into());
+    }
+
+    let params: Vec<TypeInfo> = param_types(item)?;
+    let ret: TypeInfo = ret_type(&item.sig.output)?;
+    if params.iter().any(contains_value) || contains_value(&ret) {
+        return Err("`Value` cannot be sent across threads, thus not allowed in async functions"
+            .into());
+    }
+
+    let fn_name: &Ident = &item.sig.ident;
+    let vis = &item.vis;
+    let binder_name: Ident = format_ident!("Pr47Binder_{}", fn_name);
+    let binder_const_name: Ident = binder_const_name(&binder_name);
+
+    let signature: TokenStream = generate_signature(&params, &ret, krate);
+    let (conversions, arg_names): (TokenStream, Vec<Ident>) =
+        param_conversions(&params, krate, CallKind::Async);
+
+    let mut guards: Vec<Ident> = vec![];
+    let mut guard_types: Vec<TokenStream> = vec![];
+    for (i, param) in params.iter().enumerate() {
+        if let Some(guard_type) = guard_type(param, krate) {
+            guards.push(format_ident!("guard{}", i));
+            guard_types.push(guard_type);
+        }
+    }
+
+    let ret_ty: TokenStream = match &item.sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty)
+    };
+    let is_err: TokenStream = match ret {
+        TypeInfo::Result { .. } => quote!(self.result.is_err()),
+        _ => quote!(false)
+    };
+    let resolve: TokenStream = generate_resolve(&ret, krate);
+
+    Ok(quote! {
+        #item
+
+        #[allow(non_camel_case_types)]
+        #vis struct #binder_name();
+
+        impl #krate::ffi::async_fn::AsyncFunctionBase for #binder_name {
+            fn signature(
+                tyck_info_pool: &mut #krate::data::tyck::TyckInfoPool
+            ) -> #krate::ffi::Signature {
+                #signature
+            }
+
+            #[allow(unused_variables)]
+            unsafe fn call_rtlc<A, VD, ACTX>(
+                context: &ACTX,
+                args: &[#krate::data::Value]
+            ) -> Result<#krate::ffi::async_fn::Promise<A>, #krate::ffi::FFIException>
+                where A: #krate::vm::al31f::alloc::Alloc,
+                      VD: #krate::ffi::async_fn::VMDataTrait<Alloc=A>,
+                      ACTX: #krate::ffi::async_fn::AsyncVMContext<VMData=VD>
+            {
+                struct AsyncRet {
+                    #[allow(dead_code)]
+                    guards: (#(#guard_types,)*),
+                    result: #ret_ty
+                }
+
+                impl<A: #krate::vm::al31f::alloc::Alloc>
+                    #krate::ffi::async_fn::AsyncReturnType<A> for AsyncRet
+                {
+                    fn is_err(&self) -> bool {
+                        #is_err
+                    }
+
+                    #[allow(unused_variables, unused_unsafe)]
+                    fn resolve(
+                        self: Box<Self>,
+                        alloc: &mut A,
+                        dests: &[*mut #krate::data::Value]
+                    ) -> Result<usize, #krate::data::exception::ExceptionInner> {
+                        unsafe { #resolve }
+                    }
+                }
+
+                #conversions
+
+                let fut = async move {
+                    let result: #ret_ty = #fn_name(#(#arg_names),*).await;
+                    Box::new(AsyncRet { guards: (#(#guards,)*), result })
+                        as Box<dyn #krate::ffi::async_fn::AsyncReturnType<A>>
+                };
+
+                Ok(#krate::ffi::async_fn::Promise(Box::pin(fut)))
+            }
+        }
+
+        #vis const #binder_const_name: &#binder_name = &#binder_name();
+    })
+}
+
+fn contains_value(info: &TypeInfo) -> bool {
+    match info {
+        TypeInfo::Value => true,
+        TypeInfo::Option { inner } => contains_value(inner),
+        TypeInfo::Result { ok_type, .. } => contains_value(ok_type),
+        _ => false
+    }
+}
+
+/// Type of the guard kept for a shared argument of type `param`, if any
+fn guard_type(param: &TypeInfo, krate: &TokenStream) -> Option<TokenStream> {
+    match param {
+        TypeInfo::Ref { mutable: false, .. } =>
+            Some(quote!(#krate::ffi::async_fn::AsyncShareGuard)),
+        TypeInfo::Ref { mutable: true, .. } =>
+            Some(quote!(#krate::ffi::async_fn::AsyncResetGuard)),
+        TypeInfo::Option { inner } =>
+            guard_type(inner, krate).map(|guard_type: TokenStream| quote!(Option<#guard_type>)),
+        _ => None
+    }
+}
+
+/// Generate the body of `AsyncReturnType::resolve`, storing `self.result` into `dests` and
+/// returning the count of stored values
+fn generate_resolve(ret: &TypeInfo, krate: &TokenStream) -> TokenStream {
+    match ret {
+        TypeInfo::Result { ok_type, err_type } => {
+            let ok_conversion: TokenStream = store_result(ok_type, &quote!(ret), krate);
+            quote! {
+                match self.result {
+                    Ok(ret) => { #ok_conversion },
+                    Err(err) => {
+                        #krate::util::type_assert::assert_static_base::<#err_type>();
+                        let err_value: #krate::data::Value = #krate::data::Value::new_owned(err);
+                        alloc.add_managed(err_value);
+                        Err(#krate::data::exception::ExceptionInner::Checked(err_value))
+                    }
+                }
+            }
+        },
+        ret => store_result(ret, &quote!(self.result), krate)
+    }
+}
+
+fn store_result(ret: &TypeInfo, result: &TokenStream, krate: &TokenStream) -> TokenStream {
+    if let TypeInfo::Unit = ret {
+        return quote!(Ok(0));
+    }
+
+    let ret_value: TokenStream = ret.to_value(krate, result, &quote!(alloc.add_managed));
+    quote! {
+        **dests.get_unchecked(0) = #ret_value;
+        Ok(1)
+    }
+}
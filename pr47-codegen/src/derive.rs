@@ -0,0 +1,284 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    Attribute,
+    Data,
+    DeriveInput,
+    Field,
+    Fields,
+    Index,
+    Lit,
+    Member,
+    Meta,
+    NestedMeta,
+    PathArguments,
+    Type,
+    TypePath
+};
+
+use crate::sync_fn::generate_binder;
+use crate::types::TypeInfo;
+
+/// Options given by `#[pr47(...)]` on the derived type
+struct TypeOptions {
+    local: bool,
+    name: Option<String>
+}
+
+/// Options given by `#[pr47(...)]` on a field
+#[derive(Default)]
+struct FieldOptions {
+    get: bool,
+    set: bool,
+    trace: bool
+}
+
+/// Generate a `StaticBase` implementation for the struct `item`, together with getter and setter
+/// bindings of fields marked with `#[pr47(get)]` and `#[pr47(set)]`.
+pub(crate) fn generate_derive_pr47_type(item: &DeriveInput) -> Result<TokenStream, String> {
+    let type_options: TypeOptions = parse_type_options(&item.attrs)?;
+    let krate: TokenStream = if type_options.local { quote!(crate) } else { quote!(pr47) };
+
+    if !item.generics.params.is_empty() {
+        return Err("generic types cannot be exposed".into());
+    }
+    let fields: Vec<&Field> = match &item.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+            Fields::Unit => vec![]
+        },
+        _ => return Err("only structs can be exposed".into())
+    };
+
+    let ident: &Ident = &item.ident;
+    let type_name: String = type_options.name.unwrap_or_else(|| ident.to_string());
+
+    let mut children: Vec<TokenStream> = vec![];
+    let mut getters: Vec<TokenStream> = vec![];
+    let mut binders: Vec<TokenStream> = vec![];
+    for (i, field) in fields.into_iter().enumerate() {
+        let options: FieldOptions = parse_field_options(&field.attrs)?;
+        let member: Member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i))
+        };
+        let field_name: String = match &member {
+            Member::Named(ident) => ident.to_string(),
+            Member::Unnamed(index) => index.index.to_string()
+        };
+        let ty: &Type = &field.ty;
+
+        if let Some(child) = field_children(ty, &member, options.trace, &krate) {
+            children.push(child);
+        }
+
+        if !options.get && !options.set {
+            continue;
+        }
+        let info: TypeInfo = TypeInfo::from_type(ty)
+            .map_err(|e: String| format!("field `{}`: {}", field_name, e))?;
+        if matches!(info, TypeInfo::Ref { .. } | TypeInfo::Result { .. } | TypeInfo::Unit) {
+            return Err(format!("field `{}` cannot be exposed", field_name));
+        }
+
+        if options.get {
+            if is_plain(&info) {
+                let value: TokenStream = info.to_value(&krate, &quote!(vself.#member), &quote!());
+                getters.push(quote!(#field_name => Some(#value)));
+            }
+
+            let binder_name: Ident = format_ident!("Pr47Binder_{}_get_{}", ident, field_name);
+            binders.push(generate_binder(
+                &item.vis,
+                &binder_name,
+                &[TypeInfo::Ref { mutable: false, inner: self_type(ident) }],
+                &info,
+                &krate,
+                |args: &[Ident]| {
+                    let this: &Ident = &args[0];
+                    quote!(Clone::clone(&#this.#member))
+                }
+            ));
+        }
+
+        if options.set {
+            let write_barrier: TokenStream = match &info {
+                TypeInfo::Value => quote!(context.mark(value);),
+                TypeInfo::Option { inner } if matches!(**inner, TypeInfo::Value) =>
+                    quote!(if let Some(value) = value { context.mark(value); }),
+                _ => quote!()
+            };
+            let binder_name: Ident = format_ident!("Pr47Binder_{}_set_{}", ident, field_name);
+            binders.push(generate_binder(
+                &item.vis,
+                &binder_name,
+                &[TypeInfo::Ref { mutable: true, inner: self_type(ident) }, info],
+                &TypeInfo::Unit,
+                &krate,
+                |args: &[Ident]| {
+                    let (this, value): (&Ident, &Ident) = (&args[0], &args[1]);
+                    quote! {{
+                        let value = #value;
+                        #write_barrier
+                        #this.#member = value;
+                    }}
+                }
+            ));
+        }
+    }
+
+    let children: TokenStream = if children.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            #[allow(clippy::not_unsafe_ptr_arg_deref)]
+            #[inline] fn children(vself: *const #ident) -> #krate::data::traits::ChildrenType {
+                let vself: &#ident = unsafe { &*vself };
+                Some(Box::new(std::iter::empty() #(.chain(#children))*))
+            }
+        }
+    };
+    let get_field: TokenStream = if getters.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            #[allow(clippy::not_unsafe_ptr_arg_deref)]
+            #[inline] fn get_field(
+                vself: *const #ident,
+                name: &str
+            ) -> Option<#krate::data::Value> {
+                let vself: &#ident = unsafe { &*vself };
+                match name {
+                    #(#getters,)*
+                    _ => None
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #krate::data::traits::StaticBase<#ident> for #krate::data::traits::Void {
+            fn type_name() -> String { #type_name.into() }
+
+            #children
+
+            #get_field
+        }
+
+        #(#binders)*
+    })
+}
+
+fn parse_type_options(attrs: &[Attribute]) -> Result<TypeOptions, String> {
+    let mut options: TypeOptions = TypeOptions { local: false, name: None };
+    for meta in pr47_metas(attrs)? {
+        match meta {
+            Meta::Path(path) if path.is_ident("local") => options.local = true,
+            Meta::NameValue(name_value) if name_value.path.is_ident("name") => {
+                if let Lit::Str(name) = &name_value.lit {
+                    options.name = Some(name.value());
+                } else {
+                    return Err("expected string literal for `name`".into());
+                }
+            },
+            meta => return Err(format!("unknown type option: {}", quote!(#meta)))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_field_options(attrs: &[Attribute]) -> Result<FieldOptions, String> {
+    let mut options: FieldOptions = FieldOptions::default();
+    for meta in pr47_metas(attrs)? {
+        match meta {
+            Meta::Path(path) if path.is_ident("get") => options.get = true,
+            Meta::Path(path) if path.is_ident("set") => options.set = true,
+            Meta::Path(path) if path.is_ident("trace") => options.trace = true,
+            meta => return Err(format!("unknown field option: {}", quote!(#meta)))
+        }
+    }
+    Ok(options)
+}
+
+fn pr47_metas(attrs: &[Attribute]) -> Result<Vec<Meta>, String> {
+    let mut metas: Vec<Meta> = vec![];
+    for attr in attrs.iter().filter(|attr: &&Attribute| attr.path.is_ident("pr47")) {
+        match attr.parse_meta().map_err(|e: syn::Error| e.to_string())? {
+            Meta::List(list) => for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(meta) => metas.push(meta),
+                    NestedMeta::Lit(lit) =>
+                        return Err(format!("unexpected literal: {}", quote!(#lit)))
+                }
+            },
+            _ => return Err("expected `#[pr47(...)]`".into())
+        }
+    }
+    Ok(metas)
+}
+
+/// Builtin types holding VM values, whose fields are traced even without `trace`
+const TRACED_TYPES: &[&str] = &["Object", "VMGenericVec", "VMVec", "Closure", "CaptureCell"];
+
+/// Expression iterating over VM values referred by field `member`, if any. `Value`,
+/// `Option<Value>` and `Vec<Value>` fields are traced automatically, and so are fields of the
+/// builtin types in `TRACED_TYPES`. Fields of other types are traced with their
+/// `StaticBase::children` only if `trace` is set.
+fn field_children(
+    ty: &Type,
+    member: &Member,
+    trace: bool,
+    krate: &TokenStream
+) -> Option<TokenStream> {
+    let last_ident: Option<String> = match ty {
+        Type::Path(TypePath { qself: None, path }) =>
+            path.segments.last().map(|segment| segment.ident.to_string()),
+        _ => None
+    };
+    match TypeInfo::from_type(ty) {
+        Ok(TypeInfo::Value) => return Some(quote!(std::iter::once(vself.#member))),
+        Ok(TypeInfo::Option { inner }) if matches!(*inner, TypeInfo::Value) =>
+            return Some(quote!(vself.#member.into_iter())),
+        _ => {}
+    }
+    if is_value_vec(ty) {
+        return Some(quote!(vself.#member.iter().copied()));
+    }
+    if trace || matches!(last_ident.as_deref(), Some(ident) if TRACED_TYPES.contains(&ident)) {
+        return Some(quote! {
+            <#krate::data::traits::Void as #krate::data::traits::StaticBase<#ty>>::children(
+                &vself.#member as *const #ty
+            ).into_iter().flatten()
+        });
+    }
+    None
+}
+
+fn is_value_vec(ty: &Type) -> bool {
+    if let Type::Path(TypePath { qself: None, path }) = ty {
+        if let Some(segment) = path.segments.last() {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if segment.ident == "Vec" && args.args.len() == 1 {
+                    if let syn::GenericArgument::Type(inner) = &args.args[0] {
+                        return matches!(TypeInfo::from_type(inner), Ok(TypeInfo::Value));
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Whether values of this type can be converted to `Value` without allocating
+fn is_plain(info: &TypeInfo) -> bool {
+    match info {
+        TypeInfo::Bool | TypeInfo::Char | TypeInfo::Float | TypeInfo::Int | TypeInfo::Value => true,
+        TypeInfo::Option { inner } => is_plain(inner),
+        _ => false
+    }
+}
+
+fn self_type(ident: &Ident) -> Type {
+    syn::parse_quote!(#ident)
+}
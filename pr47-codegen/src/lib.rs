@@ -1,17 +1,42 @@
+mod async_fn;
 mod attrs;
+mod derive;
+mod sync_fn;
 mod types;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, ItemFn, FnArg};
+use syn::{parse_macro_input, DeriveInput, ItemFn};
 
+use crate::async_fn::generate_async_function_bind;
 use crate::attrs::parse_function_bind_attrs;
+use crate::derive::generate_derive_pr47_type;
+use crate::sync_fn::generate_function_bind;
 
+/// Generate a `FunctionBase` implementation for an ordinary Rust function, or an
+/// `AsyncFunctionBase` implementation for an `async fn`
+///
+/// For a function `foo`, a unit struct `Pr47Binder_foo` implementing `FunctionBase` (or
+/// `AsyncFunctionBase`) and a constant `PR47BINDER_FOO` referring to it are generated next to the
+/// function. Parameter and return
+/// types are mapped as follows:
+///
+/// - `i64`, `f64`, `char` and `bool` are copied from or into value registers
+/// - `Value` is passed through as-is
+/// - `&T` and `&mut T` share a VM object with the function, with ownership checks in `call_rtlc`
+/// - any other type `T` is moved out of a VM object, or moved into a new one when returned
+/// - `Option<T>` maps `None` to `null`
+/// - a returned `Result<T, E>` throws `E` as a checked exception on `Err`
+///
+/// For `async fn`, shared objects stay shared until the returned promise gets resolved, and
+/// `Value` is not allowed since it cannot be sent across threads.
+///
+/// Pass `local` (i.e. `#[pr47_function_bind(local)]`) when using the macro inside `pr47` itself.
 #[proc_macro_attribute]
 pub fn pr47_function_bind(attr: TokenStream, item: TokenStream) -> TokenStream {
     let item: ItemFn = parse_macro_input!(item as ItemFn);
 
-    let _attrs: Vec<String> = match parse_function_bind_attrs(attr) {
+    let attrs: Vec<String> = match parse_function_bind_attrs(attr) {
         Ok(attrs) => attrs,
         Err(e) => {
             return (quote!{
@@ -20,10 +45,64 @@ pub fn pr47_function_bind(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
-    let _args: Vec<&FnArg> = item.sig.inputs.iter().collect::<_>();
-    let ret = quote!{
-        #item
+    let mut krate: proc_macro2::TokenStream = quote!(pr47);
+    for attr in attrs.iter() {
+        match attr.as_str() {
+            "local" => krate = quote!(crate),
+            _ => {
+                let e: String = format!("unknown function binder parameter: {}", attr);
+                return (quote!{
+                    compile_error!( #e ) ;
+                }).into()
+            }
+        }
+    }
+
+    let ret = if item.sig.asyncness.is_some() {
+        generate_async_function_bind(&item, &krate)
+    } else {
+        generate_function_bind(&item, &krate)
     };
+    match ret {
+        Ok(ret) => ret.into(),
+        Err(e) => {
+            let e: String = format!("cannot bind function `{}`: {}", item.sig.ident, e);
+            (quote!{
+                compile_error!( #e ) ;
+                #item
+            }).into()
+        }
+    }
+}
+
+/// Expose a Rust struct as a VM type by implementing `StaticBase` for it
+///
+/// Options are given by `#[pr47(...)]` attributes. On the struct:
+///
+/// - `name = "..."` sets the type name visible to script code, defaults to the struct name
+/// - `local` is required when deriving inside `pr47` itself
+///
+/// On fields:
+///
+/// - `get` generates a getter binding `Pr47Binder_<Type>_get_<field>` taking `&Type`. Fields of
+///   primitive types, `Value` and `Option` of them are also readable with `HOST-FIELD-GET`
+/// - `set` generates a setter binding `Pr47Binder_<Type>_set_<field>` taking `&mut Type`
+/// - `trace` visits VM values referred by the field with its own `StaticBase::children`
+///
+/// Fields of type `Value`, `Option<Value>`, `Vec<Value>`, `Object`, `VMGenericVec`, `VMVec`,
+/// `Closure` and `CaptureCell` are always visited by the generated `children`, other fields are
+/// assumed to refer no VM values unless marked `trace`.
+#[proc_macro_derive(Pr47Type, attributes(pr47))]
+pub fn derive_pr47_type(item: TokenStream) -> TokenStream {
+    let item: DeriveInput = parse_macro_input!(item as DeriveInput);
 
-    ret.into()
+    match generate_derive_pr47_type(&item) {
+        Ok(ret) => ret.into(),
+        Err(e) => {
+            let e: String = format!("cannot derive `Pr47Type` for `{}`: {}", item.ident, e);
+            (quote!{
+                compile_error!( #e ) ;
+            }).into()
+        }
+    }
 }
@@ -0,0 +1,382 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::{FnArg, ItemFn, PatType, ReturnType, Type, Visibility};
+
+use crate::types::TypeInfo;
+
+/// Generate a `FunctionBase` implementation for the Rust function `item`, named
+/// `Pr47Binder_<function name>`, together with a `PR47BINDER_<FUNCTION NAME>` constant which can
+/// be put into `CompiledProgram::ffi_funcs` directly.
+pub(crate) fn generate_function_bind(
+    item: &ItemFn,
+    krate: &TokenStream
+) -> Result<TokenStream, String> {
+    if item.sig.generics.type_params().next().is_some()
+        || item.sig.generics.const_params().next().is_some() {
+        return Err("generic functions cannot be bound".into());
+    }
+
+    let params: Vec<TypeInfo> = param_types(item)?;
+    let ret: TypeInfo = ret_type(&item.sig.output)?;
+
+    let fn_name: &Ident = &item.sig.ident;
+    let binder_name: Ident = format_ident!("Pr47Binder_{}", fn_name);
+    let binder: TokenStream = generate_binder(
+        &item.vis,
+        &binder_name,
+        &params,
+        &ret,
+        krate,
+        |arg_names: &[Ident]| quote!(#fn_name(#(#arg_names),*))
+    );
+
+    Ok(quote! {
+        #item
+
+        #binder
+    })
+}
+
+/// Generate a unit struct `binder_name` implementing `FunctionBase`, together with a constant
+/// named after `binder_name` in upper case, with `Pr47Binder` replaced by `PR47BINDER`.
+///
+/// `make_call` creates the expression calling the bound function from the names of converted
+/// arguments.
+pub(crate) fn generate_binder(
+    vis: &Visibility,
+    binder_name: &Ident,
+    params: &[TypeInfo],
+    ret: &TypeInfo,
+    krate: &TokenStream,
+    make_call: impl Fn(&[Ident]) -> TokenStream
+) -> TokenStream {
+    let binder_const_name: Ident = binder_const_name(binder_name);
+    let signature: TokenStream = generate_signature(params, ret, krate);
+    let call_rtlc: TokenStream = generate_call(params, ret, krate, CallKind::Rtlc, &make_call);
+    let call_unchecked: TokenStream =
+        generate_call(params, ret, krate, CallKind::Unchecked, &make_call);
+
+    quote! {
+        #[allow(non_camel_case_types)]
+        #vis struct #binder_name();
+
+        impl #krate::ffi::sync_fn::FunctionBase for #binder_name {
+            fn signature(
+                tyck_info_pool: &mut #krate::data::tyck::TyckInfoPool
+            ) -> #krate::ffi::Signature {
+                #signature
+            }
+
+            #[allow(unused_variables)]
+            unsafe fn call_rtlc<CTX: #krate::ffi::sync_fn::VMContext>(
+                context: &mut CTX,
+                args: &[#krate::data::Value],
+                rets: &[*mut #krate::data::Value]
+            ) -> Result<(), #krate::ffi::FFIException> {
+                #call_rtlc
+            }
+
+            #[allow(unused_variables)]
+            unsafe fn call_unchecked<CTX: #krate::ffi::sync_fn::VMContext>(
+                context: &mut CTX,
+                args: &[#krate::data::Value],
+                rets: &[*mut #krate::data::Value]
+            ) -> Result<(), #krate::ffi::FFIException> {
+                #call_unchecked
+            }
+        }
+
+        #vis const #binder_const_name: &#binder_name = &#binder_name();
+    }
+}
+
+pub(crate) fn binder_const_name(binder_name: &Ident) -> Ident {
+    let binder_name: String = binder_name.to_string();
+    let binder_name: &str = binder_name.strip_prefix("Pr47Binder_").unwrap_or(&binder_name);
+    format_ident!("PR47BINDER_{}", binder_name.to_uppercase())
+}
+
+pub(crate) fn param_types(item: &ItemFn) -> Result<Vec<TypeInfo>, String> {
+    item.sig.inputs.iter().map(|arg: &FnArg| match arg {
+        FnArg::Receiver(_) => Err("methods cannot be bound, use a free function instead".into()),
+        FnArg::Typed(PatType { ty, .. }) => match TypeInfo::from_type(ty)? {
+            TypeInfo::Unit => Err("unit parameters are not supported".into()),
+            TypeInfo::Result { .. } => Err("`Result` parameters are not supported".into()),
+            info => Ok(info)
+        }
+    }).collect()
+}
+
+pub(crate) fn ret_type(output: &ReturnType) -> Result<TypeInfo, String> {
+    let ret: TypeInfo = match output {
+        ReturnType::Default => return Ok(TypeInfo::Unit),
+        ReturnType::Type(_, ty) => TypeInfo::from_type(ty)?
+    };
+    let ok_type: &TypeInfo = match &ret {
+        TypeInfo::Result { ok_type, .. } => ok_type,
+        ret => ret
+    };
+    if let TypeInfo::Ref { .. } = ok_type {
+        return Err("returning references is not supported".into());
+    }
+    if let TypeInfo::Option { inner } = ok_type {
+        if let TypeInfo::Ref { .. } = **inner {
+            return Err("returning references is not supported".into());
+        }
+    }
+    Ok(ret)
+}
+
+pub(crate) fn generate_signature(
+    params: &[TypeInfo],
+    ret: &TypeInfo,
+    krate: &TokenStream
+) -> TokenStream {
+    let (ret, exception): (&TypeInfo, Option<&Type>) = match ret {
+        TypeInfo::Result { ok_type, err_type } => (ok_type, Some(err_type)),
+        ret => (ret, None)
+    };
+    let rets: Vec<&TypeInfo> = match ret {
+        TypeInfo::Unit => vec![],
+        ret => vec![ret]
+    };
+
+    let param_types = params.iter().map(|param: &TypeInfo| param.tyck_info(krate));
+    let param_options = params.iter().map(|param: &TypeInfo| param.data_option(krate));
+    let ret_types = rets.iter().map(|ret: &&TypeInfo| ret.tyck_info(krate));
+    let ret_options = rets.iter().map(|ret: &&TypeInfo| ret.data_option(krate));
+    let exception_types = exception.into_iter().map(|exception: &Type| {
+        quote!(tyck_info_pool.create_plain_type(std::any::TypeId::of::<#exception>()))
+    });
+
+    quote! {
+        let param_types: Vec<std::ptr::NonNull<#krate::data::tyck::TyckInfo>> =
+            vec![#(#param_types),*];
+        let ret_types: Vec<std::ptr::NonNull<#krate::data::tyck::TyckInfo>> =
+            vec![#(#ret_types),*];
+        let exception_types: Vec<std::ptr::NonNull<#krate::data::tyck::TyckInfo>> =
+            vec![#(#exception_types),*];
+
+        #krate::ffi::Signature {
+            func_type: tyck_info_pool.create_function_type(
+                &param_types, &ret_types, &exception_types
+            ),
+            param_options: vec![#(#param_options),*].into_boxed_slice(),
+            ret_option: vec![#(#ret_options),*].into_boxed_slice()
+        }
+    }
+}
+
+/// Kind of the generated call function, deciding which ownership checks and guards are used
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CallKind {
+    /// `FunctionBase::call_rtlc`
+    Rtlc,
+    /// `FunctionBase::call_unchecked`
+    Unchecked,
+    /// `AsyncFunctionBase::call_rtlc`, where guards are kept until the promise gets resolved
+    Async
+}
+
+/// Generate the body of `call_rtlc` or `call_unchecked`, according to `kind`
+fn generate_call(
+    params: &[TypeInfo],
+    ret: &TypeInfo,
+    krate: &TokenStream,
+    kind: CallKind,
+    make_call: &impl Fn(&[Ident]) -> TokenStream
+) -> TokenStream {
+    let (conversions, arg_names): (TokenStream, Vec<Ident>) =
+        param_conversions(params, krate, kind);
+    let ret_conversion: TokenStream = ret_conversion(ret, &make_call(&arg_names), krate);
+    quote! {
+        #conversions
+        #ret_conversion
+        Ok(())
+    }
+}
+
+/// Returns the statements checking and converting all arguments, and the names of the converted
+/// arguments. Guards of shared arguments are named `guard<N>` for the `N`-th argument.
+///
+/// Arguments are borrowed before any argument gets moved out, and each move-out check is done
+/// right before the corresponding move. This way, an object passed both by reference and by value
+/// fails the move-out check instead of being moved out while still borrowed.
+pub(crate) fn param_conversions(
+    params: &[TypeInfo],
+    krate: &TokenStream,
+    kind: CallKind
+) -> (TokenStream, Vec<Ident>) {
+    let mut checks: Vec<TokenStream> = vec![];
+    let mut borrows: Vec<TokenStream> = vec![];
+    let mut moves: Vec<TokenStream> = vec![];
+    let mut arg_names: Vec<Ident> = vec![];
+
+    for (i, param) in params.iter().enumerate() {
+        let arg: Ident = format_ident!("arg{}", i);
+        let guard: Ident = format_ident!("guard{}", i);
+        let (check, binding): (TokenStream, TokenStream) =
+            param_conversion(param, &arg, &guard, i, krate, kind);
+        match param {
+            TypeInfo::Object { .. } => moves.push(quote!(#check #binding)),
+            TypeInfo::Option { inner } if matches!(**inner, TypeInfo::Object { .. }) =>
+                moves.push(quote!(#check #binding)),
+            _ => {
+                checks.push(check);
+                borrows.push(binding);
+            }
+        }
+        arg_names.push(arg);
+    }
+
+    (quote!(#(#checks)* #(#borrows)* #(#moves)*), arg_names)
+}
+
+/// Returns the ownership checks of the `idx`-th argument and the statement binding the converted
+/// argument to `arg`, and the guard of a shared argument to `guard`
+fn param_conversion(
+    param: &TypeInfo,
+    arg: &Ident,
+    guard: &Ident,
+    idx: usize,
+    krate: &TokenStream,
+    kind: CallKind
+) -> (TokenStream, TokenStream) {
+    let value: TokenStream = quote!(*args.get_unchecked(#idx));
+    let null_check: TokenStream = if kind != CallKind::Unchecked {
+        quote! {
+            if (#value).is_null() {
+                return Err(#krate::ffi::FFIException::Unchecked(
+                    #krate::data::exception::UncheckedException::UnexpectedNull { value: #value }
+                ));
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    match param {
+        TypeInfo::Option { inner } => {
+            let (inner_check, inner_binding): (TokenStream, TokenStream) =
+                param_conversion_inner(inner, idx, krate, kind);
+            let binding: TokenStream = match &**inner {
+                TypeInfo::Ref { .. } if kind != CallKind::Unchecked => quote! {
+                    let (#arg, #guard) = if (#value).is_null() {
+                        (None, None)
+                    } else {
+                        let (inner, guard) = #inner_binding;
+                        (Some(inner), Some(guard))
+                    };
+                },
+                _ => quote! {
+                    let #arg = if (#value).is_null() { None } else { Some(#inner_binding) };
+                }
+            };
+            (quote!(if !(#value).is_null() { #inner_check }), binding)
+        },
+        TypeInfo::Ref { .. } | TypeInfo::Object { .. } => {
+            let (check, binding): (TokenStream, TokenStream) =
+                param_conversion_inner(param, idx, krate, kind);
+            let binding: TokenStream = match param {
+                TypeInfo::Ref { .. } if kind != CallKind::Unchecked =>
+                    quote!(let (#arg, #guard) = #binding;),
+                _ => quote!(let #arg = #binding;)
+            };
+            (quote!(#null_check #check), binding)
+        },
+        _ => {
+            let (_, binding): (TokenStream, TokenStream) =
+                param_conversion_inner(param, idx, krate, kind);
+            (quote!(), quote!(let #arg = #binding;))
+        }
+    }
+}
+
+/// Returns the ownership checks and the conversion expression of a non-null argument. For
+/// references in `call_rtlc`, the expression evaluates to a `(reference, guard)` pair.
+fn param_conversion_inner(
+    param: &TypeInfo,
+    idx: usize,
+    krate: &TokenStream,
+    kind: CallKind
+) -> (TokenStream, TokenStream) {
+    let value: TokenStream = quote!(*args.get_unchecked(#idx));
+    let ffi: TokenStream = if kind == CallKind::Async {
+        quote!(#krate::ffi::async_fn)
+    } else {
+        quote!(#krate::ffi::sync_fn)
+    };
+    match param {
+        TypeInfo::Bool => (quote!(), quote!((#value).vt_data.inner.bool_value)),
+        TypeInfo::Char => (quote!(), quote!((#value).vt_data.inner.char_value)),
+        TypeInfo::Float => (quote!(), quote!((#value).vt_data.inner.float_value)),
+        TypeInfo::Int => (quote!(), quote!((#value).vt_data.inner.int_value)),
+        TypeInfo::Value => (quote!(), quote!(#value)),
+        TypeInfo::Object { ty } => {
+            let check: TokenStream = if kind != CallKind::Unchecked {
+                quote!(#ffi::value_move_out_check_norm_noalias(#value)?;)
+            } else {
+                quote!()
+            };
+            (check, quote!(#ffi::value_move_out_norm::<#ty>(#value)))
+        },
+        TypeInfo::Ref { mutable: false, inner } => match kind {
+            CallKind::Unchecked =>
+                (quote!(), quote!(#ffi::value_into_ref_noalias::<#inner>(#value)?)),
+            _ => (quote!(), quote!(#ffi::value_into_ref::<#inner>(#value)?))
+        },
+        TypeInfo::Ref { mutable: true, inner } => match kind {
+            CallKind::Rtlc => (quote!(), quote! {{
+                let (inner, guard) = #ffi::value_into_mut_ref::<#inner>(#value)?;
+                (inner, Some(guard))
+            }}),
+            CallKind::Unchecked =>
+                (quote!(), quote!(#ffi::value_into_mut_ref_noalias::<#inner>(#value)?)),
+            CallKind::Async => (quote!(), quote!(#ffi::value_into_mut_ref::<#inner>(#value)?))
+        },
+        TypeInfo::Option { .. } | TypeInfo::Result { .. } | TypeInfo::Unit => unreachable!()
+    }
+}
+
+/// Returns the statements calling the bound function with `call` and storing its return value
+/// into `rets`
+pub(crate) fn ret_conversion(
+    ret: &TypeInfo,
+    call: &TokenStream,
+    krate: &TokenStream
+) -> TokenStream {
+    match ret {
+        TypeInfo::Unit => quote!(#call;),
+        TypeInfo::Result { ok_type, err_type } => {
+            let err_conversion: TokenStream = quote! {
+                #krate::util::type_assert::assert_static_base::<#err_type>();
+                let err_value: #krate::data::Value = #krate::data::Value::new_owned(err);
+                context.add_heap_managed(err_value);
+                return Err(#krate::ffi::FFIException::Checked(err_value));
+            };
+            if let TypeInfo::Unit = **ok_type {
+                quote! {
+                    if let Err(err) = #call {
+                        #err_conversion
+                    }
+                }
+            } else {
+                let ok_conversion: TokenStream = ret_conversion(ok_type, &quote!(ret), krate);
+                quote! {
+                    let ret = match #call {
+                        Ok(ret) => ret,
+                        Err(err) => {
+                            #err_conversion
+                        }
+                    };
+                    #ok_conversion
+                }
+            }
+        },
+        ret => {
+            let ret_value: TokenStream =
+                ret.to_value(krate, call, &quote!(context.add_heap_managed));
+            quote!(**rets.get_unchecked(0) = #ret_value;)
+        }
+    }
+}
@@ -1,19 +1,153 @@
-use proc_macro::TokenStream;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{GenericArgument, PathArguments, Type, TypePath};
 
-#[allow(unused)]
-pub enum TypeInfo {
-    AsyncCombustor,
+/// Classification of Rust types appearing in bound function signatures
+pub(crate) enum TypeInfo {
     Bool,
     Char,
-    Combustor,
     Float,
     Int,
-    Opaque { token_stream: TokenStream },
+    Unit,
+    /// `Value`, passed through without any conversion
+    Value,
+    /// Any other type, living on VM heap as an object and moved between VM and Rust
+    Object { ty: Type },
+    /// `&T` or `&mut T` where `T` is an object type
+    Ref { mutable: bool, inner: Type },
+    /// `Option<T>`, where `None` is converted to or from `null`
     Option { inner: Box<TypeInfo> },
-    Ref { mutable: bool, inner: Box<TypeInfo> },
-    Result { ok_type: Box<TypeInfo>, err_type: Box<TypeInfo> },
-    VMObject { inner: Box<TypeInfo> },
-    VMObjectRef { mutable: bool, inner: Box<TypeInfo> },
-    VMVec { inner: Box<TypeInfo> },
-    VMVecRef { mutable: bool, inner: Box<TypeInfo> }
+    /// `Result<T, E>`, where `Err` is converted to a checked exception. Only allowed as return type
+    Result { ok_type: Box<TypeInfo>, err_type: Type }
+}
+
+impl TypeInfo {
+    pub(crate) fn from_type(ty: &Type) -> Result<Self, String> {
+        match ty {
+            Type::Tuple(tuple) if tuple.elems.is_empty() => Ok(TypeInfo::Unit),
+            Type::Paren(paren) => Self::from_type(&paren.elem),
+            Type::Group(group) => Self::from_type(&group.elem),
+            Type::Reference(reference) => Ok(TypeInfo::Ref {
+                mutable: reference.mutability.is_some(),
+                inner: (*reference.elem).clone()
+            }),
+            Type::Path(TypePath { qself: None, path }) => {
+                let last = path.segments.last().ok_or("empty type path")?;
+                match (last.ident.to_string().as_str(), &last.arguments) {
+                    ("bool", PathArguments::None) => Ok(TypeInfo::Bool),
+                    ("char", PathArguments::None) => Ok(TypeInfo::Char),
+                    ("f64", PathArguments::None) => Ok(TypeInfo::Float),
+                    ("i64", PathArguments::None) => Ok(TypeInfo::Int),
+                    ("Value", PathArguments::None) => Ok(TypeInfo::Value),
+                    ("Option", PathArguments::AngleBracketed(args)) => {
+                        let types: Vec<&Type> = generic_types(args.args.iter());
+                        if types.len() != 1 {
+                            return Err("expected exactly one type argument for `Option`".into());
+                        }
+                        let inner: TypeInfo = Self::from_type(types[0])?;
+                        match inner {
+                            TypeInfo::Option { .. } | TypeInfo::Result { .. } | TypeInfo::Unit =>
+                                Err("`Option` may only wrap plain or object types".into()),
+                            inner => Ok(TypeInfo::Option { inner: Box::new(inner) })
+                        }
+                    },
+                    ("Result", PathArguments::AngleBracketed(args)) => {
+                        let types: Vec<&Type> = generic_types(args.args.iter());
+                        if types.len() != 2 {
+                            return Err("expected exactly two type arguments for `Result`".into());
+                        }
+                        let ok_type: TypeInfo = Self::from_type(types[0])?;
+                        if let TypeInfo::Result { .. } = ok_type {
+                            return Err("nested `Result` is not supported".into());
+                        }
+                        Ok(TypeInfo::Result {
+                            ok_type: Box::new(ok_type),
+                            err_type: types[1].clone()
+                        })
+                    },
+                    _ => Ok(TypeInfo::Object { ty: ty.clone() })
+                }
+            },
+            _ => Err(format!("unsupported type: {}", quote!(#ty)))
+        }
+    }
+
+    /// Expression creating the `TyckInfo` of this type in `tyck_info_pool`
+    pub(crate) fn tyck_info(&self, krate: &TokenStream) -> TokenStream {
+        match self {
+            TypeInfo::Bool => plain_type(&quote!(bool)),
+            TypeInfo::Char => plain_type(&quote!(char)),
+            TypeInfo::Float => plain_type(&quote!(f64)),
+            TypeInfo::Int => plain_type(&quote!(i64)),
+            TypeInfo::Value => quote!(tyck_info_pool.get_any_type()),
+            TypeInfo::Object { ty } => plain_type(&quote!(#ty)),
+            TypeInfo::Ref { inner, .. } => plain_type(&quote!(#inner)),
+            TypeInfo::Option { inner } => {
+                let inner: TokenStream = inner.tyck_info(krate);
+                quote! {{
+                    let inner: std::ptr::NonNull<#krate::data::tyck::TyckInfo> = #inner;
+                    tyck_info_pool.create_nullable_type(inner)
+                }}
+            },
+            TypeInfo::Unit | TypeInfo::Result { .. } => unreachable!()
+        }
+    }
+
+    /// How values of this type are passed between VM and Rust
+    pub(crate) fn data_option(&self, krate: &TokenStream) -> TokenStream {
+        match self {
+            TypeInfo::Bool | TypeInfo::Char | TypeInfo::Float | TypeInfo::Int =>
+                quote!(#krate::ffi::DataOption::Copy),
+            TypeInfo::Value => quote!(#krate::ffi::DataOption::Raw),
+            TypeInfo::Object { .. } => quote!(#krate::ffi::DataOption::Move),
+            TypeInfo::Ref { mutable: false, .. } => quote!(#krate::ffi::DataOption::Share),
+            TypeInfo::Ref { mutable: true, .. } => quote!(#krate::ffi::DataOption::MutShare),
+            TypeInfo::Option { inner } => inner.data_option(krate),
+            TypeInfo::Unit | TypeInfo::Result { .. } => unreachable!()
+        }
+    }
+
+    /// Expression converting `ret`, a value of this type, into a VM `Value`. Newly created objects
+    /// are handed over to the garbage collector by calling `manage`.
+    pub(crate) fn to_value(
+        &self,
+        krate: &TokenStream,
+        ret: &TokenStream,
+        manage: &TokenStream
+    ) -> TokenStream {
+        match self {
+            TypeInfo::Bool => quote!(#krate::data::Value::new_bool(#ret)),
+            TypeInfo::Char => quote!(#krate::data::Value::new_char(#ret)),
+            TypeInfo::Float => quote!(#krate::data::Value::new_float(#ret)),
+            TypeInfo::Int => quote!(#krate::data::Value::new_int(#ret)),
+            TypeInfo::Value => quote!(#ret),
+            TypeInfo::Object { ty } => quote! {{
+                #krate::util::type_assert::assert_static_base::<#ty>();
+                let value: #krate::data::Value = #krate::data::Value::new_owned(#ret);
+                #manage(value);
+                value
+            }},
+            TypeInfo::Option { inner } => {
+                let inner: TokenStream = inner.to_value(krate, &quote!(inner), manage);
+                quote! {
+                    match #ret {
+                        Some(inner) => #inner,
+                        None => #krate::data::Value::new_null()
+                    }
+                }
+            },
+            TypeInfo::Ref { .. } | TypeInfo::Unit | TypeInfo::Result { .. } => unreachable!()
+        }
+    }
+}
+
+fn generic_types<'a>(args: impl Iterator<Item=&'a GenericArgument>) -> Vec<&'a Type> {
+    args.filter_map(|arg: &GenericArgument| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None
+    }).collect()
+}
+
+fn plain_type(ty: &TokenStream) -> TokenStream {
+    quote!(tyck_info_pool.create_plain_type(std::any::TypeId::of::<#ty>()))
 }
@@ -1,14 +1,23 @@
 use std::any::TypeId;
+use std::cell::Cell;
+use std::iter::once;
 use std::ptr::NonNull;
 
 use smallvec::SmallVec;
+use xjbutil::mem::move_to_heap;
 use xjbutil::void::Void;
 
 use crate::data::Value;
 use crate::data::generic::GenericTypeVT;
+use crate::data::wrapper::Wrapper;
 use crate::data::traits::{ChildrenType, StaticBase};
 use crate::data::tyck::{TyckInfo, TyckInfoPool};
 
+/// A function together with its captured values
+///
+/// When called via `CALL-PTR`, captured values are put before the arguments in the callee frame.
+/// Variables assigned after being captured are captured as `CaptureCell`s, so that the closure
+/// and the enclosing function share them.
 pub struct Closure {
     pub captures: SmallVec<[Value; 4]>,
     pub func_id: usize
@@ -18,6 +27,64 @@ impl Closure {
     pub fn new(captures: SmallVec<[Value; 4]>, func_id: usize) -> Self {
         Self { captures, func_id }
     }
+
+    /// Move `self` to heap, creating a VM value of closure type `vt`
+    pub fn into_value(self, vt: *const GenericTypeVT) -> Value {
+        Value::new_container(
+            move_to_heap(Wrapper::new_owned(self)).as_ptr() as *mut Wrapper<()>,
+            vt
+        )
+    }
+}
+
+/// Shared mutable storage of a captured variable
+pub struct CaptureCell {
+    value: Cell<Value>
+}
+
+impl CaptureCell {
+    pub fn new(value: Value) -> Self {
+        Self {
+            value: Cell::new(value)
+        }
+    }
+
+    #[inline(always)] pub fn get(&self) -> Value {
+        self.value.get()
+    }
+
+    #[inline(always)] pub fn set(&self, value: Value) {
+        self.value.set(value)
+    }
+}
+
+impl StaticBase<CaptureCell> for Void {
+    fn type_name() -> String { "cell".into() }
+
+    #[inline] fn children(vself: *const CaptureCell) -> ChildrenType {
+        let vself: &CaptureCell = unsafe { &*vself.cast::<CaptureCell>() };
+        Some(Box::new(once(vself.get())))
+    }
+}
+
+/// Get the function id and captured values of a callable script value, which is either a plain
+/// function or a `Closure`. FFI functions accepting callbacks use this to inspect their arguments.
+///
+/// # Safety
+/// If `value` is a container, it must be a valid one.
+pub unsafe fn value_as_callable<'a>(value: Value) -> Option<(usize, &'a [Value])> {
+    if value.is_value() {
+        Some((value.vt_data.inner.int_value as usize, &[]))
+    } else if value.is_container() {
+        let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+        if (*vt).tyck_info.as_ref().type_id != TypeId::of::<Closure>() {
+            return None;
+        }
+        let closure: &Closure = &*(value.get_as_mut_ptr::<Closure>() as *const Closure);
+        Some((closure.func_id, &closure.captures))
+    } else {
+        None
+    }
 }
 
 impl StaticBase<Closure> for Void {
@@ -27,18 +94,22 @@ impl StaticBase<Closure> for Void {
 
     fn type_name() -> String { "closure".into() }
 
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     #[inline] fn children(vself: *const Closure) -> ChildrenType {
         let vself: &Closure = unsafe { &*vself };
-        Some(Box::new(vself.captures.iter().map(|v: &Value| *v)))
+        Some(Box::new(vself.captures.iter().copied()))
     }
 }
 
+/// Create the vtable of closures of function type `func_type`, which is recorded as the only type
+/// parameter of the closure container type
 pub fn create_closure_vt(
     tyck_info_pool: &mut TyckInfoPool,
-    arg_types: &[NonNull<TyckInfo>]
+    func_type: NonNull<TyckInfo>
 ) -> GenericTypeVT {
+    debug_assert!(matches!(unsafe { func_type.as_ref() }, TyckInfo::Function(_)));
     let tyck_info: NonNull<TyckInfo> =
-        tyck_info_pool.create_container_type(TypeId::of::<Closure>(), arg_types);
+        tyck_info_pool.create_container_type(TypeId::of::<Closure>(), &[func_type]);
 
     use crate::data::generic::gen_impls;
     GenericTypeVT {
@@ -0,0 +1,518 @@
+//! Builtin exception types, exposing `UncheckedException`s to script code
+//!
+//! Every kind of `UncheckedException` has a builtin exception type it gets converted into when
+//! caught by script code. Kinds in `CompiledProgram::fatal_exceptions` are never caught.
+//!
+//! Each type starts with an `Object` holding its accessible fields, so that scripts may read them
+//! with `OBJECT-GET`. Data which cannot be represented as a `Value` is kept in plain Rust fields.
+//!
+//! Script code raising its own exceptions should use `ScriptError`, or types built on top of it.
+//! All exception types in this module implement `ExceptionBase`.
+
+use std::any::TypeId;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::ptr::NonNull;
+
+use unchecked_unwrap::UncheckedUnwrap;
+use xjbutil::boxed_slice;
+use xjbutil::unchecked::UncheckedCellOps;
+use xjbutil::void::Void;
+
+use crate::builtins::object::Object;
+use crate::data::Value;
+use crate::data::exception::UncheckedException;
+use crate::data::traits::{ChildrenType, StaticBase};
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
+use crate::ffi::{DataOption, FFIException, Signature};
+use crate::ffi::sync_fn::{FunctionBase, VMContext, value_into_ref_noalias};
+
+/// Common interface of exception types
+pub trait ExceptionBase {
+    /// Human-readable description of what went wrong
+    fn message(&self) -> &str;
+
+    /// The exception which caused this exception, if any
+    fn cause(&self) -> Option<Value>;
+}
+
+/// Standard base type of exceptions raised by script code
+pub struct ScriptError {
+    message: String,
+    cause: Value
+}
+
+impl ScriptError {
+    pub fn new(message: impl Into<String>, cause: Option<Value>) -> Self {
+        Self {
+            message: message.into(),
+            cause: cause.unwrap_or_else(Value::new_null)
+        }
+    }
+}
+
+impl ExceptionBase for ScriptError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn cause(&self) -> Option<Value> {
+        if self.cause.is_null() { None } else { Some(self.cause) }
+    }
+}
+
+impl StaticBase<ScriptError> for Void {
+    fn type_name() -> String { "Error".into() }
+
+    fn children(vself: *const ScriptError) -> ChildrenType {
+        let this: &ScriptError = unsafe { &*vself.cast::<ScriptError>() };
+        let cause: Value = this.cause;
+        if cause.is_ref() {
+            Some(Box::new(std::iter::once(cause)))
+        } else {
+            None
+        }
+    }
+}
+
+macro_rules! builtin_exception {
+    (
+        $(#[$attr:meta])*
+        $name:ident, $type_name:literal { $($extra:ident : $extra_type:ty),* }
+    ) => {
+        $(#[$attr])*
+        // `OBJECT-GET` reads `fields` through a pointer to the whole exception
+        #[repr(C)]
+        pub struct $name {
+            fields: Object,
+            message: String,
+            cause: Value,
+            $(pub $extra: $extra_type),*
+        }
+
+        impl ExceptionBase for $name {
+            fn message(&self) -> &str {
+                &self.message
+            }
+
+            fn cause(&self) -> Option<Value> {
+                if self.cause.is_null() { None } else { Some(self.cause) }
+            }
+        }
+
+        impl $name {
+            /// Get the field `name` accessible from script, or `null` if not present
+            pub fn field(&self, name: &str) -> Value {
+                unsafe {
+                    *self.fields.fields.get_ref_unchecked()
+                        .get(name)
+                        .unwrap_or(&Value::new_null())
+                }
+            }
+        }
+
+        impl StaticBase<$name> for Void {
+            fn type_name() -> String { $type_name.into() }
+
+            #[allow(clippy::not_unsafe_ptr_arg_deref)]
+            #[inline] fn children(vself: *const $name) -> ChildrenType {
+                let this: &$name = unsafe { &*vself };
+                let cause: Value = this.cause;
+                let fields: ChildrenType =
+                    <Void as StaticBase<Object>>::children(&this.fields as *const Object);
+                Some(Box::new(fields.into_iter().flatten().chain(std::iter::once(cause))))
+            }
+        }
+    }
+}
+
+builtin_exception!(
+    /// Raised on integer division or modulo by zero
+    DivideByZeroError, "DivideByZeroError" {}
+);
+
+builtin_exception!(
+    /// Raised on vector index out of bounds. Fields: `indexed`, `index`
+    IndexOutOfBoundsError, "IndexOutOfBoundsError" {}
+);
+
+builtin_exception!(
+    /// Raised by null checks
+    UnexpectedNullError, "UnexpectedNullError" {}
+);
+
+builtin_exception!(
+    /// Raised by runtime type checks. Fields: `object`
+    TypeCheckError, "TypeCheckError" { expected_type: NonNull<TyckInfo> }
+);
+
+builtin_exception!(
+    /// Raised by runtime lifetime checks. Fields: `object`, `expected_mask`
+    OwnershipCheckError, "OwnershipCheckError" {}
+);
+
+builtin_exception!(
+    /// Raised when no overload candidate accepts the arguments. Fields: `overload_table`
+    OverloadCallError, "OverloadCallError" {}
+);
+
+builtin_exception!(
+    /// Raised by binary and unary operators applied to unsupported operands. Fields: `op`, and
+    /// either `lhs` and `rhs`, or `src`
+    InvalidOpError, "InvalidOpError" {}
+);
+
+builtin_exception!(
+    /// Raised by casts applied to unsupported operands. Fields: `src`
+    InvalidCastError, "InvalidCastError" { dest_type: &'static str }
+);
+
+builtin_exception!(
+    /// Raised when awaiting a promise which has been awaited before. Fields: `promise`
+    AlreadyAwaitedError, "AlreadyAwaitedError" {}
+);
+
+builtin_exception!(
+    /// Raised when calling a function with a wrong number of arguments. Fields: `func_id`,
+    /// `expected`, `got`
+    ArgCountError, "ArgCountError" {}
+);
+
+builtin_exception!(
+    /// Raised when a native function panics
+    ForeignPanicError, "ForeignPanicError" {}
+);
+
+builtin_exception!(
+    /// Raised when the stack limit is exceeded. Fields: `frame_depth`, `value_count`
+    StackOverflowError, "StackOverflowError" {}
+);
+
+builtin_exception!(
+    /// Raised when the receiver of a method call has no such method. Fields: `receiver`
+    MethodNotFoundError, "MethodNotFoundError" { method: String }
+);
+
+#[cfg(feature = "async")]
+builtin_exception!(
+    /// Raised when a callback called from native code awaits a promise. Fields: `promise`
+    AwaitInCallbackError, "AwaitInCallbackError" {}
+);
+
+#[cfg(feature = "async")]
+builtin_exception!(
+    /// Raised when a spawned task fails to complete
+    TaskJoinError, "JoinError" {}
+);
+
+fn make_fields(fields: &[(&str, Value)]) -> Object {
+    let fields: HashMap<String, Value> = fields.iter()
+        .map(|(name, value): &(&str, Value)| (name.to_string(), *value))
+        .collect();
+    Object { fields: UnsafeCell::new(fields) }
+}
+
+/// Get the `TypeId` of the builtin exception type `exception` would be converted into
+pub fn builtin_exception_type_id(exception: &UncheckedException) -> TypeId {
+    match exception {
+        UncheckedException::AlreadyAwaited { .. } => TypeId::of::<AlreadyAwaitedError>(),
+        UncheckedException::ArgCountMismatch { .. } => TypeId::of::<ArgCountError>(),
+        UncheckedException::DivideByZero => TypeId::of::<DivideByZeroError>(),
+        UncheckedException::IndexOutOfBounds { .. } => TypeId::of::<IndexOutOfBoundsError>(),
+        UncheckedException::UnexpectedNull { .. } => TypeId::of::<UnexpectedNullError>(),
+        UncheckedException::TypeCheckFailure { .. } => TypeId::of::<TypeCheckError>(),
+        UncheckedException::OwnershipCheckFailure { .. } => TypeId::of::<OwnershipCheckError>(),
+        UncheckedException::OverloadCallFailure { .. } => TypeId::of::<OverloadCallError>(),
+        UncheckedException::InvalidBinaryOp { .. } | UncheckedException::InvalidUnaryOp { .. } =>
+            TypeId::of::<InvalidOpError>(),
+        UncheckedException::InvalidCastOp { .. } => TypeId::of::<InvalidCastError>(),
+        UncheckedException::ForeignPanic { .. } => TypeId::of::<ForeignPanicError>(),
+        UncheckedException::StackOverflow { .. } => TypeId::of::<StackOverflowError>(),
+        UncheckedException::MethodNotFound { .. } => TypeId::of::<MethodNotFoundError>(),
+        #[cfg(feature = "async")]
+        UncheckedException::AwaitInCallback { .. } => TypeId::of::<AwaitInCallbackError>(),
+        #[cfg(feature = "async")]
+        UncheckedException::JoinError { .. } => TypeId::of::<TaskJoinError>()
+    }
+}
+
+/// Get `value` as an `ExceptionBase` if it refers to one of the exception types in this module
+///
+/// # Safety
+/// `value` must be either a value-typed data, null, or a reference to a live object.
+pub unsafe fn as_exception_base<'a>(value: Value) -> Option<&'a dyn ExceptionBase> {
+    macro_rules! try_cast {
+        ($type_id:expr, $($exception_type:ty),*) => {
+            $(
+                if $type_id == TypeId::of::<$exception_type>() {
+                    let ptr: *const $exception_type = value.get_as_mut_ptr_norm() as *const _;
+                    return Some(&*ptr);
+                }
+            )*
+        }
+    }
+
+    if !value.is_ref() || value.is_container() || !value.ownership_info_norm().is_readable() {
+        return None;
+    }
+    let type_id: TypeId = value.get_as_dyn_base().as_ref().unchecked_unwrap().dyn_type_id();
+    try_cast!(
+        type_id,
+        ScriptError,
+        DivideByZeroError,
+        IndexOutOfBoundsError,
+        UnexpectedNullError,
+        TypeCheckError,
+        OwnershipCheckError,
+        OverloadCallError,
+        InvalidOpError,
+        InvalidCastError,
+        AlreadyAwaitedError,
+        ArgCountError,
+        ForeignPanicError,
+        StackOverflowError,
+        MethodNotFoundError
+    );
+    #[cfg(feature = "async")]
+    try_cast!(type_id, AwaitInCallbackError, TaskJoinError);
+    None
+}
+
+/// Convert `exception` into an owned `Value` of the corresponding builtin exception type, caused
+/// by `cause` (may be `null`). The returned value is not yet managed by any allocator. Objects
+/// referenced by `exception` are not looked into while creating the message.
+pub fn unchecked_into_builtin(exception: UncheckedException, cause: Value) -> Value {
+    let message: String = exception.to_string();
+    let int = |value: usize| Value::new_int(value as i64);
+    match exception {
+        UncheckedException::AlreadyAwaited { promise } =>
+            Value::new_owned(AlreadyAwaitedError {
+                fields: make_fields(&[("promise", promise)]),
+                message,
+                cause
+            }),
+        UncheckedException::ArgCountMismatch { func_id, expected, got } =>
+            Value::new_owned(ArgCountError {
+                fields: make_fields(&[
+                    ("func_id", int(func_id)),
+                    ("expected", int(expected)),
+                    ("got", int(got))
+                ]),
+                message,
+                cause
+            }),
+        UncheckedException::DivideByZero =>
+            Value::new_owned(DivideByZeroError { fields: make_fields(&[]), message, cause }),
+        UncheckedException::IndexOutOfBounds { indexed, index } =>
+            Value::new_owned(IndexOutOfBoundsError {
+                fields: make_fields(&[("indexed", indexed), ("index", Value::new_int(index))]),
+                message,
+                cause
+            }),
+        UncheckedException::UnexpectedNull { .. } =>
+            Value::new_owned(UnexpectedNullError { fields: make_fields(&[]), message, cause }),
+        UncheckedException::TypeCheckFailure { object, expected_type } =>
+            Value::new_owned(TypeCheckError {
+                fields: make_fields(&[("object", object)]),
+                message,
+                cause,
+                expected_type
+            }),
+        UncheckedException::OwnershipCheckFailure { object, expected_mask } =>
+            Value::new_owned(OwnershipCheckError {
+                fields: make_fields(&[
+                    ("object", object),
+                    ("expected_mask", Value::new_int(expected_mask as i64))
+                ]),
+                message,
+                cause
+            }),
+        UncheckedException::OverloadCallFailure { overload_table } =>
+            Value::new_owned(OverloadCallError {
+                fields: make_fields(&[("overload_table", int(overload_table))]),
+                message,
+                cause
+            }),
+        UncheckedException::InvalidBinaryOp { bin_op, lhs, rhs } =>
+            Value::new_owned(InvalidOpError {
+                fields: make_fields(&[
+                    ("op", Value::new_char(bin_op)),
+                    ("lhs", lhs),
+                    ("rhs", rhs)
+                ]),
+                message,
+                cause
+            }),
+        UncheckedException::InvalidUnaryOp { unary_op, src } =>
+            Value::new_owned(InvalidOpError {
+                fields: make_fields(&[("op", Value::new_char(unary_op)), ("src", src)]),
+                message,
+                cause
+            }),
+        UncheckedException::InvalidCastOp { dest_type, src } =>
+            Value::new_owned(InvalidCastError {
+                fields: make_fields(&[("src", src)]),
+                message,
+                cause,
+                dest_type
+            }),
+        UncheckedException::ForeignPanic { .. } =>
+            Value::new_owned(ForeignPanicError { fields: make_fields(&[]), message, cause }),
+        UncheckedException::StackOverflow { frame_depth, value_count } =>
+            Value::new_owned(StackOverflowError {
+                fields: make_fields(&[
+                    ("frame_depth", int(frame_depth)),
+                    ("value_count", int(value_count))
+                ]),
+                message,
+                cause
+            }),
+        UncheckedException::MethodNotFound { receiver, method } =>
+            Value::new_owned(MethodNotFoundError {
+                fields: make_fields(&[("receiver", receiver)]),
+                message,
+                cause,
+                method
+            }),
+        #[cfg(feature = "async")]
+        UncheckedException::AwaitInCallback { promise } =>
+            Value::new_owned(AwaitInCallbackError {
+                fields: make_fields(&[("promise", promise)]),
+                message,
+                cause
+            }),
+        #[cfg(feature = "async")]
+        UncheckedException::JoinError { .. } =>
+            Value::new_owned(TaskJoinError { fields: make_fields(&[]), message, cause })
+    }
+}
+
+/// `Error(message: string, cause: any?) -> Error`, creating a `ScriptError`
+pub struct ScriptErrorNewBind();
+
+/// `message(error: any) -> string`, accepting any exception type in this module
+pub struct ScriptErrorMessageBind();
+
+/// `cause(error: any) -> any?`, accepting any exception type in this module
+pub struct ScriptErrorCauseBind();
+
+/// Get the `ExceptionBase` of the argument of `message` or `cause`
+unsafe fn exception_base_arg<'a>(error: Value) -> Result<&'a dyn ExceptionBase, FFIException> {
+    as_exception_base(error).ok_or_else(|| FFIException::Unchecked(
+        UncheckedException::InvalidCastOp { dest_type: "exception", src: error }
+    ))
+}
+
+impl FunctionBase for ScriptErrorNewBind {
+    fn signature(tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        let error_type: NonNull<TyckInfo> =
+            tyck_info_pool.create_plain_type(TypeId::of::<ScriptError>());
+        let string_type: NonNull<TyckInfo> = tyck_info_pool.get_string_type();
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+        let nullable_any: NonNull<TyckInfo> = tyck_info_pool.create_nullable_type(any_type);
+
+        Signature {
+            func_type: tyck_info_pool.create_function_type(
+                &[string_type, nullable_any], &[error_type], &[]
+            ),
+            param_options: boxed_slice![DataOption::Share, DataOption::RawUntyped],
+            ret_option: boxed_slice![DataOption::Move]
+        }
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        let message: &String = value_into_ref_noalias(*args.get_unchecked(0))?;
+        let cause: Value = *args.get_unchecked(1);
+        if cause.is_ref() {
+            context.mark(cause);
+        }
+
+        let error: Value = Value::new_owned(ScriptError {
+            message: message.clone(),
+            cause
+        });
+        context.add_heap_managed(error);
+        **rets.get_unchecked(0) = error;
+        Ok(())
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        Self::call_rtlc(context, args, rets)
+    }
+}
+
+impl FunctionBase for ScriptErrorMessageBind {
+    fn signature(tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+        let string_type: NonNull<TyckInfo> = tyck_info_pool.get_string_type();
+
+        Signature {
+            func_type: tyck_info_pool.create_function_type(&[any_type], &[string_type], &[]),
+            param_options: boxed_slice![DataOption::RawUntyped],
+            ret_option: boxed_slice![DataOption::Move]
+        }
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        let error: &dyn ExceptionBase = exception_base_arg(*args.get_unchecked(0))?;
+        let message: Value = Value::new_owned(error.message().to_string());
+        context.add_heap_managed(message);
+        **rets.get_unchecked(0) = message;
+        Ok(())
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        Self::call_rtlc(context, args, rets)
+    }
+}
+
+impl FunctionBase for ScriptErrorCauseBind {
+    fn signature(tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+        let nullable_any: NonNull<TyckInfo> = tyck_info_pool.create_nullable_type(any_type);
+
+        Signature {
+            func_type: tyck_info_pool.create_function_type(&[any_type], &[nullable_any], &[]),
+            param_options: boxed_slice![DataOption::RawUntyped],
+            ret_option: boxed_slice![DataOption::RawUntyped]
+        }
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        _context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        let error: &dyn ExceptionBase = exception_base_arg(*args.get_unchecked(0))?;
+        **rets.get_unchecked(0) = error.cause().unwrap_or_else(Value::new_null);
+        Ok(())
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        Self::call_rtlc(context, args, rets)
+    }
+}
+
+pub const SCRIPT_ERROR_NEW_BIND: &ScriptErrorNewBind = &ScriptErrorNewBind();
+pub const SCRIPT_ERROR_MESSAGE_BIND: &ScriptErrorMessageBind = &ScriptErrorMessageBind();
+pub const SCRIPT_ERROR_CAUSE_BIND: &ScriptErrorCauseBind = &ScriptErrorCauseBind();
@@ -1,6 +1,8 @@
 pub mod closure;
+pub mod exception;
 pub mod object;
 pub mod set;
 pub mod vec;
+pub mod weak;
 
 #[cfg(test)] pub mod test_container;
@@ -1,9 +1,15 @@
+use std::any::TypeId;
 use std::cell::UnsafeCell;
 use std::marker::PhantomData;
+use std::ptr::NonNull;
 
+use xjbutil::mem::move_to_heap;
+use xjbutil::unchecked::UncheckedCellOps;
 use xjbutil::void::Void;
 
-use crate::data::traits::StaticBase;
+use crate::data::generic::GenericTypeVT;
+use crate::data::traits::{ChildrenType, StaticBase};
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
 use crate::data::Value;
 use crate::data::wrapper::Wrapper;
 
@@ -12,7 +18,17 @@ pub struct VMGenericVec {
     pub inner: UnsafeCell<Vec<Value>>
 }
 
-impl StaticBase<VMGenericVec> for Void {}
+impl StaticBase<VMGenericVec> for Void {
+    fn type_name() -> String { "vector".into() }
+
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    #[inline] fn children(vself: *const VMGenericVec) -> ChildrenType {
+        unsafe {
+            let iter = Box::new((*vself).inner.get_ref_unchecked().iter().copied());
+            Some(iter)
+        }
+    }
+}
 
 #[repr(transparent)]
 pub struct VMVec<T: 'static> {
@@ -20,7 +36,41 @@ pub struct VMVec<T: 'static> {
     _phantom: PhantomData<T>
 }
 
+impl<T: 'static> StaticBase<VMVec<T>> for Void {
+    fn type_name() -> String { "vector".into() }
+
+    #[inline] fn children(vself: *const VMVec<T>) -> ChildrenType {
+        <Void as StaticBase<VMGenericVec>>::children(vself as *const VMGenericVec)
+    }
+}
+
 pub struct VMVecRef<T: 'static> {
     pub ptr: *mut Wrapper<()>,
     _phantom: PhantomData<T>
 }
+
+/// Constructor of empty vectors, used as the `CREATE-CONTAINER` constructor of vector types
+pub fn vm_vec_ctor() -> *mut Wrapper<()> {
+    let vec: VMGenericVec = VMGenericVec { inner: UnsafeCell::new(vec![]) };
+    move_to_heap(Wrapper::new_owned(vec)).as_ptr() as *mut Wrapper<()>
+}
+
+pub fn create_vm_vec_vt(
+    tyck_info_pool: &mut TyckInfoPool,
+    elem_type: NonNull<TyckInfo>
+) -> GenericTypeVT {
+    let tyck_info: NonNull<TyckInfo> =
+        tyck_info_pool.create_container_type(TypeId::of::<VMGenericVec>(), &[elem_type]);
+
+    use crate::data::generic::gen_impls;
+    GenericTypeVT {
+        tyck_info: unsafe { tyck_info.as_ref().get_container_tyck_info_unchecked() },
+        type_name: "vector".to_string(),
+        #[cfg(debug_assertions)]
+        move_out_fn: gen_impls::generic_move_out_ck::<VMGenericVec>,
+        #[cfg(not(debug_assertions))]
+        move_out_fn: gen_impls::generic_move_out::<VMGenericVec>,
+        children_fn: gen_impls::generic_children::<VMGenericVec>,
+        drop_fn: gen_impls::generic_drop::<VMGenericVec>
+    }
+}
@@ -0,0 +1,71 @@
+use std::any::TypeId;
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+use xjbutil::mem::move_to_heap;
+use xjbutil::void::Void;
+
+use crate::data::Value;
+use crate::data::generic::GenericTypeVT;
+use crate::data::wrapper::Wrapper;
+use crate::data::traits::StaticBase;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
+
+/// Weak reference to a VM-managed object
+///
+/// Weak references are containers whose only type parameter is the type of the target. A `Weak`
+/// does not report its target as a child, so it does not keep the target alive. Once registered
+/// via `Alloc::add_weak_ref`, the target gets cleared to `null` by the collector when it becomes
+/// unreachable.
+pub struct Weak {
+    target: Cell<Value>
+}
+
+impl Weak {
+    pub fn new(target: Value) -> Self {
+        Self {
+            target: Cell::new(target)
+        }
+    }
+
+    /// Get the target of this weak reference, or `null` if it has been collected
+    #[inline(always)] pub fn get(&self) -> Value {
+        self.target.get()
+    }
+
+    #[inline(always)] pub(crate) fn clear(&self) {
+        self.target.set(Value::new_null());
+    }
+
+    pub fn into_value(self, vt: *const GenericTypeVT) -> Value {
+        Value::new_container(
+            move_to_heap(Wrapper::new_owned(self)).as_ptr() as *mut Wrapper<()>,
+            vt
+        )
+    }
+}
+
+impl StaticBase<Weak> for Void {
+    fn type_name() -> String { "weak".into() }
+}
+
+/// Create the vtable of weak references to objects of type `target_type`, used by `CREATE-WEAK`
+pub fn create_weak_vt(
+    tyck_info_pool: &mut TyckInfoPool,
+    target_type: NonNull<TyckInfo>
+) -> GenericTypeVT {
+    let tyck_info: NonNull<TyckInfo> =
+        tyck_info_pool.create_container_type(TypeId::of::<Weak>(), &[target_type]);
+
+    use crate::data::generic::gen_impls;
+    GenericTypeVT {
+        tyck_info: unsafe { tyck_info.as_ref().get_container_tyck_info_unchecked() },
+        type_name: "weak".to_string(),
+        #[cfg(debug_assertions)]
+        move_out_fn: gen_impls::generic_move_out_ck::<Weak>,
+        #[cfg(not(debug_assertions))]
+        move_out_fn: gen_impls::generic_move_out::<Weak>,
+        children_fn: gen_impls::generic_children::<Weak>,
+        drop_fn: gen_impls::generic_drop::<Weak>
+    }
+}
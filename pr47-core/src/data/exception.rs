@@ -1,15 +1,21 @@
 use std::any::TypeId;
-use std::fmt::{Debug, Formatter};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
 use std::marker::PhantomData;
 use std::ptr::NonNull;
+use std::sync::Arc;
 
 use tokio::task::JoinError;
+use unchecked_unwrap::UncheckedUnwrap;
+use xjbutil::unchecked::UnsafeFrom;
 use xjbutil::void::Void;
 
+use crate::builtins::exception::{as_exception_base, unchecked_into_builtin};
 use crate::data::Value;
 use crate::data::generic::GenericTypeVT;
 use crate::data::traits::{ChildrenType, StaticBase};
 use crate::data::tyck::{TyckInfo, TyckInfoPool, ContainerTyckInfo};
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
 
 pub enum UncheckedException {
     AlreadyAwaited { promise: Value },
@@ -23,10 +29,221 @@ pub enum UncheckedException {
     OverloadCallFailure { overload_table: usize },
     UnexpectedNull { value: Value },
     IndexOutOfBounds { indexed: Value, index: i64 },
+    ForeignPanic { message: String },
+    StackOverflow { frame_depth: usize, value_count: usize },
+    MethodNotFound { receiver: Value, method: String },
+    #[cfg(feature = "async")]
+    AwaitInCallback { promise: Value },
     #[cfg(feature = "async")]
     JoinError { inner: JoinError }
 }
 
+impl UncheckedException {
+    pub fn kind(&self) -> UncheckedExceptionKind {
+        match self {
+            UncheckedException::AlreadyAwaited { .. } => UncheckedExceptionKind::AlreadyAwaited,
+            UncheckedException::ArgCountMismatch { .. } => UncheckedExceptionKind::ArgCountMismatch,
+            UncheckedException::DivideByZero => UncheckedExceptionKind::DivideByZero,
+            UncheckedException::InvalidBinaryOp { .. } => UncheckedExceptionKind::InvalidBinaryOp,
+            UncheckedException::InvalidCastOp { .. } => UncheckedExceptionKind::InvalidCastOp,
+            UncheckedException::InvalidUnaryOp { .. } => UncheckedExceptionKind::InvalidUnaryOp,
+            UncheckedException::OwnershipCheckFailure { .. } =>
+                UncheckedExceptionKind::OwnershipCheckFailure,
+            UncheckedException::TypeCheckFailure { .. } => UncheckedExceptionKind::TypeCheckFailure,
+            UncheckedException::OverloadCallFailure { .. } =>
+                UncheckedExceptionKind::OverloadCallFailure,
+            UncheckedException::UnexpectedNull { .. } => UncheckedExceptionKind::UnexpectedNull,
+            UncheckedException::IndexOutOfBounds { .. } => UncheckedExceptionKind::IndexOutOfBounds,
+            UncheckedException::ForeignPanic { .. } => UncheckedExceptionKind::ForeignPanic,
+            UncheckedException::StackOverflow { .. } => UncheckedExceptionKind::StackOverflow,
+            UncheckedException::MethodNotFound { .. } => UncheckedExceptionKind::MethodNotFound,
+            #[cfg(feature = "async")]
+            UncheckedException::AwaitInCallback { .. } => UncheckedExceptionKind::AwaitInCallback,
+            #[cfg(feature = "async")]
+            UncheckedException::JoinError { .. } => UncheckedExceptionKind::JoinError
+        }
+    }
+}
+
+impl UncheckedException {
+    /// Describe this exception, including the types of referenced objects
+    ///
+    /// # Safety
+    /// All objects referenced by this exception must be alive.
+    pub unsafe fn describe(&self) -> String {
+        LiveUnchecked(self).to_string()
+    }
+
+    fn fmt_impl(&self, f: &mut Formatter<'_>, live: bool) -> std::fmt::Result {
+        let display = |value: &Value| DisplayValue { value: *value, live };
+        match self {
+            UncheckedException::AlreadyAwaited { promise } =>
+                write!(f, "promise {} has already been awaited", display(promise)),
+            UncheckedException::ArgCountMismatch { func_id, expected, got } =>
+                write!(f, "function #{} expects {} argument(s), got {}", func_id, expected, got),
+            UncheckedException::DivideByZero => write!(f, "division by zero"),
+            UncheckedException::InvalidBinaryOp { bin_op, lhs, rhs } =>
+                write!(f, "invalid operands for binary operator `{}`: {} and {}",
+                       bin_op, display(lhs), display(rhs)),
+            UncheckedException::InvalidCastOp { dest_type, src } =>
+                write!(f, "cannot cast {} to {}", display(src), dest_type),
+            UncheckedException::InvalidUnaryOp { unary_op, src } =>
+                write!(f, "invalid operand for unary operator `{}`: {}",
+                       unary_op, display(src)),
+            UncheckedException::OwnershipCheckFailure { object, expected_mask } =>
+                write!(f, "ownership check failed for {}, expected ownership mask {:#05b}",
+                       display(object), expected_mask),
+            UncheckedException::TypeCheckFailure { object, expected_type } =>
+                write!(f, "type check failed: expected {}, got {}", unsafe { expected_type.as_ref() }, display(object)),
+            UncheckedException::OverloadCallFailure { overload_table } =>
+                write!(f, "no candidate in overload table #{} accepts the arguments",
+                       overload_table),
+            UncheckedException::UnexpectedNull { .. } => write!(f, "unexpected null value"),
+            UncheckedException::IndexOutOfBounds { indexed, index } =>
+                write!(f, "index {} out of bounds for {}", index, display(indexed)),
+            UncheckedException::ForeignPanic { message } =>
+                write!(f, "foreign function panicked: {}", message),
+            UncheckedException::StackOverflow { frame_depth, value_count } =>
+                write!(f, "stack overflow with {} frames and {} value slots",
+                       frame_depth, value_count),
+            UncheckedException::MethodNotFound { receiver, method } =>
+                write!(f, "no method `{}` for {}", method, display(receiver)),
+            #[cfg(feature = "async")]
+            UncheckedException::AwaitInCallback { promise } =>
+                write!(f, "cannot await promise {} in a call from native code", display(promise)),
+            #[cfg(feature = "async")]
+            UncheckedException::JoinError { inner } =>
+                write!(f, "failed joining task: {}", inner)
+        }
+    }
+}
+
+/// Formats an `UncheckedException` without dereferencing referenced objects, which may have been
+/// reclaimed already. Use `describe` for a more detailed description while they are alive.
+impl Display for UncheckedException {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_impl(f, false)
+    }
+}
+
+struct LiveUnchecked<'a>(&'a UncheckedException);
+
+impl<'a> Display for LiveUnchecked<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt_impl(f, true)
+    }
+}
+
+impl Debug for UncheckedException {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}({})", self.kind(), self)
+    }
+}
+
+impl Error for UncheckedException {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "async")]
+            UncheckedException::JoinError { inner } => Some(inner),
+            _ => None
+        }
+    }
+}
+
+/// Prints a `Value` for diagnostics: value-typed data is printed as-is. References are printed
+/// with their type names if `live` is set, or as plain `object` otherwise, since the referenced
+/// object may have been reclaimed already.
+struct DisplayValue {
+    value: Value,
+    live: bool
+}
+
+impl Display for DisplayValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value: Value = self.value;
+        unsafe {
+            if value.is_null() {
+                write!(f, "null")
+            } else if value.is_value() {
+                match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
+                    ValueTypeTag::Int => write!(f, "int {}", value.vt_data.inner.int_value),
+                    ValueTypeTag::Float => write!(f, "float {}", value.vt_data.inner.float_value),
+                    ValueTypeTag::Char => write!(f, "char {:?}", value.vt_data.inner.char_value),
+                    ValueTypeTag::Bool => write!(f, "bool {}", value.vt_data.inner.bool_value)
+                }
+            } else if !self.live {
+                write!(f, "object")
+            } else if value.is_container() {
+                let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+                write!(f, "{} object", (*vt).type_name)
+            } else {
+                let type_name: String =
+                    value.get_as_dyn_base().as_ref().unchecked_unwrap().dyn_type_name();
+                write!(f, "{} object", type_name)
+            }
+        }
+    }
+}
+
+/// Discriminant of `UncheckedException`, without payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum UncheckedExceptionKind {
+    AlreadyAwaited,
+    ArgCountMismatch,
+    DivideByZero,
+    InvalidBinaryOp,
+    InvalidCastOp,
+    InvalidUnaryOp,
+    OwnershipCheckFailure,
+    TypeCheckFailure,
+    OverloadCallFailure,
+    UnexpectedNull,
+    IndexOutOfBounds,
+    ForeignPanic,
+    StackOverflow,
+    MethodNotFound,
+    #[cfg(feature = "async")]
+    AwaitInCallback,
+    #[cfg(feature = "async")]
+    JoinError
+}
+
+/// Set of `UncheckedExceptionKind`s which always propagate to the host, even if script code has
+/// handlers for their builtin exception types
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FatalExceptionSet {
+    bits: u32
+}
+
+impl FatalExceptionSet {
+    /// Create an empty set, making every catchable kind catchable
+    pub const fn new() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// Create a set containing every kind, which means no unchecked exception may be caught
+    pub const fn all() -> Self {
+        Self { bits: u32::MAX }
+    }
+
+    pub const fn with(self, kind: UncheckedExceptionKind) -> Self {
+        Self { bits: self.bits | (1 << kind as u8) }
+    }
+
+    pub fn insert(&mut self, kind: UncheckedExceptionKind) {
+        self.bits |= 1 << kind as u8;
+    }
+
+    pub fn remove(&mut self, kind: UncheckedExceptionKind) {
+        self.bits &= !(1 << kind as u8);
+    }
+
+    pub const fn contains(&self, kind: UncheckedExceptionKind) -> bool {
+        self.bits & (1 << kind as u8) != 0
+    }
+}
+
 pub type CheckedException = Value;
 
 pub enum ExceptionInner {
@@ -34,12 +251,45 @@ pub enum ExceptionInner {
     Checked(CheckedException)
 }
 
+impl ExceptionInner {
+    /// Describe this exception, including the types of referenced objects and the message of
+    /// checked exceptions
+    ///
+    /// # Safety
+    /// All objects referenced by this exception must be alive.
+    pub unsafe fn describe(&self) -> String {
+        match self {
+            ExceptionInner::Unchecked(unchecked) => unchecked.describe(),
+            ExceptionInner::Checked(checked) => {
+                let mut description: String =
+                    format!("uncaught {}", DisplayValue { value: *checked, live: true });
+                if let Some(exception_base) = as_exception_base(*checked) {
+                    description.push_str(": ");
+                    description.push_str(&exception_base.message());
+                }
+                description
+            }
+        }
+    }
+}
+
+/// Formats an `ExceptionInner` without dereferencing the checked exception object, which may have
+/// been reclaimed already. Use `describe` for a more detailed description while it is alive.
+impl Display for ExceptionInner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExceptionInner::Unchecked(unchecked) => write!(f, "{}", unchecked),
+            ExceptionInner::Checked(_) => write!(f, "uncaught exception object")
+        }
+    }
+}
+
 impl Debug for ExceptionInner {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // TODO should we provide prettier printing?
         match self {
-            ExceptionInner::Unchecked(_) => write!(f, "ExceptionInner::Unchecked"),
-            ExceptionInner::Checked(_) => write!(f, "ExceptionInner::Checked")
+            ExceptionInner::Unchecked(unchecked) =>
+                write!(f, "ExceptionInner::Unchecked({:?})", unchecked),
+            ExceptionInner::Checked(_) => write!(f, "ExceptionInner::Checked({})", self)
         }
     }
 }
@@ -47,32 +297,71 @@ impl Debug for ExceptionInner {
 #[derive(Clone, Copy)]
 pub struct StackTrace {
     pub func_id: usize,
-    pub insc_ptr: usize
+    pub insc_ptr: usize,
+    /// Count of frames omitted from the trace right after this one, either because the trace got
+    /// truncated or because they were replaced by tail calls
+    pub elided_frames: usize
 }
 
 impl StackTrace {
     pub fn new(func_id: usize, insc_ptr: usize) -> Self {
-        Self { func_id, insc_ptr }
+        Self { func_id, insc_ptr, elided_frames: 0 }
+    }
+}
+
+impl Display for StackTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at function #{}, instruction #{}", self.func_id, self.insc_ptr)?;
+        if self.elided_frames != 0 {
+            write!(f, "\n    ... {} frame(s) elided", self.elided_frames)?;
+        }
+        Ok(())
     }
 }
 
 pub struct Exception {
     pub inner: ExceptionInner,
-    trace: Vec<StackTrace>
+    /// Description of `inner` captured by `capture_description` when the exception leaves the
+    /// VM, since objects referenced by `inner` are not kept alive afterwards
+    description: Option<Box<str>>,
+    trace: Vec<StackTrace>,
+    message: Option<String>,
+    cause: Option<Arc<Exception>>
 }
 
 impl Exception {
     #[inline(never)] pub fn checked_exc(checked: CheckedException) -> Self {
         Self {
             inner: ExceptionInner::Checked(checked),
-            trace: vec![]
+            description: None,
+            trace: vec![],
+            message: None,
+            cause: None
         }
     }
 
     #[inline(never)] pub fn unchecked_exc(unchecked: UncheckedException) -> Self {
         Self {
             inner: ExceptionInner::Unchecked(unchecked),
-            trace: vec![]
+            description: None,
+            trace: vec![],
+            message: None,
+            cause: None
+        }
+    }
+
+    /// Describe this exception and its causes in detail, so that the description outlives the
+    /// objects they reference. Called when the exception leaves the VM. Without a captured
+    /// description, `Display` does not look into referenced objects.
+    ///
+    /// # Safety
+    /// All objects referenced by this exception and its causes must be alive.
+    pub unsafe fn capture_description(&mut self) {
+        if self.description.is_none() {
+            self.description = Some(self.inner.describe().into_boxed_str());
+        }
+        if let Some(cause) = self.cause.as_mut().and_then(Arc::get_mut) {
+            cause.capture_description();
         }
     }
 
@@ -80,6 +369,107 @@ impl Exception {
         self.trace.push(StackTrace::new(func_id, insc_ptr))
     }
 
+    /// Record that `count` frames after the last stack trace entry have been omitted
+    pub fn elide_stack_trace(&mut self, count: usize) {
+        if let Some(last) = self.trace.last_mut() {
+            last.elided_frames += count;
+        }
+    }
+
+    pub fn trace(&self) -> &[StackTrace] {
+        &self.trace
+    }
+
+    /// Attach a message describing the context in which this exception happened
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Attach the exception which caused this exception
+    pub fn with_cause(mut self, cause: Exception) -> Self {
+        self.cause = Some(Arc::new(cause));
+        self
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn cause(&self) -> Option<&Exception> {
+        self.cause.as_deref()
+    }
+
+    /// Iterate over the cause chain of this exception, starting from its direct cause
+    pub fn causes(&self) -> impl Iterator<Item = &Exception> {
+        std::iter::successors(self.cause(), |exception: &&Exception| Exception::cause(exception))
+    }
+
+    /// Copy a checked exception together with the stack trace collected so far, so that it can be
+    /// raised again. Unchecked exceptions cannot be copied.
+    pub fn clone_checked(&self) -> Option<Self> {
+        match &self.inner {
+            ExceptionInner::Checked(checked) => Some(Self {
+                inner: ExceptionInner::Checked(*checked),
+                description: self.description.clone(),
+                trace: self.trace.clone(),
+                message: self.message.clone(),
+                cause: self.cause.clone()
+            }),
+            ExceptionInner::Unchecked(_) => None
+        }
+    }
+
+    /// Take the exception to raise again from a caught exception. Checked exceptions are copied,
+    /// while unchecked exceptions are moved out, leaving a `null` checked exception behind.
+    /// Returns `None` if there is nothing left to raise.
+    pub fn take_for_rethrow(&mut self) -> Option<Self> {
+        match self.inner {
+            ExceptionInner::Checked(checked) if checked.is_null() => None,
+            ExceptionInner::Checked(_) => self.clone_checked(),
+            ExceptionInner::Unchecked(_) => Some(std::mem::replace(self, Self {
+                inner: ExceptionInner::Checked(Value::new_null()),
+                description: self.description.clone(),
+                trace: vec![],
+                message: None,
+                cause: None
+            }))
+        }
+    }
+
+    /// Convert the unchecked exception carried by this exception into an object of its builtin
+    /// exception type, keeping stack trace, message and cause. The cause is also recorded in the
+    /// created object if it is a checked exception. Returns the created object, which is not
+    /// managed by any allocator yet, or `None` if this exception is already checked.
+    pub fn convert_to_builtin(&mut self) -> Option<Value> {
+        let placeholder: ExceptionInner = ExceptionInner::Checked(Value::new_null());
+        let unchecked: UncheckedException = match std::mem::replace(&mut self.inner, placeholder) {
+            ExceptionInner::Unchecked(unchecked) => unchecked,
+            checked => {
+                self.inner = checked;
+                return None;
+            }
+        };
+        let cause: Value = match self.cause().map(|cause: &Exception| &cause.inner) {
+            Some(ExceptionInner::Checked(cause)) => *cause,
+            _ => Value::new_null()
+        };
+        let value: Value = unchecked_into_builtin(unchecked, cause);
+        self.inner = ExceptionInner::Checked(value);
+        self.description = None;
+        Some(value)
+    }
+
+    /// Iterate over the checked exception objects carried by this exception and its cause chain
+    pub fn checked_values(&self) -> impl Iterator<Item = CheckedException> + '_ {
+        std::iter::once(self).chain(self.causes()).filter_map(|exception: &Exception| {
+            match exception.inner {
+                ExceptionInner::Checked(checked) if !checked.is_null() => Some(checked),
+                _ => None
+            }
+        })
+    }
+
     #[cfg(test)]
     pub fn assert_checked(&self) -> CheckedException {
         match &self.inner {
@@ -89,16 +479,48 @@ impl Exception {
     }
 }
 
+impl Display for Exception {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(message) = &self.message {
+            write!(f, "{}: ", message)?;
+        }
+        match &self.description {
+            Some(description) => write!(f, "{}", description)?,
+            None => write!(f, "{}", self.inner)?
+        }
+        for trace in self.trace.iter() {
+            write!(f, "\n    {}", trace)?;
+        }
+        if let Some(cause) = &self.cause {
+            write!(f, "\ncaused by: {}", cause)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Exception {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Exception")
+            .field("inner", &self.inner)
+            .field("message", &self.message)
+            .field("cause", &self.cause)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Error for Exception {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.cause.as_deref().map(|cause: &Exception| cause as &(dyn Error + 'static))
+    }
+}
+
 impl StaticBase<Exception> for Void {
     fn type_name() -> String { "Exception".into() }
 
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     fn children(vself: *const Exception) -> ChildrenType {
-        match unsafe { &(*vself).inner } {
-            ExceptionInner::Unchecked(_) => None,
-            ExceptionInner::Checked(checked) => {
-                Some(Box::new(std::iter::once(*checked)))
-            }
-        }
+        let checked: Vec<Value> = unsafe { (*vself).checked_values().collect() };
+        Some(Box::new(checked.into_iter()))
     }
 }
 
@@ -141,14 +563,11 @@ impl<E> StaticBase<ExceptionContainer<E>> for Void
         format!("ExcContainer<{}>", <Void as StaticBase<E>>::type_name())
     }
 
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     fn children(vself: *const ExceptionContainer<E>) -> ChildrenType {
         let r: &ExceptionContainer<E> = unsafe { &*vself };
-        match r.exception.inner {
-            ExceptionInner::Unchecked(_) => None,
-            ExceptionInner::Checked(checked) => {
-                Some(Box::new(std::iter::once(checked)))
-            }
-        }
+        let checked: Vec<Value> = r.exception.checked_values().collect();
+        Some(Box::new(checked.into_iter()))
     }
 }
 
@@ -321,3 +321,166 @@ impl StaticBase<TestStruct2> for Void {
 
     // TODO deallocate memory here
 }
+
+#[test] fn test_exception_display() {
+    use std::error::Error;
+
+    use crate::builtins::exception::ScriptError;
+    use crate::data::exception::{Exception, UncheckedException};
+
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let string_type: NonNull<TyckInfo> = tyck_info_pool.get_string_type();
+    let nullable_string: NonNull<TyckInfo> = tyck_info_pool.create_nullable_type(string_type);
+
+    let unchecked: UncheckedException = UncheckedException::TypeCheckFailure {
+        object: Value::new_int(3),
+        expected_type: nullable_string
+    };
+    assert_eq!(unchecked.to_string(), "type check failed: expected string?, got int 3");
+    assert_eq!(
+        unsafe { unchecked.describe() },
+        "type check failed: expected string?, got int 3"
+    );
+
+    let unchecked: UncheckedException = UncheckedException::IndexOutOfBounds {
+        indexed: Value::new_owned(String::from("abc")),
+        index: 5
+    };
+    assert_eq!(unchecked.to_string(), "index 5 out of bounds for object");
+    assert_eq!(unsafe { unchecked.describe() }, "index 5 out of bounds for string object");
+
+    let error: Value = Value::new_owned(ScriptError::new("file not found", None));
+    let cause: Exception = Exception::checked_exc(error);
+    let mut exception: Exception = Exception::unchecked_exc(UncheckedException::DivideByZero)
+        .with_message("computing average")
+        .with_cause(cause);
+    exception.push_stack_trace(1, 4);
+
+    assert_eq!(exception.message(), Some("computing average"));
+    assert_eq!(exception.causes().count(), 1);
+    assert!(exception.source().is_some());
+    // referenced objects are not looked into until the description gets captured
+    assert_eq!(
+        exception.to_string(),
+        "computing average: division by zero\n    at function #1, instruction #4\n\
+         caused by: uncaught exception object"
+    );
+    unsafe { exception.capture_description(); }
+    assert_eq!(
+        exception.to_string(),
+        "computing average: division by zero\n    at function #1, instruction #4\n\
+         caused by: uncaught Error object: file not found"
+    );
+
+    let children: Vec<Value> =
+        <Void as StaticBase<Exception>>::children(&exception as *const _).unwrap().collect();
+    assert_eq!(children.len(), 1);
+    assert_eq!(
+        unsafe { children[0].ptr_repr.ptr as *mut u8 },
+        unsafe { error.ptr_repr.ptr as *mut u8 }
+    );
+
+    let mut exception: Exception = Exception::unchecked_exc(UncheckedException::IndexOutOfBounds {
+        indexed: error,
+        index: 5
+    });
+    unsafe {
+        exception.capture_description();
+        drop(Box::from_raw(error.get_as_dyn_base()));
+    }
+    assert_eq!(exception.to_string(), "index 5 out of bounds for Error object");
+}
+
+#[test] fn test_builtin_exception_cause() {
+    use crate::builtins::exception::{
+        DivideByZeroError,
+        ExceptionBase,
+        ScriptError,
+        as_exception_base
+    };
+    use crate::data::exception::{Exception, UncheckedException};
+
+    let error: Value = Value::new_owned(ScriptError::new("file not found", None));
+    let mut exception: Exception = Exception::unchecked_exc(UncheckedException::DivideByZero)
+        .with_cause(Exception::checked_exc(error));
+    exception.push_stack_trace(1, 4);
+
+    let builtin: Value = exception.convert_to_builtin().unwrap();
+    assert!(exception.convert_to_builtin().is_none());
+    let checked: Value = exception.assert_checked();
+    assert_eq!(exception.trace().len(), 1);
+    unsafe { exception.capture_description(); }
+    assert_eq!(
+        exception.to_string(),
+        "uncaught DivideByZeroError object: division by zero\n    at function #1, instruction #4\n\
+         caused by: uncaught Error object: file not found"
+    );
+
+    unsafe {
+        assert_eq!(checked.ptr_repr.ptr as *mut u8, builtin.ptr_repr.ptr as *mut u8);
+        let base: &dyn ExceptionBase = as_exception_base(builtin).unwrap();
+        assert_eq!(base.message(), "division by zero");
+        assert_eq!(base.cause().unwrap().ptr_repr.ptr as *mut u8, error.ptr_repr.ptr as *mut u8);
+
+        let children: Vec<Value> = <Void as StaticBase<DivideByZeroError>>::children(
+            builtin.get_as_mut_ptr_norm() as *const DivideByZeroError
+        ).unwrap().collect();
+        assert!(children.iter().any(|child: &Value|
+            child.ptr_repr.ptr as *mut u8 == error.ptr_repr.ptr as *mut u8
+        ));
+    }
+}
+
+#[test] fn test_builtin_exception_binds() {
+    use crate::builtins::exception::{
+        ScriptError,
+        ScriptErrorCauseBind,
+        ScriptErrorMessageBind,
+        unchecked_into_builtin
+    };
+    use crate::data::exception::{Exception, UncheckedException};
+    use crate::ffi::FFIException;
+    use crate::ffi::sync_fn::{FunctionBase, VMContext};
+    use crate::vm::al31f::alloc::Finalizer;
+    use crate::vm::al31f::handle::RootedValue;
+
+    struct TestContext(Vec<Value>);
+
+    impl VMContext for TestContext {
+        fn add_heap_managed(&mut self, value: Value) { self.0.push(value) }
+        fn mark(&mut self, _value: Value) {}
+        fn root(&mut self, _value: Value) -> RootedValue { unreachable!() }
+        fn add_finalizer(&mut self, _value: Value, _finalizer: Finalizer) { unreachable!() }
+        fn call_value(&mut self, _callee: Value, _args: &[Value])
+            -> Result<Vec<RootedValue>, Exception>
+        {
+            unreachable!()
+        }
+    }
+
+    let error: Value = Value::new_owned(ScriptError::new("file not found", None));
+    let builtin: Value = unchecked_into_builtin(UncheckedException::DivideByZero, error);
+    let mut context: TestContext = TestContext(vec![]);
+    let mut ret: Value = Value::new_null();
+
+    unsafe {
+        // builtin exceptions answer `message` and `cause` like `ScriptError`s
+        ScriptErrorMessageBind::call_rtlc(&mut context, &[builtin], &[&mut ret]).unwrap();
+        assert_eq!(&*(ret.get_as_mut_ptr_norm() as *const String), "division by zero");
+        ScriptErrorCauseBind::call_rtlc(&mut context, &[builtin], &[&mut ret]).unwrap();
+        assert_eq!(ret.ptr_repr.ptr as *mut u8, error.ptr_repr.ptr as *mut u8);
+        ScriptErrorCauseBind::call_rtlc(&mut context, &[error], &[&mut ret]).unwrap();
+        assert!(ret.is_null());
+
+        let result: Result<(), FFIException> =
+            ScriptErrorMessageBind::call_rtlc(&mut context, &[Value::new_int(3)], &[&mut ret]);
+        assert!(matches!(
+            result,
+            Err(FFIException::Unchecked(UncheckedException::InvalidCastOp { .. }))
+        ));
+
+        for value in context.0.into_iter().chain([builtin, error]) {
+            drop(Box::from_raw(value.get_as_dyn_base()));
+        }
+    }
+}
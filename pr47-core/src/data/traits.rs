@@ -2,7 +2,9 @@ use std::any::TypeId;
 use std::iter::Iterator;
 use std::ptr::NonNull;
 
-use xjbutil::void::Void;
+/// Type implementing `StaticBase` for host types, re-exported for code generated by
+/// `#[derive(Pr47Type)]`
+pub use xjbutil::void::Void;
 
 use crate::data::Value;
 use crate::data::tyck::{TyckInfo, TyckInfoPool};
@@ -31,6 +33,10 @@ pub trait StaticBase<T: 'static> {
     }
 
     #[inline] fn children(_vself: *const T) -> ChildrenType { None }
+
+    /// Read the field `name` exposed to script code, used by the `HOST-FIELD-GET` instruction.
+    /// Returns `None` if there's no such field.
+    #[inline] fn get_field(_vself: *const T, _name: &str) -> Option<Value> { None }
 }
 
 // impl !StaticBase<i64> for Void {}
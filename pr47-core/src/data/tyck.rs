@@ -1,6 +1,7 @@
 use std::any::TypeId;
 use std::collections::HashSet;
 use std::cmp::{Eq, PartialEq};
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::hint::unreachable_unchecked;
 use std::mem::{discriminant, forget};
@@ -18,10 +19,60 @@ pub struct ContainerTyckInfo {
 
 pub struct FunctionTyckInfo {
     pub params: NonNull<[NonNull<TyckInfo>]>,
+    /// Count of trailing `params` which may be omitted by callers. Callees see fewer arguments
+    /// and apply defaults themselves.
+    pub optional_params: usize,
+    /// Element type of the variadic tail following `params`, if any
+    pub variadic: Option<NonNull<TyckInfo>>,
     pub rets: NonNull<[NonNull<TyckInfo>]>,
+    /// Element type of the variadic tail following `rets`, if any. Functions with such a tail
+    /// write as many return values as the caller asks for.
+    pub variadic_rets: Option<NonNull<TyckInfo>>,
     pub exceptions: NonNull<[NonNull<TyckInfo>]>
 }
 
+impl FunctionTyckInfo {
+    /// Count of parameters callers must always provide
+    pub fn required_params(&self) -> usize {
+        unsafe { self.params.as_ref().len() - self.optional_params }
+    }
+
+    /// Whether the function can be called with `arg_count` arguments
+    pub fn accepts_arg_count(&self, arg_count: usize) -> bool {
+        arg_count >= self.required_params()
+            && (self.variadic.is_some() || arg_count <= unsafe { self.params.as_ref().len() })
+    }
+
+    /// Type of the parameter receiving the `idx`-th argument, taking the variadic tail into
+    /// account
+    pub fn param_type(&self, idx: usize) -> Option<NonNull<TyckInfo>> {
+        unsafe { self.params.as_ref() }.get(idx).copied().or(self.variadic)
+    }
+
+    /// Whether the function can be called with `ret_count` return values expected
+    pub fn accepts_ret_count(&self, ret_count: usize) -> bool {
+        let rets: usize = unsafe { self.rets.as_ref().len() };
+        if self.variadic_rets.is_some() {
+            ret_count >= rets
+        } else {
+            ret_count == rets
+        }
+    }
+
+    /// Type of the `idx`-th return value, taking the variadic tail into account
+    pub fn ret_type(&self, idx: usize) -> Option<NonNull<TyckInfo>> {
+        unsafe { self.rets.as_ref() }.get(idx).copied().or(self.variadic_rets)
+    }
+}
+
+fn tail_eq(tail: Option<NonNull<TyckInfo>>, other_tail: Option<NonNull<TyckInfo>>) -> bool {
+    match (tail, other_tail) {
+        (Some(tail), Some(other_tail)) => unsafe { tail.as_ref().structural_eq(other_tail.as_ref()) },
+        (None, None) => true,
+        _ => false
+    }
+}
+
 pub enum TyckInfo {
     AnyType,
     Plain(TypeId),
@@ -46,6 +97,149 @@ impl TyckInfo {
             unreachable_unchecked()
         }
     }
+
+    /// Compare `self` and `other` by structure. Unlike `==`, which relies on interning and only
+    /// works for types from the same `TyckInfoPool`, this also works for types from different
+    /// pools.
+    pub fn structural_eq(&self, other: &TyckInfo) -> bool {
+        fn list_eq(lhs: NonNull<[NonNull<TyckInfo>]>, rhs: NonNull<[NonNull<TyckInfo>]>) -> bool {
+            let (lhs, rhs) = unsafe { (lhs.as_ref(), rhs.as_ref()) };
+            lhs.len() == rhs.len() && lhs.iter().zip(rhs.iter()).all(
+                |(t1, t2): (&NonNull<TyckInfo>, &NonNull<TyckInfo>)| unsafe {
+                    t1.as_ref().structural_eq(t2.as_ref())
+                }
+            )
+        }
+
+        match (self, other) {
+            (TyckInfo::AnyType, TyckInfo::AnyType) => true,
+            (TyckInfo::Plain(type_id), TyckInfo::Plain(other_type_id)) =>
+                type_id == other_type_id,
+            (TyckInfo::Nullable(underlying), TyckInfo::Nullable(other_underlying)) => unsafe {
+                underlying.as_ref().structural_eq(other_underlying.as_ref())
+            },
+            (TyckInfo::Container(container), TyckInfo::Container(other_container)) =>
+                container.type_id == other_container.type_id
+                    && list_eq(container.params, other_container.params),
+            (TyckInfo::Function(function), TyckInfo::Function(other_function)) =>
+                list_eq(function.params, other_function.params)
+                    && function.optional_params == other_function.optional_params
+                    && tail_eq(function.variadic, other_function.variadic)
+                    && list_eq(function.rets, other_function.rets)
+                    && tail_eq(function.variadic_rets, other_function.variadic_rets)
+                    && list_eq(function.exceptions, other_function.exceptions),
+            _ => false
+        }
+    }
+}
+
+/// Get the name of well-known types, used when printing `TyckInfo`s
+fn well_known_type_name(type_id: TypeId) -> Option<&'static str> {
+    if type_id == TypeId::of::<i64>() {
+        Some("int")
+    } else if type_id == TypeId::of::<f64>() {
+        Some("float")
+    } else if type_id == TypeId::of::<char>() {
+        Some("char")
+    } else if type_id == TypeId::of::<bool>() {
+        Some("bool")
+    } else if type_id == TypeId::of::<String>() {
+        Some("string")
+    } else if type_id == TypeId::of::<Object>() {
+        Some("object")
+    } else if is_promise_type(type_id) {
+        Some("promise")
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "async")]
+fn is_promise_type(type_id: TypeId) -> bool {
+    type_id == TypeId::of::<crate::ffi::async_fn::PromiseType>()
+}
+
+#[cfg(not(feature = "async"))]
+fn is_promise_type(_type_id: TypeId) -> bool {
+    false
+}
+
+fn fmt_type_id(type_id: TypeId, f: &mut Formatter<'_>) -> std::fmt::Result {
+    if let Some(type_name) = well_known_type_name(type_id) {
+        write!(f, "{}", type_name)
+    } else {
+        write!(f, "{:?}", type_id)
+    }
+}
+
+fn fmt_type_list(types: NonNull<[NonNull<TyckInfo>]>, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let types: &[NonNull<TyckInfo>] = unsafe { types.as_ref() };
+    for (i, tyck_info) /*: (usize, &NonNull<TyckInfo>)*/ in types.iter().enumerate() {
+        if i != 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", unsafe { tyck_info.as_ref() })?;
+    }
+    Ok(())
+}
+
+impl Display for TyckInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TyckInfo::AnyType => write!(f, "any"),
+            TyckInfo::Plain(type_id) => fmt_type_id(*type_id, f),
+            TyckInfo::Nullable(underlying) => write!(f, "{}?", unsafe { underlying.as_ref() }),
+            TyckInfo::Container(ContainerTyckInfo { type_id, params }) => {
+                fmt_type_id(*type_id, f)?;
+                write!(f, "<")?;
+                fmt_type_list(*params, f)?;
+                write!(f, ">")
+            },
+            TyckInfo::Function(FunctionTyckInfo {
+                params,
+                optional_params,
+                variadic,
+                rets,
+                variadic_rets,
+                exceptions
+            }) => {
+                write!(f, "fn(")?;
+                let params: &[NonNull<TyckInfo>] = unsafe { params.as_ref() };
+                let required_params: usize = params.len() - optional_params;
+                for (i, param) /*: (usize, &NonNull<TyckInfo>)*/ in params.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    if i < required_params {
+                        write!(f, "{}", unsafe { param.as_ref() })?;
+                    } else {
+                        write!(f, "[{}]", unsafe { param.as_ref() })?;
+                    }
+                }
+                if let Some(variadic) = variadic {
+                    if !params.is_empty() {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "...{}", unsafe { variadic.as_ref() })?;
+                }
+                write!(f, ") -> (")?;
+                fmt_type_list(*rets, f)?;
+                if let Some(variadic_rets) = variadic_rets {
+                    if unsafe { !rets.as_ref().is_empty() } {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "...{}", unsafe { variadic_rets.as_ref() })?;
+                }
+                write!(f, ")")?;
+                if unsafe { !exceptions.as_ref().is_empty() } {
+                    write!(f, " throws (")?;
+                    fmt_type_list(*exceptions, f)?;
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl Drop for TyckInfo {
@@ -55,7 +249,7 @@ impl Drop for TyckInfo {
                 let boxed: Box<[NonNull<TyckInfo>]> = unsafe { Box::reclaim(*params) };
                 drop(boxed);
             },
-            TyckInfo::Function(FunctionTyckInfo { params, rets, exceptions }) => {
+            TyckInfo::Function(FunctionTyckInfo { params, rets, exceptions, .. }) => {
                 let boxed: Box<[NonNull<TyckInfo>]> = unsafe { Box::reclaim(*params) };
                 drop(boxed);
                 let boxed: Box<[NonNull<TyckInfo>]> = unsafe { Box::reclaim(*rets) };
@@ -90,17 +284,27 @@ impl Hash for TyckInfo {
                     state.write_usize(param.as_ptr() as usize);
                 }
             },
-            TyckInfo::Function(FunctionTyckInfo { params, rets, exceptions }) => {
+            TyckInfo::Function(FunctionTyckInfo {
+                params,
+                optional_params,
+                variadic,
+                rets,
+                variadic_rets,
+                exceptions
+            }) => {
                 discriminant(self).hash(state);
                 let params: &[NonNull<TyckInfo>] = unsafe { params.as_ref() };
                 for param /*: &NonNull<TyckInfo>*/ in params.iter() {
                     state.write_usize(param.as_ptr() as usize);
                 }
+                state.write_usize(*optional_params);
+                variadic.map(NonNull::as_ptr).hash(state);
 
                 let rets: &[NonNull<TyckInfo>] = unsafe { rets.as_ref() };
                 for ret /*: &NonNull<TyckInfo>*/ in rets.iter() {
                     state.write_usize(ret.as_ptr() as usize);
                 }
+                variadic_rets.map(NonNull::as_ptr).hash(state);
 
                 let exceptions: &[NonNull<TyckInfo>] = unsafe { exceptions.as_ref() };
                 for exception /*: &NonNull<TyckInfo>*/ in exceptions.iter() {
@@ -138,6 +342,7 @@ impl PartialEq for TyckInfo {
                         other_container_tyck_info.params.as_ref()
                     };
                     *type_id == other_container_tyck_info.type_id
+                    && self_params.len() == other_params.len()
                     && self_params.iter().zip(other_params.iter()).all(
                         |(p1, p2): (&NonNull<TyckInfo>, &NonNull<TyckInfo>)| {
                             p1.as_ptr() == p2.as_ptr()
@@ -147,7 +352,14 @@ impl PartialEq for TyckInfo {
                     false
                 }
             },
-            TyckInfo::Function(FunctionTyckInfo { params, rets, exceptions }) => {
+            TyckInfo::Function(FunctionTyckInfo {
+                params,
+                optional_params,
+                variadic,
+                rets,
+                variadic_rets,
+                exceptions
+            }) => {
                 let self_params: &[NonNull<TyckInfo>] = unsafe { params.as_ref() };
                 let self_rets: &[NonNull<TyckInfo>] = unsafe { rets.as_ref() };
                 let self_exceptions: &[NonNull<TyckInfo>] = unsafe { exceptions.as_ref() };
@@ -161,16 +373,24 @@ impl PartialEq for TyckInfo {
                     let other_exceptions: &[NonNull<TyckInfo>] = unsafe {
                         other_function_tyck_info.exceptions.as_ref()
                     };
-                    self_params.iter().zip(other_params.iter()).all(
+                    self_params.len() == other_params.len()
+                    && self_rets.len() == other_rets.len()
+                    && self_exceptions.len() == other_exceptions.len()
+                    && self_params.iter().zip(other_params.iter()).all(
                         |(p1, p2): (&NonNull<TyckInfo>, &NonNull<TyckInfo>)| {
                             p1.as_ptr() == p2.as_ptr()
                         }
                     )
+                    && *optional_params == other_function_tyck_info.optional_params
+                    && variadic.map(NonNull::as_ptr)
+                        == other_function_tyck_info.variadic.map(NonNull::as_ptr)
                     && self_rets.iter().zip(other_rets.iter()).all(
                         |(r1, r2): (&NonNull<TyckInfo>, &NonNull<TyckInfo>)| {
                             r1.as_ptr() == r2.as_ptr()
                         }
                     )
+                    && variadic_rets.map(NonNull::as_ptr)
+                        == other_function_tyck_info.variadic_rets.map(NonNull::as_ptr)
                     && self_exceptions.iter().zip(other_exceptions.iter()).all(
                         |(e1, e2): (&NonNull<TyckInfo>, &NonNull<TyckInfo>)| {
                             e1.as_ptr() == e2.as_ptr()
@@ -283,9 +503,40 @@ impl TyckInfoPool {
         rets: &[NonNull<TyckInfo>],
         exceptions: &[NonNull<TyckInfo>]
     ) -> NonNull<TyckInfo> {
+        self.create_function_type_with_tail(params, 0, None, rets, exceptions)
+    }
+
+    /// Create a function type whose last `optional_params` parameters may be omitted, and which
+    /// accepts any count of `variadic` typed arguments after `params` if `variadic` is given
+    pub fn create_function_type_with_tail(
+        &mut self,
+        params: &[NonNull<TyckInfo>],
+        optional_params: usize,
+        variadic: Option<NonNull<TyckInfo>>,
+        rets: &[NonNull<TyckInfo>],
+        exceptions: &[NonNull<TyckInfo>]
+    ) -> NonNull<TyckInfo> {
+        self.create_function_type_with_tails(params, optional_params, variadic, rets, None, exceptions)
+    }
+
+    /// Same as `create_function_type_with_tail`, for functions which also write any count of
+    /// `variadic_rets` typed return values after `rets` if `variadic_rets` is given
+    pub fn create_function_type_with_tails(
+        &mut self,
+        params: &[NonNull<TyckInfo>],
+        optional_params: usize,
+        variadic: Option<NonNull<TyckInfo>>,
+        rets: &[NonNull<TyckInfo>],
+        variadic_rets: Option<NonNull<TyckInfo>>,
+        exceptions: &[NonNull<TyckInfo>]
+    ) -> NonNull<TyckInfo> {
+        assert!(optional_params <= params.len(), "more optional parameters than parameters");
         let query_tyck_info: TyckInfo = TyckInfo::Function(FunctionTyckInfo {
             params: unsafe { NonNull::new_unchecked(params as *const _ as *mut _) },
+            optional_params,
+            variadic,
             rets: unsafe { NonNull::new_unchecked(rets as *const _ as *mut _) },
+            variadic_rets,
             exceptions: unsafe { NonNull::new_unchecked(exceptions as *const _ as *mut _) }
         });
 
@@ -295,7 +546,10 @@ impl TyckInfoPool {
             } else {
                 let tyck_info: TyckInfo = TyckInfo::Function(FunctionTyckInfo {
                     params: Vec::from(params).into_slice_ptr(),
+                    optional_params,
+                    variadic,
                     rets: Vec::from(rets).into_slice_ptr(),
+                    variadic_rets,
                     exceptions: Vec::from(exceptions).into_slice_ptr()
                 });
                 let tyck_info: Korobka<TyckInfo> = Korobka::new(tyck_info);
@@ -308,14 +562,67 @@ impl TyckInfoPool {
         ret
     }
 
+    /// Re-create `tyck_info`, which may come from another pool, in this pool
+    pub fn import_type(&mut self, tyck_info: &TyckInfo) -> NonNull<TyckInfo> {
+        fn import_list(
+            pool: &mut TyckInfoPool,
+            types: NonNull<[NonNull<TyckInfo>]>
+        ) -> Vec<NonNull<TyckInfo>> {
+            unsafe { types.as_ref() }.iter()
+                .map(|tyck_info: &NonNull<TyckInfo>| pool.import_type(unsafe { tyck_info.as_ref() }))
+                .collect()
+        }
+
+        match tyck_info {
+            TyckInfo::AnyType => self.any_type,
+            TyckInfo::Plain(type_id) => self.create_plain_type(*type_id),
+            TyckInfo::Nullable(base) => {
+                let base: NonNull<TyckInfo> = self.import_type(unsafe { base.as_ref() });
+                self.create_nullable_type(base)
+            },
+            TyckInfo::Container(ContainerTyckInfo { type_id, params }) => {
+                let params: Vec<NonNull<TyckInfo>> = import_list(self, *params);
+                self.create_container_type(*type_id, &params)
+            },
+            TyckInfo::Function(FunctionTyckInfo {
+                params,
+                optional_params,
+                variadic,
+                rets,
+                variadic_rets,
+                exceptions
+            }) => {
+                let params: Vec<NonNull<TyckInfo>> = import_list(self, *params);
+                let variadic: Option<NonNull<TyckInfo>> = variadic.map(
+                    |variadic: NonNull<TyckInfo>| self.import_type(unsafe { variadic.as_ref() })
+                );
+                let rets: Vec<NonNull<TyckInfo>> = import_list(self, *rets);
+                let variadic_rets: Option<NonNull<TyckInfo>> = variadic_rets.map(
+                    |variadic_rets: NonNull<TyckInfo>| {
+                        self.import_type(unsafe { variadic_rets.as_ref() })
+                    }
+                );
+                let exceptions: Vec<NonNull<TyckInfo>> = import_list(self, *exceptions);
+                self.create_function_type_with_tails(
+                    &params,
+                    *optional_params,
+                    variadic,
+                    &rets,
+                    variadic_rets,
+                    &exceptions
+                )
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test_tyck_info_pool {
     use std::any::TypeId;
+    use std::mem::ManuallyDrop;
     use std::ptr::NonNull;
 
-    use crate::data::tyck::{TyckInfo, TyckInfoPool};
+    use crate::data::tyck::{FunctionTyckInfo, TyckInfo, TyckInfoPool};
 
     struct TestType1();
     struct TestType2();
@@ -396,4 +703,110 @@ mod test_tyck_info_pool {
         assert_ne!(tyck_info7, tyck_info9);
         assert_ne!(tyck_info8, tyck_info9);
     }
+
+    #[test]
+    fn test_function_type_with_tail() {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+
+        let fixed: NonNull<TyckInfo> =
+            tyck_info_pool.create_function_type(&[int_type, int_type], &[], &[]);
+        let optional: NonNull<TyckInfo> = tyck_info_pool.create_function_type_with_tail(
+            &[int_type, int_type], 1, None, &[], &[]
+        );
+        let variadic: NonNull<TyckInfo> = tyck_info_pool.create_function_type_with_tail(
+            &[int_type, int_type], 1, Some(any_type), &[], &[]
+        );
+        let variadic_1: NonNull<TyckInfo> = tyck_info_pool.create_function_type_with_tail(
+            &[int_type, int_type], 1, Some(any_type), &[], &[]
+        );
+        assert_ne!(fixed, optional);
+        assert_ne!(optional, variadic);
+        assert_eq!(variadic, variadic_1);
+
+        unsafe {
+            assert_eq!(optional.as_ref().to_string(), "fn(int, [int]) -> ()");
+            assert_eq!(variadic.as_ref().to_string(), "fn(int, [int], ...any) -> ()");
+
+            let optional_info = optional.as_ref().get_function_tyck_info_unchecked().as_ref();
+            assert!(!optional_info.accepts_arg_count(0));
+            assert!(optional_info.accepts_arg_count(1));
+            assert!(optional_info.accepts_arg_count(2));
+            assert!(!optional_info.accepts_arg_count(3));
+            assert_eq!(optional_info.param_type(2), None);
+
+            let variadic_info = variadic.as_ref().get_function_tyck_info_unchecked().as_ref();
+            assert!(variadic_info.accepts_arg_count(5));
+            assert_eq!(variadic_info.param_type(1), Some(int_type));
+            assert_eq!(variadic_info.param_type(4), Some(any_type));
+        }
+
+        let mut other_pool: TyckInfoPool = TyckInfoPool::new();
+        let imported: NonNull<TyckInfo> = other_pool.import_type(unsafe { variadic.as_ref() });
+        unsafe {
+            assert!(imported.as_ref().structural_eq(variadic.as_ref()));
+            assert!(!imported.as_ref().structural_eq(optional.as_ref()));
+        }
+        assert_eq!(imported, other_pool.import_type(unsafe { variadic.as_ref() }));
+    }
+
+    #[test]
+    fn test_function_type_with_variadic_rets() {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+
+        let fixed: NonNull<TyckInfo> = tyck_info_pool.create_function_type(&[], &[int_type], &[]);
+        let variadic_rets: NonNull<TyckInfo> = tyck_info_pool.create_function_type_with_tails(
+            &[], 0, None, &[int_type], Some(any_type), &[]
+        );
+        assert_ne!(fixed, variadic_rets);
+
+        unsafe {
+            assert_eq!(variadic_rets.as_ref().to_string(), "fn() -> (int, ...any)");
+
+            let fixed_info = fixed.as_ref().get_function_tyck_info_unchecked().as_ref();
+            assert!(fixed_info.accepts_ret_count(1));
+            assert!(!fixed_info.accepts_ret_count(2));
+            assert_eq!(fixed_info.ret_type(1), None);
+
+            let info = variadic_rets.as_ref().get_function_tyck_info_unchecked().as_ref();
+            assert!(!info.accepts_ret_count(0));
+            assert!(info.accepts_ret_count(3));
+            assert_eq!(info.ret_type(0), Some(int_type));
+            assert_eq!(info.ret_type(2), Some(any_type));
+        }
+
+        let mut other_pool: TyckInfoPool = TyckInfoPool::new();
+        let imported: NonNull<TyckInfo> = other_pool.import_type(unsafe { variadic_rets.as_ref() });
+        unsafe {
+            assert!(imported.as_ref().structural_eq(variadic_rets.as_ref()));
+            assert!(!imported.as_ref().structural_eq(fixed.as_ref()));
+        }
+    }
+
+    #[test]
+    fn test_function_type_eq_compares_lengths() {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+        let int_types: [NonNull<TyckInfo>; 1] = [int_type];
+
+        // equality is also used to resolve hash collisions in the pool, so function types which
+        // only differ in one list being a prefix of the other must not compare equal
+        // `TyckInfo` frees its slices on drop, while these ones are borrowed
+        let function_type = |exceptions: &[NonNull<TyckInfo>]| ManuallyDrop::new(
+            TyckInfo::Function(FunctionTyckInfo {
+                params: NonNull::from(&int_types[..]),
+                optional_params: 0,
+                variadic: None,
+                rets: NonNull::from(&int_types[..]),
+                variadic_rets: None,
+                exceptions: NonNull::from(exceptions)
+            })
+        );
+        assert!(*function_type(&[]) == *function_type(&[]));
+        assert!(*function_type(&[]) != *function_type(&int_types));
+        assert!(*function_type(&int_types) != *function_type(&[]));
+    }
 }
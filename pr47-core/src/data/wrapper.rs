@@ -6,6 +6,7 @@ use unchecked_unwrap::UncheckedUnwrap;
 use xjbutil::unchecked::UnsafeFrom;
 use xjbutil::void::Void;
 
+use crate::data::Value;
 use crate::data::traits::{ChildrenType, StaticBase};
 use crate::data::tyck::TyckInfo;
 
@@ -156,6 +157,8 @@ pub trait DynBase {
     unsafe fn move_out(&mut self, out: *mut ());
 
     fn children(&self) -> ChildrenType;
+
+    fn dyn_get_field(&self, name: &str) -> Option<Value>;
 }
 
 impl<T: 'static> DynBase for Wrapper<T> where Void: StaticBase<T> {
@@ -198,4 +201,14 @@ impl<T: 'static> DynBase for Wrapper<T> where Void: StaticBase<T> {
         };
         <Void as StaticBase<T>>::children(vself)
     }
+
+    fn dyn_get_field(&self, name: &str) -> Option<Value> {
+        let vself: *const T = if (self.ownership_info & OWN_INFO_OWNED_MASK) != 0 {
+            unsafe { self.data.owned.as_ptr() }
+        } else {
+            debug_assert_ne!(self.ownership_info & OWN_INFO_READ_MASK, 0);
+            unsafe { self.data.ptr }
+        };
+        <Void as StaticBase<T>>::get_field(vself, name)
+    }
 }
@@ -1,10 +1,15 @@
+use std::any::TypeId;
 use std::future::Future;
+use std::panic::{AssertUnwindSafe, catch_unwind};
 use std::pin::Pin;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use xjbutil::void::Void;
 
 use crate::data::Value;
-use crate::data::exception::{ExceptionInner, UncheckedException};
+use crate::data::exception::{Exception, ExceptionInner, UncheckedException};
 use crate::data::generic::GenericTypeRef;
 use crate::data::traits::{StaticBase};
 use crate::data::wrapper::{OwnershipInfo, Wrapper};
@@ -14,8 +19,10 @@ use crate::data::wrapper::{
     OWN_INFO_READ_MASK,
     OWN_INFO_WRITE_MASK
 };
-use crate::ffi::{FFIException, Signature};
+use crate::ffi::{FFIException, FFIHandle, Signature, panic_message};
+use crate::ffi::sync_fn::ClosureSignature;
 use crate::util::serializer::{CoroutineSharedData, Serializer};
+use crate::vm::al31f::handle::RootedValue;
 
 pub trait VMDataTrait : 'static + Sized + Send {
     type Alloc;
@@ -27,8 +34,19 @@ pub trait AsyncVMContext: 'static + Sized + Send + Sync {
     type VMData: VMDataTrait;
 
     fn serializer(&self) -> &Serializer<(CoroutineSharedData, Self::VMData)>;
+
+    /// Call `callee`, a script function or closure, with `args` on a new VM thread. The returned
+    /// future waits for the running permission, so it must be awaited instead of blocked on.
+    ///
+    /// `callee` and `args` must be kept alive (e.g. rooted) until the future completes. Returned
+    /// values are rooted, since other tasks may run garbage collection before they get used.
+    fn call_value(&self, callee: Value, args: Vec<Value>) -> CallValueFuture;
 }
 
+/// Future returned by `AsyncVMContext::call_value`
+pub type CallValueFuture =
+    Pin<Box<dyn Future<Output=Result<Vec<RootedValue>, Exception>> + Send>>;
+
 pub trait AsyncFunctionBase: 'static {
     fn signature(tyck_info_pool: &mut TyckInfoPool) -> Signature;
 
@@ -39,7 +57,9 @@ pub trait AsyncFunctionBase: 'static {
 }
 
 pub trait AsyncFunction<A: Alloc, VD: VMDataTrait, ACTX: AsyncVMContext>: 'static {
-    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Signature;
+    /// Signature of the function, or `None` if it has no standard signature and checks its
+    /// arguments itself
+    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Option<Signature>;
 
     unsafe fn call_rtlc(&self, context: &ACTX, args: &[Value]) -> Result<Promise<A>, FFIException>;
 }
@@ -50,8 +70,8 @@ impl<AFBase, A, VD, ACTX> AsyncFunction<A, VD, ACTX> for AFBase where
     VD: VMDataTrait<Alloc= A>,
     ACTX: AsyncVMContext<VMData = VD>
 {
-    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Signature {
-        <AFBase as AsyncFunctionBase>::signature(tyck_info_pool)
+    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Option<Signature> {
+        Some(<AFBase as AsyncFunctionBase>::signature(tyck_info_pool))
     }
 
     unsafe fn call_rtlc(&self, context: &ACTX, args: &[Value]) -> Result<Promise<A>, FFIException> {
@@ -59,6 +79,68 @@ impl<AFBase, A, VD, ACTX> AsyncFunction<A, VD, ACTX> for AFBase where
     }
 }
 
+impl<F, A, VD, ACTX> From<&'static F> for FFIHandle<dyn AsyncFunction<A, VD, ACTX>> where
+    F: AsyncFunction<A, VD, ACTX>,
+    A: Alloc,
+    VD: VMDataTrait,
+    ACTX: AsyncVMContext
+{
+    fn from(func: &'static F) -> Self {
+        FFIHandle::from_static(func)
+    }
+}
+
+/// Body of an `AsyncClosureFunction`, returning the promise of the asynchronous call
+pub type AsyncClosureBody<A, ACTX> =
+    dyn Fn(&ACTX, &[Value]) -> Result<Promise<A>, FFIException> + Send + Sync;
+
+/// An asynchronous native function implemented by a Rust closure, which may capture state
+///
+/// Like `ClosureFunction`, arguments are passed as-is and checked by the closure itself.
+pub struct AsyncClosureFunction<A: Alloc, ACTX: AsyncVMContext> {
+    signature: Option<Box<ClosureSignature>>,
+    body: Box<AsyncClosureBody<A, ACTX>>
+}
+
+impl<A: Alloc, ACTX: AsyncVMContext> AsyncClosureFunction<A, ACTX> {
+    pub fn new(
+        signature: impl Fn(&mut TyckInfoPool) -> Signature + Send + Sync + 'static,
+        body: impl Fn(&ACTX, &[Value]) -> Result<Promise<A>, FFIException> + Send + Sync + 'static
+    ) -> Self {
+        Self { signature: Some(Box::new(signature)), body: Box::new(body) }
+    }
+
+    /// Create an asynchronous closure function without a standard signature
+    pub fn untyped(
+        body: impl Fn(&ACTX, &[Value]) -> Result<Promise<A>, FFIException> + Send + Sync + 'static
+    ) -> Self {
+        Self { signature: None, body: Box::new(body) }
+    }
+}
+
+impl<A, VD, ACTX> AsyncFunction<A, VD, ACTX> for AsyncClosureFunction<A, ACTX> where
+    A: Alloc,
+    VD: VMDataTrait<Alloc = A>,
+    ACTX: AsyncVMContext<VMData = VD>
+{
+    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Option<Signature> {
+        self.signature.as_deref()
+            .map(|signature: &ClosureSignature| signature(tyck_info_pool))
+    }
+
+    unsafe fn call_rtlc(&self, context: &ACTX, args: &[Value]) -> Result<Promise<A>, FFIException> {
+        (self.body)(context, args)
+    }
+}
+
+impl<A, VD, ACTX> From<AsyncClosureFunction<A, ACTX>> for FFIHandle<dyn AsyncFunction<A, VD, ACTX>>
+    where A: Alloc, VD: VMDataTrait<Alloc = A>, ACTX: AsyncVMContext<VMData = VD>
+{
+    fn from(func: AsyncClosureFunction<A, ACTX>) -> Self {
+        FFIHandle::from_shared(Arc::new(func))
+    }
+}
+
 pub struct AsyncResetGuard {
     wrapper_ptr: *mut Wrapper<()>,
     original: u8
@@ -107,12 +189,73 @@ pub type PromiseResult<A> = Box<dyn AsyncReturnType<A>>;
 
 pub struct Promise<A: Alloc>(pub Pin<Box<dyn Future<Output=PromiseResult<A>> + Send>>);
 
+/// Marker standing for promises in `TyckInfo`s, since the type of `Promise<A>` depends on the
+/// allocator while signatures don't
+pub enum PromiseType {}
+
 impl<A: Alloc> StaticBase<Promise<A>> for Void {
+    fn tyck_info(tyck_info_pool: &mut TyckInfoPool) -> NonNull<TyckInfo> {
+        tyck_info_pool.create_plain_type(TypeId::of::<PromiseType>())
+    }
+
+    fn tyck(tyck_info: &TyckInfo) -> bool {
+        if let TyckInfo::Plain(type_id) = tyck_info {
+            *type_id == TypeId::of::<PromiseType>()
+        } else {
+            false
+        }
+    }
+
     fn type_name() -> String {
         "promise".to_string()
     }
 }
 
+struct ForeignPanicResult {
+    message: String
+}
+
+impl<A: Alloc> AsyncReturnType<A> for ForeignPanicResult {
+    fn is_err(&self) -> bool {
+        true
+    }
+
+    fn resolve(
+        self: Box<Self>,
+        _alloc: &mut A,
+        _dests: &[*mut Value]
+    ) -> Result<usize, ExceptionInner> {
+        Err(ExceptionInner::Unchecked(UncheckedException::ForeignPanic { message: self.message }))
+    }
+}
+
+struct CatchPanic<A: Alloc> {
+    inner: Option<Pin<Box<dyn Future<Output=PromiseResult<A>> + Send>>>
+}
+
+impl<A: Alloc> Future for CatchPanic<A> {
+    type Output = PromiseResult<A>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner: &mut Pin<Box<dyn Future<Output=PromiseResult<A>> + Send>> =
+            self.inner.as_mut().expect("polling a completed promise");
+        match catch_unwind(AssertUnwindSafe(|| inner.as_mut().poll(cx))) {
+            Ok(poll) => poll,
+            Err(payload) => {
+                // dropping the future releases ownership guards it holds
+                self.inner = None;
+                Poll::Ready(Box::new(ForeignPanicResult { message: panic_message(payload.as_ref()) }))
+            }
+        }
+    }
+}
+
+/// Wrap `promise` so that a panic raised while polling it resolves the promise with
+/// `UncheckedException::ForeignPanic` instead
+pub fn catch_promise_panic<A: Alloc>(promise: Promise<A>) -> Promise<A> {
+    Promise(Box::pin(CatchPanic { inner: Some(promise.0) }))
+}
+
 pub use crate::ffi::sync_fn::{
     value_copy,
     value_copy_norm,
@@ -123,7 +266,7 @@ pub use crate::ffi::sync_fn::{
     value_move_out_norm,
     value_move_out_norm_noalias
 };
-use crate::data::tyck::TyckInfoPool;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
 use crate::vm::al31f::alloc::Alloc;
 
 #[inline] pub unsafe fn value_into_ref<'a, T>(
@@ -0,0 +1,296 @@
+//! Checked conversions between Rust values and VM `Value`s, used by typed function calls like
+//! `VMThread::call`
+
+use std::any::TypeId;
+use std::cell::UnsafeCell;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use xjbutil::unchecked::UncheckedCellOps;
+use xjbutil::void::Void;
+
+use crate::builtins::vec::VMGenericVec;
+use crate::data::Value;
+use crate::data::exception::Exception;
+use crate::data::generic::GenericTypeVT;
+use crate::data::traits::StaticBase;
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::data::wrapper::OWN_INFO_READ_MASK;
+use crate::vm::al31f::alloc::Alloc;
+
+/// Failure converting a VM `Value` to a Rust value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    /// Expected `expected` values, got `got`
+    CountMismatch { expected: usize, got: usize },
+    /// Got `null` where a non-null value is required
+    UnexpectedNull,
+    /// Got a value not of type `expected`
+    TypeMismatch { expected: String },
+    /// The referenced object cannot be accessed due to its ownership state
+    OwnershipCheckFailure { expected_mask: u8 },
+    /// Converting the `index`-th element of a tuple or vector failed
+    Element { index: usize, inner: Box<ConversionError> }
+}
+
+impl ConversionError {
+    fn element(index: usize, inner: ConversionError) -> Self {
+        ConversionError::Element { index, inner: Box::new(inner) }
+    }
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionError::CountMismatch { expected, got } =>
+                write!(f, "expected {} value(s), got {}", expected, got),
+            ConversionError::UnexpectedNull => write!(f, "unexpected null value"),
+            ConversionError::TypeMismatch { expected } =>
+                write!(f, "type mismatch, expected {}", expected),
+            ConversionError::OwnershipCheckFailure { expected_mask } =>
+                write!(f, "ownership check failed, expected ownership mask {:#05b}",
+                       expected_mask),
+            ConversionError::Element { index, inner } =>
+                write!(f, "element #{}: {}", index, inner)
+        }
+    }
+}
+
+impl Error for ConversionError {}
+
+/// Failure of a typed function call
+#[derive(Debug)]
+pub enum CallError {
+    /// The called function threw an exception
+    Exception(Exception),
+    /// Return values of the called function could not be converted to the expected types
+    Conversion(ConversionError)
+}
+
+impl Display for CallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::Exception(exception) => write!(f, "{}", exception),
+            CallError::Conversion(error) =>
+                write!(f, "failed converting return values: {}", error)
+        }
+    }
+}
+
+impl Error for CallError {}
+
+impl From<Exception> for CallError {
+    fn from(exception: Exception) -> Self {
+        CallError::Exception(exception)
+    }
+}
+
+impl From<ConversionError> for CallError {
+    fn from(error: ConversionError) -> Self {
+        CallError::Conversion(error)
+    }
+}
+
+/// Rust types which can be converted to a VM `Value`
+pub trait IntoValue: Sized {
+    /// Convert `self` into a `Value`. Objects created are put under management of `alloc`.
+    ///
+    /// The returned value is not rooted, so it may get reclaimed by the next garbage collection
+    /// unless it is stored somewhere reachable first.
+    fn into_value<A: Alloc>(self, alloc: &mut A) -> Value;
+}
+
+/// Rust types which can be converted from a VM `Value`
+///
+/// Objects are never moved out of the VM: object types are cloned after checking that the object
+/// is readable, and vectors are read element by element.
+pub trait FromValue: Sized {
+    /// Convert `value` to `Self`
+    ///
+    /// # Safety
+    /// If `value` is a reference, it must point to a live object.
+    unsafe fn from_value(value: Value) -> Result<Self, ConversionError>;
+}
+
+/// Lists of Rust values convertible to lists of `Value`s, implemented for tuples
+pub trait IntoValues: Sized {
+    fn into_values<A: Alloc>(self, alloc: &mut A) -> Vec<Value>;
+}
+
+/// Lists of Rust values convertible from lists of `Value`s, implemented for tuples
+pub trait FromValues: Sized {
+    /// Convert `values` to `Self`, failing if the count of values does not match
+    ///
+    /// # Safety
+    /// References in `values` must point to live objects.
+    unsafe fn from_values(values: &[Value]) -> Result<Self, ConversionError>;
+}
+
+macro_rules! impl_value_typed_conversion {
+    ($type:ty, $tag:ident, $ctor:ident, $field:ident, $name:literal) => {
+        impl IntoValue for $type {
+            #[inline] fn into_value<A: Alloc>(self, _alloc: &mut A) -> Value {
+                Value::$ctor(self)
+            }
+        }
+
+        impl FromValue for $type {
+            unsafe fn from_value(value: Value) -> Result<Self, ConversionError> {
+                if value.is_null() {
+                    Err(ConversionError::UnexpectedNull)
+                } else if value.is_value()
+                    && (value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK == ValueTypeTag::$tag as u8
+                {
+                    Ok(value.vt_data.inner.$field)
+                } else {
+                    Err(ConversionError::TypeMismatch { expected: $name.into() })
+                }
+            }
+        }
+    }
+}
+
+impl_value_typed_conversion!(i64, Int, new_int, int_value, "int");
+impl_value_typed_conversion!(f64, Float, new_float, float_value, "float");
+impl_value_typed_conversion!(char, Char, new_char, char_value, "char");
+impl_value_typed_conversion!(bool, Bool, new_bool, bool_value, "bool");
+
+/// Run `f` with garbage collection disallowed, so that values created by `f` are not reclaimed
+/// before all of them get returned
+fn with_gc_disallowed<A: Alloc, R>(alloc: &mut A, f: impl FnOnce(&mut A) -> R) -> R {
+    let gc_allowed: bool = alloc.gc_allowed();
+    alloc.set_gc_allowed(false);
+    let ret: R = f(alloc);
+    alloc.set_gc_allowed(gc_allowed);
+    ret
+}
+
+/// Check that `value` is a readable, non-container reference to an object of type `type_id`
+unsafe fn check_object(
+    value: Value,
+    type_id: TypeId,
+    type_name: impl FnOnce() -> String
+) -> Result<(), ConversionError> {
+    if value.is_null() {
+        return Err(ConversionError::UnexpectedNull);
+    }
+    if !value.is_ref() || value.is_container()
+        || (*value.get_as_dyn_base()).dyn_type_id() != type_id
+    {
+        return Err(ConversionError::TypeMismatch { expected: type_name() });
+    }
+    if !value.ownership_info_norm().is_readable() {
+        return Err(ConversionError::OwnershipCheckFailure { expected_mask: OWN_INFO_READ_MASK });
+    }
+    Ok(())
+}
+
+impl<T> IntoValue for T where T: 'static, Void: StaticBase<T> {
+    fn into_value<A: Alloc>(self, alloc: &mut A) -> Value {
+        let value: Value = Value::new_owned(self);
+        unsafe { alloc.add_managed(value); }
+        value
+    }
+}
+
+impl<T> FromValue for T where T: 'static + Clone, Void: StaticBase<T> {
+    unsafe fn from_value(value: Value) -> Result<Self, ConversionError> {
+        check_object(value, TypeId::of::<T>(), <Void as StaticBase<T>>::type_name)?;
+        let data_ptr: *const T = value.get_as_mut_ptr_norm() as *const T;
+        Ok((*data_ptr).clone())
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value<A: Alloc>(self, alloc: &mut A) -> Value {
+        match self {
+            Some(data) => data.into_value(alloc),
+            None => Value::new_null()
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    unsafe fn from_value(value: Value) -> Result<Self, ConversionError> {
+        if value.is_null() {
+            Ok(None)
+        } else {
+            T::from_value(value).map(Some)
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value<A: Alloc>(self, alloc: &mut A) -> Value {
+        with_gc_disallowed(alloc, |alloc: &mut A| {
+            let elements: Vec<Value> = self.into_iter()
+                .map(|element: T| element.into_value(alloc))
+                .collect();
+            VMGenericVec { inner: UnsafeCell::new(elements) }.into_value(alloc)
+        })
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    unsafe fn from_value(value: Value) -> Result<Self, ConversionError> {
+        // vectors are either plain `VMGenericVec` objects, or containers created with
+        // `CREATE-CONTAINER` and a vector VT
+        if value.is_container() {
+            let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+            if (*vt).tyck_info.as_ref().type_id != TypeId::of::<VMGenericVec>() {
+                return Err(ConversionError::TypeMismatch { expected: "vector".into() });
+            }
+            if !value.ownership_info().is_readable() {
+                return Err(ConversionError::OwnershipCheckFailure {
+                    expected_mask: OWN_INFO_READ_MASK
+                });
+            }
+        } else {
+            check_object(value, TypeId::of::<VMGenericVec>(), || "vector".into())?;
+        }
+        let vec: &VMGenericVec = &*(value.get_as_mut_ptr() as *const VMGenericVec);
+        vec.inner.get_ref_unchecked()
+            .iter()
+            .enumerate()
+            .map(|(index, element): (usize, &Value)|
+                T::from_value(*element)
+                    .map_err(|inner: ConversionError| ConversionError::element(index, inner)))
+            .collect()
+    }
+}
+
+macro_rules! impl_tuple_conversion {
+    ($count:literal; $($name:ident: $index:tt),*) => {
+        impl<$($name: IntoValue),*> IntoValues for ($($name,)*) {
+            #[allow(unused_variables)]
+            fn into_values<A: Alloc>(self, alloc: &mut A) -> Vec<Value> {
+                with_gc_disallowed(alloc, |alloc: &mut A| vec![$(self.$index.into_value(alloc)),*])
+            }
+        }
+
+        impl<$($name: FromValue),*> FromValues for ($($name,)*) {
+            unsafe fn from_values(values: &[Value]) -> Result<Self, ConversionError> {
+                if values.len() != $count {
+                    return Err(ConversionError::CountMismatch {
+                        expected: $count,
+                        got: values.len()
+                    });
+                }
+                Ok(($(
+                    $name::from_value(values[$index])
+                        .map_err(|inner: ConversionError| ConversionError::element($index, inner))?,
+                )*))
+            }
+        }
+    }
+}
+
+impl_tuple_conversion!(0;);
+impl_tuple_conversion!(1; T0: 0);
+impl_tuple_conversion!(2; T0: 0, T1: 1);
+impl_tuple_conversion!(3; T0: 0, T1: 1, T2: 2);
+impl_tuple_conversion!(4; T0: 0, T1: 1, T2: 2, T3: 3);
+impl_tuple_conversion!(5; T0: 0, T1: 1, T2: 2, T3: 3, T4: 4);
+impl_tuple_conversion!(6; T0: 0, T1: 1, T2: 2, T3: 3, T4: 4, T5: 5);
+impl_tuple_conversion!(7; T0: 0, T1: 1, T2: 2, T3: 3, T4: 4, T5: 5, T6: 6);
+impl_tuple_conversion!(8; T0: 0, T1: 1, T2: 2, T3: 3, T4: 4, T5: 5, T6: 6, T7: 7);
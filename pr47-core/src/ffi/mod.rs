@@ -1,13 +1,18 @@
+use std::any::Any;
+use std::ops::Deref;
 use std::ptr::NonNull;
+use std::sync::Arc;
 
-use crate::data::tyck::TyckInfo;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
 use crate::data::exception::ExceptionInner;
 
+pub mod convert;
 pub mod sync_fn;
 
 #[cfg(feature = "async")]
 pub mod async_fn;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum DataOption {
     Share,
@@ -21,8 +26,115 @@ pub enum DataOption {
 pub struct Signature {
     pub func_type: NonNull<TyckInfo>,
 
+    /// How each parameter is passed, followed by how the arguments of the variadic tail are passed
+    /// if the function is variadic
     pub param_options: Box<[DataOption]>,
+    /// How each return value is passed, followed by how the values of the variadic return tail
+    /// are passed if there is one
     pub ret_option: Box<[DataOption]>
 }
 
 pub type FFIException = ExceptionInner;
+
+/// A native function referred to by compiled programs and `FfiRegistry`
+///
+/// The function is either `'static`, like the unit structs generated by `pr47_function_bind`, or
+/// shared through an `Arc`, in which case the engine keeps it alive as long as any program or
+/// registry refers to it. Either way, calling through the handle costs no more than calling
+/// through a `&'static dyn` reference.
+pub struct FFIHandle<F: ?Sized + 'static> {
+    func: NonNull<F>,
+    owner: Option<Arc<F>>,
+    variadic_check: Option<Arc<VariadicCheck>>
+}
+
+impl<F: ?Sized + 'static> FFIHandle<F> {
+    pub fn from_static(func: &'static F) -> Self {
+        Self { func: NonNull::from(func), owner: None, variadic_check: None }
+    }
+
+    pub fn from_shared(func: Arc<F>) -> Self {
+        Self { func: NonNull::from(&*func), owner: Some(func), variadic_check: None }
+    }
+
+    /// Whether the function is shared through an `Arc` instead of being `'static`
+    pub fn is_shared(&self) -> bool {
+        self.owner.is_some()
+    }
+
+    /// Make `FFICallRtlc` and `FFICallAsync` check the variadic arguments of calls through this
+    /// handle against the element type of `variadic_check`
+    pub fn with_variadic_check(self, variadic_check: VariadicCheck) -> Self {
+        Self { variadic_check: Some(Arc::new(variadic_check)), ..self }
+    }
+
+    #[inline(always)] pub fn variadic_check(&self) -> Option<&VariadicCheck> {
+        self.variadic_check.as_deref()
+    }
+}
+
+/// Run time check of the variadic tail of native function calls
+///
+/// The element type is copied into a pool owned by the check, so that it stays valid for as long
+/// as any program refers to the check.
+pub struct VariadicCheck {
+    fixed_params: usize,
+    elem_type: NonNull<TyckInfo>,
+    _tyck_info_pool: TyckInfoPool
+}
+
+// the check and its pool are never mutated after creation
+unsafe impl Send for VariadicCheck {}
+unsafe impl Sync for VariadicCheck {}
+
+impl VariadicCheck {
+    /// Check arguments following the first `fixed_params` ones against `elem_type`
+    pub fn new(fixed_params: usize, elem_type: &TyckInfo) -> Self {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let elem_type: NonNull<TyckInfo> = tyck_info_pool.import_type(elem_type);
+        Self { fixed_params, elem_type, _tyck_info_pool: tyck_info_pool }
+    }
+
+    #[inline(always)] pub fn fixed_params(&self) -> usize {
+        self.fixed_params
+    }
+
+    #[inline(always)] pub fn elem_type(&self) -> NonNull<TyckInfo> {
+        self.elem_type
+    }
+}
+
+impl<F: ?Sized + 'static> Deref for FFIHandle<F> {
+    type Target = F;
+
+    #[inline(always)] fn deref(&self) -> &F {
+        unsafe { self.func.as_ref() }
+    }
+}
+
+impl<F: ?Sized + 'static> Clone for FFIHandle<F> {
+    fn clone(&self) -> Self {
+        Self {
+            func: self.func,
+            owner: self.owner.clone(),
+            variadic_check: self.variadic_check.clone()
+        }
+    }
+}
+
+impl<F: ?Sized + 'static> From<Arc<F>> for FFIHandle<F> {
+    fn from(func: Arc<F>) -> Self {
+        Self::from_shared(func)
+    }
+}
+
+/// Extract the message from the payload of a caught panic
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
@@ -1,21 +1,47 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+
+use smallvec::SmallVec;
 use xjbutil::void::Void;
 
 use crate::data::Value;
 use crate::data::generic::GenericTypeRef;
-use crate::data::exception::{UncheckedException};
+use crate::data::exception::{Exception, UncheckedException};
 use crate::data::traits::StaticBase;
 use crate::data::tyck::TyckInfoPool;
 use crate::data::wrapper::{OwnershipInfo, Wrapper};
 use crate::data::wrapper::{
+    OWN_INFO_MOVE_MASK,
     OWN_INFO_OWNED_MASK,
     OWN_INFO_READ_MASK,
     OWN_INFO_WRITE_MASK
 };
-use crate::ffi::{FFIException, Signature};
+use crate::ffi::{FFIException, FFIHandle, Signature, panic_message};
+use crate::vm::al31f::alloc::Finalizer;
+use crate::vm::al31f::handle::RootedValue;
 
-pub trait VMContext: 'static + Sized {
+pub trait VMContext: 'static {
     fn add_heap_managed(&mut self, wide_ptr: Value);
     fn mark(&mut self, wide_ptr: Value);
+
+    /// Root `value`, keeping it alive for as long as the returned handle lives. The handle may be
+    /// dropped after the VM, though the value must not be accessed then.
+    fn root(&mut self, value: Value) -> RootedValue;
+
+    /// Register `finalizer` for `value`, which must be managed by the VM. Once `value` becomes
+    /// unreachable, `finalizer` gets run by the VM at the next safe point, with `value` kept alive
+    /// until `finalizer` returns. Null and value-typed `value`s are ignored.
+    fn add_finalizer(&mut self, value: Value, finalizer: Finalizer);
+
+    /// Call `callee`, a script function or closure, with `args` and wait for it to return. The
+    /// call runs on the stack of the VM thread calling the current FFI function, and exceptions
+    /// thrown by `callee` are returned after unwinding its frames. `callee` must not await
+    /// promises. Returned values are rooted, since they no longer live in any stack frame.
+    fn call_value(
+        &mut self,
+        callee: Value,
+        args: &[Value]
+    ) -> Result<Vec<RootedValue>, Exception>;
 }
 
 pub trait FunctionBase: 'static {
@@ -35,7 +61,9 @@ pub trait FunctionBase: 'static {
 }
 
 pub trait Function<CTX: VMContext>: 'static {
-    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Signature;
+    /// Signature of the function, or `None` if it has no standard signature and checks its
+    /// arguments itself
+    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Option<Signature>;
 
     unsafe fn call_rtlc(
         &self,
@@ -56,8 +84,8 @@ impl<FBase, CTX> Function<CTX> for FBase where
     FBase: FunctionBase,
     CTX: VMContext
 {
-    #[inline] fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Signature {
-        <FBase as FunctionBase>::signature(tyck_info_pool)
+    #[inline] fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Option<Signature> {
+        Some(<FBase as FunctionBase>::signature(tyck_info_pool))
     }
 
     #[inline] unsafe fn call_rtlc(
@@ -79,6 +107,99 @@ impl<FBase, CTX> Function<CTX> for FBase where
     }
 }
 
+impl<F: Function<CTX>, CTX: VMContext> From<&'static F> for FFIHandle<dyn Function<CTX>> {
+    fn from(func: &'static F) -> Self {
+        FFIHandle::from_static(func)
+    }
+}
+
+/// Signature provider of a `ClosureFunction`
+pub type ClosureSignature = dyn Fn(&mut TyckInfoPool) -> Signature + Send + Sync;
+
+/// Body of a `ClosureFunction`, returning values to be stored into return value locations
+pub type ClosureBody<CTX> =
+    dyn Fn(&mut CTX, &[Value]) -> Result<Vec<Value>, FFIException> + Send + Sync;
+
+/// A native function implemented by a Rust closure, which may capture state
+///
+/// Arguments are passed as-is: the closure checks their types and ownership itself, and objects
+/// it creates must be put under management with `VMContext::add_heap_managed`. The closure must
+/// return exactly as many values as the caller expects, otherwise the call fails with
+/// `UncheckedException::ForeignPanic`.
+pub struct ClosureFunction<CTX: VMContext> {
+    signature: Option<Box<ClosureSignature>>,
+    body: Box<ClosureBody<CTX>>
+}
+
+impl<CTX: VMContext> ClosureFunction<CTX> {
+    pub fn new(
+        signature: impl Fn(&mut TyckInfoPool) -> Signature + Send + Sync + 'static,
+        body: impl Fn(&mut CTX, &[Value]) -> Result<Vec<Value>, FFIException> + Send + Sync + 'static
+    ) -> Self {
+        Self { signature: Some(Box::new(signature)), body: Box::new(body) }
+    }
+
+    /// Create a closure function without a standard signature, like `print`
+    pub fn untyped(
+        body: impl Fn(&mut CTX, &[Value]) -> Result<Vec<Value>, FFIException> + Send + Sync + 'static
+    ) -> Self {
+        Self { signature: None, body: Box::new(body) }
+    }
+
+    unsafe fn call(
+        &self,
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        let values: Vec<Value> = (self.body)(context, args)?;
+        if values.len() != rets.len() {
+            return Err(FFIException::Unchecked(UncheckedException::ForeignPanic {
+                message: format!(
+                    "closure function returned {} values, expected {}",
+                    values.len(),
+                    rets.len()
+                )
+            }));
+        }
+        for (ret, value) /*: (&*mut Value, Value)*/ in rets.iter().zip(values) {
+            **ret = value;
+        }
+        Ok(())
+    }
+}
+
+impl<CTX: VMContext> Function<CTX> for ClosureFunction<CTX> {
+    fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Option<Signature> {
+        self.signature.as_deref()
+            .map(|signature: &ClosureSignature| signature(tyck_info_pool))
+    }
+
+    unsafe fn call_rtlc(
+        &self,
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        self.call(context, args, rets)
+    }
+
+    unsafe fn call_unchecked(
+        &self,
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        self.call(context, args, rets)
+    }
+}
+
+impl<CTX: VMContext> From<ClosureFunction<CTX>> for FFIHandle<dyn Function<CTX>> {
+    fn from(func: ClosureFunction<CTX>) -> Self {
+        FFIHandle::from_shared(Arc::new(func))
+    }
+}
+
 pub struct OwnershipGuard {
     wrapper_ptr: *mut Wrapper<()>,
     ownership_info: u8
@@ -99,17 +220,51 @@ impl Drop for OwnershipGuard {
     }
 }
 
+/// Run the FFI call `f` with arguments `args`, converting a panic escaping from `f` into
+/// `UncheckedException::ForeignPanic`
+///
+/// Ownership info of referenced arguments is restored on panic, unless the argument has been moved
+/// to Rust, in which case it may already have been dropped.
+///
+/// # Safety
+/// Every reference in `args` must point to a live object.
+pub unsafe fn call_catch_panic<F, R>(args: &[Value], f: F) -> Result<R, FFIException>
+    where F: FnOnce() -> Result<R, FFIException>
+{
+    let mut saved: SmallVec<[(*mut Wrapper<()>, u8); 8]> = SmallVec::new();
+    for arg /*: &Value*/ in args.iter() {
+        if arg.is_ref() {
+            let wrapper_ptr: *mut Wrapper<()> = arg.untagged_ptr_field() as *mut _;
+            saved.push((wrapper_ptr, (*wrapper_ptr).ownership_info));
+        }
+    }
+
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            for (wrapper_ptr, ownership_info) /*: (*mut Wrapper<()>, u8)*/ in saved {
+                if (*wrapper_ptr).ownership_info != OwnershipInfo::MovedToRust as u8 {
+                    (*wrapper_ptr).ownership_info = ownership_info;
+                }
+            }
+            Err(FFIException::Unchecked(UncheckedException::ForeignPanic {
+                message: panic_message(payload.as_ref())
+            }))
+        }
+    }
+}
+
 #[inline] pub unsafe fn value_move_out_check(
     value: Value
 ) -> Result<OwnershipGuard, FFIException> {
     let wrapper_ptr: *mut Wrapper<()> = value.untagged_ptr_field() as *mut _;
     let original: u8 = (*wrapper_ptr).ownership_info;
-    if original & (OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_OWNED_MASK) != 0 {
+    if original & OWN_INFO_MOVE_MASK != 0 {
         Ok(OwnershipGuard::new(wrapper_ptr, original))
     } else {
         Err(FFIException::Unchecked(UncheckedException::OwnershipCheckFailure {
             object: value,
-            expected_mask: (OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_OWNED_MASK)
+            expected_mask: OWN_INFO_MOVE_MASK
         }))
     }
 }
@@ -119,12 +274,12 @@ impl Drop for OwnershipGuard {
 ) -> Result<OwnershipGuard, FFIException> {
     let wrapper_ptr: *mut Wrapper<()> = value.ptr_repr.ptr as *mut _;
     let original: u8 = (*wrapper_ptr).ownership_info;
-    if original & (OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_OWNED_MASK) != 0 {
+    if original & OWN_INFO_MOVE_MASK != 0 {
         Ok(OwnershipGuard::new(wrapper_ptr, original))
     } else {
         Err(FFIException::Unchecked(UncheckedException::OwnershipCheckFailure {
             object: value,
-            expected_mask: (OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_OWNED_MASK)
+            expected_mask: OWN_INFO_MOVE_MASK
         }))
     }
 }
@@ -133,12 +288,12 @@ impl Drop for OwnershipGuard {
     value: Value
 ) -> Result<(), FFIException> {
     let original: u8 = value.ownership_info_norm() as u8;
-    if original & (OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_OWNED_MASK) != 0 {
+    if original & OWN_INFO_MOVE_MASK != 0 {
         Ok(())
     } else {
         Err(FFIException::Unchecked(UncheckedException::OwnershipCheckFailure {
             object: value,
-            expected_mask: (OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_OWNED_MASK)
+            expected_mask: OWN_INFO_MOVE_MASK
         }))
     }
 }
@@ -4,8 +4,9 @@ use xjbutil::defer;
 
 use crate::awa;
 use crate::diag::diag_data;
-use crate::diag::location::SourceRange;
+use crate::diag::location::{SourceLoc, SourceRange};
 use crate::parse::lexer::LexerMode;
+use crate::syntax::decl::FunctionParam;
 use crate::syntax::expr::{
     ConcreteAsExpr,
     ConcreteAwaitExpr,
@@ -13,12 +14,14 @@ use crate::syntax::expr::{
     ConcreteExpr,
     ConcreteFieldRefExpr,
     ConcreteFuncCallExpr,
+    ConcreteLambdaExpr,
     ConcreteLiteralExpr,
     ConcreteParenthesizedExpr,
     ConcreteSubscriptExpr,
     ConcreteUnaryExpr
 };
 use crate::syntax::id::Identifier;
+use crate::syntax::stmt::ConcreteCompoundStmt;
 use crate::syntax::token::{Token, TokenInner};
 use crate::syntax::ty::ConcreteType;
 
@@ -242,6 +245,11 @@ impl<'s, 'd> Parser<'s, 'd> {
                     rparen_loc: rparen_range.right()
                 }))
             },
+            TokenInner::KwdFunc => {
+                let func_token: Token<'s> = self.consume_token();
+                self.parse_lambda_expression(func_token, skip_set)
+                    .map(|lambda_expr: ConcreteLambdaExpr| ConcreteExpr::LambdaExpr(lambda_expr))
+            },
             _ => {
                 self.diag.borrow_mut()
                     .diag(self.current_token().range.left(),
@@ -254,7 +262,8 @@ impl<'s, 'd> Parser<'s, 'd> {
                         TokenInner::LitStr(""),
                         TokenInner::KwdTrue,
                         TokenInner::KwdFalse,
-                        TokenInner::SymLParen
+                        TokenInner::SymLParen,
+                        TokenInner::KwdFunc
                     ])
                     .add_arg2(self.current_token().token_inner)
                     .add_mark(self.current_token().range.into())
@@ -265,6 +274,53 @@ impl<'s, 'd> Parser<'s, 'd> {
         }
     }
 
+    pub fn parse_lambda_expression(
+        &mut self,
+        kwd_token: Token<'s>,
+        skip_set: &[&[TokenInner<'_>]]
+    ) -> Option<ConcreteLambdaExpr<'s>> {
+        let func_kwd_range: SourceRange = kwd_token.range;
+        let lparen_loc: SourceLoc =
+            self.expect_n_consume(TokenInner::SymLParen, skip_set)?.range.left();
+        let (param_list, rparen_range): (Vec<FunctionParam>, SourceRange) =
+            self.parse_list_alike(
+                Self::parse_func_param,
+                skip_set,
+                TokenInner::SymComma,
+                TokenInner::SymRParen,
+                skip_set
+            )?;
+
+        let has_colon: bool = self.skip_optional(TokenInner::SymColon);
+        let return_types: Vec<ConcreteType<'s>> =
+            if self.current_token().token_inner == TokenInner::SymLBrace {
+                if has_colon {
+                    self.diag.borrow_mut()
+                        .diag(self.current_token().range.left(), diag_data::err_missing_type_got_0)
+                        .add_arg2(self.current_token().token_inner)
+                        .add_mark(self.current_token().range.into())
+                        .emit();
+                    self.skip_to_any_of(skip_set);
+                    return None;
+                }
+                Vec::new()
+            } else {
+                self.parse_func_ret_type(skip_set)?
+            };
+
+        let lbrace_token: Token<'s> = self.expect_n_consume(TokenInner::SymLBrace, skip_set)?;
+        let body: ConcreteCompoundStmt<'s> = self.parse_compound_stmt(lbrace_token, skip_set)?;
+
+        Some(ConcreteLambdaExpr {
+            param_list,
+            return_types,
+            body,
+            func_kwd_range,
+            param_open_paren_loc: lparen_loc,
+            param_close_paren_loc: rparen_range.left()
+        })
+    }
+
     pub fn parse_expression_list(
         &mut self,
         separation: TokenInner,
@@ -1,6 +1,9 @@
 use super::Parser;
 
-use crate::syntax::stmt::ConcreteCompoundStmt;
+use crate::diag::location::{SourceLoc, SourceRange};
+use crate::syntax::decl::{ConcreteDecl, ConcreteObjectDecl};
+use crate::syntax::expr::ConcreteExpr;
+use crate::syntax::stmt::{ConcreteCompoundStmt, ConcreteStmt};
 use crate::syntax::token::{Token, TokenInner};
 
 impl<'s, 'd> Parser<'s, 'd> {
@@ -10,6 +13,97 @@ impl<'s, 'd> Parser<'s, 'd> {
         _failsafe_set: &[&[TokenInner<'_>]]
     ) -> Option<ConcreteCompoundStmt<'s>> {
         debug_assert_eq!(lbrace_token.token_inner, TokenInner::SymLBrace);
-        todo!()
+
+        let mut stmts: Vec<ConcreteStmt<'s>> = Vec::new();
+        loop {
+            match self.current_token().token_inner {
+                TokenInner::SymRBrace => {
+                    let rbrace_range: SourceRange = self.consume_token().range;
+                    return Some(ConcreteCompoundStmt {
+                        stmts,
+                        left_brace_loc: lbrace_token.range.left(),
+                        right_brace_loc: rbrace_range.left()
+                    });
+                },
+                TokenInner::EndOfInput => {
+                    self.diag_unexpected_eoi(self.current_token().range);
+                    return None;
+                },
+                _ => if let Some(stmt /*: ConcreteStmt*/) = self.parse_stmt() {
+                    stmts.push(stmt);
+                } else {
+                    // skipped to a statement boundary, go on with the next statement
+                    let _ = self.skip_optional(TokenInner::SymSemicolon);
+                }
+            }
+        }
+    }
+
+    pub fn parse_stmt(&mut self) -> Option<ConcreteStmt<'s>> {
+        match self.current_token().token_inner {
+            TokenInner::SymLBrace => {
+                let lbrace_token: Token<'s> = self.consume_token();
+                self.parse_compound_stmt(lbrace_token, STMT_FAILSAFE)
+                    .map(|compound_stmt: ConcreteCompoundStmt| {
+                        ConcreteStmt::CompoundStmt(compound_stmt)
+                    })
+            },
+            TokenInner::KwdVar | TokenInner::KwdConst => {
+                let kwd_token: Token<'s> = self.consume_token();
+                let start_loc: SourceLoc = kwd_token.range.left();
+                let is_var: bool = kwd_token.token_inner == TokenInner::KwdVar;
+                let decl: ConcreteObjectDecl<'s> =
+                    self.parse_object_decl(kwd_token, STMT_FAILSAFE)?;
+                let decl: ConcreteDecl<'s> = if is_var {
+                    ConcreteDecl::VarDecl(decl)
+                } else {
+                    ConcreteDecl::ConstDecl(decl)
+                };
+                Some(ConcreteStmt::DeclStmt(decl, start_loc))
+            },
+            _ => {
+                let start_loc: SourceLoc = self.current_token().range.left();
+                let expr: ConcreteExpr<'s> = self.parse_expression(STMT_FAILSAFE)?;
+                self.expect_n_consume(TokenInner::SymSemicolon, STMT_FAILSAFE)?;
+                Some(ConcreteStmt::ExprStmt(expr, start_loc))
+            }
+        }
+    }
+}
+
+const STMT_FAILSAFE: &[&[TokenInner<'static>]] = &[
+    &[TokenInner::SymSemicolon, TokenInner::SymRBrace]
+];
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use crate::diag::DiagContext;
+    use crate::parse::parser::Parser;
+    use crate::syntax::decl::ConcreteObjectDecl;
+    use crate::syntax::expr::ConcreteExpr;
+    use crate::syntax::token::Token;
+
+    #[test]
+    fn test_parse_lambda() {
+        let source: &str = "const f = func (x int, y) int { var z = x + y; { z; } z * 2; };";
+
+        let diag: RefCell<DiagContext> = RefCell::new(DiagContext::new());
+        let mut parser: Parser = Parser::new(
+            0, source, &diag
+        );
+
+        let kwd_token: Token = parser.consume_token();
+        let decl: ConcreteObjectDecl = parser.parse_object_decl(kwd_token, &[]).unwrap();
+        if let ConcreteExpr::LambdaExpr(lambda) = &decl.init_expr {
+            assert_eq!(lambda.param_list.len(), 2);
+            assert_eq!(lambda.return_types.len(), 1);
+            assert_eq!(lambda.body.stmts.len(), 3);
+        } else {
+            panic!()
+        }
+
+        dbg!(decl);
     }
 }
@@ -13,13 +13,14 @@ use xjbutil::async_utils::join_all;
 use crate::data::Value;
 use crate::data::exception::ExceptionInner;
 use crate::data::tyck::{TyckInfo, TyckInfoPool};
-use crate::ffi::{FFIException, Signature};
+use crate::ffi::{DataOption, FFIException, Signature};
 use crate::ffi::async_fn::{
     AsyncFunctionBase,
     AsyncReturnType,
     AsyncVMContext,
     Promise,
     PromiseResult,
+    PromiseType,
     VMDataTrait
 };
 use crate::ffi::async_fn::{value_move_out_check_norm_noalias, value_move_out_norm_noalias};
@@ -29,9 +30,20 @@ pub struct JoinBind();
 
 impl AsyncFunctionBase for JoinBind {
     fn signature(
-        _tyck_info_pool: &mut TyckInfoPool
+        tyck_info_pool: &mut TyckInfoPool
     ) -> Signature {
-        unimplemented!("join operation does not have standard signature")
+        let promise_type: NonNull<TyckInfo> =
+            tyck_info_pool.create_plain_type(TypeId::of::<PromiseType>());
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+
+        // returns the results of all joined promises, in order
+        Signature {
+            func_type: tyck_info_pool.create_function_type_with_tails(
+                &[], 0, Some(promise_type), &[], Some(any_type), &[]
+            ),
+            param_options: boxed_slice![DataOption::Move],
+            ret_option: boxed_slice![DataOption::Move]
+        }
     }
 
     unsafe fn call_rtlc<A: Alloc, VD: VMDataTrait<Alloc=A>, ACTX: AsyncVMContext<VMData=VD>> (
@@ -97,8 +109,20 @@ pub const JOIN_BIND: &'static JoinBind = &JoinBind();
 pub struct SelectBind();
 
 impl AsyncFunctionBase for SelectBind {
-    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
-        unimplemented!("select operation does not have standard signature")
+    fn signature(tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        let promise_type: NonNull<TyckInfo> =
+            tyck_info_pool.create_plain_type(TypeId::of::<PromiseType>());
+        let i64_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+
+        // returns the index of the selected promise, followed by its results
+        Signature {
+            func_type: tyck_info_pool.create_function_type_with_tails(
+                &[], 0, Some(promise_type), &[i64_type], Some(any_type), &[]
+            ),
+            param_options: boxed_slice![DataOption::Move],
+            ret_option: boxed_slice![DataOption::Copy, DataOption::Move]
+        }
     }
 
     unsafe fn call_rtlc<A: Alloc, VD: VMDataTrait<Alloc=A>, ACTX: AsyncVMContext<VMData=VD>>(
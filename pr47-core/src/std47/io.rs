@@ -1,21 +1,32 @@
 use std::any::TypeId;
+use std::ptr::NonNull;
+
 use unchecked_unwrap::UncheckedUnwrap;
 
+use xjbutil::boxed_slice;
 use xjbutil::unchecked::UnsafeFrom;
 
 use crate::data::exception::UncheckedException;
-use crate::data::tyck::TyckInfoPool;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
 use crate::data::Value;
 use crate::data::value_typed::ValueTypeTag;
 use crate::data::wrapper::OWN_INFO_READ_MASK;
-use crate::ffi::{FFIException, Signature};
+use crate::ffi::{DataOption, FFIException, Signature};
 use crate::ffi::sync_fn::{FunctionBase, VMContext};
 
 pub struct PrintBind();
 
 impl FunctionBase for PrintBind {
-    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
-        unimplemented!("print does not have a standard signature")
+    fn signature(tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+
+        Signature {
+            func_type: tyck_info_pool.create_function_type_with_tail(
+                &[], 0, Some(any_type), &[], &[]
+            ),
+            param_options: boxed_slice![DataOption::Share],
+            ret_option: boxed_slice![]
+        }
     }
 
     unsafe fn call_rtlc<CTX: VMContext>(
@@ -5,3 +5,22 @@ pub mod math;
 pub mod time;
 
 #[cfg(feature = "async")] pub mod futures;
+
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::registry::{FfiRegistry, LinkError};
+
+/// Register native functions of the standard library under `std.<module>.<function>`
+pub fn register_std47<A: Alloc>(registry: &mut FfiRegistry<A>) -> Result<(), LinkError> {
+    registry.register("std.io.print", io::PRINT_BIND)?;
+    registry.register("std.time.duration_for_millis", time::DURATION_FOR_MILLIS_BIND)?;
+
+    #[cfg(feature = "async")]
+    {
+        registry.register_async("std.fs.read_to_string", fs::ASYNC_READ_TO_STRING_BIND)?;
+        registry.register_async("std.futures.join", futures::JOIN_BIND)?;
+        registry.register_async("std.futures.select", futures::SELECT_BIND)?;
+        registry.register_async("std.futures.sleep_ms", futures::SLEEP_MS_BIND)?;
+    }
+
+    Ok(())
+}
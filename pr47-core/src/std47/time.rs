@@ -53,3 +53,5 @@ impl FunctionBase for DurationForMillisBind {
         Ok(())
     }
 }
+
+pub const DURATION_FOR_MILLIS_BIND: &'static DurationForMillisBind = &DurationForMillisBind();
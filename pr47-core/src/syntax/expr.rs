@@ -43,6 +43,9 @@
 //!                     | literal
 //!                     | '(' binary-expression ')'
 //!                     | intrinsic-op '(' expression-list ')'
+//!                     | lambda-expression
+//!
+//! lambda-expression ::= 'func' '(' func-param-list ')' maybe-func-return-type compound-statement
 //!
 //! expression-list ::= expression-list ',' binary-expression
 //!                   | binary-expression
@@ -66,7 +69,9 @@
 //! ```
 
 use crate::diag::location::{SourceLoc, SourceRange};
+use crate::syntax::decl::FunctionParam;
 use crate::syntax::id::Identifier;
+use crate::syntax::stmt::ConcreteCompoundStmt;
 use crate::syntax::token::Token;
 use crate::syntax::ty::ConcreteType;
 
@@ -83,6 +88,7 @@ pub enum ConcreteExpr<'a> {
     AsExpr(ConcreteAsExpr<'a>),
     AwaitExpr(ConcreteAwaitExpr<'a>),
     ParenthesizedExpr(ConcreteParenthesizedExpr<'a>),
+    LambdaExpr(ConcreteLambdaExpr<'a>),
 }
 
 #[cfg(test)]
@@ -98,7 +104,8 @@ impl<'a> Debug for ConcreteExpr<'a> {
             ConcreteExpr::FieldRefExpr(expr) => expr.fmt(f),
             ConcreteExpr::AsExpr(expr) => expr.fmt(f),
             ConcreteExpr::AwaitExpr(expr) => expr.fmt(f),
-            ConcreteExpr::ParenthesizedExpr(expr) => expr.fmt(f)
+            ConcreteExpr::ParenthesizedExpr(expr) => expr.fmt(f),
+            ConcreteExpr::LambdaExpr(expr) => expr.fmt(f)
         }
     }
 }
@@ -215,6 +222,18 @@ pub struct ConcreteParenthesizedExpr<'a> {
     pub rparen_loc: SourceLoc
 }
 
+/// An anonymous function. Variables of enclosing functions used in `body` get captured.
+#[cfg_attr(test, derive(Debug))]
+pub struct ConcreteLambdaExpr<'a> {
+    pub param_list: Vec<FunctionParam<'a>>,
+    pub return_types: Vec<ConcreteType<'a>>,
+    pub body: ConcreteCompoundStmt<'a>,
+
+    pub func_kwd_range: SourceRange,
+    pub param_open_paren_loc: SourceLoc,
+    pub param_close_paren_loc: SourceLoc
+}
+
 #[cfg(test)]
 impl<'a> Debug for ConcreteAwaitExpr<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -162,6 +162,16 @@ impl<SD: 'static + Send> CoroutineContext<SD> {
         }
     }
 
+    /// Creates a context for a task started from outside the serialization group, for example a
+    /// callback run by an asynchronous FFI function. The task gets its own ID, but is not tracked
+    /// by `CoroutineSharedData`, so the main task does not wait for it on `finish`.
+    pub async fn attached_context(serializer: Serializer<(CoroutineSharedData, SD)>) -> Self {
+        let mut permit: SerializerLock<'static, (CoroutineSharedData, SD)>
+            = unsafe { transmute::<>(serializer.lock().await) };
+        let task_id: u32 = permit.0.get_next_id();
+        Self::child_context(serializer, permit, task_id)
+    }
+
     /// Given the fact that the permit is held, and there's not another mutable reference to the
     /// shared data, retrieve the shared data.
     pub fn get_shared_data_mut(&mut self) -> &mut SD {
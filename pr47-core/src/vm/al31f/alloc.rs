@@ -1,13 +1,55 @@
 pub mod default_alloc;
 pub mod no_gc_alloc;
+pub mod snapshot;
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
 use xjbutil::flex::FlexArray;
 
 use crate::data::Value;
+use crate::ffi::sync_fn::VMContext;
 use crate::vm::al31f::stack::Stack;
 
 pub type AllocPin = FlexArray<bool, Value>;
 
+/// Statistics reported by `Alloc::stats`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllocStats {
+    /// Count of objects currently managed
+    pub managed_count: usize,
+    /// Count of objects alive after the last collection
+    pub live_count: usize,
+    /// Total count of collections performed, including minor and major ones
+    pub collections: usize,
+    /// Count of major (full) collections performed
+    pub major_collections: usize,
+    /// Pause time of the last collection
+    pub last_pause: Duration,
+    /// Accumulated pause time of all collections
+    pub total_pause: Duration,
+    /// Count of objects in the pinned set, including rooted objects
+    pub pinned_count: usize,
+    /// Objects allocated since the last collection
+    pub debt: usize,
+    /// Debt threshold which triggers a collection
+    pub max_debt: usize
+}
+
+/// Events reported to the callback installed by `Alloc::set_gc_callback`
+#[derive(Debug, Clone, Copy)]
+pub enum GCEvent {
+    CollectStart { major: bool },
+    CollectEnd { major: bool, reclaimed: usize, pause: Duration }
+}
+
+pub type GCCallback = Box<dyn FnMut(&GCEvent) + Send + Sync>;
+
+/// Finalizer of a managed object, invoked with the VM context and the object itself by the
+/// executor, at the first safe point after the collection which found the object unreachable
+pub type Finalizer = Box<dyn FnOnce(&mut dyn VMContext, Value) + Send + Sync>;
+
 /// Abstract memory manager of `AL31F` engine
 pub trait Alloc: 'static + Send + Sync {
     /// Add one stack to `Alloc` management
@@ -20,18 +62,64 @@ pub trait Alloc: 'static + Send + Sync {
     unsafe fn add_managed(&mut self, data: Value);
 
     /// Mark the object denoted by `data` as useful when it gets added into some container. This
-    /// method is used by tri-color GC.
+    /// method serves as the write barrier of tri-color and generational GC.
     unsafe fn mark_object(&mut self, data: Value);
 
+    /// Register a managed `Weak` object, so that its target gets cleared once the target becomes
+    /// unreachable
+    unsafe fn add_weak_ref(&mut self, weak: Value);
+
+    /// Register a finalizer for the managed object denoted by `data`. When `data` becomes
+    /// unreachable, it is kept alive and its finalizer gets queued. The executor takes queued
+    /// finalizers via `take_pending_finalizer` between instructions and runs them, keeping `data`
+    /// rooted until its finalizer returns.
+    ///
+    /// Host code should use `VMContext::add_finalizer` instead.
+    unsafe fn add_finalizer(&mut self, data: Value, finalizer: Finalizer);
+
+    /// Check if previous collections queued finalizers which are not taken yet. This reads a flag
+    /// set by collections, so the executor may check it between instructions at little cost.
+    fn has_pending_finalizers(&self) -> bool;
+
+    /// Take one finalizer queued by previous collections, along with the object to finalize.
+    /// Objects with queued finalizers are treated as roots until taken.
+    fn take_pending_finalizer(&mut self) -> Option<(Value, Finalizer)>;
+
     /// Pin the object denoted by `data` pointer, thus it is scanned every turn. This effect
     /// lasts until the `non_flex` part of `AllocPin` is cleared.
     ///
     /// This "pin" is irrelevant with `std::pin`.
     unsafe fn pin_objects(&mut self, pinned: &[Value]) -> *mut bool;
 
-    /// Perform garbage collection
+    /// Register the object denoted by `data` as a root, thus it is scanned every turn until the
+    /// returned flag gets cleared. Unlike `pin_objects`, the flag is shared, so it may be cleared
+    /// from other threads, or after this `Alloc` has been dropped.
+    ///
+    /// # Safety
+    /// `data` must be managed by this `Alloc`.
+    unsafe fn root_object(&mut self, data: Value) -> Arc<AtomicBool>;
+
+    /// Perform garbage collection. This always collects, regardless of `set_gc_allowed` and
+    /// `set_gc_enabled`
     unsafe fn collect(&mut self);
 
-    /// Allow or disallow garbage collection
+    /// Allow or disallow garbage collection. This is controlled by the VM, and garbage collection
+    /// is only allowed after the VM gets fully initialized
     fn set_gc_allowed(&mut self, allowed: bool);
+
+    /// Check if garbage collection is allowed, as set by `set_gc_allowed`
+    fn gc_allowed(&self) -> bool;
+
+    /// Enable or disable automatic garbage collection. This is controlled by the user, and is
+    /// independent of `set_gc_allowed`
+    fn set_gc_enabled(&mut self, enabled: bool);
+
+    /// Set the debt thresholds which trigger garbage collection and pin cleanup
+    fn set_max_debt(&mut self, max_debt: usize, max_pin_debt: usize);
+
+    /// Query statistics of this allocator
+    fn stats(&self) -> AllocStats;
+
+    /// Install or remove the callback invoked on collection start and end
+    fn set_gc_callback(&mut self, callback: Option<GCCallback>);
 }
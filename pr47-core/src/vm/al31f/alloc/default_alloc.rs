@@ -1,39 +1,350 @@
 use std::collections::VecDeque;
+use std::mem::take;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 use unchecked_unwrap::UncheckedUnwrap;
 
+use crate::builtins::weak::Weak;
 use crate::data::Value;
 use crate::data::generic::GenericTypeVT;
 use crate::data::wrapper::{DynBase, OWN_INFO_COLLECT_MASK, OWN_INFO_GLOBAL_MASK};
-use crate::vm::al31f::alloc::{Alloc, AllocPin};
+use crate::vm::al31f::alloc::{Alloc, AllocPin, AllocStats, Finalizer, GCCallback, GCEvent};
+use crate::vm::al31f::alloc::snapshot::HeapSnapshot;
 use crate::vm::al31f::stack::Stack;
 
-/// Default allocator for `AL31F`, with STW GC.
+/// Default allocator for `AL31F`, with generational STW GC.
+///
+/// Newly allocated objects are placed into the young generation, which gets collected each time
+/// the allocation debt exceeds `max_debt` ("minor collection"). Young objects surviving a minor
+/// collection are promoted to the old generation. Old objects are only scanned and reclaimed
+/// during major collections, which happen when the old generation grows beyond
+/// `major_threshold`, or when `collect` is called explicitly.
+///
+/// References from old objects to young objects are tracked by the remembered set, which is fed
+/// by the write barrier `mark_object`. Any value stored into a container must go through the
+/// barrier, otherwise young objects only reachable from old containers may get reclaimed.
+/// Young objects that cannot be traced because they are lent to Rust code are also kept in the
+/// remembered set, since their referrers may get promoted meanwhile.
+///
+/// Targets of registered `Weak` objects are cleared before finalizers get queued, so objects
+/// resurrected for finalization are never observable through weak references. Queued finalizers
+/// are not run by the allocator, but taken by the executor at safe points.
 pub struct DefaultAlloc {
     stacks: Vec<*const Stack>,
-    managed: Vec<Value>,
+    young: Vec<Value>,
+    old: Vec<Value>,
+    remembered: Vec<Value>,
+    weak_refs: Vec<Value>,
+    finalizers: Vec<(Value, Finalizer)>,
+    pending_finalizers: VecDeque<(Value, Finalizer)>,
+    has_pending_finalizers: bool,
     pinned: Vec<AllocPin>,
+    rooted: Vec<(Arc<AtomicBool>, Value)>,
     debt: usize,
     pin_debt: usize,
     max_debt: usize,
     max_pin_debt: usize,
-    gc_allowed: bool
+    major_threshold: usize,
+    gc_allowed: bool,
+    gc_enabled: bool,
+    stats: AllocStats,
+    callback: Option<GCCallback>
 }
 
 #[repr(u8)]
 pub enum DefaultGCStatus {
     Unmarked = 0,
-    Marked = 1
+    Marked = 1,
+    Old = 2,
+    Remembered = 3
 }
 
 pub const DEFAULT_MAX_DEBT: usize = 1024;
 pub const DEFAULT_MAX_PIN_DEBT: usize = 128;
+pub const DEFAULT_MIN_MAJOR_THRESHOLD: usize = 8192;
 
 impl DefaultAlloc {
     unsafe fn cleanup_pins(&mut self) {
         self.pinned.retain(|pinned: &AllocPin| *pinned.fixed());
+        // roots may be released by `RootedValue`s dropped from other threads
+        self.rooted.retain(|(flag, _): &(Arc<AtomicBool>, Value)| flag.load(Ordering::Acquire));
         self.pin_debt = 0;
     }
+
+    unsafe fn collect_roots(&self, to_scan: &mut VecDeque<Value>) {
+        for stack /*: &*const Stack*/ in self.stacks.iter() {
+            #[cfg(debug_assertions)]
+            for stack_value /*: &Option<Value>*/ in (**stack).values() {
+                if let Some(stack_value /*: &Value*/) = stack_value {
+                    if !stack_value.is_null() && !stack_value.is_value() {
+                        to_scan.push_back(*stack_value);
+                    }
+                }
+            }
+
+            #[cfg(not(debug_assertions))]
+            for stack_value /*: &Value*/ in (**stack).values() {
+                if !stack_value.is_null() && !stack_value.is_value() {
+                    to_scan.push_back(*stack_value);
+                }
+            }
+        }
+
+        for pin /*: &AllocPin*/ in self.pinned.iter() {
+            for pinned_object /*: &Value*/ in pin.flex().iter() {
+                if !pinned_object.is_null() && !pinned_object.is_value() {
+                    to_scan.push_back(*pinned_object);
+                }
+            }
+        }
+
+        for (_, rooted_object) /*: &(Arc<AtomicBool>, Value)*/ in self.rooted.iter() {
+            if !rooted_object.is_null() && !rooted_object.is_value() {
+                to_scan.push_back(*rooted_object);
+            }
+        }
+
+        for (pending_object, _) /*: &(Value, Finalizer)*/ in self.pending_finalizers.iter() {
+            to_scan.push_back(*pending_object);
+        }
+    }
+
+    /// Marks everything reachable from `to_scan`. `Marked` and `Old` objects are not traversed,
+    /// so during minor collections old objects act as a boundary. Objects skipped because they
+    /// are not collectable right now are put into `remembered`, so that they are still treated as
+    /// roots by the next minor collection after being returned to the VM.
+    unsafe fn mark(to_scan: &mut VecDeque<Value>, remembered: &mut Vec<Value>) {
+        while let Some(value /*: Value*/) = to_scan.pop_front() {
+            if value.is_null() || value.is_value() {
+                continue;
+            }
+
+            let gc_info: u8 = value.gc_info();
+            let ownership_info: u8 = value.ownership_info() as u8;
+            if (gc_info == DefaultGCStatus::Marked as u8) ||
+                (gc_info == DefaultGCStatus::Old as u8) ||
+                (ownership_info & OWN_INFO_GLOBAL_MASK != 0)
+            {
+                continue;
+            }
+
+            if ownership_info & OWN_INFO_COLLECT_MASK == 0 {
+                if gc_info == DefaultGCStatus::Unmarked as u8 {
+                    value.set_gc_info(DefaultGCStatus::Remembered as u8);
+                    remembered.push(value);
+                }
+                continue;
+            }
+
+            value.set_gc_info(DefaultGCStatus::Marked as u8);
+            if !value.is_container() {
+                let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
+                if let Some(children /*: Box<dyn Iterator>*/) = (*dyn_base).children() {
+                    for child /*: Value*/ in children {
+                        to_scan.push_back(child);
+                    }
+                }
+            } else {
+                let container_vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+                let data: *const () = value.get_as_mut_ptr() as *const ();
+                if let Some(children /*: Box<dyn Iterator> */) = ((*container_vt).children_fn)(data)
+                {
+                    for child /*: Value*/ in children {
+                        to_scan.push_back(child);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks if `value` is going to be reclaimed by the ongoing collection.
+    unsafe fn is_garbage(value: &Value) -> bool {
+        let ownership_info: u8 = value.ownership_info() as u8;
+        let gc_info: u8 = value.gc_info();
+        gc_info != DefaultGCStatus::Marked as u8 &&
+            gc_info != DefaultGCStatus::Old as u8 &&
+            (ownership_info & OWN_INFO_COLLECT_MASK != 0) &&
+            (ownership_info & OWN_INFO_GLOBAL_MASK == 0)
+    }
+
+    /// Clears weak references to unreachable objects, then resurrects unreachable objects with
+    /// finalizers and queues their finalizers. Must be called between marking and sweeping.
+    unsafe fn process_unreachable(&mut self) {
+        self.weak_refs.retain(|weak: &Value| {
+            if Self::is_garbage(weak) {
+                return false;
+            }
+            if weak.ownership_info().is_readable() {
+                let weak: &Weak = &*(weak.get_as_mut_ptr::<Weak>() as *const Weak);
+                let target: Value = weak.get();
+                if !target.is_null() && !target.is_value() && Self::is_garbage(&target) {
+                    weak.clear();
+                }
+            }
+            true
+        });
+
+        let mut to_scan: VecDeque<Value> = VecDeque::new();
+        for (value, finalizer) /*: (Value, Finalizer)*/ in take(&mut self.finalizers) {
+            if Self::is_garbage(&value) {
+                to_scan.push_back(value);
+                self.pending_finalizers.push_back((value, finalizer));
+                self.has_pending_finalizers = true;
+            } else {
+                self.finalizers.push((value, finalizer));
+            }
+        }
+        Self::mark(&mut to_scan, &mut self.remembered);
+    }
+
+    /// Reclaims `value` if it was not marked and is collectable. Returns `true` if the object
+    /// is still alive.
+    unsafe fn sweep_one(value: &Value) -> bool {
+        if Self::is_garbage(value) {
+            if value.is_container() {
+                let container: *mut () = value.untagged_ptr_field() as *mut _;
+                let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+                ((*vt).drop_fn)(container);
+            } else {
+                let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
+                let boxed: Box<dyn DynBase> = Box::from_raw(dyn_base);
+                drop(boxed);
+            }
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Sweeps the young generation, moving marked objects into the old generation. Unmarked
+    /// objects that were kept alive for other reasons (for example, lent to Rust code) stay in the
+    /// young generation.
+    unsafe fn sweep_young(&mut self) {
+        let old: &mut Vec<Value> = &mut self.old;
+        self.young.retain(|value: &Value| {
+            if !Self::sweep_one(value) {
+                false
+            } else if value.gc_info() == DefaultGCStatus::Marked as u8 {
+                value.set_gc_info(DefaultGCStatus::Old as u8);
+                old.push(*value);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Collects the young generation only. Roots are the stacks, pinned objects and the
+    /// remembered set.
+    ///
+    /// # Safety
+    /// All registered stacks and pinned objects must be valid, and every store of a young object
+    /// into an old container must have gone through `mark_object`.
+    pub unsafe fn collect_minor(&mut self) {
+        self.collect_with_events(false);
+    }
+
+    /// Collects both generations.
+    ///
+    /// # Safety
+    /// All registered stacks and pinned objects must be valid.
+    pub unsafe fn collect_major(&mut self) {
+        self.collect_with_events(true);
+    }
+
+    unsafe fn collect_with_events(&mut self, major: bool) {
+        let managed_before: usize = self.young.len() + self.old.len();
+        #[cfg(feature = "with-log")]
+        log::debug!("GC: {} collection started, {} objects managed",
+                    if major { "major" } else { "minor" },
+                    managed_before);
+        #[cfg(feature = "with-tracing")]
+        let _span: tracing::span::EnteredSpan =
+            tracing::debug_span!("gc_collect", major, managed_before).entered();
+
+        if let Some(callback /*: &mut GCCallback*/) = &mut self.callback {
+            callback(&GCEvent::CollectStart { major });
+        }
+
+        let start: Instant = Instant::now();
+        if major {
+            self.collect_major_impl();
+        } else {
+            self.collect_minor_impl();
+        }
+        let pause: Duration = start.elapsed();
+
+        let live_count: usize = self.young.len() + self.old.len();
+        let reclaimed: usize = managed_before - live_count;
+        self.stats.live_count = live_count;
+        self.stats.collections += 1;
+        if major {
+            self.stats.major_collections += 1;
+        }
+        self.stats.last_pause = pause;
+        self.stats.total_pause += pause;
+
+        #[cfg(feature = "with-log")]
+        log::debug!("GC: {} collection finished, {} objects reclaimed, {} alive, took {:?}",
+                    if major { "major" } else { "minor" },
+                    reclaimed,
+                    live_count,
+                    pause);
+        #[cfg(feature = "with-tracing")]
+        tracing::debug!(reclaimed, live_count, pause_us = pause.as_micros() as u64, "gc finished");
+
+        if let Some(callback /*: &mut GCCallback*/) = &mut self.callback {
+            callback(&GCEvent::CollectEnd { major, reclaimed, pause });
+        }
+    }
+
+    unsafe fn collect_minor_impl(&mut self) {
+        self.cleanup_pins();
+        self.debt = 0;
+
+        // young objects are either `Unmarked` or `Remembered` here: survivors of the previous
+        // collection have been promoted, and the rest have never been marked.
+        let mut to_scan: VecDeque<Value> = VecDeque::new();
+        self.collect_roots(&mut to_scan);
+        for value /*: Value*/ in self.remembered.drain(..) {
+            if value.gc_info() == DefaultGCStatus::Remembered as u8 {
+                value.set_gc_info(DefaultGCStatus::Unmarked as u8);
+            }
+            to_scan.push_back(value);
+        }
+        Self::mark(&mut to_scan, &mut self.remembered);
+        self.process_unreachable();
+
+        self.sweep_young();
+    }
+
+    unsafe fn collect_major_impl(&mut self) {
+        self.cleanup_pins();
+        self.debt = 0;
+        self.remembered.clear();
+
+        for value /*: &Value*/ in self.young.iter().chain(self.old.iter()) {
+            value.set_gc_info(DefaultGCStatus::Unmarked as u8);
+        }
+
+        let mut to_scan: VecDeque<Value> = VecDeque::new();
+        self.collect_roots(&mut to_scan);
+        Self::mark(&mut to_scan, &mut self.remembered);
+        self.process_unreachable();
+
+        self.old.retain(|value: &Value| {
+            let alive: bool = Self::sweep_one(value);
+            if alive {
+                value.set_gc_info(DefaultGCStatus::Old as u8);
+            }
+            alive
+        });
+        self.sweep_young();
+
+        self.major_threshold = usize::max(self.old.len() * 2, DEFAULT_MIN_MAJOR_THRESHOLD);
+    }
 }
 
 impl DefaultAlloc {
@@ -44,19 +355,51 @@ impl DefaultAlloc {
     pub fn with_max_debt(max_debt: usize, max_pin_debt: usize) -> Self {
         Self {
             stacks: Vec::new(),
-            managed: Vec::new(),
+            young: Vec::new(),
+            old: Vec::new(),
+            remembered: Vec::new(),
+            weak_refs: Vec::new(),
+            finalizers: Vec::new(),
+            pending_finalizers: VecDeque::new(),
+            has_pending_finalizers: false,
             pinned: Vec::new(),
+            rooted: Vec::new(),
             debt: 0,
             pin_debt: 0,
             max_debt,
             max_pin_debt,
-            gc_allowed: false
+            major_threshold: DEFAULT_MIN_MAJOR_THRESHOLD,
+            gc_allowed: false,
+            gc_enabled: true,
+            stats: AllocStats::default(),
+            callback: None
         }
     }
 
+    /// Take a snapshot of all managed objects, with stacks and pinned objects as roots
+    ///
+    /// # Safety
+    /// All registered stacks and pinned objects must be valid.
+    pub unsafe fn heap_snapshot(&self) -> HeapSnapshot {
+        HeapSnapshot::take(
+            self.young.iter().chain(self.old.iter()),
+            &self.stacks,
+            &self.pinned,
+            &self.rooted
+        )
+    }
+
     #[cfg(test)]
     pub fn contains_ptr(&self, ptr: xjbutil::wide_ptr::WidePointer) -> bool {
-        self.managed.iter().map(|x| unsafe { x.ptr_repr }).any(|x| x == ptr)
+        self.young.iter()
+            .chain(self.old.iter())
+            .map(|x| unsafe { x.ptr_repr })
+            .any(|x| x == ptr)
+    }
+
+    #[cfg(test)]
+    pub fn is_old(&self, ptr: xjbutil::wide_ptr::WidePointer) -> bool {
+        self.old.iter().map(|x| unsafe { x.ptr_repr }).any(|x| x == ptr)
     }
 }
 
@@ -69,7 +412,7 @@ impl Default for DefaultAlloc {
 impl Drop for DefaultAlloc {
     fn drop(&mut self) {
         // TODO should we extract this out? Or we use `Value` or so instead?
-        for value /*: &Value*/ in self.managed.iter() {
+        for value /*: &Value*/ in self.young.iter().chain(self.old.iter()) {
             let ownership_info: u8 = unsafe { value.ownership_info() as u8 };
 
             if ownership_info & OWN_INFO_COLLECT_MASK == 0 {
@@ -81,7 +424,7 @@ impl Drop for DefaultAlloc {
 
             if value.is_container() {
                 unsafe {
-                    let container: *mut () = value.ptr_repr.ptr as *mut _;
+                    let container: *mut () = value.untagged_ptr_field() as *mut _;
                     let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
                     ((*vt).drop_fn)(container);
                 }
@@ -110,15 +453,44 @@ impl Alloc for DefaultAlloc {
     }
 
     unsafe fn add_managed(&mut self, data: Value) {
-        if self.max_debt < self.debt && self.gc_allowed {
-            self.collect();
+        if self.max_debt < self.debt && self.gc_allowed && self.gc_enabled {
+            if self.old.len() >= self.major_threshold {
+                self.collect_major();
+            } else {
+                self.collect_minor();
+            }
         }
-        self.managed.push(data);
+        self.young.push(data);
         self.debt += 1;
     }
 
-    #[inline(always)] unsafe fn mark_object(&mut self, _data: Value) {
-        // do nothing
+    #[inline(always)] unsafe fn mark_object(&mut self, data: Value) {
+        // already remembered objects are not pushed again, so the remembered set does not grow
+        // unboundedly while collection is disabled
+        if !data.is_null() && !data.is_value()
+            && data.gc_info() == DefaultGCStatus::Unmarked as u8
+        {
+            data.set_gc_info(DefaultGCStatus::Remembered as u8);
+            self.remembered.push(data);
+        }
+    }
+
+    unsafe fn add_weak_ref(&mut self, weak: Value) {
+        self.weak_refs.push(weak);
+    }
+
+    unsafe fn add_finalizer(&mut self, data: Value, finalizer: Finalizer) {
+        self.finalizers.push((data, finalizer));
+    }
+
+    #[inline(always)] fn has_pending_finalizers(&self) -> bool {
+        self.has_pending_finalizers
+    }
+
+    fn take_pending_finalizer(&mut self) -> Option<(Value, Finalizer)> {
+        let ret: Option<(Value, Finalizer)> = self.pending_finalizers.pop_front();
+        self.has_pending_finalizers = !self.pending_finalizers.is_empty();
+        ret
     }
 
     unsafe fn pin_objects(&mut self, pinned: &[Value]) -> *mut bool {
@@ -133,101 +505,51 @@ impl Alloc for DefaultAlloc {
         ret_ptr
     }
 
-    unsafe fn collect(&mut self) {
-        self.cleanup_pins();
-        self.debt = 0;
-
-        for value /*: &Value*/ in self.managed.iter() {
-            value.set_gc_info(DefaultGCStatus::Unmarked as u8);
+    unsafe fn root_object(&mut self, data: Value) -> Arc<AtomicBool> {
+        self.pin_debt += 1;
+        if self.pin_debt > self.max_pin_debt {
+            self.cleanup_pins();
         }
 
-        let mut to_scan: VecDeque<Value> = VecDeque::new();
+        let flag: Arc<AtomicBool> = Arc::new(AtomicBool::new(true));
+        self.rooted.push((flag.clone(), data));
+        flag
+    }
 
-        for stack /*: &*const Stack*/ in self.stacks.iter() {
-            #[cfg(debug_assertions)]
-            for stack_value /*: &Option<Value>*/ in (**stack).values.iter() {
-                if let Some(stack_value /*: &Value*/) = stack_value {
-                    if !stack_value.is_null() && !stack_value.is_value() {
-                        to_scan.push_back(*stack_value);
-                    }
-                }
-            }
+    unsafe fn collect(&mut self) {
+        self.collect_major();
+    }
 
-            #[cfg(not(debug_assertions))]
-            for stack_value /*: &Value*/ in (**stack).values.iter() {
-                if !stack_value.is_null() && !stack_value.is_value() {
-                    to_scan.push_back(*stack_value);
-                }
-            }
-        }
+    fn set_gc_allowed(&mut self, allowed: bool) {
+        self.gc_allowed = allowed;
+    }
 
-        for pin /*: &AllocPin*/ in self.pinned.iter() {
-            for pinned_object /*: &Value*/ in pin.flex().iter() {
-                if !pinned_object.is_null() && !pinned_object.is_value() {
-                    to_scan.push_back(*pinned_object);
-                }
-            }
-        }
+    fn gc_allowed(&self) -> bool {
+        self.gc_allowed
+    }
 
-        while !to_scan.is_empty() {
-            let value: Value = to_scan.pop_front().unwrap();
-            let gc_info: u8 = value.gc_info() as u8;
-            let ownership_info: u8 = value.ownership_info() as u8;
+    fn set_gc_enabled(&mut self, enabled: bool) {
+        self.gc_enabled = enabled;
+    }
 
-            if value.is_null() ||
-                value.is_value() ||
-                (gc_info == DefaultGCStatus::Marked as u8) ||
-                (ownership_info & OWN_INFO_COLLECT_MASK == 0) ||
-                (ownership_info & OWN_INFO_GLOBAL_MASK != 0)
-            {
-                continue;
-            }
+    fn set_max_debt(&mut self, max_debt: usize, max_pin_debt: usize) {
+        self.max_debt = max_debt;
+        self.max_pin_debt = max_pin_debt;
+    }
 
-            value.set_gc_info(DefaultGCStatus::Marked as u8);
-            if !value.is_container() {
-                let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
-                if let Some(children /*: Box<dyn Iterator>*/) = (*dyn_base).children() {
-                    for child /*: Value*/ in children {
-                        to_scan.push_back(child);
-                    }
-                }
-            } else {
-                let container_vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
-                let data: *const () = value.get_as_mut_ptr() as *const ();
-                if let Some(children /*: Box<dyn Iterator> */) = ((*container_vt).children_fn)(data)
-                {
-                    for child /*: Value*/ in children {
-                        to_scan.push_back(child);
-                    }
-                }
-            }
+    fn stats(&self) -> AllocStats {
+        AllocStats {
+            managed_count: self.young.len() + self.old.len(),
+            pinned_count: self.pinned.iter().map(|pin: &AllocPin| pin.flex().len()).sum::<usize>()
+                + self.rooted.len(),
+            debt: self.debt,
+            max_debt: self.max_debt,
+            ..self.stats
         }
-
-        self.managed.retain(|value: &Value| {
-            let ownership_info: u8 = value.ownership_info() as u8;
-            let gc_info: u8 = value.gc_info() as u8;
-            if gc_info == DefaultGCStatus::Unmarked as u8 &&
-                (ownership_info & OWN_INFO_COLLECT_MASK != 0) &&
-                (ownership_info & OWN_INFO_GLOBAL_MASK == 0)
-            {
-                if value.is_container() {
-                    let container: *mut () = value.untagged_ptr_field() as *mut _;
-                    let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
-                    ((*vt).drop_fn)(container);
-                } else {
-                    let dyn_base: *mut dyn DynBase = value.get_as_dyn_base();
-                    let boxed: Box<dyn DynBase> = Box::from_raw(dyn_base);
-                    drop(boxed);
-                }
-                false
-            } else {
-                true
-            }
-        });
     }
 
-    fn set_gc_allowed(&mut self, allowed: bool) {
-        self.gc_allowed = allowed;
+    fn set_gc_callback(&mut self, callback: Option<GCCallback>) {
+        self.callback = callback;
     }
 }
 
@@ -346,4 +668,243 @@ mod test {
             assert!(!alloc.contains_ptr(container.ptr_repr));
         }
     }
+
+    #[test] fn test_default_collector_generational() {
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let mut stack: Stack = Stack::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 2, &[]) };
+
+        let str1: Value = Value::new_owned::<String>("114".into());
+        let str2: Value = Value::new_owned::<String>("514".into());
+        let container: Value = Value::new_owned::<TestContainer<String>>(TestContainer::new());
+
+        unsafe {
+            alloc.add_stack(&stack);
+            alloc.add_managed(str1);
+            alloc.add_managed(container);
+
+            stack_slice.set_value(0, str1);
+            stack_slice.set_value(1, container);
+            alloc.collect_minor();
+            assert!(alloc.is_old(str1.ptr_repr));
+            assert!(alloc.is_old(container.ptr_repr));
+
+            // old objects are not reclaimed by minor collections
+            stack_slice.set_value(0, Value::new_null());
+            alloc.collect_minor();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+
+            // young objects stored into old containers are kept alive by the remembered set
+            alloc.add_managed(str2);
+            alloc.mark_object(str2);
+            (*(container.get_as_mut_ptr::<TestContainer<String>>()))
+                .inner.elements.push(str2);
+            alloc.collect_minor();
+            assert!(alloc.is_old(str2.ptr_repr));
+
+            alloc.collect();
+            assert!(!alloc.contains_ptr(str1.ptr_repr));
+            assert!(alloc.contains_ptr(str2.ptr_repr));
+            assert!(alloc.contains_ptr(container.ptr_repr));
+
+            stack_slice.set_value(1, Value::new_null());
+            alloc.collect();
+            assert!(!alloc.contains_ptr(str2.ptr_repr));
+            assert!(!alloc.contains_ptr(container.ptr_repr));
+        }
+    }
+
+    #[test] fn test_default_collector_minor_shared_child() {
+        use crate::data::wrapper::OwnershipInfo;
+
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let mut stack: Stack = Stack::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 1, &[]) };
+
+        let str1: Value = Value::new_owned::<String>("114".into());
+        let mut container: TestContainer<String> = TestContainer::new();
+        container.inner.elements.push(str1);
+        let container: Value = Value::new_owned::<TestContainer<String>>(container);
+
+        unsafe {
+            alloc.add_stack(&stack);
+            alloc.add_managed(str1);
+            alloc.add_managed(container);
+            stack_slice.set_value(0, container);
+
+            // the child is lent to Rust, so it cannot be traced while its parent gets promoted
+            str1.set_ownership_info(OwnershipInfo::SharedToRust);
+            alloc.collect_minor();
+            assert!(alloc.is_old(container.ptr_repr));
+            assert!(!alloc.is_old(str1.ptr_repr));
+
+            str1.set_ownership_info(OwnershipInfo::VMOwned);
+            alloc.collect_minor();
+            assert!(alloc.is_old(str1.ptr_repr));
+            assert_eq!(&*(str1.get_as_mut_ptr::<String>() as *const String), "114");
+
+            alloc.collect();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+        }
+    }
+
+    #[test] fn test_default_collector_remembered_dedup() {
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let str1: Value = Value::new_owned::<String>("514".into());
+
+        unsafe {
+            alloc.add_managed(str1);
+            for _ in 0..16 {
+                alloc.mark_object(str1);
+            }
+        }
+        assert_eq!(alloc.remembered.len(), 1);
+    }
+
+    #[test] fn test_default_collector_minor_reclaims_young() {
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let stack: Stack = Stack::new();
+
+        let str1: Value = Value::new_owned::<String>("1919810".into());
+        unsafe {
+            alloc.add_stack(&stack);
+            alloc.add_managed(str1);
+            alloc.collect_minor();
+            assert!(!alloc.contains_ptr(str1.ptr_repr));
+        }
+    }
+
+    #[test] fn test_default_collector_stats_and_events() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use crate::vm::al31f::alloc::{AllocStats, GCEvent};
+
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let stack: Stack = Stack::new();
+
+        let events: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let reclaimed: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let events1: Arc<AtomicUsize> = events.clone();
+        let reclaimed1: Arc<AtomicUsize> = reclaimed.clone();
+        alloc.set_gc_callback(Some(Box::new(move |event: &GCEvent| {
+            events1.fetch_add(1, Ordering::SeqCst);
+            if let GCEvent::CollectEnd { reclaimed, .. } = event {
+                reclaimed1.fetch_add(*reclaimed, Ordering::SeqCst);
+            }
+        })));
+
+        alloc.set_max_debt(1, 128);
+        alloc.set_gc_allowed(true);
+        alloc.set_gc_enabled(false);
+        unsafe {
+            alloc.add_stack(&stack);
+            for i in 0..4 {
+                alloc.add_managed(Value::new_owned::<String>(i.to_string()));
+            }
+        }
+
+        let stats: AllocStats = alloc.stats();
+        assert_eq!(stats.managed_count, 4);
+        assert_eq!(stats.collections, 0);
+        assert_eq!(stats.debt, 4);
+        assert_eq!(stats.max_debt, 1);
+        assert_eq!(events.load(Ordering::SeqCst), 0);
+
+        alloc.set_gc_enabled(true);
+        unsafe {
+            alloc.add_managed(Value::new_owned::<String>("114514".into()));
+        }
+
+        let stats: AllocStats = alloc.stats();
+        assert_eq!(stats.managed_count, 1);
+        assert_eq!(stats.live_count, 0);
+        assert_eq!(stats.collections, 1);
+        assert_eq!(stats.major_collections, 0);
+        assert_eq!(stats.debt, 1);
+        assert_eq!(events.load(Ordering::SeqCst), 2);
+        assert_eq!(reclaimed.load(Ordering::SeqCst), 4);
+
+        unsafe { alloc.collect(); }
+        let stats: AllocStats = alloc.stats();
+        assert_eq!(stats.managed_count, 0);
+        assert_eq!(stats.collections, 2);
+        assert_eq!(stats.major_collections, 1);
+        assert!(stats.total_pause >= stats.last_pause);
+        assert_eq!(events.load(Ordering::SeqCst), 4);
+        assert_eq!(reclaimed.load(Ordering::SeqCst), 5);
+    }
+
+    #[test] fn test_default_collector_weak_and_finalizer() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use crate::builtins::weak::Weak;
+        use crate::data::exception::Exception;
+        use crate::ffi::sync_fn::VMContext;
+        use crate::vm::al31f::alloc::Finalizer;
+        use crate::vm::al31f::handle::RootedValue;
+
+        struct NoContext();
+
+        impl VMContext for NoContext {
+            fn add_heap_managed(&mut self, _value: Value) { unreachable!() }
+            fn mark(&mut self, _value: Value) { unreachable!() }
+            fn root(&mut self, _value: Value) -> RootedValue { unreachable!() }
+            fn add_finalizer(&mut self, _value: Value, _finalizer: Finalizer) { unreachable!() }
+            fn call_value(&mut self, _callee: Value, _args: &[Value])
+                -> Result<Vec<RootedValue>, Exception>
+            {
+                unreachable!()
+            }
+        }
+
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let mut stack: Stack = Stack::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 2, &[]) };
+
+        let str1: Value = Value::new_owned::<String>("114".into());
+        let weak: Value = Value::new_owned(Weak::new(str1));
+        let finalized: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let finalized1: Arc<AtomicBool> = finalized.clone();
+
+        unsafe {
+            alloc.add_stack(&stack);
+            alloc.add_managed(str1);
+            alloc.add_managed(weak);
+            alloc.add_weak_ref(weak);
+            alloc.add_finalizer(str1, Box::new(move |_context: &mut dyn VMContext, value: Value| {
+                let value: &String = &*(value.get_as_mut_ptr::<String>() as *const String);
+                assert_eq!(value, "114");
+                finalized1.store(true, Ordering::SeqCst);
+            }));
+
+            stack_slice.set_value(0, str1);
+            stack_slice.set_value(1, weak);
+            alloc.collect();
+            let weak_ref: &Weak = &*(weak.get_as_mut_ptr::<Weak>() as *const Weak);
+            assert_eq!(weak_ref.get().ptr_repr, str1.ptr_repr);
+            assert!(!finalized.load(Ordering::SeqCst));
+
+            stack_slice.set_value(0, Value::new_null());
+            alloc.collect();
+            assert!(weak_ref.get().is_null());
+            // finalizers are only queued by the collector, and stay alive until taken
+            assert!(!finalized.load(Ordering::SeqCst));
+            alloc.collect();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+            assert!(alloc.has_pending_finalizers());
+
+            let (value, finalizer): (Value, Finalizer) = alloc.take_pending_finalizer().unwrap();
+            assert_eq!(value.ptr_repr, str1.ptr_repr);
+            assert!(!alloc.has_pending_finalizers());
+            assert!(alloc.take_pending_finalizer().is_none());
+            finalizer(&mut NoContext(), value);
+            assert!(finalized.load(Ordering::SeqCst));
+
+            alloc.collect();
+            assert!(!alloc.contains_ptr(str1.ptr_repr));
+        }
+    }
 }
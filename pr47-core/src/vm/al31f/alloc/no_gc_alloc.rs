@@ -1,5 +1,8 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
 use crate::data::Value;
-use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::alloc::{Alloc, AllocStats, Finalizer, GCCallback};
 use crate::vm::al31f::stack::Stack;
 
 pub struct NoGCAlloc {
@@ -33,13 +36,45 @@ impl Alloc for NoGCAlloc {
 
     #[inline(always)] unsafe fn mark_object(&mut self, _data: Value) {}
 
+    #[inline(always)] unsafe fn add_weak_ref(&mut self, _weak: Value) {}
+
+    #[inline(always)] unsafe fn add_finalizer(&mut self, _data: Value, _finalizer: Finalizer) {}
+
+    #[inline(always)] fn has_pending_finalizers(&self) -> bool {
+        false
+    }
+
+    #[inline(always)] fn take_pending_finalizer(&mut self) -> Option<(Value, Finalizer)> {
+        None
+    }
+
     #[inline(always)] unsafe fn pin_objects(&mut self, _pinned: &[Value]) -> *mut bool {
         std::ptr::null_mut()
     }
 
+    unsafe fn root_object(&mut self, _data: Value) -> Arc<AtomicBool> {
+        Arc::new(AtomicBool::new(true))
+    }
+
     #[inline(always)] unsafe fn collect(&mut self) {}
 
     #[inline(always)] fn set_gc_allowed(&mut self, _allowed: bool) {}
+
+    #[inline(always)] fn gc_allowed(&self) -> bool { false }
+
+    #[inline(always)] fn set_gc_enabled(&mut self, _enabled: bool) {}
+
+    #[inline(always)] fn set_max_debt(&mut self, _max_debt: usize, _max_pin_debt: usize) {}
+
+    fn stats(&self) -> AllocStats {
+        AllocStats {
+            managed_count: self.managed.len(),
+            live_count: self.managed.len(),
+            ..AllocStats::default()
+        }
+    }
+
+    #[inline(always)] fn set_gc_callback(&mut self, _callback: Option<GCCallback>) {}
 }
 
 unsafe impl Send for NoGCAlloc {}
@@ -0,0 +1,407 @@
+//! Heap snapshots for inspecting object graphs and leaks
+
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::data::Value;
+use crate::data::generic::GenericTypeVT;
+use crate::data::wrapper::{
+    OWN_INFO_COLLECT_MASK,
+    OWN_INFO_GLOBAL_MASK,
+    OWN_INFO_MOVE_MASK,
+    OWN_INFO_OWNED_MASK,
+    OWN_INFO_READ_MASK,
+    OWN_INFO_WRITE_MASK
+};
+use crate::vm::al31f::alloc::AllocPin;
+use crate::vm::al31f::stack::Stack;
+
+/// One object recorded in a `HeapSnapshot`
+#[derive(Debug, Clone)]
+pub struct HeapObject {
+    /// Address of the object, used as its identity inside the snapshot
+    pub addr: usize,
+    pub type_name: String,
+    pub ownership_info: u8,
+    pub gc_info: u8,
+    /// Whether this object is managed by the allocator. Unmanaged objects are only reachable
+    /// through edges from managed ones
+    pub managed: bool,
+    /// Addresses of objects directly referenced by this object
+    pub children: Vec<usize>
+}
+
+/// Where a root reference comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapRootKind {
+    Stack { stack: usize, slot: usize },
+    Pinned { pin: usize, slot: usize },
+    Rooted { index: usize }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeapRoot {
+    pub kind: HeapRootKind,
+    pub target: usize
+}
+
+/// A chain of references from a root to some object
+#[derive(Debug, Clone)]
+pub struct RetainingPath {
+    pub root: HeapRoot,
+    /// Addresses of the objects on the path, starting from the root target and ending with the
+    /// queried object
+    pub objects: Vec<usize>
+}
+
+/// Snapshot of the object graph managed by an allocator
+#[derive(Debug, Clone, Default)]
+pub struct HeapSnapshot {
+    pub objects: Vec<HeapObject>,
+    pub roots: Vec<HeapRoot>,
+    index: HashMap<usize, usize>
+}
+
+/// Format ownership info flags in `GRWMCO` order, with `-` for unset flags
+pub fn ownership_flags(ownership_info: u8) -> String {
+    [
+        (OWN_INFO_GLOBAL_MASK, 'G'),
+        (OWN_INFO_READ_MASK, 'R'),
+        (OWN_INFO_WRITE_MASK, 'W'),
+        (OWN_INFO_MOVE_MASK, 'M'),
+        (OWN_INFO_COLLECT_MASK, 'C'),
+        (OWN_INFO_OWNED_MASK, 'O')
+    ].iter()
+        .map(|(mask, flag): &(u8, char)| if ownership_info & mask != 0 { *flag } else { '-' })
+        .collect()
+}
+
+fn escape_json(s: &str) -> String {
+    let mut ret: String = String::with_capacity(s.len());
+    for c /*: char*/ in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(ret, "\\u{:04x}", c as u32); },
+            c => ret.push(c)
+        }
+    }
+    ret
+}
+
+impl HeapSnapshot {
+    /// Walk all managed objects, stacks, pinned and rooted objects to create a snapshot
+    ///
+    /// # Safety
+    /// All values, stacks and pins must be valid.
+    pub(crate) unsafe fn take<'a>(
+        managed: impl Iterator<Item=&'a Value>,
+        stacks: &[*const Stack],
+        pinned: &[AllocPin],
+        rooted: &[(Arc<AtomicBool>, Value)]
+    ) -> Self {
+        let mut ret: HeapSnapshot = HeapSnapshot::default();
+        let mut to_visit: VecDeque<Value> = VecDeque::new();
+
+        for value /*: &Value*/ in managed {
+            ret.add_object(*value, true, &mut to_visit);
+        }
+
+        for (stack_idx, stack) /*: (usize, &*const Stack)*/ in stacks.iter().enumerate() {
+            #[cfg(debug_assertions)]
+            for (slot, stack_value) /*: (usize, &Option<Value>)*/ in
+                (**stack).values().enumerate()
+            {
+                if let Some(stack_value /*: &Value*/) = stack_value {
+                    if !stack_value.is_null() && !stack_value.is_value() {
+                        ret.roots.push(HeapRoot {
+                            kind: HeapRootKind::Stack { stack: stack_idx, slot },
+                            target: stack_value.untagged_ptr_field()
+                        });
+                        to_visit.push_back(*stack_value);
+                    }
+                }
+            }
+
+            #[cfg(not(debug_assertions))]
+            for (slot, stack_value) /*: (usize, &Value)*/ in (**stack).values().enumerate() {
+                if !stack_value.is_null() && !stack_value.is_value() {
+                    ret.roots.push(HeapRoot {
+                        kind: HeapRootKind::Stack { stack: stack_idx, slot },
+                        target: stack_value.untagged_ptr_field()
+                    });
+                    to_visit.push_back(*stack_value);
+                }
+            }
+        }
+
+        for (pin_idx, pin) /*: (usize, &AllocPin)*/ in pinned.iter().enumerate() {
+            if !*pin.fixed() {
+                continue;
+            }
+            for (slot, pinned_object) /*: (usize, &Value)*/ in pin.flex().iter().enumerate() {
+                if !pinned_object.is_null() && !pinned_object.is_value() {
+                    ret.roots.push(HeapRoot {
+                        kind: HeapRootKind::Pinned { pin: pin_idx, slot },
+                        target: pinned_object.untagged_ptr_field()
+                    });
+                    to_visit.push_back(*pinned_object);
+                }
+            }
+        }
+
+        for (index, (flag, rooted_object)) /*: (usize, &(Arc<AtomicBool>, Value))*/ in
+            rooted.iter().enumerate()
+        {
+            if flag.load(Ordering::Acquire)
+                && !rooted_object.is_null()
+                && !rooted_object.is_value()
+            {
+                ret.roots.push(HeapRoot {
+                    kind: HeapRootKind::Rooted { index },
+                    target: rooted_object.untagged_ptr_field()
+                });
+                to_visit.push_back(*rooted_object);
+            }
+        }
+
+        // objects not managed by the allocator but reachable from roots or managed objects
+        while let Some(value /*: Value*/) = to_visit.pop_front() {
+            if !ret.index.contains_key(&value.untagged_ptr_field()) {
+                ret.add_object(value, false, &mut to_visit);
+            }
+        }
+
+        ret
+    }
+
+    unsafe fn add_object(&mut self, value: Value, managed: bool, to_visit: &mut VecDeque<Value>) {
+        let addr: usize = value.untagged_ptr_field();
+        let ownership_info: u8 = value.ownership_info() as u8;
+
+        let type_name: String = if value.is_container() {
+            let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+            (*vt).type_name.clone()
+        } else {
+            (*value.get_as_dyn_base()).dyn_type_name()
+        };
+
+        let mut children: Vec<usize> = Vec::new();
+        if ownership_info & OWN_INFO_READ_MASK != 0 {
+            let children_iter: Option<Box<dyn Iterator<Item=Value>>> = if value.is_container() {
+                let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+                ((*vt).children_fn)(value.get_as_mut_ptr() as *const ())
+            } else {
+                (*value.get_as_dyn_base()).children()
+            };
+            if let Some(children_iter /*: Box<dyn Iterator>*/) = children_iter {
+                for child /*: Value*/ in children_iter {
+                    if !child.is_null() && !child.is_value() {
+                        children.push(child.untagged_ptr_field());
+                        to_visit.push_back(child);
+                    }
+                }
+            }
+        }
+
+        self.index.insert(addr, self.objects.len());
+        self.objects.push(HeapObject {
+            addr,
+            type_name,
+            ownership_info,
+            gc_info: value.gc_info(),
+            managed,
+            children
+        });
+    }
+
+    /// Look up an object by its address
+    pub fn get(&self, addr: usize) -> Option<&HeapObject> {
+        self.index.get(&addr).map(|idx: &usize| &self.objects[*idx])
+    }
+
+    /// Find a shortest chain of references from some root to the object at `addr`, explaining
+    /// why it is still alive. Returns `None` if the object is unreachable.
+    pub fn retaining_path(&self, addr: usize) -> Option<RetainingPath> {
+        let mut parent: HashMap<usize, Option<usize>> = HashMap::new();
+        let mut root_of: HashMap<usize, HeapRoot> = HashMap::new();
+        let mut queue: VecDeque<usize> = VecDeque::new();
+
+        for root /*: &HeapRoot*/ in self.roots.iter() {
+            if let Entry::Vacant(entry) = parent.entry(root.target) {
+                entry.insert(None);
+                root_of.insert(root.target, *root);
+                queue.push_back(root.target);
+            }
+        }
+
+        while let Some(current /*: usize*/) = queue.pop_front() {
+            if current == addr {
+                let mut objects: Vec<usize> = vec![current];
+                let mut cursor: usize = current;
+                while let Some(Some(prev /*: &usize*/)) = parent.get(&cursor) {
+                    objects.push(*prev);
+                    cursor = *prev;
+                }
+                objects.reverse();
+                return Some(RetainingPath { root: root_of[&cursor], objects });
+            }
+
+            if let Some(object /*: &HeapObject*/) = self.get(current) {
+                for child /*: &usize*/ in object.children.iter() {
+                    if let Entry::Vacant(entry) = parent.entry(*child) {
+                        entry.insert(Some(current));
+                        queue.push_back(*child);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Export this snapshot as JSON
+    pub fn to_json(&self) -> String {
+        let mut ret: String = String::from("{\"objects\":[");
+        for (i, object) /*: (usize, &HeapObject)*/ in self.objects.iter().enumerate() {
+            if i != 0 {
+                ret.push(',');
+            }
+            let _ = write!(
+                ret,
+                "{{\"addr\":\"{:#x}\",\"type\":\"{}\",\"ownership\":\"{}\",\"gc_info\":{},\
+                 \"managed\":{},\"children\":[",
+                object.addr,
+                escape_json(&object.type_name),
+                ownership_flags(object.ownership_info),
+                object.gc_info,
+                object.managed
+            );
+            for (j, child) /*: (usize, &usize)*/ in object.children.iter().enumerate() {
+                if j != 0 {
+                    ret.push(',');
+                }
+                let _ = write!(ret, "\"{:#x}\"", child);
+            }
+            ret.push_str("]}");
+        }
+        ret.push_str("],\"roots\":[");
+        for (i, root) /*: (usize, &HeapRoot)*/ in self.roots.iter().enumerate() {
+            if i != 0 {
+                ret.push(',');
+            }
+            let _ = match root.kind {
+                HeapRootKind::Stack { stack, slot } => write!(
+                    ret,
+                    "{{\"kind\":\"stack\",\"stack\":{},\"slot\":{},\"target\":\"{:#x}\"}}",
+                    stack, slot, root.target
+                ),
+                HeapRootKind::Pinned { pin, slot } => write!(
+                    ret,
+                    "{{\"kind\":\"pinned\",\"pin\":{},\"slot\":{},\"target\":\"{:#x}\"}}",
+                    pin, slot, root.target
+                ),
+                HeapRootKind::Rooted { index } => write!(
+                    ret,
+                    "{{\"kind\":\"rooted\",\"index\":{},\"target\":\"{:#x}\"}}",
+                    index, root.target
+                )
+            };
+        }
+        ret.push_str("]}");
+        ret
+    }
+
+    /// Export this snapshot as a Graphviz DOT graph
+    pub fn to_dot(&self) -> String {
+        let mut ret: String = String::from("digraph heap {\n");
+        for object /*: &HeapObject*/ in self.objects.iter() {
+            let _ = writeln!(
+                ret,
+                "  \"{:#x}\" [label=\"{}\\n{:#x}\\n{} gc={}\"{}];",
+                object.addr,
+                escape_json(&object.type_name),
+                object.addr,
+                ownership_flags(object.ownership_info),
+                object.gc_info,
+                if object.managed { "" } else { ", style=dashed" }
+            );
+        }
+        for (i, root) /*: (usize, &HeapRoot)*/ in self.roots.iter().enumerate() {
+            let label: String = match root.kind {
+                HeapRootKind::Stack { stack, slot } => format!("stack {} %{}", stack, slot),
+                HeapRootKind::Pinned { pin, slot } => format!("pin {} #{}", pin, slot),
+                HeapRootKind::Rooted { index } => format!("rooted {}", index)
+            };
+            let _ = writeln!(ret, "  \"root{}\" [label=\"{}\", shape=box];", i, label);
+            let _ = writeln!(ret, "  \"root{}\" -> \"{:#x}\";", i, root.target);
+        }
+        for object /*: &HeapObject*/ in self.objects.iter() {
+            for child /*: &usize*/ in object.children.iter() {
+                let _ = writeln!(ret, "  \"{:#x}\" -> \"{:#x}\";", object.addr, child);
+            }
+        }
+        ret.push_str("}\n");
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::builtins::test_container::TestContainer;
+    use crate::data::Value;
+    use crate::vm::al31f::alloc::Alloc;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::alloc::snapshot::{HeapRootKind, HeapSnapshot, RetainingPath};
+    use crate::vm::al31f::stack::{Stack, StackSlice};
+
+    #[test] fn test_heap_snapshot() {
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+        let mut stack: Stack = Stack::new();
+
+        let mut stack_slice: StackSlice = unsafe { stack.ext_func_call_grow_stack(0, 2, &[]) };
+
+        let str1: Value = Value::new_owned::<String>("114".into());
+        let str2: Value = Value::new_owned::<String>("514".into());
+        let mut container: TestContainer<String> = TestContainer::new();
+        container.inner.elements.push(str1);
+        let container: Value = Value::new_owned::<TestContainer<String>>(container);
+
+        unsafe {
+            alloc.add_stack(&stack);
+            alloc.add_managed(str1);
+            alloc.add_managed(str2);
+            alloc.add_managed(container);
+            stack_slice.set_value(1, container);
+
+            let snapshot: HeapSnapshot = alloc.heap_snapshot();
+            assert_eq!(snapshot.objects.len(), 3);
+            assert_eq!(snapshot.roots.len(), 1);
+            assert_eq!(snapshot.roots[0].kind, HeapRootKind::Stack { stack: 0, slot: 1 });
+
+            let str1_addr: usize = str1.untagged_ptr_field();
+            let container_addr: usize = container.untagged_ptr_field();
+            assert_eq!(snapshot.get(str1_addr).unwrap().type_name, "string");
+            assert_eq!(snapshot.get(container_addr).unwrap().children, vec![str1_addr]);
+
+            let path: RetainingPath = snapshot.retaining_path(str1_addr).unwrap();
+            assert_eq!(path.objects, vec![container_addr, str1_addr]);
+            assert!(snapshot.retaining_path(str2.untagged_ptr_field()).is_none());
+
+            let json: String = snapshot.to_json();
+            assert!(json.starts_with("{\"objects\":["));
+            assert!(json.contains("\"type\":\"string\""));
+            assert!(json.contains("\"kind\":\"stack\""));
+
+            let dot: String = snapshot.to_dot();
+            assert!(dot.starts_with("digraph heap {"));
+            assert!(dot.contains(&format!("\"{:#x}\" -> \"{:#x}\";", container_addr, str1_addr)));
+        }
+    }
+}
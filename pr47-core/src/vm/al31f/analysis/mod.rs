@@ -1,7 +1,9 @@
 pub mod anf;
+pub mod ownership;
 
 use std::ptr::NonNull;
 
+use crate::data::generic::GenericTypeVT;
 use crate::data::tyck::TyckInfo;
 
 pub enum Instruction {
@@ -407,7 +409,8 @@ pub enum Instruction {
 
         /// `OWNERSHIP-INFO-CHECK [SRC] [MASK]`
         ///
-        /// Check if data stored in `SRC` satisfies given `MASK`, throws RTLC exception if not.
+        /// Check if the ownership info of the object stored in `SRC` has all bits of `MASK` set,
+        /// throws RTLC exception if not. Value-typed data and `null` never pass the check.
         OwnershipInfoCheck(usize, u8),
 
         /// `CALL-UNCHECKED [FUNC-ID] [ARGS..] [RETS..]`
@@ -422,12 +425,15 @@ pub enum Instruction {
         /// values to `RETS`. **No type checking**.
         CallPtr(usize, &'static [usize], &'static [usize]),
 
-        /// `CALL-OVERLOAD [OVERLOAD-TBL] [ARGS..] [RETS..]`
+        /// `CALL-OVERLOAD [CALL-SITE] [ARGS..] [RETS..]`
         CallOverload(usize, &'static [usize], &'static [usize]),
 
         /// `FFI-CALL-RTLC [FFI-FUNC-ID] [ARGS..] [RETS..]`
         FFICallRtlc(usize, &'static [usize], &'static [usize]),
 
+        /// `CALL-METHOD [METHOD-TBL] [ARGS..] [RETS..]`
+        CallMethod(usize, &'static [usize], &'static [usize]),
+
         /// `FFI-CALL [FFI-FUNC-ID] [ARGS..] [RETS..]`
         #[cfg(feature = "optimized-rtlc")]
         FFICall(usize, &'static [usize], &'static [usize]),
@@ -451,6 +457,29 @@ pub enum Instruction {
 
         // CreateContainer(GenericTypeCtor, NonNull<GenericTypeVT>, usize),
 
+        /// `MAKE-CLOSURE [FUNC-ID] [CAPTURES..] [CLOSURE-VT] [DEST]`
+        ///
+        /// Create a closure of the function denoted by `FUNC-ID`, capturing values stored in
+        /// `CAPTURES` in order, and save it to `DEST`. `CLOSURE-VT` should be created by
+        /// `create_closure_vt`.
+        MakeClosure(usize, &'static [usize], NonNull<GenericTypeVT>, usize),
+
+        /// `MAKE-CELL [SRC] [DEST]`
+        ///
+        /// Box the data stored in `SRC` into a new capture cell, and save the cell to `DEST`. Used
+        /// for variables captured by closures and assigned afterwards.
+        #[cfg(feature = "al31f-builtin-ops")] MakeCell(usize, usize),
+
+        /// `CELL-GET [CELL] [DEST]`
+        ///
+        /// Save the data stored in capture cell `CELL` to `DEST`.
+        #[cfg(feature = "al31f-builtin-ops")] CellGet(usize, usize),
+
+        /// `CELL-SET [CELL] [SRC]`
+        ///
+        /// Store the data in `SRC` into capture cell `CELL`.
+        #[cfg(feature = "al31f-builtin-ops")] CellSet(usize, usize),
+
         #[cfg(feature = "al31f-builtin-ops")] CreateString(usize),
         #[cfg(feature = "al31f-builtin-ops")] CreateObject(usize),
 
@@ -468,7 +497,25 @@ pub enum Instruction {
         #[cfg(feature = "al31f-builtin-ops")] ObjectGetDyn(usize, usize, usize),
 
         #[cfg(feature = "al31f-builtin-ops")] ObjectPut(usize, NonNull<str>, usize),
-        #[cfg(feature = "al31f-builtin-ops")] ObjectPutDyn(usize, usize, usize)
+        #[cfg(feature = "al31f-builtin-ops")] ObjectPutDyn(usize, usize, usize),
+
+        /// `HOST-FIELD-GET [SRC] [FIELD] [DEST]`
+        ///
+        /// Read the field `FIELD` of the host object stored in `SRC`, as exposed by
+        /// `StaticBase::get_field`, and save it to `DEST`. Saves `null` if there's no such field.
+        #[cfg(feature = "al31f-builtin-ops")] HostFieldGet(usize, NonNull<str>, usize),
+
+        /// `CREATE-WEAK [SRC] [WEAK-VT] [DEST]`
+        ///
+        /// Create a weak reference to the object stored in `SRC`, and save it to `DEST`. `WEAK-VT`
+        /// should be created by `create_weak_vt`.
+        #[cfg(feature = "al31f-builtin-ops")] CreateWeak(usize, NonNull<GenericTypeVT>, usize),
+
+        /// `WEAK-GET [SRC] [DEST]`
+        ///
+        /// Save the target of the weak reference stored in `SRC` to `DEST`, or `null` if the target
+        /// has been collected.
+        #[cfg(feature = "al31f-builtin-ops")] WeakGet(usize, usize)
 }
 
 pub enum Terminator {
@@ -484,6 +531,12 @@ pub enum Terminator {
     /// `RAISE [EXCEPTION]`
     Raise(usize),
 
+    /// `TAIL-CALL [FUNC-ID] [ARGS..]`
+    TailCall(usize, &'static [usize]),
+
+    /// `TAIL-CALL-PTR [SRC] [ARGS..]`
+    TailCallPtr(usize, &'static [usize]),
+
     JumpIfTrue(usize, usize),
     JumpIfFalse(usize, usize),
     Jump(usize),
@@ -0,0 +1,945 @@
+//! # `ownership`: static ownership analysis of native function calls
+//!
+//! Objects passed to native functions with `DataOption::Share`, `DataOption::MutShare` or
+//! `DataOption::Move` have their ownership checked at run time by `FFICallRtlc`, following the
+//! state machine modelled in `brute_force_proof.rs`: objects owned by the VM may be borrowed,
+//! mutably borrowed or moved, borrowed objects may only be borrowed again, and moved objects may
+//! not be used at all.
+//!
+//! `check_ownership` tracks the ownership state of locals through a function. Objects created in
+//! the function are owned by the VM until they escape to places the analysis cannot follow
+//! (containers, capture cells, closures, weak references, constants), and async native calls
+//! keep their arguments borrowed until the returned promise gets awaited. Calls which may call
+//! back into script code through callable arguments, including all calls to script functions
+//! with arguments, forget the states of all locals. Calls whose arguments are all proven safe
+//! are turned into `FFICall`s, calls with few unknown arguments get explicit
+//! `OwnershipInfoCheck`s instead, and the remaining calls keep using `FFICallRtlc`. Using an
+//! object after moving it, or passing one object to a call more than once while moving or
+//! mutably sharing it, always fails at run time and is reported as an error.
+
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ptr::NonNull;
+
+use crate::builtins::object::Object;
+use crate::data::tyck::{FunctionTyckInfo, TyckInfo};
+use crate::data::wrapper::{OWN_INFO_MOVE_MASK, OWN_INFO_READ_MASK, OWN_INFO_WRITE_MASK};
+use crate::ffi::{DataOption, Signature};
+use crate::vm::al31f::analysis::{Function, Instruction, Terminator};
+
+/// Signatures of the native functions called by a function, indexed the same way as
+/// `CompiledProgram::ffi_funcs` and `CompiledProgram::async_ffi_funcs`. `None` for functions
+/// without standard signature, arguments of which are never proven safe.
+pub struct FfiSignatures<'a> {
+    pub ffi_funcs: &'a [Option<&'a Signature>],
+    #[cfg(feature = "async")]
+    pub async_ffi_funcs: &'a [Option<&'a Signature>]
+}
+
+/// Why a native function call always fails its ownership checks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnershipErrorKind {
+    /// The object has already been moved to native code
+    UseAfterMove,
+    /// The object is passed more than once, and at least once with `DataOption::MutShare` or
+    /// `DataOption::Move`
+    ConflictingUse
+}
+
+/// A native function call which always fails its ownership checks. `index` is the index of the
+/// call in the instructions of basic block `block`, and `local` the offending argument, passed
+/// with `option`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnershipError {
+    pub block: u64,
+    pub index: usize,
+    pub local: usize,
+    pub option: DataOption,
+    pub kind: OwnershipErrorKind
+}
+
+impl Display for OwnershipError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "B.{}, #{}: ", self.block, self.index)?;
+        match self.kind {
+            OwnershipErrorKind::UseAfterMove =>
+                write!(f, "%{} is passed with {:?} after being moved to native code",
+                       self.local, self.option),
+            OwnershipErrorKind::ConflictingUse =>
+                write!(f, "%{} is passed with {:?} while passed to the same call otherwise",
+                       self.local, self.option)
+        }
+    }
+}
+
+impl Error for OwnershipError {}
+
+/// Check the ownership of all objects `function` passes to native functions, and pick the
+/// cheapest way of checking each call at run time. `function` is only modified if no error is
+/// found.
+///
+/// Without feature `optimized-rtlc` there's no `FFICall` instruction, and calls are left as they
+/// are.
+pub fn check_ownership(
+    function: &mut Function,
+    signatures: &FfiSignatures<'_>
+) -> Result<(), Vec<OwnershipError>> {
+    let entry_states: Vec<Option<FlowState>> = analyze(function, signatures);
+
+    let mut errors: Vec<OwnershipError> = vec![];
+    let mut reports: Vec<Vec<Option<CallReport>>> = vec![];
+    for (block, entry_state) in function.basic_blocks.iter().zip(entry_states) {
+        // unreachable blocks are left as they are
+        let mut state: FlowState = if let Some(state) = entry_state {
+            state
+        } else {
+            reports.push(vec![]);
+            continue;
+        };
+
+        let mut block_reports: Vec<Option<CallReport>> = vec![];
+        for (index, instruction) in block.instructions.iter().enumerate() {
+            let report: Option<CallReport> = state.transfer(instruction, signatures);
+            if let Some(report) = &report {
+                for (local, option, kind) in report.violations.iter() {
+                    errors.push(OwnershipError {
+                        block: block.id,
+                        index,
+                        local: *local,
+                        option: *option,
+                        kind: *kind
+                    });
+                }
+            }
+            block_reports.push(report);
+        }
+        reports.push(block_reports);
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    for (block, block_reports) in function.basic_blocks.iter_mut().zip(reports) {
+        if block_reports.is_empty() {
+            continue;
+        }
+
+        let mut instructions: Vec<Instruction> = Vec::with_capacity(block.instructions.len());
+        let old_instructions: Vec<Instruction> = std::mem::take(&mut block.instructions);
+        for (instruction, report) in old_instructions.into_iter().zip(block_reports) {
+            if let Some(report) = report {
+                rewrite_call(instruction, report, &mut instructions);
+            } else {
+                instructions.push(instruction);
+            }
+        }
+        block.instructions = instructions;
+    }
+    Ok(())
+}
+
+/// Ownership state of the object in a local. Locals not tracked in a `FlowState` are unknown,
+/// i.e. may hold objects of any state, or `null`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(feature = "async", feature = "optimized-rtlc")), allow(dead_code))]
+enum OwnershipState {
+    /// Created in this function and owned by the VM: readable, writable and movable
+    Own,
+    /// Owned by the VM, but shared with this many pending async calls: only readable
+    Borrow(usize),
+    /// Owned by the VM, but mutably shared with a pending async call
+    BorrowMut,
+    /// Moved to native code
+    Moved
+}
+
+/// How a native function call should be checked, as found by `FlowState::transfer`
+struct CallReport {
+    violations: Vec<(usize, DataOption, OwnershipErrorKind)>,
+    /// Locals to check with `OwnershipInfoCheck`, with their masks
+    checks: Vec<(usize, u8)>,
+    /// Whether the call may skip RTLC, given `checks` are done
+    elidable: bool
+}
+
+#[derive(Clone, Default, PartialEq, Eq)]
+struct FlowState {
+    locals: BTreeMap<usize, OwnershipState>,
+    /// Borrow counts of `(promise, local)` pairs, released when `promise` gets awaited
+    pending: BTreeMap<(usize, usize), usize>
+}
+
+impl FlowState {
+    fn state(&self, local: usize) -> Option<OwnershipState> {
+        self.locals.get(&local).copied()
+    }
+
+    /// Store a new value of state `state` (unknown if `None`) into `local`. Objects borrowed by
+    /// the promise previously stored in `local` are released at some unknown point.
+    fn define(&mut self, local: usize, state: Option<OwnershipState>) {
+        let released: Vec<usize> = self.pending.keys()
+            .filter(|(promise, _)| *promise == local)
+            .map(|(_, borrowed)| *borrowed)
+            .collect();
+        for borrowed in released {
+            self.locals.remove(&borrowed);
+        }
+        self.pending.retain(|(promise, borrowed), _| *promise != local && *borrowed != local);
+
+        if let Some(state) = state {
+            self.locals.insert(local, state);
+        } else {
+            self.locals.remove(&local);
+        }
+    }
+
+    fn define_all(&mut self, locals: &[usize]) {
+        for local in locals {
+            self.define(*local, None);
+        }
+    }
+
+    /// Forget all tracked states. Used when calling code which may call back into script code
+    /// through a callable argument, after which any object may be in any state.
+    fn invalidate(&mut self) {
+        self.locals.clear();
+        self.pending.clear();
+    }
+
+    /// Apply the effects of calling a script function with `args`. The types of `args` are not
+    /// known, so any of them may be callable.
+    fn transfer_call(&mut self, args: &[usize], rets: &[usize]) {
+        if args.is_empty() {
+            self.define_all(rets);
+        } else {
+            self.invalidate();
+        }
+    }
+
+    fn join(&self, other: &FlowState) -> FlowState {
+        let mut locals: BTreeMap<usize, OwnershipState> = self.locals.iter()
+            .filter(|(local, state)| other.locals.get(local) == Some(state))
+            .map(|(local, state)| (*local, *state))
+            .collect();
+        let mut pending: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+        for (key, count) in self.pending.iter().chain(other.pending.iter()) {
+            if self.pending.get(key) == other.pending.get(key) {
+                pending.insert(*key, *count);
+            } else {
+                locals.remove(&key.1);
+            }
+        }
+        pending.retain(|(_, borrowed), _| locals.contains_key(borrowed));
+        FlowState { locals, pending }
+    }
+
+    /// Apply the effects of `instruction`. Returns how to check the call if `instruction` is a
+    /// native function call.
+    fn transfer(
+        &mut self,
+        instruction: &Instruction,
+        signatures: &FfiSignatures<'_>
+    ) -> Option<CallReport> {
+        match instruction {
+            Instruction::FFICallRtlc(func_id, args, rets) =>
+                return Some(self.transfer_ffi_call(signatures.ffi_funcs, *func_id, args, rets)),
+            #[cfg(feature = "optimized-rtlc")]
+            Instruction::FFICall(func_id, args, rets) =>
+                return Some(self.transfer_ffi_call(signatures.ffi_funcs, *func_id, args, rets)),
+            #[cfg(all(feature = "async", feature = "optimized-rtlc"))]
+            Instruction::FFICallAsync(func_id, args, ret) => {
+                let mut report: CallReport =
+                    self.transfer_ffi_call_async(signatures.async_ffi_funcs, *func_id, args, *ret);
+                report.elidable = false;
+                return Some(report);
+            },
+            #[cfg(feature = "async")]
+            Instruction::Await(promise, rets) => {
+                self.release(*promise);
+                self.define_all(rets);
+            },
+
+            Instruction::Call(_, args, rets)
+            | Instruction::CallPtr(_, args, rets)
+            | Instruction::CallOverload(_, args, rets)
+            | Instruction::CallMethod(_, args, rets) => self.transfer_call(args, rets),
+            #[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+            Instruction::Spawn(_, args) => self.transfer_call(args, &[]),
+
+            // objects escape to places not tracked
+            Instruction::SaveConst(src, _) => self.define(*src, None),
+            Instruction::MakeClosure(_, captures, _, dest) => {
+                self.define_all(captures);
+                self.define(*dest, None);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Instruction::VecIndexPut(_, _, src)
+            | Instruction::VecPush(_, src)
+            | Instruction::ObjectPut(_, _, src)
+            | Instruction::ObjectPutDyn(_, _, src)
+            | Instruction::CellSet(_, src) => self.define(*src, None),
+            // the target stays reachable through `WeakGet`, aliasing other locals
+            #[cfg(feature = "al31f-builtin-ops")]
+            Instruction::MakeCell(src, dest)
+            | Instruction::CreateWeak(src, _, dest) => {
+                self.define(*src, None);
+                self.define(*dest, Some(OwnershipState::Own));
+            },
+
+            // fresh objects
+            #[cfg(feature = "al31f-builtin-ops")]
+            Instruction::CreateString(dest)
+            | Instruction::CreateObject(dest)
+            | Instruction::StrClone(_, dest)
+            | Instruction::StrConcat(_, dest) => self.define(*dest, Some(OwnershipState::Own)),
+
+            Instruction::AddInt(_, _, dest)
+            | Instruction::AddFloat(_, _, dest)
+            | Instruction::AddAny(_, _, dest)
+            | Instruction::SubInt(_, _, dest)
+            | Instruction::SubFloat(_, _, dest)
+            | Instruction::SubAny(_, _, dest)
+            | Instruction::MulInt(_, _, dest)
+            | Instruction::MulFloat(_, _, dest)
+            | Instruction::MulAny(_, _, dest)
+            | Instruction::DivInt(_, _, dest)
+            | Instruction::DivFloat(_, _, dest)
+            | Instruction::DivAny(_, _, dest)
+            | Instruction::ModInt(_, _, dest)
+            | Instruction::ModAny(_, _, dest)
+            | Instruction::EqValue(_, _, dest)
+            | Instruction::EqRef(_, _, dest)
+            | Instruction::EqAny(_, _, dest)
+            | Instruction::NeValue(_, _, dest)
+            | Instruction::NeRef(_, _, dest)
+            | Instruction::NeAny(_, _, dest)
+            | Instruction::LtInt(_, _, dest)
+            | Instruction::LtFloat(_, _, dest)
+            | Instruction::LtAny(_, _, dest)
+            | Instruction::GtInt(_, _, dest)
+            | Instruction::GtFloat(_, _, dest)
+            | Instruction::GtAny(_, _, dest)
+            | Instruction::LeInt(_, _, dest)
+            | Instruction::LeFloat(_, _, dest)
+            | Instruction::LeAny(_, _, dest)
+            | Instruction::GeInt(_, _, dest)
+            | Instruction::GeFloat(_, _, dest)
+            | Instruction::GeAny(_, _, dest)
+            | Instruction::BAndInt(_, _, dest)
+            | Instruction::BAndAny(_, _, dest)
+            | Instruction::BOrInt(_, _, dest)
+            | Instruction::BOrAny(_, _, dest)
+            | Instruction::BXorInt(_, _, dest)
+            | Instruction::BXorAny(_, _, dest)
+            | Instruction::AndBool(_, _, dest)
+            | Instruction::AndAny(_, _, dest)
+            | Instruction::OrBool(_, _, dest)
+            | Instruction::OrAny(_, _, dest)
+            | Instruction::ShlInt(_, _, dest)
+            | Instruction::ShlAny(_, _, dest)
+            | Instruction::ShrInt(_, _, dest)
+            | Instruction::ShrAny(_, _, dest)
+            | Instruction::IsType(_, _, dest)
+            | Instruction::IncrInt(dest)
+            | Instruction::DecrInt(dest)
+            | Instruction::BNotInt(_, dest)
+            | Instruction::BNotAny(_, dest)
+            | Instruction::NegInt(_, dest)
+            | Instruction::NegFloat(_, dest)
+            | Instruction::NegAny(_, dest)
+            | Instruction::NotBool(_, dest)
+            | Instruction::NotAny(_, dest)
+            | Instruction::MakeIntConst(_, dest)
+            | Instruction::MakeFloatConst(_, dest)
+            | Instruction::MakeCharConst(_, dest)
+            | Instruction::MakeBoolConst(_, dest)
+            | Instruction::MakeNull(dest)
+            | Instruction::LoadConst(_, dest)
+            | Instruction::CastFloatInt(_, dest)
+            | Instruction::CastBoolInt(_, dest)
+            | Instruction::CastAnyInt(_, dest)
+            | Instruction::CastIntFloat(_, dest)
+            | Instruction::CastAnyFloat(_, dest)
+            | Instruction::CastAnyChar(_, dest)
+            | Instruction::CastIntBool(_, dest)
+            | Instruction::CastAnyBool(_, dest)
+            | Instruction::IsNull(_, dest) => self.define(*dest, None),
+            #[cfg(feature = "al31f-builtin-ops")]
+            Instruction::VecIndex(_, _, dest)
+            | Instruction::VecLen(_, dest)
+            | Instruction::StrLen(_, dest)
+            | Instruction::StrEquals(_, _, dest)
+            | Instruction::ObjectGet(_, _, dest)
+            | Instruction::ObjectGetDyn(_, _, dest)
+            | Instruction::CellGet(_, dest)
+            | Instruction::HostFieldGet(_, _, dest)
+            | Instruction::WeakGet(_, dest) => self.define(*dest, None),
+
+            Instruction::NullCheck(_)
+            | Instruction::TypeCheck(_, _)
+            | Instruction::OwnershipInfoCheck(_, _) => {}
+        }
+        None
+    }
+
+    fn transfer_ffi_call(
+        &mut self,
+        signatures: &[Option<&Signature>],
+        func_id: usize,
+        args: &[usize],
+        rets: &[usize]
+    ) -> CallReport {
+        let signature: &Signature = if let Some(Some(signature)) = signatures.get(func_id) {
+            signature
+        } else {
+            self.invalidate();
+            return CallReport { violations: vec![], checks: vec![], elidable: false };
+        };
+
+        let mut report: CallReport = self.check_args(signature, args);
+        let mut exclusive_checks: usize = 0;
+        for (idx, local) in args.iter().enumerate() {
+            let option: DataOption = param_option(signature, idx);
+            match option {
+                DataOption::Copy => continue,
+                DataOption::Raw | DataOption::RawUntyped => {
+                    report.elidable = false;
+                    continue;
+                },
+                _ => {}
+            }
+            match (self.state(*local), option) {
+                (Some(OwnershipState::Own), _)
+                | (Some(OwnershipState::Borrow(_)), DataOption::Share)
+                | (Some(OwnershipState::Moved), _) => {},
+                _ => {
+                    if param_nullable(signature, idx) {
+                        report.elidable = false;
+                    }
+                    if option != DataOption::Share {
+                        exclusive_checks += 1;
+                    }
+                    let check: (usize, u8) = (*local, ownership_mask(option));
+                    if !report.checks.contains(&check) {
+                        report.checks.push(check);
+                    }
+                }
+            }
+        }
+        // unknown objects may alias each other
+        if exclusive_checks != 0 && report.checks.len() > 1 {
+            report.elidable = false;
+        }
+
+        if takes_callable(signature, args) {
+            self.invalidate();
+        }
+        for (idx, local) in args.iter().enumerate() {
+            match param_option(signature, idx) {
+                DataOption::Move => self.define(*local, Some(OwnershipState::Moved)),
+                DataOption::Raw | DataOption::RawUntyped => self.define(*local, None),
+                _ => {}
+            }
+        }
+        for (idx, local) in rets.iter().enumerate() {
+            self.define(*local, ret_state(signature, idx));
+        }
+        report
+    }
+
+    #[cfg(all(feature = "async", feature = "optimized-rtlc"))]
+    fn transfer_ffi_call_async(
+        &mut self,
+        signatures: &[Option<&Signature>],
+        func_id: usize,
+        args: &[usize],
+        ret: usize
+    ) -> CallReport {
+        let signature: &Signature = if let Some(Some(signature)) = signatures.get(func_id) {
+            signature
+        } else {
+            self.invalidate();
+            return CallReport { violations: vec![], checks: vec![], elidable: false };
+        };
+
+        let report: CallReport = self.check_args(signature, args);
+        if takes_callable(signature, args) {
+            self.invalidate();
+        }
+        let mut borrows: Vec<(usize, OwnershipState)> = vec![];
+        for (idx, local) in args.iter().enumerate() {
+            let option: DataOption = param_option(signature, idx);
+            let new_state: Option<OwnershipState> = match (self.state(*local), option) {
+                (_, DataOption::Copy) => continue,
+                (_, DataOption::Move) => Some(OwnershipState::Moved),
+                (Some(OwnershipState::Own), DataOption::Share) => Some(OwnershipState::Borrow(1)),
+                (Some(OwnershipState::Borrow(count)), DataOption::Share) =>
+                    Some(OwnershipState::Borrow(count + 1)),
+                (Some(OwnershipState::Own), DataOption::MutShare) =>
+                    Some(OwnershipState::BorrowMut),
+                (Some(OwnershipState::Moved), _) => continue,
+                _ => None
+            };
+            match new_state {
+                Some(state @ (OwnershipState::Borrow(_) | OwnershipState::BorrowMut)) => {
+                    self.locals.insert(*local, state);
+                    borrows.push((*local, state));
+                },
+                state => self.define(*local, state)
+            }
+        }
+
+        self.define(ret, None);
+        for (local, _) in borrows {
+            if local != ret && self.state(local).is_some() {
+                *self.pending.entry((ret, local)).or_insert(0) += 1;
+            }
+        }
+        report
+    }
+
+    /// Release the objects borrowed by `promise`, which is being awaited
+    #[cfg(feature = "async")]
+    fn release(&mut self, promise: usize) {
+        let released: Vec<(usize, usize)> = self.pending.iter()
+            .filter(|((awaited, _), _)| *awaited == promise)
+            .map(|((_, borrowed), count)| (*borrowed, *count))
+            .collect();
+        for (borrowed, count) in released {
+            self.pending.remove(&(promise, borrowed));
+            match self.state(borrowed) {
+                Some(OwnershipState::Borrow(n)) if n > count =>
+                    self.locals.insert(borrowed, OwnershipState::Borrow(n - count)),
+                Some(OwnershipState::Borrow(_)) | Some(OwnershipState::BorrowMut) =>
+                    self.locals.insert(borrowed, OwnershipState::Own),
+                _ => None
+            };
+        }
+    }
+
+    /// Find the arguments which always fail their ownership checks
+    fn check_args(&self, signature: &Signature, args: &[usize]) -> CallReport {
+        let mut violations: Vec<(usize, DataOption, OwnershipErrorKind)> = vec![];
+        for (idx, local) in args.iter().enumerate() {
+            let option: DataOption = param_option(signature, idx);
+            if !matches!(option, DataOption::Share | DataOption::MutShare | DataOption::Move) {
+                continue;
+            }
+
+            match self.state(*local) {
+                Some(OwnershipState::Moved) => {
+                    violations.push((*local, option, OwnershipErrorKind::UseAfterMove));
+                    continue;
+                },
+                // an unknown local may be `null`, which may be passed any times
+                None => continue,
+                _ => {}
+            }
+            let conflicting: bool = args[..idx].iter()
+                .enumerate()
+                .filter(|(_, other)| *other == local)
+                .map(|(other_idx, _)| param_option(signature, other_idx))
+                .any(|other_option: DataOption| match other_option {
+                    DataOption::Share => option != DataOption::Share,
+                    DataOption::MutShare | DataOption::Move => true,
+                    _ => false
+                });
+            if conflicting {
+                violations.push((*local, option, OwnershipErrorKind::ConflictingUse));
+            }
+        }
+        CallReport { violations, checks: vec![], elidable: true }
+    }
+}
+
+/// Find the state at the entry of each basic block of `function`, or `None` for unreachable
+/// blocks. Jump targets are the `id`s of basic blocks, and blocks without terminator fall
+/// through to the next block.
+fn analyze(function: &Function, signatures: &FfiSignatures<'_>) -> Vec<Option<FlowState>> {
+    let block_count: usize = function.basic_blocks.len();
+    let mut entry_states: Vec<Option<FlowState>> = vec![None; block_count];
+    if block_count == 0 {
+        return entry_states;
+    }
+
+    let block_indices: HashMap<u64, usize> = function.basic_blocks.iter()
+        .enumerate()
+        .map(|(idx, block)| (block.id, idx))
+        .collect();
+    entry_states[0] = Some(FlowState::default());
+    let mut worklist: VecDeque<usize> = VecDeque::from(vec![0]);
+    while let Some(idx) = worklist.pop_front() {
+        let mut state: FlowState = entry_states[idx].clone().unwrap();
+        for instruction in function.basic_blocks[idx].instructions.iter() {
+            state.transfer(instruction, signatures);
+        }
+
+        let jump_target = |target: &usize| block_indices.get(&(*target as u64)).copied();
+        let successors: Vec<usize> = match &function.basic_blocks[idx].terminator {
+            None => vec![idx + 1],
+            Some(Terminator::Jump(target)) => jump_target(target).into_iter().collect(),
+            Some(Terminator::JumpIfTrue(_, target) | Terminator::JumpIfFalse(_, target)) =>
+                jump_target(target).into_iter().chain(Some(idx + 1)).collect(),
+            Some(_) => vec![]
+        };
+        for successor in successors.into_iter().filter(|successor| *successor < block_count) {
+            let joined: FlowState = match &entry_states[successor] {
+                Some(entry_state) => entry_state.join(&state),
+                None => state.clone()
+            };
+            if entry_states[successor].as_ref() != Some(&joined) {
+                entry_states[successor] = Some(joined);
+                worklist.push_back(successor);
+            }
+        }
+    }
+    entry_states
+}
+
+/// Push `instruction`, a native function call checked as described by `report`, to
+/// `instructions`
+fn rewrite_call(instruction: Instruction, report: CallReport, instructions: &mut Vec<Instruction>) {
+    #[cfg(feature = "optimized-rtlc")]
+    match instruction {
+        Instruction::FFICallRtlc(func_id, args, rets) | Instruction::FFICall(func_id, args, rets) =>
+            if report.elidable {
+                for (local, mask) in report.checks {
+                    instructions.push(Instruction::OwnershipInfoCheck(local, mask));
+                }
+                instructions.push(Instruction::FFICall(func_id, args, rets));
+            } else {
+                instructions.push(Instruction::FFICallRtlc(func_id, args, rets));
+            },
+        instruction => instructions.push(instruction)
+    }
+
+    #[cfg(not(feature = "optimized-rtlc"))]
+    {
+        let _ = report;
+        instructions.push(instruction);
+    }
+}
+
+/// How the `idx`-th argument is passed, taking the variadic tail into account
+fn param_option(signature: &Signature, idx: usize) -> DataOption {
+    signature.param_options.get(idx)
+        .or_else(|| signature.param_options.last())
+        .copied()
+        .unwrap_or(DataOption::Copy)
+}
+
+fn function_tyck_info(signature: &Signature) -> &FunctionTyckInfo {
+    unsafe { signature.func_type.as_ref().get_function_tyck_info_unchecked().as_ref() }
+}
+
+/// Whether the `idx`-th parameter may receive `null`, which `OwnershipInfoCheck` rejects
+fn param_nullable(signature: &Signature, idx: usize) -> bool {
+    let param: Option<NonNull<TyckInfo>> = function_tyck_info(signature).param_type(idx);
+    !matches!(
+        param.map(|param: NonNull<TyckInfo>| unsafe { param.as_ref() }),
+        Some(TyckInfo::Plain(_) | TyckInfo::Container(_) | TyckInfo::Function(_))
+    )
+}
+
+/// Whether any of `args` may be or hold a callable, through which the native function may call
+/// back into script code
+fn takes_callable(signature: &Signature, args: &[usize]) -> bool {
+    (0..args.len()).any(|idx: usize| match function_tyck_info(signature).param_type(idx) {
+        Some(param) => may_hold_callable(param),
+        None => true
+    })
+}
+
+/// Whether values of type `tyck_info` may be or hold callables. Objects may hold values of any
+/// type in their fields.
+fn may_hold_callable(tyck_info: NonNull<TyckInfo>) -> bool {
+    match unsafe { tyck_info.as_ref() } {
+        TyckInfo::Plain(type_id) => *type_id == TypeId::of::<Object>(),
+        TyckInfo::Nullable(inner) => may_hold_callable(*inner),
+        TyckInfo::Container(container) =>
+            unsafe { container.params.as_ref() }.iter().copied().any(may_hold_callable),
+        TyckInfo::AnyType | TyckInfo::Function(_) => true
+    }
+}
+
+/// State of the `idx`-th return value. Objects moved out of native code are owned by the VM.
+fn ret_state(signature: &Signature, idx: usize) -> Option<OwnershipState> {
+    let ret: NonNull<TyckInfo> = function_tyck_info(signature).ret_type(idx)?;
+    let option: &DataOption = signature.ret_option.get(idx)
+        .or_else(|| signature.ret_option.last())?;
+    match (option, unsafe { ret.as_ref() }) {
+        (DataOption::Move, TyckInfo::Plain(_) | TyckInfo::Container(_)) =>
+            Some(OwnershipState::Own),
+        _ => None
+    }
+}
+
+fn ownership_mask(option: DataOption) -> u8 {
+    match option {
+        DataOption::Share => OWN_INFO_READ_MASK,
+        DataOption::MutShare => OWN_INFO_WRITE_MASK,
+        _ => OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_MOVE_MASK
+    }
+}
+
+#[cfg(all(test, feature = "optimized-rtlc"))]
+mod test_ownership {
+    use std::any::TypeId;
+    use std::ptr::NonNull;
+
+    use crate::data::tyck::{TyckInfo, TyckInfoPool};
+    use crate::data::wrapper::{OWN_INFO_MOVE_MASK, OWN_INFO_READ_MASK, OWN_INFO_WRITE_MASK};
+    use crate::ffi::{DataOption, Signature};
+    use crate::vm::al31f::analysis::{BasicBlock, Function, Instruction, Terminator};
+    use crate::vm::al31f::analysis::ownership::{
+        FfiSignatures,
+        OwnershipError,
+        OwnershipErrorKind,
+        check_ownership
+    };
+
+    /// `@0(&string)`, `@1(string)`, `@2(&mut string, &string)`, `@3(&string?)`, `@4(&mut string)`,
+    /// `@5(&string, any)`
+    fn signatures(tyck_info_pool: &mut TyckInfoPool) -> Vec<Signature> {
+        let string_type: NonNull<TyckInfo> =
+            tyck_info_pool.create_plain_type(TypeId::of::<String>());
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+        let nullable_string_type: NonNull<TyckInfo> =
+            tyck_info_pool.create_nullable_type(string_type);
+        let mut signature = |params: &[NonNull<TyckInfo>], param_options: &[DataOption]| {
+            Signature {
+                func_type: tyck_info_pool.create_function_type(params, &[], &[]),
+                param_options: param_options.into(),
+                ret_option: Box::new([])
+            }
+        };
+        vec![
+            signature(&[string_type], &[DataOption::Share]),
+            signature(&[string_type], &[DataOption::Move]),
+            signature(&[string_type, string_type], &[DataOption::MutShare, DataOption::Share]),
+            signature(&[nullable_string_type], &[DataOption::Share]),
+            signature(&[string_type], &[DataOption::MutShare]),
+            signature(&[string_type, any_type], &[DataOption::Share, DataOption::Copy])
+        ]
+    }
+
+    fn make_function(blocks: Vec<(Vec<Instruction>, Option<Terminator>)>) -> Function {
+        Function {
+            name: "test".into(),
+            basic_blocks: blocks.into_iter()
+                .enumerate()
+                .map(|(id, (instructions, terminator))| BasicBlock {
+                    id: id as u64,
+                    instructions,
+                    terminator
+                })
+                .collect()
+        }
+    }
+
+    fn run(function: &mut Function) -> Result<(), Vec<OwnershipError>> {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let signatures: Vec<Signature> = signatures(&mut tyck_info_pool);
+        let signatures: Vec<Option<&Signature>> = signatures.iter().map(Some).collect();
+        check_ownership(function, &FfiSignatures {
+            ffi_funcs: &signatures,
+            #[cfg(feature = "async")]
+            async_ffi_funcs: &signatures
+        })
+    }
+
+    const MOVE_MASK: u8 = OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK | OWN_INFO_MOVE_MASK;
+
+    fn calls(function: &Function, block: usize) -> Vec<String> {
+        function.basic_blocks[block].instructions.iter()
+            .filter_map(|instruction: &Instruction| match instruction {
+                Instruction::OwnershipInfoCheck(local, mask) => Some(match *mask {
+                    OWN_INFO_READ_MASK => format!("check read %{}", local),
+                    OWN_INFO_WRITE_MASK => format!("check write %{}", local),
+                    MOVE_MASK => format!("check move %{}", local),
+                    _ => unreachable!()
+                }),
+                Instruction::FFICall(func_id, _, _) => Some(format!("call @{}", func_id)),
+                Instruction::FFICallRtlc(func_id, _, _) => Some(format!("call-rtlc @{}", func_id)),
+                _ => None
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_unknown_args() {
+        let mut function: Function = make_function(vec![(
+            vec![
+                Instruction::FFICallRtlc(0, &[0], &[]),
+                Instruction::FFICallRtlc(3, &[0], &[]),
+                Instruction::FFICallRtlc(2, &[0, 1], &[]),
+                Instruction::FFICallRtlc(4, &[0], &[]),
+                Instruction::FFICallRtlc(1, &[0], &[])
+            ],
+            Some(Terminator::ReturnNothing)
+        )]);
+        run(&mut function).unwrap();
+        assert_eq!(calls(&function, 0), [
+            "check read %0",
+            "call @0",
+            // `null` is accepted by nullable parameters
+            "call-rtlc @3",
+            // unknown objects may alias each other
+            "call-rtlc @2",
+            "check write %0",
+            "call @4",
+            "check move %0",
+            "call @1"
+        ]);
+    }
+
+    #[test]
+    fn test_move_errors() {
+        let mut function: Function = make_function(vec![(
+            vec![
+                Instruction::FFICallRtlc(1, &[0], &[]),
+                Instruction::FFICallRtlc(0, &[0], &[]),
+                Instruction::FFICallRtlc(2, &[1, 1], &[]),
+                Instruction::FFICall(4, &[2], &[])
+            ],
+            Some(Terminator::ReturnNothing)
+        )]);
+        assert_eq!(run(&mut function).err().unwrap(), vec![OwnershipError {
+            block: 0,
+            index: 1,
+            local: 0,
+            option: DataOption::Share,
+            kind: OwnershipErrorKind::UseAfterMove
+        }]);
+        // nothing gets rewritten if there are errors
+        assert_eq!(calls(&function, 0), ["call-rtlc @1", "call-rtlc @0", "call-rtlc @2", "call @4"]);
+    }
+
+    #[cfg(feature = "al31f-builtin-ops")]
+    #[test]
+    fn test_owned_objects() {
+        let mut function: Function = make_function(vec![
+            (
+                vec![
+                    Instruction::CreateString(0),
+                    Instruction::CreateString(1),
+                    Instruction::FFICallRtlc(0, &[0], &[]),
+                    Instruction::FFICallRtlc(2, &[0, 1], &[]),
+                    Instruction::FFICall(4, &[2], &[]),
+                    // escapes into the vector in %3
+                    Instruction::VecPush(3, 1),
+                    Instruction::FFICallRtlc(1, &[1], &[])
+                ],
+                Some(Terminator::JumpIfTrue(4, 2))
+            ),
+            (vec![Instruction::FFICallRtlc(1, &[0], &[])], None),
+            // moved on one path only
+            (vec![Instruction::FFICallRtlc(0, &[0], &[])], Some(Terminator::ReturnNothing))
+        ]);
+        run(&mut function).unwrap();
+        assert_eq!(calls(&function, 0), [
+            "call @0",
+            "call @2",
+            "check write %2",
+            "call @4",
+            "check move %1",
+            "call @1"
+        ]);
+        assert_eq!(calls(&function, 1), ["call @1"]);
+        assert_eq!(calls(&function, 2), ["check read %0", "call @0"]);
+
+        let mut function: Function = make_function(vec![(
+            vec![
+                Instruction::CreateString(0),
+                Instruction::FFICallRtlc(2, &[0, 0], &[]),
+                Instruction::FFICallRtlc(1, &[0], &[]),
+                Instruction::FFICallRtlc(1, &[0], &[])
+            ],
+            Some(Terminator::ReturnNothing)
+        )]);
+        assert_eq!(run(&mut function).err().unwrap(), vec![
+            OwnershipError {
+                block: 0,
+                index: 1,
+                local: 0,
+                option: DataOption::Share,
+                kind: OwnershipErrorKind::ConflictingUse
+            },
+            OwnershipError {
+                block: 0,
+                index: 3,
+                local: 0,
+                option: DataOption::Move,
+                kind: OwnershipErrorKind::UseAfterMove
+            }
+        ]);
+    }
+
+    #[cfg(feature = "al31f-builtin-ops")]
+    #[test]
+    fn test_escapes_and_callbacks() {
+        let mut function: Function = make_function(vec![(
+            vec![
+                Instruction::CreateString(0),
+                Instruction::MakeClosure(0, &[0], NonNull::dangling(), 1),
+                Instruction::FFICallRtlc(0, &[0], &[]),
+                Instruction::CreateString(2),
+                Instruction::MakeCell(2, 3),
+                Instruction::FFICallRtlc(0, &[2], &[]),
+                // `any` argument may be a callable
+                Instruction::CreateString(4),
+                Instruction::FFICallRtlc(5, &[4, 1], &[]),
+                Instruction::FFICallRtlc(0, &[4], &[]),
+                Instruction::CreateString(5),
+                Instruction::Call(0, &[6], &[]),
+                Instruction::FFICallRtlc(0, &[5], &[])
+            ],
+            Some(Terminator::ReturnNothing)
+        )]);
+        run(&mut function).unwrap();
+        assert_eq!(calls(&function, 0), [
+            // captured by the closure in %1
+            "check read %0",
+            "call @0",
+            // boxed into the cell in %3
+            "check read %2",
+            "call @0",
+            "call @5",
+            "check read %4",
+            "call @0",
+            "check read %5",
+            "call @0"
+        ]);
+    }
+
+    #[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+    #[test]
+    fn test_async_borrows() {
+        let mut function: Function = make_function(vec![(
+            vec![
+                Instruction::CreateString(0),
+                Instruction::FFICallAsync(0, &[0], 1),
+                Instruction::FFICallRtlc(0, &[0], &[]),
+                Instruction::FFICallRtlc(4, &[0], &[]),
+                Instruction::Await(1, &[]),
+                Instruction::FFICallRtlc(4, &[0], &[]),
+                Instruction::FFICallAsync(4, &[0], 1),
+                // the promise gets overwritten before being awaited
+                Instruction::MakeNull(1),
+                Instruction::FFICallRtlc(0, &[0], &[])
+            ],
+            Some(Terminator::ReturnNothing)
+        )]);
+        run(&mut function).unwrap();
+        assert_eq!(calls(&function, 0), [
+            "call @0",
+            "check write %0",
+            "call @4",
+            "call @4",
+            "check read %0",
+            "call @0"
+        ]);
+    }
+}
@@ -1,9 +1,16 @@
 use std::any::TypeId;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
 use std::ptr::NonNull;
+
+use smallvec::SmallVec;
 use xjbutil::slice_arena::SliceArena;
 
 use crate::data::Value;
+use crate::data::exception::FatalExceptionSet;
+use crate::data::generic::GenericTypeVT;
 use crate::data::tyck::TyckInfo;
+use crate::ffi::FFIHandle;
 use crate::ffi::sync_fn::Function as FFIFunction;
 use crate::vm::al31f::{AL31F, Combustor};
 use crate::vm::al31f::alloc::Alloc;
@@ -12,9 +19,35 @@ use crate::vm::al31f::insc::Insc;
 #[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
 #[cfg(feature = "async")] use crate::vm::al31f::AsyncCombustor;
 
+/// Exception types an `ExceptionHandlingBlock` handles
+pub enum ExceptionFilter {
+    /// Handles every exception that is not fatal, used for `catch` without type
+    Any,
+    /// Handles every exception including fatal ones, used for `finally` regions. The handler
+    /// sees the exception as raised and must end with `RETHROW`.
+    Finally,
+    Single(TypeId),
+    OneOf(Box<[TypeId]>)
+}
+
+impl ExceptionFilter {
+    #[inline] pub fn matches(&self, exception_id: TypeId) -> bool {
+        match self {
+            ExceptionFilter::Any | ExceptionFilter::Finally => true,
+            ExceptionFilter::Single(handled) => *handled == exception_id,
+            ExceptionFilter::OneOf(handled) => handled.contains(&exception_id)
+        }
+    }
+}
+
+/// An exception handler covering instructions in `insc_ptr_range`
+///
+/// Handlers of one function are tried in order, so handlers of inner `try` blocks must come
+/// before outer ones, and `catch` handlers before the `finally` handler of the same `try` block.
+/// The caught exception is stored into the last slot of the stack frame.
 pub struct ExceptionHandlingBlock {
     pub insc_ptr_range: (usize, usize),
-    pub exception_id: TypeId,
+    pub filter: ExceptionFilter,
     pub handler_addr: usize
 }
 
@@ -27,10 +60,54 @@ impl ExceptionHandlingBlock {
     ) -> Self {
         Self {
             insc_ptr_range: (insc_ptr_start, insc_ptr_end),
-            exception_id,
+            filter: ExceptionFilter::Single(exception_id),
+            handler_addr
+        }
+    }
+
+    pub fn new_multi(
+        insc_ptr_start: usize,
+        insc_ptr_end: usize,
+        exception_ids: Box<[TypeId]>,
+        handler_addr: usize
+    ) -> Self {
+        Self {
+            insc_ptr_range: (insc_ptr_start, insc_ptr_end),
+            filter: ExceptionFilter::OneOf(exception_ids),
             handler_addr
         }
     }
+
+    pub fn new_catch_all(insc_ptr_start: usize, insc_ptr_end: usize, handler_addr: usize) -> Self {
+        Self {
+            insc_ptr_range: (insc_ptr_start, insc_ptr_end),
+            filter: ExceptionFilter::Any,
+            handler_addr
+        }
+    }
+
+    /// Create the handler of a `finally` region
+    ///
+    /// The handler is expected to hold a copy of the cleanup code followed by `RETHROW`, and to
+    /// cover both the `try` body and its `catch` handlers. Running the cleanup code on normal exit
+    /// and before `return` is left to whoever emits the code, since the compiler does not lower
+    /// `try/catch/finally` yet.
+    pub fn new_finally(insc_ptr_start: usize, insc_ptr_end: usize, handler_addr: usize) -> Self {
+        Self {
+            insc_ptr_range: (insc_ptr_start, insc_ptr_end),
+            filter: ExceptionFilter::Finally,
+            handler_addr
+        }
+    }
+
+    #[inline] pub fn covers(&self, insc_ptr: usize) -> bool {
+        let (start_insc, end_insc): (usize, usize) = self.insc_ptr_range;
+        insc_ptr >= start_insc && insc_ptr <= end_insc
+    }
+
+    #[inline] pub fn handles(&self, insc_ptr: usize, exception_id: TypeId) -> bool {
+        self.covers(insc_ptr) && self.filter.matches(exception_id)
+    }
 }
 
 pub struct CompiledFunction {
@@ -80,6 +157,125 @@ impl CompiledFunction {
     }
 }
 
+/// Maximum count of entries kept in the inline cache of one `OverloadCallSite`
+pub const OVERLOAD_CACHE_SIZE: usize = 4;
+
+/// Argument type tags used as the key of overload inline caches
+pub type OverloadCacheKey = SmallVec<[usize; 4]>;
+
+pub struct OverloadCacheEntry {
+    pub key: OverloadCacheKey,
+    pub func_id: usize
+}
+
+/// Candidate functions of one overloaded function
+///
+/// `CallOverload` selects from `candidates` (indices into `CompiledProgram::functions`) by
+/// checking arguments against each candidate's `param_tyck_info`.
+pub struct OverloadTable {
+    pub candidates: Box<[usize]>
+}
+
+impl OverloadTable {
+    pub fn new(candidates: Box<[usize]>) -> Self {
+        Self { candidates }
+    }
+}
+
+/// One `CallOverload` instruction, calling the overloaded function `overload_table`
+///
+/// Selection results are cached by argument type tags (see `overload_cache_key`) in each call
+/// site, so that call sites seeing different argument types do not evict each other's entries.
+pub struct OverloadCallSite {
+    pub overload_table: usize,
+    pub cache: UnsafeCell<SmallVec<[OverloadCacheEntry; OVERLOAD_CACHE_SIZE]>>
+}
+
+impl OverloadCallSite {
+    pub fn new(overload_table: usize) -> Self {
+        Self {
+            overload_table,
+            cache: UnsafeCell::new(SmallVec::new())
+        }
+    }
+}
+
+/// Implementation of a method by one host type
+pub struct MethodImpl<A: Alloc> {
+    pub func: FFIHandle<dyn FFIFunction<Combustor<A>>>,
+    /// Count of fixed parameters (including the receiver) and return values of `func`, or `None`
+    /// if `func` has no standard signature and checks its arguments itself
+    pub counts: Option<(usize, usize)>,
+    /// Whether `func` accepts arguments after its fixed parameters
+    pub variadic: bool,
+    /// Whether `func` can write return values after its fixed return values
+    pub variadic_rets: bool
+}
+
+impl<A: Alloc> MethodImpl<A> {
+    /// Check whether `func` can be called with `arg_count` arguments, writing `ret_count` return
+    /// values
+    pub fn accepts(&self, arg_count: usize, ret_count: usize) -> bool {
+        match self.counts {
+            Some((param_count, ret_count_expected)) => {
+                let args_ok: bool = if self.variadic {
+                    arg_count >= param_count
+                } else {
+                    arg_count == param_count
+                };
+                let rets_ok: bool = if self.variadic_rets {
+                    ret_count >= ret_count_expected
+                } else {
+                    ret_count == ret_count_expected
+                };
+                args_ok && rets_ok
+            },
+            None => true
+        }
+    }
+}
+
+/// Implementations of one method by host types
+///
+/// `CallMethod` selects from `impls` by the type of the receiver, which is the first argument.
+/// Value-typed data and `null` have no methods. Since implementations for different types may
+/// differ in arity, `CallMethod` checks argument and return value counts against the selected
+/// implementation.
+pub struct MethodTable<A: Alloc> {
+    pub name: String,
+    pub impls: HashMap<TypeId, MethodImpl<A>>
+}
+
+impl<A: Alloc> MethodTable<A> {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            impls: HashMap::new()
+        }
+    }
+
+    /// Find the implementation of this method for `receiver`
+    ///
+    /// # Safety
+    /// If `receiver` is a reference, it must point to a live object.
+    pub unsafe fn lookup(
+        &self,
+        receiver: Value
+    ) -> Option<&MethodImpl<A>> {
+        if receiver.is_null() || !receiver.is_ref() {
+            return None;
+        }
+
+        let type_id: TypeId = if receiver.is_container() {
+            let vt: *const GenericTypeVT = receiver.ptr_repr.trivia as *const _;
+            (*vt).tyck_info.as_ref().type_id
+        } else {
+            (*receiver.get_as_dyn_base()).dyn_type_id()
+        };
+        self.impls.get(&type_id)
+    }
+}
+
 pub struct CompiledProgram<A: Alloc> {
     pub slice_arena: SliceArena<8192, 8>,
 
@@ -87,8 +283,13 @@ pub struct CompiledProgram<A: Alloc> {
     pub const_pool: Box<[Value]>,
     pub init_proc: usize,
     pub functions: Box<[CompiledFunction]>,
+    pub overload_tables: Box<[OverloadTable]>,
+    pub overload_call_sites: Box<[OverloadCallSite]>,
+    pub method_tables: Box<[MethodTable<A>]>,
+    /// Unchecked exceptions of these kinds are never delivered to script handlers
+    pub fatal_exceptions: FatalExceptionSet,
 
-    pub ffi_funcs: Box<[&'static dyn FFIFunction<Combustor<A>>]>,
+    pub ffi_funcs: Box<[FFIHandle<dyn FFIFunction<Combustor<A>>>]>,
     #[cfg(feature = "async")]
-    pub async_ffi_funcs: Box<[&'static dyn FFIAsyncFunction<A, AL31F<A>, AsyncCombustor<A>>]>
+    pub async_ffi_funcs: Box<[FFIHandle<dyn FFIAsyncFunction<A, AL31F<A>, AsyncCombustor<A>>>]>
 }
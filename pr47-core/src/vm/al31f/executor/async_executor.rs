@@ -8,22 +8,27 @@ use smallvec::{SmallVec, smallvec};
 use xjbutil::unchecked::{UncheckedCellOps, UncheckedSendSync};
 use xjbutil::wide_ptr::WidePointer;
 
-use crate::builtins::closure::Closure;
+use crate::builtins::closure::{CaptureCell, Closure, value_as_callable};
 use crate::builtins::object::Object;
 use crate::builtins::vec::VMGenericVec;
+use crate::builtins::weak::Weak;
 use crate::data::Value;
 use crate::data::exception::{Exception, ExceptionInner, UncheckedException};
 use crate::data::value_typed::INT_TYPE_TAG;
-use crate::ffi::FFIException;
+#[cfg(feature = "al31f-builtin-ops")] use crate::data::wrapper::OWN_INFO_READ_MASK;
+use crate::ffi::{FFIException, FFIHandle};
+use crate::ffi::convert::{CallError, FromValues, IntoValues};
 use crate::ffi::sync_fn::Function as FFIFunction;
+use crate::ffi::sync_fn::call_catch_panic;
 use crate::vm::al31f::{AL31F, Combustor};
 use crate::vm::al31f::alloc::Alloc;
-use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram};
+use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram, MethodImpl, MethodTable};
+use crate::vm::al31f::handle::RootedValue;
 use crate::vm::al31f::executor::checked_bin_ops::*;
 use crate::vm::al31f::executor::checked_cast_ops::*;
 use crate::vm::al31f::executor::checked_unary_ops::*;
 use crate::vm::al31f::executor::overload::call_overload;
-use crate::vm::al31f::executor::rtti::check_type;
+use crate::vm::al31f::executor::rtti::{TyckDepth, check_type_with_depth, check_variadic_args};
 use crate::vm::al31f::executor::unwinding::*;
 use crate::vm::al31f::insc::Insc;
 use crate::vm::al31f::stack::{Stack, StackSlice};
@@ -32,7 +37,7 @@ use crate::vm::al31f::stack::{Stack, StackSlice};
 #[cfg(feature = "async")] use std::mem::transmute;
 #[cfg(feature = "async")] use futures::FutureExt;
 #[cfg(feature = "async")] use crate::data::wrapper::{Wrapper, OwnershipInfo};
-#[cfg(feature = "async")] use crate::ffi::async_fn::{Promise, PromiseResult};
+#[cfg(feature = "async")] use crate::ffi::async_fn::{Promise, PromiseResult, catch_promise_panic};
 #[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
 #[cfg(feature = "async")] use crate::util::serializer::CoroutineContext;
 #[cfg(feature = "async")] use crate::vm::al31f::AsyncCombustor;
@@ -143,9 +148,16 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                         this.insc_ptr = insc_ptr_next;
                     },
                     ExceptionInner::Unchecked(unchecked) => {
-                        return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                            unchecked, &mut this.thread.stack, this.insc_ptr
-                        )));
+                        let (new_slice, insc_ptr_next): (StackSlice, usize) =
+                            unchecked_exception_unwind_stack_catch(
+                                get_vm!(this.thread),
+                                this.thread.program.as_ref(),
+                                unchecked,
+                                &mut this.thread.stack,
+                                this.insc_ptr
+                            )?;
+                        this.slice = new_slice;
+                        this.insc_ptr = insc_ptr_next;
                     }
                 }
             }
@@ -176,6 +188,10 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             }
         }
 
+        if get_vm!(thread).alloc.has_pending_finalizers() {
+            run_pending_finalizers(thread);
+        }
+
         #[cfg(not(debug_assertions))]
         let insc: &Insc = program.code.get_unchecked(insc_ptr);
         #[cfg(debug_assertions)]
@@ -211,9 +227,9 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 if let Some(result) = i64::checked_div(src1, src2) {
                     slice.set_value(*dst, Value::new_int(result))
                 } else {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::DivideByZero, &mut thread.stack, insc_ptr
-                    )));
+                    impl_catch_unchecked![
+                        slice, UncheckedException::DivideByZero, thread, insc_ptr
+                    ];
                 }
             },
             Insc::DivFloat(src1, src2, dst) => impl_float_binop![slice, src1, src2, dst, /],
@@ -225,9 +241,9 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 if let Some(result) = i64::checked_rem(src1, src2) {
                     slice.set_value(*dst, Value::new_int(result))
                 } else {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::DivideByZero, &mut thread.stack, insc_ptr
-                    )));
+                    impl_catch_unchecked![
+                        slice, UncheckedException::DivideByZero, thread, insc_ptr
+                    ];
                 }
             },
             Insc::ModAny(src1, src2, dst) =>
@@ -377,41 +393,52 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             Insc::NullCheck(src) => {
                 let src: Value = slice.get_value(*src);
                 if src.is_null() {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::UnexpectedNull { value: src },
-                        &mut thread.stack,
-                        insc_ptr
-                    )));
+                    impl_catch_unchecked![
+                        slice, UncheckedException::UnexpectedNull { value: src }, thread, insc_ptr
+                    ];
                 }
             },
             Insc::IsType(src, tyck_info, dest) => {
                 let src: Value = slice.get_value(*src);
-                slice.set_value(*dest, Value::new_bool(check_type(src, *tyck_info)));
+                let depth: TyckDepth = get_vm!(thread).tyck_depth;
+                let result: bool = check_type_with_depth(src, *tyck_info, depth);
+                slice.set_value(*dest, Value::new_bool(result));
             },
             Insc::TypeCheck(src, tyck_info) => {
                 let src: Value = slice.get_value(*src);
-                if !check_type(src, *tyck_info) {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
+                let depth: TyckDepth = get_vm!(thread).tyck_depth;
+                if !check_type_with_depth(src, *tyck_info, depth) {
+                    impl_catch_unchecked![
+                        slice,
                         UncheckedException::TypeCheckFailure {
                             object: src,
                             expected_type: *tyck_info
                         },
-                        &mut thread.stack,
+                        thread,
                         insc_ptr
-                    )));
+                    ];
                 }
             },
             Insc::OwnershipInfoCheck(src, mask) => {
                 let src: Value = slice.get_value(*src);
-                if src.is_value() || src.ownership_info_norm() as u8 & mask != 0 {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
+                if src.is_null() {
+                    impl_catch_unchecked![
+                        slice,
+                        UncheckedException::UnexpectedNull { value: src },
+                        thread,
+                        insc_ptr
+                    ];
+                }
+                if src.is_value() || src.ownership_info() as u8 & mask != *mask {
+                    impl_catch_unchecked![
+                        slice,
                         UncheckedException::OwnershipCheckFailure {
                             object: src,
                             expected_mask: *mask
                         },
-                        &mut thread.stack,
+                        thread,
                         insc_ptr
-                    )));
+                    ];
                 }
             },
             Insc::Call(func_id, args, rets) => {
@@ -421,6 +448,13 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let compiled: &CompiledFunction = &program.functions[*func_id];
 
                 debug_assert_eq!(compiled.arg_count, args.len());
+                if thread.stack.would_overflow(compiled.stack_size) {
+                    let unchecked: UncheckedException = UncheckedException::StackOverflow {
+                        frame_depth: thread.stack.frames.len(),
+                        value_count: thread.stack.value_count()
+                    };
+                    impl_catch_unchecked![slice, unchecked, thread, insc_ptr];
+                }
                 *slice = thread.stack.func_call_grow_stack(
                     *func_id,
                     compiled.stack_size,
@@ -441,6 +475,13 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     let compiled: &CompiledFunction = &program.functions[func_id];
 
                     debug_assert_eq!(compiled.arg_count, args.len());
+                    if thread.stack.would_overflow(compiled.stack_size) {
+                        let unchecked: UncheckedException = UncheckedException::StackOverflow {
+                            frame_depth: thread.stack.frames.len(),
+                            value_count: thread.stack.value_count()
+                        };
+                        impl_catch_unchecked![slice, unchecked, thread, insc_ptr];
+                    }
                     *slice = thread.stack.func_call_grow_stack(
                         func_id,
                         compiled.stack_size,
@@ -450,7 +491,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     );
                     insc_ptr = compiled.start_addr;
                 } else {
-                    let closure: &Closure = &*(func.get_as_mut_ptr_norm::<Closure>() as *const _);
+                    let closure: &Closure = &*(func.get_as_mut_ptr::<Closure>() as *const _);
                     let func_id: usize = closure.func_id;
 
                     #[cfg(not(debug_assertions))]
@@ -458,6 +499,13 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     #[cfg(debug_assertions)]
                     let compiled: &CompiledFunction = &program.functions[func_id];
 
+                    if thread.stack.would_overflow(compiled.stack_size) {
+                        let unchecked: UncheckedException = UncheckedException::StackOverflow {
+                            frame_depth: thread.stack.frames.len(),
+                            value_count: thread.stack.value_count()
+                        };
+                        impl_catch_unchecked![slice, unchecked, thread, insc_ptr];
+                    }
                     *slice = thread.stack.closure_call_grow_stack(
                         func_id,
                         compiled.stack_size,
@@ -469,12 +517,63 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     insc_ptr = compiled.start_addr;
                 }
             },
-            Insc::CallOverload(overload_table, args, rets) => {
+            Insc::TailCall(func_id, args) => {
+                #[cfg(not(debug_assertions))]
+                let compiled: &CompiledFunction = program.functions.get_unchecked(*func_id);
+                #[cfg(debug_assertions)]
+                let compiled: &CompiledFunction = &program.functions[*func_id];
+
+                debug_assert_eq!(compiled.arg_count, args.len());
+                if thread.stack.would_overflow_tail_call(compiled.stack_size) {
+                    let unchecked: UncheckedException = UncheckedException::StackOverflow {
+                        frame_depth: thread.stack.frames.len(),
+                        value_count: thread.stack.value_count()
+                    };
+                    impl_catch_unchecked![slice, unchecked, thread, insc_ptr];
+                }
+                *slice = thread.stack.tail_call_reuse_stack(
+                    *func_id,
+                    compiled.stack_size,
+                    &[],
+                    args
+                );
+                insc_ptr = compiled.start_addr;
+            },
+            Insc::TailCallPtr(func, args) => {
+                let func: Value = slice.get_value(*func);
+                let (func_id, captures): (usize, &[Value]) = if func.is_value() {
+                    (func.vt_data.inner.int_value as usize, &[])
+                } else {
+                    let closure: &Closure = &*(func.get_as_mut_ptr::<Closure>() as *const _);
+                    (closure.func_id, &closure.captures)
+                };
+
+                #[cfg(not(debug_assertions))]
+                let compiled: &CompiledFunction = program.functions.get_unchecked(func_id);
+                #[cfg(debug_assertions)]
+                let compiled: &CompiledFunction = &program.functions[func_id];
+
+                if thread.stack.would_overflow_tail_call(compiled.stack_size) {
+                    let unchecked: UncheckedException = UncheckedException::StackOverflow {
+                        frame_depth: thread.stack.frames.len(),
+                        value_count: thread.stack.value_count()
+                    };
+                    impl_catch_unchecked![slice, unchecked, thread, insc_ptr];
+                }
+                *slice = thread.stack.tail_call_reuse_stack(
+                    func_id,
+                    compiled.stack_size,
+                    captures,
+                    args
+                );
+                insc_ptr = compiled.start_addr;
+            },
+            Insc::CallOverload(call_site, args, rets) => {
                 match call_overload(
                     thread,
                     *slice,
                     insc_ptr,
-                    *overload_table,
+                    *call_site,
                     args,
                     rets)
                 {
@@ -522,57 +621,78 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 }
             },
             Insc::FFICallRtlc(ffi_func_id, args, ret_value_locs) => {
-                let ffi_function: &'static dyn FFIFunction<Combustor<A>>
-                    = program.ffi_funcs[*ffi_func_id];
-
-                let args_len: usize = args.len();
-                for i /*: usize*/ in 0..args_len {
-                    let arg_idx: usize = *args.get_unchecked(i);
-                    *ffi_args.get_unchecked_mut(i) = slice.get_value(arg_idx);
-                }
-
-                let ret_locs_len: usize = ret_value_locs.len();
-                for i /*: usize*/ in 0..ret_locs_len {
-                    let ret_value_loc_idx: usize = *ret_value_locs.get_unchecked(i);
-                    *ffi_rets.get_unchecked_mut(i) = slice.get_value_mut_ref(ret_value_loc_idx);
+                let ffi_handle: &FFIHandle<dyn FFIFunction<Combustor<A>>>
+                    = &program.ffi_funcs[*ffi_func_id];
+                impl_ffi_call_rtlc![
+                    ffi_handle,
+                    args,
+                    ret_value_locs,
+                    slice,
+                    ffi_args,
+                    ffi_rets,
+                    program,
+                    thread,
+                    insc_ptr
+                ];
+            },
+            Insc::CallMethod(method_table_id, args, ret_value_locs) => {
+                let method_table: &MethodTable<A> = &program.method_tables[*method_table_id];
+                let receiver: Value = slice.get_value(*args.get_unchecked(0));
+                if receiver.is_null() {
+                    impl_catch_unchecked![
+                        slice,
+                        UncheckedException::UnexpectedNull { value: receiver },
+                        thread,
+                        insc_ptr
+                    ];
                 }
-
-                let mut combustor: Combustor<A> = Combustor::new(NonNull::from(get_vm!(thread)));
-
-                if let Err(e /*: FFIException*/) = ffi_function.call_rtlc(
-                    &mut combustor,
-                    &ffi_args[0..args_len],
-                    &mut ffi_rets[0..ret_locs_len]
-                ) {
-                    match e {
-                        FFIException::Checked(checked) => {
-                            let (new_slice, insc_ptr_next): (StackSlice, usize) =
-                                checked_exception_unwind_stack(
-                                    get_vm!(thread),
-                                    &program,
-                                    checked,
-                                    &mut thread.stack,
-                                    insc_ptr
-                                )?;
-                            *slice = new_slice;
-                            insc_ptr = insc_ptr_next;
+                let method_impl: &MethodImpl<A> =
+                    if let Some(method_impl) = method_table.lookup(receiver) {
+                        method_impl
+                    } else {
+                        impl_catch_unchecked![
+                            slice,
+                            UncheckedException::MethodNotFound {
+                                receiver,
+                                method: method_table.name.clone()
+                            },
+                            thread,
+                            insc_ptr
+                        ];
+                    };
+                if !method_impl.accepts(args.len(), ret_value_locs.len()) {
+                    impl_catch_unchecked![
+                        slice,
+                        UncheckedException::ArgCountMismatch {
+                            func_id: *method_table_id,
+                            expected: method_impl.counts.unwrap_unchecked().0,
+                            got: args.len()
                         },
-                        FFIException::Unchecked(unchecked) => {
-                            return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                                unchecked, &mut thread.stack, insc_ptr
-                            )));
-                        }
-                    }
+                        thread,
+                        insc_ptr
+                    ];
                 }
+                let ffi_handle: &FFIHandle<dyn FFIFunction<Combustor<A>>> = &method_impl.func;
+                impl_ffi_call_rtlc![
+                    ffi_handle,
+                    args,
+                    ret_value_locs,
+                    slice,
+                    ffi_args,
+                    ffi_rets,
+                    program,
+                    thread,
+                    insc_ptr
+                ];
             },
             #[cfg(feature = "optimized-rtlc")]
             Insc::FFICall(ffi_func_id, args, ret_value_locs) => {
                 #[cfg(not(debug_assertions))]
-                let ffi_function: &'static dyn FFIFunction<Combustor<A>>
-                    = *program.ffi_funcs.get_unchecked(*ffi_func_id);
+                let ffi_function: &dyn FFIFunction<Combustor<A>>
+                    = &**program.ffi_funcs.get_unchecked(*ffi_func_id);
                 #[cfg(debug_assertions)]
-                let ffi_function: &'static dyn FFIFunction<Combustor<A>>
-                    = program.ffi_funcs[*ffi_func_id];
+                let ffi_function: &dyn FFIFunction<Combustor<A>>
+                    = &*program.ffi_funcs[*ffi_func_id];
 
                 let args_len: usize = args.len();
                 for i /*: usize*/ in 0..args_len {
@@ -586,13 +706,22 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     *ffi_rets.get_unchecked_mut(i) = slice.get_value_mut_ref(ret_value_loc_idx);
                 }
 
-                let mut combustor: Combustor<A> = Combustor::new(NonNull::from(get_vm!(thread)));
+                let mut combustor: Combustor<A> = Combustor::new(NonNull::from(&mut *thread));
 
-                if let Err(e /*: FFIException*/) = ffi_function.call_unchecked(
-                    &mut combustor,
-                    &ffi_args[0..args_len],
-                    &mut ffi_rets[0..ret_locs_len]
-                ) {
+                let result: Result<(), FFIException> = if get_vm!(thread).catch_foreign_panics {
+                    call_catch_panic(&ffi_args[0..args_len], || ffi_function.call_unchecked(
+                        &mut combustor,
+                        &ffi_args[0..args_len],
+                        &ffi_rets[0..ret_locs_len]
+                    ))
+                } else {
+                    ffi_function.call_unchecked(
+                        &mut combustor,
+                        &ffi_args[0..args_len],
+                        &ffi_rets[0..ret_locs_len]
+                    )
+                };
+                if let Err(e /*: FFIException*/) = result {
                     match e {
                         FFIException::Checked(checked) => {
                             let (new_slice, insc_ptr_next): (StackSlice, usize) =
@@ -607,9 +736,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                             insc_ptr = insc_ptr_next;
                         },
                         FFIException::Unchecked(unchecked) => {
-                            return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                                unchecked, &mut thread.stack, insc_ptr
-                            )));
+                            impl_catch_unchecked![slice, unchecked, thread, insc_ptr];
                         }
                     }
                 }
@@ -617,11 +744,12 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             #[cfg(all(feature = "optimized-rtlc", feature = "async"))]
             Insc::FFICallAsync(async_ffi_func_id, args, ret) => {
                 #[cfg(not(debug_assertions))]
-                let async_ffi_function: &'static dyn FFIAsyncFunction<A, _, _>
-                    = *program.async_ffi_funcs.get_unchecked(*async_ffi_func_id);
+                let async_ffi_handle: &FFIHandle<dyn FFIAsyncFunction<A, _, _>>
+                    = program.async_ffi_funcs.get_unchecked(*async_ffi_func_id);
                 #[cfg(debug_assertions)]
-                let async_ffi_function: &'static dyn FFIAsyncFunction<A, _, _>
-                    = program.async_ffi_funcs[*async_ffi_func_id];
+                let async_ffi_handle: &FFIHandle<dyn FFIAsyncFunction<A, _, _>>
+                    = &program.async_ffi_funcs[*async_ffi_func_id];
+                let async_ffi_function: &dyn FFIAsyncFunction<A, _, _> = &**async_ffi_handle;
 
                 let args_len: usize = args.len();
                 for i /*: usize*/ in 0..args_len {
@@ -629,13 +757,43 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                     *ffi_args.get_unchecked_mut(i) = slice.get_value(arg_idx);
                 }
 
-                let mut combustor: AsyncCombustor<A> = AsyncCombustor::new(
+                if let Some(variadic_check) = async_ffi_handle.variadic_check() {
+                    let depth: TyckDepth = get_vm!(thread).tyck_depth;
+                    let args: &[Value] = &ffi_args[0..args_len];
+                    if let Some(arg) = check_variadic_args(args, variadic_check, depth) {
+                        impl_catch_unchecked![
+                            slice,
+                            UncheckedException::TypeCheckFailure {
+                                object: arg,
+                                expected_type: variadic_check.elem_type()
+                            },
+                            thread,
+                            insc_ptr
+                        ];
+                    }
+                }
+
+                let combustor: AsyncCombustor<A> = AsyncCombustor::new(
                     thread.vm.serializer.clone(),
-                    thread.program
+                    thread.program,
+                    thread.stack.config
                 );
 
-                match async_ffi_function.call_rtlc(&mut combustor, &ffi_args[0..args_len]) {
+                let catch_foreign_panics: bool = get_vm!(thread).catch_foreign_panics;
+                let result: Result<Promise<A>, FFIException> = if catch_foreign_panics {
+                    call_catch_panic(&ffi_args[0..args_len], || {
+                        async_ffi_function.call_rtlc(&combustor, &ffi_args[0..args_len])
+                    })
+                } else {
+                    async_ffi_function.call_rtlc(&combustor, &ffi_args[0..args_len])
+                };
+                match result {
                     Ok(promise /*: Promise*/) => {
+                        let promise: Promise<A> = if catch_foreign_panics {
+                            catch_promise_panic(promise)
+                        } else {
+                            promise
+                        };
                         let promise: Value = Value::new_owned(promise);
                         thread.vm.get_shared_data_mut().alloc.add_managed(promise);
                         slice.set_value(*ret, promise);
@@ -655,9 +813,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                                 insc_ptr = insc_ptr_next;
                             },
                             FFIException::Unchecked(unchecked) => {
-                                return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                                    unchecked, &mut thread.stack, insc_ptr
-                                )));
+                                impl_catch_unchecked![slice, unchecked, thread, insc_ptr];
                             }
                         }
                     }
@@ -668,11 +824,15 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let promise: Value = slice.get_value(*promise);
                 let wrapper: *mut Wrapper<()> = promise.ptr_repr.ptr as *mut Wrapper<()>;
                 if (*wrapper).ownership_info == OwnershipInfo::MovedToRust as u8 {
-                    return Poll::Ready(Err(unchecked_exception_unwind_stack(
-                        UncheckedException::AlreadyAwaited { promise },
-                        &mut thread.stack,
-                        insc_ptr
-                    )));
+                    impl_catch_unchecked![
+                        slice, UncheckedException::AlreadyAwaited { promise }, thread, insc_ptr
+                    ];
+                }
+                // a callback from native code gets blocked on, which may deadlock on the promise
+                if thread.stack.in_reentrant_call() {
+                    impl_catch_unchecked![
+                        slice, UncheckedException::AwaitInCallback { promise }, thread, insc_ptr
+                    ];
                 }
 
                 let Promise(fut) = promise.move_out::<Promise<A>>();
@@ -707,6 +867,42 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 insc_ptr = insc_ptr_next;
                 continue;
             },
+            Insc::Rethrow(exception_ptr) => {
+                let caught: &mut Exception =
+                    &mut *(slice.get_value(*exception_ptr).get_as_mut_ptr_norm() as *mut _);
+                let exception: Exception = if let Some(exception) = caught.take_for_rethrow() {
+                    exception
+                } else {
+                    // the caught unchecked exception has been raised again already
+                    impl_catch_unchecked![
+                        slice,
+                        UncheckedException::UnexpectedNull { value: Value::new_null() },
+                        thread,
+                        insc_ptr
+                    ];
+                };
+                let (new_slice, insc_ptr_next): (StackSlice, usize) =
+                    exception_unwind_stack(
+                        get_vm!(thread),
+                        program,
+                        exception,
+                        &mut thread.stack,
+                        insc_ptr,
+                        true
+                    )?;
+                *slice = new_slice;
+                insc_ptr = insc_ptr_next;
+                continue;
+            },
+            Insc::ExceptionValue(src, dest) => {
+                let exception: &Exception =
+                    &*(slice.get_value(*src).get_as_mut_ptr_norm() as *const _);
+                let value: Value = match &exception.inner {
+                    ExceptionInner::Checked(checked) => *checked,
+                    ExceptionInner::Unchecked(_) => Value::new_null()
+                };
+                slice.set_value(*dest, value);
+            },
             Insc::JumpIfTrue(condition, dest) => {
                 let condition: bool = slice.get_value(*condition).vt_data.inner.bool_value;
                 if condition {
@@ -727,6 +923,34 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 get_vm!(thread).alloc.add_managed(container);
                 slice.set_value(*dest, container);
             },
+            Insc::MakeClosure(func_id, captures, vt, dest) => {
+                let captures: SmallVec<[Value; 4]> = captures.iter()
+                    .map(|capture: &usize| slice.get_value(*capture))
+                    .collect();
+                let closure: Value = Closure::new(captures, *func_id).into_value(vt.as_ptr());
+                get_vm!(thread).alloc.add_managed(closure);
+                slice.set_value(*dest, closure);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::MakeCell(src, dest) => {
+                let cell: Value = Value::new_owned(CaptureCell::new(slice.get_value(*src)));
+                get_vm!(thread).alloc.add_managed(cell);
+                slice.set_value(*dest, cell);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CellGet(cell, dest) => {
+                let cell: &CaptureCell =
+                    &*(slice.get_value(*cell).get_as_mut_ptr_norm() as *const _);
+                slice.set_value(*dest, cell.get());
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CellSet(cell, src) => {
+                let cell: &CaptureCell =
+                    &*(slice.get_value(*cell).get_as_mut_ptr_norm() as *const _);
+                let data: Value = slice.get_value(*src);
+                get_vm!(thread).alloc.mark_object(data);
+                cell.set(data);
+            },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::CreateString(dest) => {
                 let string: String = String::new();
@@ -744,43 +968,41 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecIndex(src, index, dst) => {
                 let vec_value: Value = slice.get_value(*src);
-                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr_norm() as *const _);
+                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr() as *const _);
                 let index: i64 = slice.get_value(*index).vt_data.inner.int_value;
                 if let Some(data) = vec.inner.get_ref_unchecked().get(index as usize) {
                     slice.set_value(*dst, *data);
                 } else {
-                    return Poll::Ready(Err(
-                        unchecked_exception_unwind_stack(
-                            UncheckedException::IndexOutOfBounds { indexed: vec_value, index },
-                            &mut thread.stack,
-                            insc_ptr
-                        )
-                    ));
+                    impl_catch_unchecked![
+                        slice,
+                        UncheckedException::IndexOutOfBounds { indexed: vec_value, index },
+                        thread,
+                        insc_ptr
+                    ];
                 }
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecIndexPut(src, index, value) => {
                 let vec_value: Value = slice.get_value(*src);
-                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr_norm() as *const _);
+                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr() as *const _);
                 let index: i64 = slice.get_value(*index).vt_data.inner.int_value;
                 if let Some(data) = vec.inner.get_mut_ref_unchecked().get_mut(index as usize) {
                     let value: Value = slice.get_value(*value);
                     get_vm!(thread).alloc.mark_object(value);
                     *data = value;
                 } else {
-                    return Poll::Ready(Err(
-                        unchecked_exception_unwind_stack(
-                            UncheckedException::IndexOutOfBounds { indexed: vec_value, index },
-                            &mut thread.stack,
-                            insc_ptr
-                        )
-                    ));
+                    impl_catch_unchecked![
+                        slice,
+                        UncheckedException::IndexOutOfBounds { indexed: vec_value, index },
+                        thread,
+                        insc_ptr
+                    ];
                 }
             },
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecPush(src, data) => {
                 let vec_value: Value = slice.get_value(*src);
-                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr_norm() as *const _);
+                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr() as *const _);
                 let data: Value = slice.get_value(*data);
                 get_vm!(thread).alloc.mark_object(data);
                 vec.inner.get_mut_ref_unchecked().push(data);
@@ -788,7 +1010,7 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
             #[cfg(feature = "al31f-builtin-ops")]
             Insc::VecLen(src, dst) => {
                 let vec_value: Value = slice.get_value(*src);
-                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr_norm() as *const _);
+                let vec: &VMGenericVec = &*(vec_value.get_as_mut_ptr() as *const _);
                 slice.set_value(*dst, Value::new_int(vec.inner.get_ref_unchecked().len() as i64));
             },
 
@@ -858,6 +1080,38 @@ unsafe fn poll_unsafe<'a, A: Alloc, const S: bool>(
                 let data: Value = slice.get_value(*data);
                 get_vm!(thread).alloc.mark_object(data);
                 object.fields.get_mut_ref_unchecked().insert(field.to_string(), data);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::HostFieldGet(src, field, dest) => {
+                let src: Value = slice.get_value(*src);
+                if !src.ownership_info_norm().is_readable() {
+                    impl_catch_unchecked![
+                        slice,
+                        UncheckedException::OwnershipCheckFailure {
+                            object: src,
+                            expected_mask: OWN_INFO_READ_MASK
+                        },
+                        thread,
+                        insc_ptr
+                    ];
+                }
+                let value: Value = (*src.get_as_dyn_base())
+                    .dyn_get_field(field.as_ref())
+                    .unwrap_or_else(Value::new_null);
+                slice.set_value(*dest, value);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::CreateWeak(src, vt, dest) => {
+                let target: Value = slice.get_value(*src);
+                let weak: Value = Weak::new(target).into_value(vt.as_ptr());
+                get_vm!(thread).alloc.add_managed(weak);
+                get_vm!(thread).alloc.add_weak_ref(weak);
+                slice.set_value(*dest, weak);
+            },
+            #[cfg(feature = "al31f-builtin-ops")]
+            Insc::WeakGet(src, dest) => {
+                let weak: &Weak = &*slice.get_value(*src).get_as_mut_ptr::<Weak>();
+                slice.set_value(*dest, weak.get());
             }
         }
     }
@@ -869,6 +1123,12 @@ impl<'a, A: Alloc, const S: bool> Future for VMThreadRunFunctionFut<'a, A, S> {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         unsafe {
             match poll_unsafe(Pin::into_inner(self), cx) {
+                Poll::Ready(Err(mut exception)) => {
+                    // objects referenced by the exception are still alive, but may get reclaimed
+                    // once it leaves the VM
+                    exception.capture_description();
+                    Poll::Ready(UncheckedSendSync::new(Err(exception)))
+                },
                 Poll::Ready(r) => Poll::Ready(UncheckedSendSync::new(r)),
                 Poll::Pending => Poll::Pending
             }
@@ -892,6 +1152,14 @@ pub unsafe fn vm_thread_run_function<'a, A: Alloc, const S: bool>(
         return Err(Exception::unchecked_exc(exception));
     }
 
+    if compiled_function.stack_size > thread.stack.config.max_values {
+        let exception: UncheckedException = UncheckedException::StackOverflow {
+            frame_depth: 0,
+            value_count: 0
+        };
+        return Err(Exception::unchecked_exc(exception));
+    }
+
     let slice: StackSlice =
         thread.stack.ext_func_call_grow_stack(func_id, compiled_function.stack_size, args);
     let insc_ptr: usize = compiled_function.start_addr;
@@ -904,3 +1172,130 @@ pub unsafe fn vm_thread_run_function<'a, A: Alloc, const S: bool>(
         #[cfg(feature = "async")] awaiting_promise: None
     })
 }
+
+impl<A: Alloc> VMThread<A> {
+    /// Typed version of `vm_thread_run_function`: convert `args` to `Value`s, run the function
+    /// `func_id` on this thread, and convert its return values back to `Rets`. Unlike
+    /// `vm_thread_run_function`, the thread may be reused for other calls afterwards.
+    ///
+    /// ```no_run
+    /// # use pr47::ffi::convert::CallError;
+    /// # use pr47::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    /// # use pr47::vm::al31f::compiled::CompiledProgram;
+    /// # use pr47::vm::al31f::executor::{VMThread, create_vm_main_thread};
+    /// # async fn example(
+    /// #     program: &CompiledProgram<DefaultAlloc>,
+    /// #     func_id: usize
+    /// # ) -> Result<bool, CallError> {
+    /// # let mut thread: Box<VMThread<DefaultAlloc>> =
+    /// #     create_vm_main_thread(DefaultAlloc::new(), program).await;
+    /// let (ok,): (bool,) = unsafe {
+    ///     thread.call::<(i64, String), (bool,)>(func_id, (42, "42".into())).await?
+    /// };
+    /// # Ok(ok)
+    /// # }
+    /// ```
+    ///
+    /// # Safety
+    /// `func_id` must be a valid function of the program this thread runs.
+    pub async unsafe fn call<Args, Rets>(
+        &mut self,
+        func_id: usize,
+        args: Args
+    ) -> Result<Rets, CallError>
+        where Args: IntoValues,
+              Rets: FromValues
+    {
+        self.call_impl::<Args, Rets, false>(func_id, args).await
+    }
+
+    pub(crate) async unsafe fn call_impl<Args, Rets, const S: bool>(
+        &mut self,
+        func_id: usize,
+        args: Args
+    ) -> Result<Rets, CallError>
+        where Args: IntoValues,
+              Rets: FromValues
+    {
+        let args: Vec<Value> = args.into_values(&mut get_vm!(self).alloc);
+        let arg_pack: (&mut VMThread<A>, usize, &[Value]) = (self, func_id, &args);
+        let fut: VMThreadRunFunctionFut<A, S> =
+            vm_thread_run_function::<_, S>(UncheckedSendSync::new(arg_pack))?;
+        let rets: Result<Vec<Value>, Exception> = fut.await.into_inner();
+        let rets: Result<Rets, CallError> = match rets {
+            Ok(rets) => Rets::from_values(&rets).map_err(CallError::from),
+            Err(exception) => Err(CallError::Exception(exception))
+        };
+        self.stack.ext_func_call_done();
+        rets
+    }
+}
+
+/// Run finalizers queued by the allocator, between two instructions. Each finalized value stays
+/// rooted until its finalizer returns, since it is no longer treated as a root by the allocator.
+#[cold]
+unsafe fn run_pending_finalizers<A: Alloc>(thread: &mut VMThread<A>) {
+    while let Some((value, finalizer)) = get_vm!(thread).alloc.take_pending_finalizer() {
+        let _rooted: RootedValue = RootedValue::new(&mut get_vm!(thread).alloc, value);
+        let mut combustor: Combustor<A> = Combustor::new(NonNull::from(&mut *thread));
+        finalizer(&mut combustor, value);
+    }
+}
+
+/// Call `callee`, a function or closure value, with `args` on top of the frames already running on
+/// `thread`, and run it until it returns. Used by FFI functions calling back into script code.
+///
+/// The callee runs in a frame of its own, and an exception thrown by it unwinds the stack only
+/// down to that frame before being returned. Since the callee is blocked on, awaiting promises is
+/// not supported and throws `AwaitInCallback`. Returned values are rooted, since the callee's
+/// frame is gone once this function returns.
+///
+/// # Safety
+/// `thread` must be in the middle of running an FFI call, with `callee` and `args` alive.
+pub unsafe fn vm_thread_call_value<A: Alloc>(
+    thread: &mut VMThread<A>,
+    callee: Value,
+    args: &[Value]
+) -> Result<Vec<RootedValue>, Exception> {
+    let (func_id, captures): (usize, &[Value]) = match value_as_callable(callee) {
+        Some(callable) => callable,
+        None => return Err(Exception::unchecked_exc(UncheckedException::InvalidCastOp {
+            dest_type: "closure",
+            src: callee
+        }))
+    };
+
+    let program: &CompiledProgram<A> = thread.program.as_ref();
+    let compiled_function: &CompiledFunction = &program.functions[func_id];
+    if compiled_function.arg_count != captures.len() + args.len() {
+        let exception: UncheckedException = UncheckedException::ArgCountMismatch {
+            func_id, expected: compiled_function.arg_count, got: captures.len() + args.len()
+        };
+        return Err(Exception::unchecked_exc(exception));
+    }
+
+    if thread.stack.would_overflow(compiled_function.stack_size) {
+        let exception: UncheckedException = UncheckedException::StackOverflow {
+            frame_depth: thread.stack.frames.len(),
+            value_count: thread.stack.value_count()
+        };
+        return Err(Exception::unchecked_exc(exception));
+    }
+
+    let (slice, prev_entry_frame): (StackSlice, usize) = thread.stack.reentrant_call_grow_stack(
+        func_id, compiled_function.stack_size, captures, args
+    );
+    let thread_ptr: *mut VMThread<A> = thread as *mut _;
+    let fut: VMThreadRunFunctionFut<A, true> = VMThreadRunFunctionFut {
+        thread: &mut *thread_ptr,
+        slice,
+        insc_ptr: compiled_function.start_addr,
+
+        #[cfg(feature = "async")] awaiting_promise: None
+    };
+    let result: Result<Vec<Value>, Exception> = pollster::block_on(fut).into_inner();
+    (*thread_ptr).stack.reentrant_call_done(prev_entry_frame);
+    let values: Vec<Value> = result?;
+    let alloc: &mut A = &mut get_vm!((*thread_ptr)).alloc;
+    Ok(values.into_iter().map(|value: Value| RootedValue::new(alloc, value)).collect())
+}
@@ -11,7 +11,7 @@ use crate::ffi::async_fn::{AsyncReturnType, Promise};
 use crate::vm::al31f::alloc::Alloc;
 use crate::vm::al31f::compiled::CompiledProgram;
 use crate::vm::al31f::executor::{VMThread, create_vm_child_thread, vm_thread_run_function};
-use crate::vm::al31f::stack::StackSlice;
+use crate::vm::al31f::stack::{StackConfig, StackSlice};
 
 #[inline(never)]
 pub unsafe fn coroutine_spawn<A: Alloc>(
@@ -123,15 +123,18 @@ pub unsafe fn coroutine_spawn<A: Alloc>(
     let thread: &'static mut VMThread<A> = transmute::<_, _>(thread);
     let args: Box<[Value]> = args.iter().map(|arg: &usize| slice.get_value(*arg)).collect();
     let program: NonNull<CompiledProgram<A>> = thread.program;
-    let arg_pack: UncheckedSendSync<_> = UncheckedSendSync::new((args, program));
+    let stack_config: StackConfig = thread.stack.config;
+    let arg_pack: UncheckedSendSync<_> = UncheckedSendSync::new((args, program, stack_config));
 
     let get_join_handle = async move {
         let join_handle: JoinHandle<Box<dyn AsyncReturnType<A>>> = thread.vm.co_spawn_task(
             |child_context, (func_id, arg_pack)| UncheckedSendFut::new(async move {
-                let (args, program): (Box<[Value]>, NonNull<CompiledProgram<A>>) =
+                let (args, program, stack_config):
+                    (Box<[Value]>, NonNull<CompiledProgram<A>>, StackConfig) =
                     arg_pack.into_inner();
                 let mut new_thread: Box<VMThread<A>> =
                     create_vm_child_thread(child_context, program);
+                new_thread.stack.config = stack_config;
                 let arg_pack = UncheckedSendSync::new(
                     (new_thread.as_mut(), func_id, args.as_ref())
                 );
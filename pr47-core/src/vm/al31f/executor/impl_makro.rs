@@ -86,16 +86,14 @@ macro_rules! impl_checked_op2 {
         $dst:ident,
         $checked_op:expr,
         $thread:expr,
-        $insc_ptr:expr
+        $insc_ptr:ident
     ) => {
         {
             let src1: Value = $slice.get_value(*$src1);
             let src2: Value = $slice.get_value(*$src2);
             let dst: &mut Value = &mut *$slice.get_value_mut_ref(*$dst);
             if let Err(e /*: UncheckedException*/) = $checked_op($thread, src1, src2, dst) {
-                return Poll::Ready(Err(
-                    unchecked_exception_unwind_stack(e, &mut $thread.stack, $insc_ptr)
-                ));
+                impl_catch_unchecked![$slice, e, $thread, $insc_ptr];
             }
         }
     }
@@ -110,37 +108,132 @@ macro_rules! impl_checked_bin_op {
         $dst:ident,
         $checked_op:expr,
         $thread:expr,
-        $insc_ptr:expr
+        $insc_ptr:ident
     ) => {
         {
             let src1: Value = $slice.get_value(*$src1);
             let src2: Value = $slice.get_value(*$src2);
             let dst: &mut Value = &mut *$slice.get_value_mut_ref(*$dst);
             if let Err(e /*: UncheckedException*/) = $checked_op(src1, src2, dst) {
-                return Poll::Ready(
-                    Err(unchecked_exception_unwind_stack(e, &mut $thread.stack, $insc_ptr))
-                );
+                impl_catch_unchecked![$slice, e, $thread, $insc_ptr];
             }
         }
     }
 }
 
 macro_rules! impl_checked_unary_op {
-    ($slice:ident, $src:ident, $dst:ident, $checked_op:expr, $thread:expr, $insc_ptr:expr) => {
+    ($slice:ident, $src:ident, $dst:ident, $checked_op:expr, $thread:expr, $insc_ptr:ident) => {
         {
             let src: Value = $slice.get_value(*$src);
             let dst: &mut Value = &mut *$slice.get_value_mut_ref(*$dst);
             if let Err(e /*: UncheckedException*/) = $checked_op(src, dst) {
-                return Poll::Ready(
-                    Err(unchecked_exception_unwind_stack(e, &mut $thread.stack, $insc_ptr))
-                );
+                impl_catch_unchecked![$slice, e, $thread, $insc_ptr];
             }
         }
     }
 }
 
 macro_rules! impl_checked_cast_op {
-    ($slice:ident, $src:ident, $dst:ident, $checked_op:expr, $thread:expr, $insc_ptr:expr) => {
+    ($slice:ident, $src:ident, $dst:ident, $checked_op:expr, $thread:expr, $insc_ptr:ident) => {
         impl_checked_unary_op![$slice, $src, $dst, $checked_op, $thread, $insc_ptr]
     }
 }
+
+macro_rules! impl_catch_unchecked {
+    ($slice:ident, $unchecked:expr, $thread:expr, $insc_ptr:ident) => {
+        {
+            let (new_slice, insc_ptr_next): (StackSlice, usize) =
+                unchecked_exception_unwind_stack_catch(
+                    get_vm!($thread),
+                    $thread.program.as_ref(),
+                    $unchecked,
+                    &mut $thread.stack,
+                    $insc_ptr
+                )?;
+            *$slice = new_slice;
+            $insc_ptr = insc_ptr_next;
+            continue;
+        }
+    }
+}
+
+macro_rules! impl_ffi_call_rtlc {
+    (
+        $ffi_handle:ident,
+        $args:ident,
+        $ret_value_locs:ident,
+        $slice:ident,
+        $ffi_args:ident,
+        $ffi_rets:ident,
+        $program:ident,
+        $thread:ident,
+        $insc_ptr:ident
+    ) => {
+        {
+            let ffi_function: &dyn FFIFunction<Combustor<A>> = &**$ffi_handle;
+
+            let args_len: usize = $args.len();
+            for i /*: usize*/ in 0..args_len {
+                let arg_idx: usize = *$args.get_unchecked(i);
+                *$ffi_args.get_unchecked_mut(i) = $slice.get_value(arg_idx);
+            }
+
+            if let Some(variadic_check) = $ffi_handle.variadic_check() {
+                let depth: TyckDepth = get_vm!($thread).tyck_depth;
+                let args: &[Value] = &$ffi_args[0..args_len];
+                if let Some(arg) = check_variadic_args(args, variadic_check, depth) {
+                    impl_catch_unchecked![
+                        $slice,
+                        UncheckedException::TypeCheckFailure {
+                            object: arg,
+                            expected_type: variadic_check.elem_type()
+                        },
+                        $thread,
+                        $insc_ptr
+                    ];
+                }
+            }
+
+            let ret_locs_len: usize = $ret_value_locs.len();
+            for i /*: usize*/ in 0..ret_locs_len {
+                let ret_value_loc_idx: usize = *$ret_value_locs.get_unchecked(i);
+                *$ffi_rets.get_unchecked_mut(i) = $slice.get_value_mut_ref(ret_value_loc_idx);
+            }
+
+            let mut combustor: Combustor<A> = Combustor::new(NonNull::from(&mut *$thread));
+
+            let result: Result<(), FFIException> = if get_vm!($thread).catch_foreign_panics {
+                call_catch_panic(&$ffi_args[0..args_len], || ffi_function.call_rtlc(
+                    &mut combustor,
+                    &$ffi_args[0..args_len],
+                    &$ffi_rets[0..ret_locs_len]
+                ))
+            } else {
+                ffi_function.call_rtlc(
+                    &mut combustor,
+                    &$ffi_args[0..args_len],
+                    &$ffi_rets[0..ret_locs_len]
+                )
+            };
+            if let Err(e /*: FFIException*/) = result {
+                match e {
+                    FFIException::Checked(checked) => {
+                        let (new_slice, insc_ptr_next): (StackSlice, usize) =
+                            checked_exception_unwind_stack(
+                                get_vm!($thread),
+                                &$program,
+                                checked,
+                                &mut $thread.stack,
+                                $insc_ptr
+                            )?;
+                        *$slice = new_slice;
+                        $insc_ptr = insc_ptr_next;
+                    },
+                    FFIException::Unchecked(unchecked) => {
+                        impl_catch_unchecked![$slice, unchecked, $thread, $insc_ptr];
+                    }
+                }
+            }
+        }
+    }
+}
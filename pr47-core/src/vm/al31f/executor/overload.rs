@@ -1,21 +1,180 @@
+use std::ptr::NonNull;
+
+use smallvec::SmallVec;
+use xjbutil::unchecked::UncheckedCellOps;
+
+use crate::data::Value;
 use crate::data::exception::{Exception, UncheckedException};
+use crate::data::tyck::TyckInfo;
+use crate::data::value_typed::{VALUE_TYPE_MASK, VALUE_TYPE_TAG_MASK};
 use crate::vm::al31f::alloc::Alloc;
-use crate::vm::al31f::executor::unwinding::unchecked_exception_unwind_stack;
+use crate::vm::al31f::compiled::{
+    CompiledFunction,
+    CompiledProgram,
+    OVERLOAD_CACHE_SIZE,
+    OverloadCacheEntry,
+    OverloadCallSite,
+    OverloadCacheKey,
+    OverloadTable
+};
+use crate::vm::al31f::executor::rtti::check_type;
+use crate::vm::al31f::executor::unwinding::unchecked_exception_unwind_stack_catch;
 use crate::vm::al31f::executor::VMThread;
 use crate::vm::al31f::stack::StackSlice;
 
+include!("get_vm_makro.rs");
+
+/// Type tag of `value` used for overload inline caching
+///
+/// Value-typed data is keyed by its value type tag, references are keyed by their vtable (either
+/// the `DynBase` vtable or the `GenericTypeVT` for containers), and `null` is keyed by `0`. Since
+/// vtables are never located in the first page, these keys never collide.
+///
+/// # Safety
+/// `value` must be a valid `Value`.
+#[inline(always)]
+pub unsafe fn overload_cache_key(value: Value) -> usize {
+    if value.is_null() {
+        0
+    } else if value.is_value() {
+        (value.vt_data.tag as u8 & (VALUE_TYPE_TAG_MASK | VALUE_TYPE_MASK)) as usize
+    } else {
+        value.ptr_repr.trivia
+    }
+}
+
+/// How specific a parameter type is. Candidates with higher total specificity are preferred.
+#[inline(always)]
+unsafe fn param_specificity(tyck_info: Option<NonNull<TyckInfo>>) -> usize {
+    match tyck_info.map(|tyck_info: NonNull<TyckInfo>| tyck_info.as_ref()) {
+        None | Some(TyckInfo::AnyType) => 0,
+        Some(TyckInfo::Nullable(_)) => 1,
+        Some(_) => 2
+    }
+}
+
+/// Select the best candidate of `overload_table` for `args`, returns `None` if no candidate
+/// accepts the arguments. Among accepting candidates, the one with highest specificity wins, and
+/// ties are broken by candidate order.
+///
+/// Only shallow type checks are used, so that the result solely depends on argument type tags
+/// and may be cached.
+///
+/// # Safety
+/// `stack_slice` must be the current stack frame, and `args` must be valid indices into it.
+pub unsafe fn select_overload<A: Alloc>(
+    program: &CompiledProgram<A>,
+    overload_table: &OverloadTable,
+    stack_slice: StackSlice,
+    args: &[usize]
+) -> Option<usize> {
+    let mut best: Option<(usize, usize)> = None;
+    'candidates: for func_id /*: &usize*/ in overload_table.candidates.iter() {
+        let compiled: &CompiledFunction = &program.functions[*func_id];
+        if compiled.arg_count != args.len() {
+            continue;
+        }
+
+        let mut specificity: usize = 0;
+        for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
+            let tyck_info: Option<NonNull<TyckInfo>> =
+                compiled.param_tyck_info.get(i).copied().flatten();
+            if let Some(tyck_info) = tyck_info {
+                if !check_type(*stack_slice.get_value_mut_ref(*arg), tyck_info) {
+                    continue 'candidates;
+                }
+            }
+            specificity += param_specificity(tyck_info);
+        }
+
+        let more_specific: bool = match best {
+            Some((_, best_specificity)) => specificity > best_specificity,
+            None => true
+        };
+        if more_specific {
+            best = Some((*func_id, specificity));
+        }
+    }
+    best.map(|(func_id, _)| func_id)
+}
+
+/// Select a candidate of the overloaded function called at `call_site` for `args`, consulting
+/// the inline cache of `call_site` first, and then set up the stack frame for calling it
+///
+/// # Safety
+/// `stack_slice` must be the current stack frame of `thread`, and `call_site`, `args` and `rets`
+/// must come from a valid `CallOverload` instruction of the program `thread` runs.
 #[inline(never)]
 pub unsafe fn call_overload<A: Alloc>(
     thread: &mut VMThread<A>,
-    _stack_slice: StackSlice,
+    stack_slice: StackSlice,
     insc_ptr: usize,
-    overload_table: usize,
-    _args: &'static [usize],
-    _rets: &'static [usize]
+    call_site: usize,
+    args: &'static [usize],
+    rets: &'static [usize]
 ) -> Result<(StackSlice, usize), Exception> {
-    Err(unchecked_exception_unwind_stack(
-        UncheckedException::OverloadCallFailure { overload_table },
-        &mut thread.stack,
+    let program: &CompiledProgram<A> = thread.program.as_ref();
+    #[cfg(not(debug_assertions))]
+    let call_site: &OverloadCallSite = program.overload_call_sites.get_unchecked(call_site);
+    #[cfg(debug_assertions)]
+    let call_site: &OverloadCallSite = &program.overload_call_sites[call_site];
+    let overload_table: usize = call_site.overload_table;
+    #[cfg(not(debug_assertions))]
+    let table: &OverloadTable = program.overload_tables.get_unchecked(overload_table);
+    #[cfg(debug_assertions)]
+    let table: &OverloadTable = &program.overload_tables[overload_table];
+
+    let mut key: OverloadCacheKey = OverloadCacheKey::new();
+    for arg /*: &usize*/ in args.iter() {
+        key.push(overload_cache_key(*stack_slice.get_value_mut_ref(*arg)));
+    }
+
+    let cache: &mut SmallVec<[OverloadCacheEntry; OVERLOAD_CACHE_SIZE]> =
+        call_site.cache.get_mut_ref_unchecked();
+    let func_id: usize =
+        if let Some(entry) = cache.iter().find(|entry: &&OverloadCacheEntry| entry.key == key) {
+            entry.func_id
+        } else if let Some(func_id) = select_overload(program, table, stack_slice, args) {
+            // megamorphic call sites simply evict the oldest entry
+            if cache.len() == OVERLOAD_CACHE_SIZE {
+                cache.remove(0);
+            }
+            cache.push(OverloadCacheEntry { key, func_id });
+            func_id
+        } else {
+            return unchecked_exception_unwind_stack_catch(
+                get_vm!(thread),
+                program,
+                UncheckedException::OverloadCallFailure { overload_table },
+                &mut thread.stack,
+                insc_ptr
+            );
+        };
+
+    #[cfg(not(debug_assertions))]
+    let compiled: &CompiledFunction = program.functions.get_unchecked(func_id);
+    #[cfg(debug_assertions)]
+    let compiled: &CompiledFunction = &program.functions[func_id];
+
+    if thread.stack.would_overflow(compiled.stack_size) {
+        return unchecked_exception_unwind_stack_catch(
+            get_vm!(thread),
+            program,
+            UncheckedException::StackOverflow {
+                frame_depth: thread.stack.frames.len(),
+                value_count: thread.stack.value_count()
+            },
+            &mut thread.stack,
+            insc_ptr
+        );
+    }
+
+    let new_slice: StackSlice = thread.stack.func_call_grow_stack(
+        func_id,
+        compiled.stack_size,
+        args,
+        NonNull::from(rets),
         insc_ptr
-    ))
+    );
+    Ok((new_slice, compiled.start_addr))
 }
@@ -3,18 +3,51 @@ use std::ptr::NonNull;
 
 use unchecked_unwrap::UncheckedUnwrap;
 use xjbutil::unchecked::UnsafeFrom;
-use crate::data::generic::GenericTypeVT;
 
-use crate::data::tyck::TyckInfo;
+use crate::builtins::closure::Closure;
+use crate::data::generic::GenericTypeVT;
+use crate::data::tyck::{ContainerTyckInfo, FunctionTyckInfo, TyckInfo};
 use crate::data::Value;
-use crate::data::value_typed::ValueTypeTag;
+use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::ffi::VariadicCheck;
+
+/// How deep `check_type` looks into container values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TyckDepth {
+    #[default]
+    /// Only compare against the type parameters recorded in the container's `GenericTypeVT`.
+    /// This costs O(1) regardless of the container size.
+    Shallow,
+    /// When the recorded type parameters do not match, scan the elements of single-parameter
+    /// containers and check them one by one. This allows e.g. a `vector<any>` which happens to
+    /// contain only `int`s to pass a `vector<int>` check, at O(n) cost.
+    Full
+}
 
 #[inline(never)]
 pub unsafe fn check_type(value: Value, tyck_info: NonNull<TyckInfo>) -> bool {
+    check_type_with_depth(value, tyck_info, TyckDepth::Shallow)
+}
+
+/// Check whether `value` conforms to `tyck_info`, looking into containers as deep as `depth`
+///
+/// # Safety
+/// `value` must be a valid `Value`, and containers reachable from it must have valid
+/// `GenericTypeVT`s.
+#[inline(never)]
+pub unsafe fn check_type_with_depth(
+    value: Value,
+    tyck_info: NonNull<TyckInfo>,
+    depth: TyckDepth
+) -> bool {
+    if value.is_null() {
+        return matches!(tyck_info.as_ref(), TyckInfo::AnyType | TyckInfo::Nullable(_));
+    }
+
     match tyck_info.as_ref() {
         TyckInfo::AnyType => true,
         TyckInfo::Plain(plain) => if value.is_value() {
-            match ValueTypeTag::unsafe_from(value.vt_data.tag as u8) {
+            match ValueTypeTag::unsafe_from((value.vt_data.tag as u8) & VALUE_TYPE_TAG_MASK) {
                 ValueTypeTag::Int => *plain == TypeId::of::<i64>(),
                 ValueTypeTag::Float => *plain == TypeId::of::<f64>(),
                 ValueTypeTag::Char => *plain == TypeId::of::<char>(),
@@ -25,29 +58,328 @@ pub unsafe fn check_type(value: Value, tyck_info: NonNull<TyckInfo>) -> bool {
         } else {
             false
         },
-        TyckInfo::Nullable(inner) => {
-            if value.is_null() {
-                true
-            } else {
-                check_type(value, *inner)
-            }
-        }
-        TyckInfo::Container(_) => {
+        TyckInfo::Nullable(inner) => check_type_with_depth(value, *inner, depth),
+        TyckInfo::Container(expected) => {
             if value.is_container() {
-                todo!()
+                check_container_type(value, expected, depth)
             } else if value.is_ref() {
                 value.get_as_dyn_base().as_ref().unchecked_unwrap().dyn_tyck(tyck_info.as_ref())
             } else {
                 false
             }
         }
-        TyckInfo::Function(_) => {
+        TyckInfo::Function(expected) => {
             if value.is_container() {
-                let _vt: *const GenericTypeVT = value.ptr_repr.trivia as _;
-                todo!()
+                check_function_type(value, expected)
             } else {
                 false
             }
         }
     }
 }
+
+/// Check the variadic tail of `args` against the element type of `variadic_check`, returning the
+/// first argument failing the check
+///
+/// # Safety
+/// Same as `check_type_with_depth`, for every argument in the variadic tail.
+pub unsafe fn check_variadic_args(
+    args: &[Value],
+    variadic_check: &VariadicCheck,
+    depth: TyckDepth
+) -> Option<Value> {
+    let elem_type: NonNull<TyckInfo> = variadic_check.elem_type();
+    args.iter()
+        .skip(variadic_check.fixed_params())
+        .find(|arg: &&Value| !check_type_with_depth(**arg, elem_type, depth))
+        .copied()
+}
+
+/// Whether `actual`, a type recorded in a container or closure vtable, can be used where
+/// `expected` is expected. `TyckInfo`s are interned by `TyckInfoPool`, so types from the same
+/// pool are usually equal by address. Otherwise they are compared structurally, and `any` in
+/// `expected` accepts everything at any depth.
+unsafe fn type_matches(actual: NonNull<TyckInfo>, expected: NonNull<TyckInfo>) -> bool {
+    if actual == expected {
+        return true;
+    }
+    match (actual.as_ref(), expected.as_ref()) {
+        (_, TyckInfo::AnyType) => true,
+        (TyckInfo::Plain(actual), TyckInfo::Plain(expected)) => actual == expected,
+        (TyckInfo::Nullable(actual), TyckInfo::Nullable(expected)) =>
+            type_matches(*actual, *expected),
+        (TyckInfo::Container(actual), TyckInfo::Container(expected)) =>
+            actual.type_id == expected.type_id
+                && list_matches(actual.params.as_ref(), expected.params.as_ref()),
+        (TyckInfo::Function(actual), TyckInfo::Function(expected)) =>
+            function_matches(actual, expected),
+        _ => false
+    }
+}
+
+unsafe fn list_matches(actual: &[NonNull<TyckInfo>], expected: &[NonNull<TyckInfo>]) -> bool {
+    actual.len() == expected.len()
+        && actual.iter()
+            .zip(expected.iter())
+            .all(|(actual, expected)| type_matches(*actual, *expected))
+}
+
+/// Whether a function of type `actual` can be used where `expected` is expected. Parameter types
+/// are contravariant: a function accepting `any` can be used where a function accepting `int` is
+/// expected, but not the other way around. Return types are covariant, and every exception
+/// `actual` throws must be declared by `expected`.
+unsafe fn function_matches(actual: &FunctionTyckInfo, expected: &FunctionTyckInfo) -> bool {
+    let tails_match: bool = match (actual.variadic, expected.variadic) {
+        (Some(actual), Some(expected)) => type_matches(expected, actual),
+        (None, None) => true,
+        _ => false
+    };
+    let ret_tails_match: bool = match (actual.variadic_rets, expected.variadic_rets) {
+        (Some(actual), Some(expected)) => type_matches(actual, expected),
+        (None, None) => true,
+        _ => false
+    };
+    let expected_exceptions: &[NonNull<TyckInfo>] = expected.exceptions.as_ref();
+
+    actual.optional_params == expected.optional_params
+        && tails_match
+        && list_matches(expected.params.as_ref(), actual.params.as_ref())
+        && ret_tails_match
+        && list_matches(actual.rets.as_ref(), expected.rets.as_ref())
+        && actual.exceptions.as_ref().iter().all(|actual: &NonNull<TyckInfo>| {
+            expected_exceptions.iter().any(|expected| type_matches(*actual, *expected))
+        })
+}
+
+unsafe fn check_container_type(
+    value: Value,
+    expected: &ContainerTyckInfo,
+    depth: TyckDepth
+) -> bool {
+    let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+    let actual: &ContainerTyckInfo = (*vt).tyck_info.as_ref();
+    if actual.type_id != expected.type_id {
+        return false;
+    }
+
+    let actual_params: &[NonNull<TyckInfo>] = actual.params.as_ref();
+    let expected_params: &[NonNull<TyckInfo>] = expected.params.as_ref();
+    if actual_params.len() != expected_params.len() {
+        return false;
+    }
+
+    if list_matches(actual_params, expected_params) {
+        return true;
+    }
+
+    // for containers with more than one parameter, there's no way to tell which parameter an
+    // element corresponds to, so only single-parameter containers get scanned. Captured values
+    // of closures are not elements, and containers lent to native code as mutable may not be
+    // read, so neither get scanned either.
+    if depth != TyckDepth::Full
+        || expected_params.len() != 1
+        || actual.type_id == TypeId::of::<Closure>()
+        || !value.ownership_info().is_readable()
+    {
+        return false;
+    }
+
+    let elem_tyck_info: NonNull<TyckInfo> = *expected_params.get_unchecked(0);
+    let data: *const () = value.get_as_mut_ptr() as *const ();
+    if let Some(children /*: Box<dyn Iterator>*/) = ((*vt).children_fn)(data) {
+        for child /*: Value*/ in children {
+            if !check_type_with_depth(child, elem_tyck_info, depth) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+unsafe fn check_function_type(value: Value, expected: &FunctionTyckInfo) -> bool {
+    let vt: *const GenericTypeVT = value.ptr_repr.trivia as *const _;
+    let actual: &ContainerTyckInfo = (*vt).tyck_info.as_ref();
+    if actual.type_id != TypeId::of::<Closure>() {
+        return false;
+    }
+
+    // closures created by `create_closure_vt` record their function type as the only container
+    // type parameter. Closures always take a fixed count of arguments, so they cannot be used
+    // where optional or variadic parameters are expected.
+    if expected.optional_params != 0 || expected.variadic.is_some() {
+        return false;
+    }
+    match actual.params.as_ref().first().map(|func_type: &NonNull<TyckInfo>| func_type.as_ref()) {
+        Some(TyckInfo::Function(actual)) => function_matches(actual, expected),
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use std::ptr::NonNull;
+
+    use smallvec::SmallVec;
+    use xjbutil::mem::move_to_heap;
+
+    use crate::builtins::closure::{Closure, create_closure_vt};
+    use crate::builtins::test_container::{TestContainer, create_test_container_vt};
+    use crate::data::Value;
+    use crate::data::generic::GenericTypeVT;
+    use crate::data::tyck::{TyckInfo, TyckInfoPool};
+    use crate::data::wrapper::{OwnershipInfo, Wrapper};
+    use crate::vm::al31f::executor::rtti::{TyckDepth, check_type, check_type_with_depth};
+
+    #[test] fn test_check_container_type() {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+        let string_type: NonNull<TyckInfo> = tyck_info_pool.get_string_type();
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+        let container_type_id: TypeId = TypeId::of::<TestContainer<()>>();
+        let int_container: NonNull<TyckInfo> =
+            tyck_info_pool.create_container_type(container_type_id, &[int_type]);
+        let string_container: NonNull<TyckInfo> =
+            tyck_info_pool.create_container_type(container_type_id, &[string_type]);
+        let any_container: NonNull<TyckInfo> =
+            tyck_info_pool.create_container_type(container_type_id, &[any_type]);
+
+        let string_vt: GenericTypeVT = create_test_container_vt::<String>(&mut tyck_info_pool);
+        let mut any_vt: GenericTypeVT = create_test_container_vt::<String>(&mut tyck_info_pool);
+        any_vt.tyck_info = unsafe { any_container.as_ref().get_container_tyck_info_unchecked() };
+
+        let mut container: TestContainer<String> = TestContainer::new();
+        container.inner.elements.push(Value::new_owned::<String>("114".into()));
+        container.inner.elements.push(Value::new_owned::<String>("514".into()));
+        let string_value: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(container)).as_ptr() as *mut Wrapper<()>,
+            &string_vt
+        );
+
+        let mut container: TestContainer<String> = TestContainer::new();
+        container.inner.elements.push(Value::new_owned::<String>("1919810".into()));
+        let any_value: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(container)).as_ptr() as *mut Wrapper<()>,
+            &any_vt
+        );
+
+        unsafe {
+            assert!(check_type(string_value, string_container));
+            assert!(check_type(string_value, any_container));
+            assert!(!check_type(string_value, int_container));
+
+            assert!(check_type(any_value, any_container));
+            assert!(!check_type(any_value, string_container));
+            assert!(check_type_with_depth(any_value, string_container, TyckDepth::Full));
+            assert!(!check_type_with_depth(any_value, int_container, TyckDepth::Full));
+
+            // containers lent to native code as mutable cannot be scanned
+            any_value.set_ownership_info(OwnershipInfo::MutSharedToRust);
+            assert!(!check_type_with_depth(any_value, string_container, TyckDepth::Full));
+            any_value.set_ownership_info(OwnershipInfo::VMOwned);
+        }
+    }
+
+    #[test] fn test_check_nested_container_type() {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let string_type: NonNull<TyckInfo> = tyck_info_pool.get_string_type();
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+        let container_type_id: TypeId = TypeId::of::<TestContainer<()>>();
+        let string_container: NonNull<TyckInfo> =
+            tyck_info_pool.create_container_type(container_type_id, &[string_type]);
+        let any_container: NonNull<TyckInfo> =
+            tyck_info_pool.create_container_type(container_type_id, &[any_type]);
+        let nested_string: NonNull<TyckInfo> =
+            tyck_info_pool.create_container_type(container_type_id, &[string_container]);
+        let nested_any: NonNull<TyckInfo> =
+            tyck_info_pool.create_container_type(container_type_id, &[any_container]);
+
+        // the vtable comes from another pool, so its types are not interned with the above
+        let mut other_pool: TyckInfoPool = TyckInfoPool::new();
+        let other_string_container: NonNull<TyckInfo> =
+            other_pool.import_type(unsafe { string_container.as_ref() });
+        let mut nested_vt: GenericTypeVT = create_test_container_vt::<String>(&mut other_pool);
+        nested_vt.tyck_info = unsafe {
+            other_pool.create_container_type(container_type_id, &[other_string_container])
+                .as_ref()
+                .get_container_tyck_info_unchecked()
+        };
+        let nested_value: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(TestContainer::<String>::new())).as_ptr()
+                as *mut Wrapper<()>,
+            &nested_vt
+        );
+
+        unsafe {
+            assert!(check_type(nested_value, nested_string));
+            assert!(check_type(nested_value, nested_any));
+            assert!(!check_type(nested_value, string_container));
+        }
+    }
+
+    #[test] fn test_check_function_type() {
+        let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+        let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+        let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+        let int_to_int: NonNull<TyckInfo> = tyck_info_pool.create_function_type(
+            &[int_type], &[int_type], &[]
+        );
+        let any_to_int: NonNull<TyckInfo> = tyck_info_pool.create_function_type(
+            &[any_type], &[int_type], &[]
+        );
+
+        let string_type: NonNull<TyckInfo> = tyck_info_pool.get_string_type();
+        let any_to_any: NonNull<TyckInfo> = tyck_info_pool.create_function_type(
+            &[any_type], &[any_type], &[]
+        );
+        let any_to_int_throws: NonNull<TyckInfo> = tyck_info_pool.create_function_type(
+            &[any_type], &[int_type], &[string_type]
+        );
+
+        let int_closure_vt: GenericTypeVT = create_closure_vt(&mut tyck_info_pool, int_to_int);
+        let any_closure_vt: GenericTypeVT = create_closure_vt(&mut tyck_info_pool, any_to_int);
+        let any_any_closure_vt: GenericTypeVT =
+            create_closure_vt(&mut tyck_info_pool, any_to_any);
+        let throwing_closure_vt: GenericTypeVT =
+            create_closure_vt(&mut tyck_info_pool, any_to_int_throws);
+        let int_closure: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(Closure::new(SmallVec::new(), 0))).as_ptr()
+                as *mut Wrapper<()>,
+            &int_closure_vt
+        );
+        let any_closure: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(Closure::new(SmallVec::new(), 0))).as_ptr()
+                as *mut Wrapper<()>,
+            &any_closure_vt
+        );
+
+        let any_any_closure: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(Closure::new(SmallVec::new(), 0))).as_ptr()
+                as *mut Wrapper<()>,
+            &any_any_closure_vt
+        );
+        let throwing_closure: Value = Value::new_container(
+            move_to_heap(Wrapper::new_owned(Closure::new(SmallVec::new(), 0))).as_ptr()
+                as *mut Wrapper<()>,
+            &throwing_closure_vt
+        );
+
+        unsafe {
+            assert!(check_type(int_closure, int_to_int));
+            assert!(!check_type(int_closure, any_to_int));
+            assert!(check_type(any_closure, int_to_int));
+            assert!(check_type(any_closure, any_to_int));
+            assert!(!check_type(Value::new_int(0), int_to_int));
+
+            // return types are covariant
+            assert!(check_type(any_closure, any_to_any));
+            assert!(!check_type(any_any_closure, any_to_int));
+            // undeclared exceptions are rejected
+            assert!(!check_type(throwing_closure, any_to_int));
+            assert!(check_type(throwing_closure, any_to_int_throws));
+            assert!(!check_type(any_closure, tyck_info_pool.create_function_type(
+                &[any_type], &[], &[]
+            )));
+        }
+    }
+}
@@ -5,12 +5,16 @@ use xjbutil::unchecked::UncheckedSendSync;
 
 use crate::data::Value;
 use crate::data::exception::Exception;
+use crate::ffi::convert::{CallError, FromValues, IntoValues};
 use crate::vm::al31f::AL31F;
 use crate::vm::al31f::alloc::Alloc;
 use crate::vm::al31f::compiled::CompiledProgram;
 use crate::vm::al31f::stack::Stack;
 use crate::vm::al31f::executor::{vm_thread_run_function, VMThread};
 
+#[cfg(feature = "async")]
+use crate::vm::al31f::executor::create_vm_main_thread;
+
 #[cfg(feature = "async")]
 use crate::util::serializer::CoroutineContext;
 
@@ -47,3 +51,36 @@ pub unsafe fn vm_run_function_sync<A: Alloc>(
             .await
     });
 }
+
+/// Typed version of `vm_run_function_sync`, see `VMThread::call`
+///
+/// Return values are converted before the VM gets dropped, so `Rets` may contain objects.
+///
+/// # Safety
+/// `func_id` must be a valid function of `program`.
+pub unsafe fn vm_call_function_sync<Args, Rets, A>(
+    alloc: A,
+    program: &CompiledProgram<A>,
+    func_id: usize,
+    args: Args
+) -> Result<Rets, CallError>
+    where Args: IntoValues,
+          Rets: FromValues,
+          A: Alloc
+{
+    #[cfg(feature = "async")]
+    return pollster::block_on(async {
+        let mut thread: Box<VMThread<A>> = create_vm_main_thread(alloc, program).await;
+        thread.call_impl::<Args, Rets, true>(func_id, args).await
+    });
+
+    #[cfg(not(feature = "async"))]
+    return pollster::block_on(async {
+        let mut thread: VMThread<A> = VMThread {
+            vm: AL31F::new(alloc),
+            program: NonNull::new_unchecked(program as *const _ as *mut _),
+            stack: Stack::new()
+        };
+        thread.call_impl::<Args, Rets, true>(func_id, args).await
+    });
+}
@@ -1,30 +1,41 @@
-use std::any::TypeId;
-
 use unchecked_unwrap::UncheckedUnwrap;
 
-use crate::data::exception::{CheckedException, Exception, UncheckedException};
+use crate::builtins::exception::builtin_exception_type_id;
+use crate::data::exception::{CheckedException, Exception, ExceptionInner, UncheckedException};
 use crate::data::Value;
 use crate::vm::al31f::AL31F;
 use crate::vm::al31f::alloc::Alloc;
-use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram};
+use crate::vm::al31f::compiled::{
+    CompiledFunction,
+    CompiledProgram,
+    ExceptionFilter,
+    ExceptionHandlingBlock
+};
 use crate::vm::al31f::stack::{FrameInfo, Stack, StackSlice};
 
-pub unsafe fn unchecked_exception_unwind_stack(
+/// Count of innermost frames kept in the stack trace of `UncheckedException::StackOverflow`
+pub const STACK_OVERFLOW_TRACE_DEPTH: usize = 64;
+
+/// Unwind the stack for `unchecked_exception`, giving script handlers a chance to catch it
+///
+/// If the kind of `unchecked_exception` is not fatal for `program`, it may be caught by handlers
+/// for its builtin exception type (see `crate::builtins::exception`), in which case it gets
+/// converted into that type. `finally` handlers run for all unchecked exceptions. If nothing
+/// catches the exception, the stack is unwound completely and the exception is returned to the
+/// host.
+///
+/// # Safety
+/// `stack` must be the stack of a VM thread running `program` on `vm`, and `insc_ptr` must be the
+/// instruction pointer right after the instruction raising `unchecked_exception`.
+pub unsafe fn unchecked_exception_unwind_stack_catch<A: Alloc>(
+    vm: &mut AL31F<A>,
+    program: &CompiledProgram<A>,
     unchecked_exception: UncheckedException,
     stack: &mut Stack,
     insc_ptr: usize
-) -> Exception {
-    let mut exception: Exception = Exception::unchecked_exc(unchecked_exception);
-
-    let mut insc_ptr: usize = insc_ptr;
-    while stack.frames.len() != 0 {
-        let last_frame: &FrameInfo = stack.frames.last().unchecked_unwrap();
-        exception.push_stack_trace(last_frame.func_id, insc_ptr);
-        insc_ptr = last_frame.ret_addr - 1;
-
-        stack.unwind_shrink_slice();
-    }
-    exception
+) -> Result<(StackSlice, usize), Exception> {
+    let exception: Exception = Exception::unchecked_exc(unchecked_exception);
+    exception_unwind_stack(vm, program, exception, stack, insc_ptr, false)
 }
 
 pub unsafe fn checked_exception_unwind_stack<A: Alloc>(
@@ -34,35 +45,120 @@ pub unsafe fn checked_exception_unwind_stack<A: Alloc>(
     stack: &mut Stack,
     insc_ptr: usize
 ) -> Result<(StackSlice, usize), Exception> {
-    let exception_type_id: TypeId = (*checked_exception.get_as_dyn_base()).dyn_type_id();
+    let exception: Exception = Exception::checked_exc(checked_exception);
+    exception_unwind_stack(vm, program, exception, stack, insc_ptr, false)
+}
+
+/// How an `ExceptionHandlingBlock` deals with an exception
+enum Handling {
+    Skip,
+    Handle,
+    /// Handle the exception after converting it into its builtin exception type
+    ConvertAndHandle
+}
 
-    let mut exception: Exception = Exception::checked_exc(checked_exception);
+unsafe fn match_handler<A: Alloc>(
+    program: &CompiledProgram<A>,
+    exc_handler: &ExceptionHandlingBlock,
+    exception: &ExceptionInner
+) -> Handling {
+    match exception {
+        ExceptionInner::Checked(checked) => {
+            if exc_handler.filter.matches((*checked.get_as_dyn_base()).dyn_type_id()) {
+                Handling::Handle
+            } else {
+                Handling::Skip
+            }
+        },
+        ExceptionInner::Unchecked(unchecked) => {
+            if let ExceptionFilter::Finally = exc_handler.filter {
+                return Handling::Handle;
+            }
+            if program.fatal_exceptions.contains(unchecked.kind()) {
+                return Handling::Skip;
+            }
+            if exc_handler.filter.matches(builtin_exception_type_id(unchecked)) {
+                Handling::ConvertAndHandle
+            } else {
+                Handling::Skip
+            }
+        }
+    }
+}
+
+/// Unwind the stack for `exception`, running the first matching handler. The caught exception
+/// is stored into the last slot of the handler's frame as is, except for unchecked exceptions
+/// caught by handlers other than `finally` handlers, which get converted into their builtin
+/// exception types.
+///
+/// If `rethrow` is set, `exception` was caught in the current frame before and already carries
+/// stack trace of it.
+///
+/// # Safety
+/// `stack` must be the stack of a VM thread running `program` on `vm`, and `insc_ptr` must be the
+/// instruction pointer right after the instruction raising `exception`.
+pub unsafe fn exception_unwind_stack<A: Alloc>(
+    vm: &mut AL31F<A>,
+    program: &CompiledProgram<A>,
+    exception: Exception,
+    stack: &mut Stack,
+    insc_ptr: usize,
+    rethrow: bool
+) -> Result<(StackSlice, usize), Exception> {
+    let trace_depth: usize =
+        if let ExceptionInner::Unchecked(UncheckedException::StackOverflow { .. }) =
+            exception.inner
+        {
+            STACK_OVERFLOW_TRACE_DEPTH
+        } else {
+            usize::MAX
+        };
+
+    let mut exception: Exception = exception;
     let mut insc_ptr: usize = insc_ptr;
+    let mut traced: usize = 0;
+    let mut skip_trace: bool = rethrow;
 
-    while stack.frames.len() != 0 {
+    while stack.frames.len() > stack.entry_frame {
         let frame: &FrameInfo = stack.frames.last().unchecked_unwrap();
         let func_id: usize = frame.func_id;
-        exception.push_stack_trace(func_id, insc_ptr);
+        if skip_trace {
+            skip_trace = false;
+        } else {
+            if traced < trace_depth {
+                exception.push_stack_trace(func_id, insc_ptr);
+                traced += 1;
+            } else {
+                exception.elide_stack_trace(1);
+            }
+            exception.elide_stack_trace(frame.elided_frames);
+        }
 
         let compiled_function: &CompiledFunction = &program.functions[func_id];
 
         if let Some(exc_handlers /*: &Box<[ExceptionHandlingBlock]>*/)
-        = &compiled_function.exc_handlers
+            = &compiled_function.exc_handlers
         {
             for exc_handler /*: &ExceptionHandlingBlock*/ in exc_handlers.as_ref().iter() {
-                let (start_insc, end_insc): (usize, usize) = exc_handler.insc_ptr_range;
-                if insc_ptr >= start_insc &&
-                    insc_ptr <= end_insc &&
-                    exception_type_id == exc_handler.exception_id
-                {
-                    let frame_size: usize = frame.frame_end - frame.frame_start;
-                    let exception_value: Value = Value::new_owned(exception);
-                    vm.alloc.add_managed(exception_value);
-                    let mut stack_slice: StackSlice = stack.last_frame_slice();
-                    stack_slice.set_value(frame_size - 1, exception_value);
-
-                    return Ok((stack_slice, exc_handler.handler_addr));
+                if !exc_handler.covers(insc_ptr) {
+                    continue;
+                }
+                match match_handler(program, exc_handler, &exception.inner) {
+                    Handling::Skip => continue,
+                    Handling::Handle => {},
+                    Handling::ConvertAndHandle => {
+                        let builtin: Value = exception.convert_to_builtin().unchecked_unwrap();
+                        vm.alloc.add_managed(builtin);
+                    }
                 }
+
+                let frame_size: usize = frame.frame_end - frame.frame_start;
+                let exception_value: Value = Value::new_owned(exception);
+                vm.alloc.add_managed(exception_value);
+                let mut stack_slice: StackSlice = stack.last_frame_slice();
+                stack_slice.set_value(frame_size - 1, exception_value);
+
+                return Ok((stack_slice, exc_handler.handler_addr));
             }
         }
 
@@ -0,0 +1,260 @@
+//! Host-held handles keeping VM-managed values alive
+
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use xjbutil::void::Void;
+
+use crate::data::Value;
+use crate::data::traits::StaticBase;
+use crate::ffi::FFIException;
+use crate::ffi::sync_fn::{OwnershipGuard, value_into_mut_ref, value_into_ref};
+use crate::vm::al31f::alloc::Alloc;
+
+#[cfg(feature = "async")] use crate::ffi::async_fn::{AsyncVMContext, VMDataTrait};
+
+/// A `Value` registered as a GC root for as long as this handle lives
+///
+/// `RootedValue` wraps the `Alloc::root_object` protocol: the value is rooted on creation, and
+/// the shared root flag is cleared on drop. Dropping the handle after the allocator is fine, but
+/// the value itself is only accessible while the allocator is alive, which the handle cannot
+/// check. Hence all methods dereferencing the value are `unsafe`.
+///
+/// Handles may be sent to other threads, since they only touch the root flag on their own.
+/// Accessing the value from another thread requires holding the VM exclusively, for example
+/// through its serializer.
+pub struct RootedValue {
+    value: Value,
+    flag: Arc<AtomicBool>
+}
+
+impl RootedValue {
+    /// Root `value` in `alloc`
+    ///
+    /// # Safety
+    /// `value` must be managed by `alloc`.
+    pub unsafe fn new<A: Alloc>(alloc: &mut A, value: Value) -> Self {
+        let flag: Arc<AtomicBool> = alloc.root_object(value);
+        Self { value, flag }
+    }
+
+    #[inline(always)] pub fn value(&self) -> Value {
+        self.value
+    }
+
+    /// Convert into a typed handle, if the rooted value is an object of type `T`
+    ///
+    /// # Safety
+    /// The allocator which rooted the value must be alive, and must not be in use by another
+    /// thread.
+    pub unsafe fn downcast<T>(self) -> Result<Handle<T>, Self>
+        where T: 'static,
+              Void: StaticBase<T>
+    {
+        let value: Value = self.value;
+        if value.is_null() || value.is_value() || value.is_container() {
+            return Err(self);
+        }
+
+        let type_id: TypeId = (*value.get_as_dyn_base()).dyn_type_id();
+        if type_id == <Void as StaticBase<T>>::type_id() {
+            Ok(Handle { rooted: self, _phantom: PhantomData })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Drop for RootedValue {
+    fn drop(&mut self) {
+        // the collector may be reading the flag concurrently when used from async contexts
+        self.flag.store(false, Ordering::Release);
+    }
+}
+
+unsafe impl Send for RootedValue {}
+unsafe impl Sync for RootedValue {}
+
+/// Typed version of `RootedValue`
+pub struct Handle<T: 'static> {
+    rooted: RootedValue,
+    _phantom: PhantomData<fn() -> T>
+}
+
+impl<T: 'static> Handle<T> where Void: StaticBase<T> {
+    #[inline(always)] pub fn value(&self) -> Value {
+        self.rooted.value()
+    }
+
+    #[inline(always)] pub fn into_rooted(self) -> RootedValue {
+        self.rooted
+    }
+
+    /// Borrow the referenced object, going through the same ownership checks as FFI calls.
+    /// Ownership info gets restored once the returned reference is dropped.
+    ///
+    /// # Safety
+    /// The allocator which rooted the value must be alive, and the VM must not run on another
+    /// thread until the returned reference is dropped.
+    pub unsafe fn get(&self) -> Result<HandleRef<'_, T>, FFIException> {
+        let (data, guard): (&T, Option<OwnershipGuard>) = value_into_ref(self.rooted.value)?;
+        Ok(HandleRef { data, _guard: guard })
+    }
+
+    /// Mutably borrow the referenced object, going through the same ownership checks as FFI
+    /// calls. Ownership info gets restored once the returned reference is dropped.
+    ///
+    /// # Safety
+    /// Same as `get`.
+    pub unsafe fn get_mut(&mut self) -> Result<HandleMut<'_, T>, FFIException> {
+        let (data, guard): (&mut T, OwnershipGuard) = value_into_mut_ref(self.rooted.value)?;
+        Ok(HandleMut { data, _guard: guard })
+    }
+}
+
+/// Shared reference to the object of a `Handle`, holding the ownership guard of the borrow
+pub struct HandleRef<'a, T: 'static> {
+    data: &'a T,
+    _guard: Option<OwnershipGuard>
+}
+
+impl<'a, T: 'static> Deref for HandleRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.data
+    }
+}
+
+/// Mutable reference to the object of a `Handle`, holding the ownership guard of the borrow
+pub struct HandleMut<'a, T: 'static> {
+    data: &'a mut T,
+    _guard: OwnershipGuard
+}
+
+impl<'a, T: 'static> Deref for HandleMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.data
+    }
+}
+
+impl<'a, T: 'static> DerefMut for HandleMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.data
+    }
+}
+
+/// Root `value` from an async context, acquiring the serializer for the operation
+#[cfg(feature = "async")]
+pub async fn root_async<A, VD, ACTX>(context: &ACTX, value: Value) -> RootedValue
+    where A: Alloc,
+          VD: VMDataTrait<Alloc=A>,
+          ACTX: AsyncVMContext<VMData=VD>
+{
+    let mut lock = context.serializer().lock().await;
+    unsafe { RootedValue::new(lock.1.get_alloc(), value) }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::data::Value;
+    use crate::vm::al31f::alloc::Alloc;
+    use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
+    use crate::vm::al31f::handle::{Handle, HandleMut, HandleRef, RootedValue};
+
+    #[test] fn test_rooted_value() {
+        let mut alloc: DefaultAlloc = DefaultAlloc::new();
+
+        let str1: Value = Value::new_owned::<String>("114".into());
+        let str2: Value = Value::new_owned::<String>("514".into());
+        unsafe {
+            alloc.add_managed(str1);
+            alloc.add_managed(str2);
+
+            let rooted: RootedValue = RootedValue::new(&mut alloc, str1);
+            let rooted2: RootedValue = RootedValue::new(&mut alloc, str2);
+            alloc.collect();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+            assert!(alloc.contains_ptr(str2.ptr_repr));
+
+            let rooted: RootedValue = match rooted.downcast::<crate::builtins::object::Object>() {
+                Ok(_) => panic!(),
+                Err(rooted) => rooted
+            };
+            let mut handle: Handle<String> = rooted.downcast::<String>().ok().unwrap();
+            {
+                let s: HandleRef<String> = handle.get().unwrap();
+                assert_eq!(*s, "114");
+                assert!(handle.get_mut().is_err());
+            }
+            {
+                let mut s: HandleMut<String> = handle.get_mut().unwrap();
+                s.push_str("514");
+                assert!(handle.get().is_err());
+            }
+            assert_eq!(*handle.get().unwrap(), "114514");
+
+            drop(rooted2);
+            alloc.collect();
+            assert!(alloc.contains_ptr(str1.ptr_repr));
+            assert!(!alloc.contains_ptr(str2.ptr_repr));
+
+            drop(handle);
+            alloc.collect();
+            assert!(!alloc.contains_ptr(str1.ptr_repr));
+        }
+
+        // handles only share the root flag with the allocator, so they may outlive it
+        let mut alloc2: DefaultAlloc = DefaultAlloc::new();
+        let str3: Value = Value::new_owned::<String>("1919810".into());
+        let rooted3: RootedValue = unsafe {
+            alloc2.add_managed(str3);
+            RootedValue::new(&mut alloc2, str3)
+        };
+        assert_eq!(alloc2.stats().pinned_count, 1);
+        drop(alloc2);
+        drop(rooted3);
+    }
+
+    #[cfg(feature = "async")]
+    #[test] fn test_rooted_value_async() {
+        use std::ptr::NonNull;
+        use std::sync::Arc;
+        use xjbutil::async_utils::{Mutex, block_on_future};
+        use xjbutil::wide_ptr::WidePointer;
+        use crate::vm::al31f::{AL31F, AsyncCombustor};
+        use crate::ffi::async_fn::AsyncVMContext;
+        use crate::vm::al31f::compiled::CompiledProgram;
+        use crate::vm::al31f::handle::root_async;
+        use crate::vm::al31f::stack::StackConfig;
+        use crate::vm::al31f::test_program::basic_program;
+        use crate::util::serializer::CoroutineSharedData;
+
+        block_on_future(async {
+            let program: CompiledProgram<DefaultAlloc> = basic_program();
+            let str1: Value = Value::new_owned::<String>("114".into());
+            let str1_ptr: WidePointer = unsafe { str1.ptr_repr };
+
+            let mut vm: AL31F<DefaultAlloc> = AL31F::new(DefaultAlloc::new());
+            unsafe { vm.alloc.add_managed(str1); }
+            let combustor: AsyncCombustor<DefaultAlloc> = AsyncCombustor::new(
+                Arc::new(Mutex::new((CoroutineSharedData::new(), vm))),
+                NonNull::from(&program),
+                StackConfig::default()
+            );
+
+            let rooted: RootedValue = root_async(&combustor, str1).await;
+            unsafe { combustor.serializer().lock().await.1.alloc.collect(); }
+            assert!(combustor.serializer().lock().await.1.alloc.contains_ptr(str1_ptr));
+
+            drop(rooted);
+            unsafe { combustor.serializer().lock().await.1.alloc.collect(); }
+            assert!(!combustor.serializer().lock().await.1.alloc.contains_ptr(str1_ptr));
+        });
+    }
+}
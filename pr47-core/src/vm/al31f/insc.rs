@@ -412,7 +412,8 @@ pub enum Insc {
 
     /// `OWNERSHIP-INFO-CHECK [SRC] [MASK]`
     ///
-    /// Check if data stored in `SRC` satisfies given `MASK`, throws RTLC exception if not.
+    /// Check if the ownership info of the object stored in `SRC` has all bits of `MASK` set,
+    /// throws RTLC exception if not. Value-typed data and `null` never pass the check.
     OwnershipInfoCheck(usize, u8),
 
     /// `CALL-UNCHECKED [FUNC-ID] [ARGS..] [RETS..]`
@@ -427,9 +428,26 @@ pub enum Insc {
     /// values to `RETS`. **No type checking**.
     CallPtr(usize, &'static [usize], &'static [usize]),
 
-    /// `CALL-OVERLOAD [OVERLOAD-TBL] [ARGS..] [RETS..]`
+    /// `CALL-OVERLOAD [CALL-SITE] [ARGS..] [RETS..]`
+    ///
+    /// Call the candidate of the overloaded function of `CALL-SITE` (an index into
+    /// `CompiledProgram::overload_call_sites`) selected by the types of `ARGS`, store the return
+    /// values to `RETS`. Each `CALL-OVERLOAD` instruction has its own call site.
     CallOverload(usize, &'static [usize], &'static [usize]),
 
+    /// `TAIL-CALL [FUNC-ID] [ARGS..]`
+    ///
+    /// Call the function denoted by `FUNC-ID` with given `ARGS`, reusing the frame of current
+    /// function. The return values are passed to the caller of current function directly.
+    /// **No type checking**.
+    TailCall(usize, &'static [usize]),
+
+    /// `TAIL-CALL-PTR [SRC] [ARGS..]`
+    ///
+    /// Call the function pointer or closure stored in `SRC` with given `ARGS`, reusing the frame
+    /// of current function. **No type checking**.
+    TailCallPtr(usize, &'static [usize]),
+
     /// `RETURN-NOTHING`
     ReturnNothing,
 
@@ -442,6 +460,12 @@ pub enum Insc {
     /// `FFI-CALL-RTLC [FFI-FUNC-ID] [ARGS..] [RETS..]`
     FFICallRtlc(usize, &'static [usize], &'static [usize]),
 
+    /// `CALL-METHOD [METHOD-TBL] [ARGS..] [RETS..]`
+    ///
+    /// Call the implementation of the method denoted by `METHOD-TBL` for the type of `ARGS[0]`,
+    /// the receiver. Works like `FFI-CALL-RTLC` once the implementation gets selected.
+    CallMethod(usize, &'static [usize], &'static [usize]),
+
     /// `FFI-CALL [FFI-FUNC-ID] [ARGS..] [RETS..]`
     #[cfg(feature = "optimized-rtlc")]
     FFICall(usize, &'static [usize], &'static [usize]),
@@ -466,12 +490,49 @@ pub enum Insc {
     /// `RAISE [EXCEPTION]`
     Raise(usize),
 
+    /// `RETHROW [EXCEPTION]`
+    ///
+    /// Raise again the exception caught in `EXCEPTION`, keeping its original stack trace. Used at
+    /// the end of `finally` handlers and for `throw` without operand inside `catch` blocks.
+    /// Unchecked exceptions are moved out of `EXCEPTION`, so they can be raised again only once.
+    Rethrow(usize),
+
+    /// `EXCEPTION-VALUE [SRC] [DEST]`
+    ///
+    /// Save the raised value carried by the exception caught in `SRC` to `DEST`. For unchecked
+    /// exceptions caught by script, this is an object of the corresponding builtin exception type,
+    /// or `null` inside `finally` handlers.
+    ExceptionValue(usize, usize),
+
     JumpIfTrue(usize, usize),
     JumpIfFalse(usize, usize),
     Jump(usize),
 
     CreateContainer(GenericTypeCtor, NonNull<GenericTypeVT>, usize),
 
+    /// `MAKE-CLOSURE [FUNC-ID] [CAPTURES..] [CLOSURE-VT] [DEST]`
+    ///
+    /// Create a closure of the function denoted by `FUNC-ID`, capturing values stored in
+    /// `CAPTURES` in order, and save it to `DEST`. `CLOSURE-VT` should be created by
+    /// `create_closure_vt`.
+    MakeClosure(usize, &'static [usize], NonNull<GenericTypeVT>, usize),
+
+    /// `MAKE-CELL [SRC] [DEST]`
+    ///
+    /// Box the data stored in `SRC` into a new capture cell, and save the cell to `DEST`. Used
+    /// for variables captured by closures and assigned afterwards.
+    #[cfg(feature = "al31f-builtin-ops")] MakeCell(usize, usize),
+
+    /// `CELL-GET [CELL] [DEST]`
+    ///
+    /// Save the data stored in capture cell `CELL` to `DEST`.
+    #[cfg(feature = "al31f-builtin-ops")] CellGet(usize, usize),
+
+    /// `CELL-SET [CELL] [SRC]`
+    ///
+    /// Store the data in `SRC` into capture cell `CELL`.
+    #[cfg(feature = "al31f-builtin-ops")] CellSet(usize, usize),
+
     #[cfg(feature = "al31f-builtin-ops")] CreateString(usize),
     #[cfg(feature = "al31f-builtin-ops")] CreateObject(usize),
 
@@ -489,7 +550,25 @@ pub enum Insc {
     #[cfg(feature = "al31f-builtin-ops")] ObjectGetDyn(usize, usize, usize),
 
     #[cfg(feature = "al31f-builtin-ops")] ObjectPut(usize, NonNull<str>, usize),
-    #[cfg(feature = "al31f-builtin-ops")] ObjectPutDyn(usize, usize, usize)
+    #[cfg(feature = "al31f-builtin-ops")] ObjectPutDyn(usize, usize, usize),
+
+    /// `HOST-FIELD-GET [SRC] [FIELD] [DEST]`
+    ///
+    /// Read the field `FIELD` of the host object stored in `SRC`, as exposed by
+    /// `StaticBase::get_field`, and save it to `DEST`. Saves `null` if there's no such field.
+    #[cfg(feature = "al31f-builtin-ops")] HostFieldGet(usize, NonNull<str>, usize),
+
+    /// `CREATE-WEAK [SRC] [WEAK-VT] [DEST]`
+    ///
+    /// Create a weak reference to the object stored in `SRC`, and save it to `DEST`. `WEAK-VT`
+    /// should be created by `create_weak_vt`.
+    #[cfg(feature = "al31f-builtin-ops")] CreateWeak(usize, NonNull<GenericTypeVT>, usize),
+
+    /// `WEAK-GET [SRC] [DEST]`
+    ///
+    /// Save the target of the weak reference stored in `SRC` to `DEST`, or `null` if the target
+    /// has been collected.
+    #[cfg(feature = "al31f-builtin-ops")] WeakGet(usize, usize)
 }
 
 impl Insc {
@@ -528,6 +607,23 @@ impl Insc {
                 }
                 result
             },
+            Insc::TailCall(func_id, args) => {
+                let mut result: String = String::from("tail call F.");
+                result.push_str(&func_id.to_string());
+                for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
+                    result.push_str(if i == 0 { " %" } else { ", %" });
+                    result.push_str(&arg.to_string());
+                }
+                result
+            },
+            Insc::TailCallPtr(src, args) => {
+                let mut result: String = format!("tail call %{}", src);
+                for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
+                    result.push_str(if i == 0 { " %" } else { ", %" });
+                    result.push_str(&arg.to_string());
+                }
+                result
+            },
             Insc::ReturnNothing => "ret".into(),
             Insc::ReturnOne(ret_value_loc) => format!("ret %{}", ret_value_loc),
             Insc::Return(ret_value_locs) => {
@@ -542,7 +638,42 @@ impl Insc {
                 }
                 result
             },
+            Insc::MakeClosure(func_id, captures, _, dest) => {
+                let mut result: String = format!("%{} = closure F.{}", dest, func_id);
+                for (i, capture) /*: (usize, &usize)*/ in captures.iter().enumerate() {
+                    result.push_str(if i == 0 { " %" } else { ", %" });
+                    result.push_str(&capture.to_string());
+                }
+                result
+            },
+            Insc::MakeCell(src, dest) => format!("%{} = new cell %{}", dest, src),
+            Insc::CellGet(cell, dest) => format!("%{} = cell get %{}", dest, cell),
+            Insc::CellSet(cell, src) => format!("cell set %{}, %{}", cell, src),
             Insc::CreateObject(dest) => format!("%{} = new object", dest),
+            Insc::CallMethod(method_table, args, rets) => {
+                let mut result: String = String::from("[");
+                for (i, ret) /*: (usize, &usize)*/ in rets.iter().enumerate() {
+                    result.push('%');
+                    result.push_str(&ret.to_string());
+                    if i != rets.len() - 1 {
+                        result.push(',');
+                        result.push(' ');
+                    }
+                }
+                result.push_str("] = call method M.");
+                result.push_str(&method_table.to_string());
+                for (i, arg) /*: (usize, &usize)*/ in args.iter().enumerate() {
+                    result.push_str(if i == 0 { " %" } else { ", %" });
+                    result.push_str(&arg.to_string());
+                }
+                result
+            },
+            Insc::HostFieldGet(src, field, dest) =>
+                format!("%{} = host field get %{}, {}", dest, src, field.as_ref()),
+            Insc::CreateWeak(src, _, dest) => format!("%{} = new weak %{}", dest, src),
+            Insc::WeakGet(src, dest) => format!("%{} = weak get %{}", dest, src),
+            Insc::Rethrow(src) => format!("rethrow %{}", src),
+            Insc::ExceptionValue(src, dest) => format!("%{} = exception value %{}", dest, src),
             Insc::JumpIfTrue(condition, dest) => format!("if %{} goto L.{}", condition, dest),
             Insc::JumpIfFalse(condition, dest) => format!("if not %{} goto L.{}", condition, dest),
             Insc::Jump(dest) => format!("goto L.{}", dest),
@@ -1,7 +1,9 @@
 pub mod alloc;
 pub mod compiled;
 pub mod executor;
+pub mod handle;
 pub mod insc;
+pub mod registry;
 pub mod stack;
 pub mod analysis;
 
@@ -12,21 +14,42 @@ pub mod analysis;
 use std::ptr::NonNull;
 
 use crate::data::Value;
+use crate::data::exception::Exception;
 use crate::ffi::sync_fn::VMContext;
-use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::alloc::{Alloc, Finalizer};
 use crate::vm::al31f::compiled::CompiledProgram;
-
-#[cfg(feature = "async")] use crate::ffi::async_fn::AsyncVMContext;
+use crate::vm::al31f::executor::{VMThread, vm_thread_call_value};
+use crate::vm::al31f::executor::rtti::TyckDepth;
+use crate::vm::al31f::handle::RootedValue;
+
+#[cfg(feature = "async")] use xjbutil::unchecked::{UncheckedSendFut, UncheckedSendSync};
+#[cfg(feature = "async")] use crate::builtins::closure::value_as_callable;
+#[cfg(feature = "async")] use crate::data::exception::UncheckedException;
+#[cfg(feature = "async")] use crate::ffi::async_fn::{AsyncVMContext, CallValueFuture};
 #[cfg(feature = "async")] use crate::ffi::async_fn::VMDataTrait;
-#[cfg(feature = "async")] use crate::util::serializer::{CoroutineSharedData, Serializer};
+#[cfg(feature = "async")] use crate::util::serializer::{
+    CoroutineContext,
+    CoroutineSharedData,
+    Serializer
+};
+#[cfg(feature = "async")] use crate::vm::al31f::executor::{
+    create_vm_child_thread,
+    vm_thread_run_function
+};
+#[cfg(feature = "async")] use crate::vm::al31f::stack::StackConfig;
 
 pub struct AL31F<A: Alloc> {
-    pub alloc: A
+    pub alloc: A,
+    /// How deep `IsType` and `TypeCheck` look into container values
+    pub tyck_depth: TyckDepth,
+    /// Whether panics raised by FFI functions get caught and converted to
+    /// `UncheckedException::ForeignPanic`. Disabled by default.
+    pub catch_foreign_panics: bool
 }
 
 impl<A: Alloc> AL31F<A> {
     pub fn new(alloc: A) -> Self {
-        Self { alloc }
+        Self { alloc, tyck_depth: TyckDepth::default(), catch_foreign_panics: false }
     }
 }
 
@@ -40,38 +63,69 @@ impl<A: Alloc> VMDataTrait for AL31F<A> {
 }
 
 pub struct Combustor<A: Alloc> {
-    vm: NonNull<AL31F<A>>
+    thread: NonNull<VMThread<A>>
 }
 
 impl<A: Alloc> Combustor<A> {
-    pub fn new(vm: NonNull<AL31F<A>>) -> Self {
-        Self { vm }
+    pub fn new(thread: NonNull<VMThread<A>>) -> Self {
+        Self { thread }
+    }
+
+    #[cfg(feature = "async")]
+    unsafe fn vm(&mut self) -> &mut AL31F<A> {
+        self.thread.as_mut().vm.get_shared_data_mut()
+    }
+
+    #[cfg(not(feature = "async"))]
+    unsafe fn vm(&mut self) -> &mut AL31F<A> {
+        &mut self.thread.as_mut().vm
     }
 }
 
 impl<A: Alloc> VMContext for Combustor<A> {
     fn add_heap_managed(&mut self, value: Value) {
-        unsafe { self.vm.as_mut().alloc.add_managed(value); }
+        unsafe { self.vm().alloc.add_managed(value); }
     }
 
     fn mark(&mut self, value: Value) {
-        unsafe { self.vm.as_mut().alloc.mark_object(value); }
+        unsafe { self.vm().alloc.mark_object(value); }
+    }
+
+    fn root(&mut self, value: Value) -> RootedValue {
+        unsafe { RootedValue::new(&mut self.vm().alloc, value) }
+    }
+
+    fn add_finalizer(&mut self, value: Value, finalizer: Finalizer) {
+        if !value.is_null() && !value.is_value() {
+            unsafe { self.vm().alloc.add_finalizer(value, finalizer); }
+        }
+    }
+
+    fn call_value(
+        &mut self,
+        callee: Value,
+        args: &[Value]
+    ) -> Result<Vec<RootedValue>, Exception> {
+        unsafe { vm_thread_call_value(self.thread.as_mut(), callee, args) }
     }
 }
 
 #[cfg(feature = "async")]
 pub struct AsyncCombustor<A: Alloc> {
     vm: Serializer<(CoroutineSharedData, AL31F<A>)>,
-    pub program: NonNull<CompiledProgram<A>>
+    pub program: NonNull<CompiledProgram<A>>,
+    /// Stack configuration of VM threads running callbacks, see `AsyncVMContext::call_value`
+    pub stack_config: StackConfig
 }
 
 #[cfg(feature = "async")]
 impl<A: Alloc> AsyncCombustor<A> {
     pub fn new(
         vm: Serializer<(CoroutineSharedData, AL31F<A>)>,
-        program: NonNull<CompiledProgram<A>>
+        program: NonNull<CompiledProgram<A>>,
+        stack_config: StackConfig
     ) -> Self {
-        Self { vm, program }
+        Self { vm, program, stack_config }
     }
 }
 
@@ -88,4 +142,38 @@ impl<A: Alloc> AsyncVMContext for AsyncCombustor<A> {
     fn serializer(&self) -> &Serializer<(CoroutineSharedData, Self::VMData)> {
         &self.vm
     }
+
+    fn call_value(&self, callee: Value, args: Vec<Value>) -> CallValueFuture {
+        let serializer: Serializer<(CoroutineSharedData, AL31F<A>)> = self.vm.clone();
+        unsafe {
+            let arg_pack: UncheckedSendSync<_> =
+                UncheckedSendSync::new((callee, args, self.program, self.stack_config));
+
+            Box::pin(UncheckedSendFut::new(async move {
+                let (callee, args, program, stack_config):
+                    (Value, Vec<Value>, NonNull<CompiledProgram<A>>, StackConfig) =
+                    arg_pack.into_inner();
+                let (func_id, captures): (usize, &[Value]) = match value_as_callable(callee) {
+                    Some(callable) => callable,
+                    None => return Err(Exception::unchecked_exc(
+                        UncheckedException::InvalidCastOp { dest_type: "closure", src: callee }
+                    ))
+                };
+                let args: Vec<Value> = captures.iter().copied().chain(args).collect();
+
+                let context: CoroutineContext<AL31F<A>> =
+                    CoroutineContext::attached_context(serializer).await;
+                let mut thread: Box<VMThread<A>> = create_vm_child_thread(context, program);
+                thread.stack.config = stack_config;
+
+                let arg_pack = UncheckedSendSync::new((thread.as_mut(), func_id, args.as_ref()));
+                let values: Vec<Value> =
+                    vm_thread_run_function::<_, false>(arg_pack)?.await.into_inner()?;
+                let alloc: &mut A = &mut thread.vm.get_shared_data_mut().alloc;
+                Ok(values.into_iter()
+                    .map(|value: Value| RootedValue::new(alloc, value))
+                    .collect())
+            }))
+        }
+    }
 }
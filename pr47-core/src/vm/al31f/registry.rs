@@ -0,0 +1,622 @@
+//! # `registry`: name-based linking of native functions
+//!
+//! Hosts register native functions under qualified names like `std.io.print`. Compilers resolve
+//! calls to imported native names with `FfiRegistry::resolve_call`, and record what they import
+//! as `FfiImport`s. `FfiRegistry::link` later turns these imports into the positional
+//! `CompiledProgram::ffi_funcs` and `CompiledProgram::async_ffi_funcs` arrays indexed by
+//! `FFICall` instructions, checking that every imported function exists and has the expected
+//! signature.
+//!
+//! Host types may also get methods, registered per type with `FfiRegistry::register_method`.
+//! A method call is resolved statically to a plain `FFICall` if the type of the receiver is known
+//! at compile time, or dispatched at run time through a `CompiledProgram::method_tables` entry
+//! and the `CallMethod` instruction otherwise.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ptr::NonNull;
+
+use xjbutil::void::Void;
+
+use crate::data::traits::StaticBase;
+use crate::data::tyck::{FunctionTyckInfo, TyckInfo, TyckInfoPool};
+use crate::ffi::{FFIHandle, Signature, VariadicCheck};
+use crate::ffi::sync_fn::Function as FFIFunction;
+use crate::vm::al31f::Combustor;
+use crate::vm::al31f::alloc::Alloc;
+use crate::vm::al31f::compiled::{CompiledProgram, MethodImpl, MethodTable};
+
+#[cfg(feature = "async")] use crate::ffi::async_fn::AsyncFunction as FFIAsyncFunction;
+#[cfg(feature = "async")] use crate::vm::al31f::{AL31F, AsyncCombustor};
+
+pub type SyncFunction<A> = dyn FFIFunction<Combustor<A>>;
+#[cfg(feature = "async")]
+pub type AsyncFunction<A> = dyn FFIAsyncFunction<A, AL31F<A>, AsyncCombustor<A>>;
+
+/// Kind of a native function, deciding which instructions call it and which array of
+/// `CompiledProgram` it goes to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FfiKind {
+    Sync,
+    /// Method dispatched at run time by the type of the receiver
+    Method,
+    #[cfg(feature = "async")]
+    Async
+}
+
+impl Display for FfiKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FfiKind::Sync => write!(f, "native function"),
+            FfiKind::Method => write!(f, "method"),
+            #[cfg(feature = "async")]
+            FfiKind::Async => write!(f, "async native function")
+        }
+    }
+}
+
+/// A registered native function
+pub struct FfiSymbol<F: ?Sized + 'static> {
+    pub func: FFIHandle<F>,
+    /// Signature of the function, with types allocated in the `TyckInfoPool` of the registry.
+    /// `None` for functions without a standard signature (e.g. `print`), calls to which are not
+    /// type checked.
+    pub signature: Option<Signature>
+}
+
+/// A native function imported by compiled code
+pub struct FfiImport {
+    pub name: String,
+    pub kind: FfiKind,
+    /// Function type the importing code was compiled against. `None` if the code was compiled
+    /// against a function without standard signature.
+    pub func_type: Option<NonNull<TyckInfo>>,
+    /// For statically resolved method calls, the type the method `name` is registered for
+    pub receiver: Option<TypeId>
+}
+
+impl FfiImport {
+    pub fn new(
+        name: impl Into<String>,
+        kind: FfiKind,
+        func_type: Option<NonNull<TyckInfo>>
+    ) -> Self {
+        Self { name: name.into(), kind, func_type, receiver: None }
+    }
+
+    /// Import of the method `name` of type `receiver`, called with `FFICall` instructions
+    pub fn method(
+        receiver: TypeId,
+        name: impl Into<String>,
+        func_type: Option<NonNull<TyckInfo>>
+    ) -> Self {
+        Self { name: name.into(), kind: FfiKind::Sync, func_type, receiver: Some(receiver) }
+    }
+
+    /// Import of the method `name` for all types having it, called with `CallMethod` instructions
+    pub fn dyn_method(name: impl Into<String>) -> Self {
+        Self { name: name.into(), kind: FfiKind::Method, func_type: None, receiver: None }
+    }
+}
+
+/// A call to a native function resolved by `FfiRegistry::resolve_call` or
+/// `FfiRegistry::resolve_method_call`
+pub struct ResolvedCall {
+    pub kind: FfiKind,
+    pub func_type: Option<NonNull<TyckInfo>>
+}
+
+/// Failure registering, resolving or linking native functions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkError {
+    /// A native function has already been registered under `name`
+    DuplicateSymbol { name: String },
+    /// No native function of kind `kind` is registered under `name`
+    MissingSymbol { name: String, kind: FfiKind },
+    /// The type `type_name` already has a method `name`
+    DuplicateMethod { type_name: String, name: String },
+    /// The type `type_name` has no method `name`
+    MissingMethod { type_name: String, name: String },
+    /// `name` was imported with function type `expected`, but registered with type `found`
+    SignatureMismatch { name: String, expected: String, found: String },
+    /// `name` expects `min` to `max` arguments (no upper bound if `max` is `None`, for variadic
+    /// functions), but is called with `got`
+    ArgCountMismatch { name: String, min: usize, max: Option<usize>, got: usize },
+    /// The argument at `index` of a call to `name` has type `found`, not accepted by parameter
+    /// type `expected`
+    ArgTypeMismatch { name: String, index: usize, expected: String, found: String }
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkError::DuplicateSymbol { name } =>
+                write!(f, "native function `{}` has already been registered", name),
+            LinkError::MissingSymbol { name, kind } =>
+                write!(f, "missing {} `{}`", kind, name),
+            LinkError::DuplicateMethod { type_name, name } =>
+                write!(f, "method `{}` of `{}` has already been registered", name, type_name),
+            LinkError::MissingMethod { type_name, name } =>
+                write!(f, "no method `{}` for `{}`", name, type_name),
+            LinkError::SignatureMismatch { name, expected, found } =>
+                write!(f, "native function `{}` was expected to have type `{}`, found `{}`",
+                       name, expected, found),
+            LinkError::ArgCountMismatch { name, min, max, got } => {
+                write!(f, "native function `{}` expects ", name)?;
+                match max {
+                    Some(max) if max == min => write!(f, "{}", min)?,
+                    Some(max) => write!(f, "{} to {}", min, max)?,
+                    None => write!(f, "at least {}", min)?
+                }
+                write!(f, " argument(s), got {}", got)
+            },
+            LinkError::ArgTypeMismatch { name, index, expected, found } =>
+                write!(f, "argument #{} of native function `{}` expects `{}`, found `{}`",
+                       index, name, expected, found)
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+/// Registry of native functions, keyed by qualified names
+pub struct FfiRegistry<A: Alloc> {
+    tyck_info_pool: TyckInfoPool,
+    ffi_funcs: HashMap<String, FfiSymbol<SyncFunction<A>>>,
+    #[cfg(feature = "async")]
+    async_ffi_funcs: HashMap<String, FfiSymbol<AsyncFunction<A>>>,
+    methods: HashMap<TypeId, HashMap<String, FfiSymbol<SyncFunction<A>>>>,
+    type_names: HashMap<TypeId, String>
+}
+
+impl<A: Alloc> FfiRegistry<A> {
+    pub fn new() -> Self {
+        Self {
+            tyck_info_pool: TyckInfoPool::new(),
+            ffi_funcs: HashMap::new(),
+            #[cfg(feature = "async")]
+            async_ffi_funcs: HashMap::new(),
+            methods: HashMap::new(),
+            type_names: HashMap::new()
+        }
+    }
+
+    /// The pool holding signatures of registered functions. Compilers should create types they
+    /// check against these signatures in this pool.
+    pub fn tyck_info_pool(&mut self) -> &mut TyckInfoPool {
+        &mut self.tyck_info_pool
+    }
+
+    /// Register `func` under `name`, along with its signature if it has one. `func` may be a `'static` function
+    /// like `PR47BINDER_FOO`, a `ClosureFunction`, or a stateful object shared through an `Arc`,
+    /// which is kept alive as long as this registry or any program linked against it.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Into<FFIHandle<SyncFunction<A>>>
+    ) -> Result<(), LinkError> {
+        let func: FFIHandle<SyncFunction<A>> = func.into();
+        let name: String = self.check_unused(name.into())?;
+        let signature: Option<Signature> = func.signature(&mut self.tyck_info_pool);
+        self.ffi_funcs.insert(name, FfiSymbol { func, signature });
+        Ok(())
+    }
+
+    /// Register `func`, which does not have a standard signature, under `name`
+    pub fn register_untyped(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Into<FFIHandle<SyncFunction<A>>>
+    ) -> Result<(), LinkError> {
+        let func: FFIHandle<SyncFunction<A>> = func.into();
+        let name: String = self.check_unused(name.into())?;
+        self.ffi_funcs.insert(name, FfiSymbol { func, signature: None });
+        Ok(())
+    }
+
+    /// Register the async function `func` under `name`, along with its signature if it has one
+    #[cfg(feature = "async")]
+    pub fn register_async(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Into<FFIHandle<AsyncFunction<A>>>
+    ) -> Result<(), LinkError> {
+        let func: FFIHandle<AsyncFunction<A>> = func.into();
+        let name: String = self.check_unused(name.into())?;
+        let signature: Option<Signature> = func.signature(&mut self.tyck_info_pool);
+        self.async_ffi_funcs.insert(name, FfiSymbol { func, signature });
+        Ok(())
+    }
+
+    /// Register the async function `func`, which does not have a standard signature, under `name`
+    #[cfg(feature = "async")]
+    pub fn register_async_untyped(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Into<FFIHandle<AsyncFunction<A>>>
+    ) -> Result<(), LinkError> {
+        let func: FFIHandle<AsyncFunction<A>> = func.into();
+        let name: String = self.check_unused(name.into())?;
+        self.async_ffi_funcs.insert(name, FfiSymbol { func, signature: None });
+        Ok(())
+    }
+
+    /// Register `func` as the method `name` of the host type `T`, along with its signature if it
+    /// has one. The
+    /// receiver is passed as the first argument, so the first parameter of `func` should accept
+    /// `T`.
+    pub fn register_method<T: 'static>(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Into<FFIHandle<SyncFunction<A>>>
+    ) -> Result<(), LinkError>
+        where Void: StaticBase<T>
+    {
+        let func: FFIHandle<SyncFunction<A>> = func.into();
+        let signature: Option<Signature> = func.signature(&mut self.tyck_info_pool);
+        self.insert_method::<T>(name.into(), FfiSymbol { func, signature })
+    }
+
+    /// Register `func`, which does not have a standard signature, as the method `name` of the
+    /// host type `T`
+    pub fn register_method_untyped<T: 'static>(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Into<FFIHandle<SyncFunction<A>>>
+    ) -> Result<(), LinkError>
+        where Void: StaticBase<T>
+    {
+        let func: FFIHandle<SyncFunction<A>> = func.into();
+        self.insert_method::<T>(name.into(), FfiSymbol { func, signature: None })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FfiSymbol<SyncFunction<A>>> {
+        self.ffi_funcs.get(name)
+    }
+
+    #[cfg(feature = "async")]
+    pub fn get_async(&self, name: &str) -> Option<&FfiSymbol<AsyncFunction<A>>> {
+        self.async_ffi_funcs.get(name)
+    }
+
+    pub fn get_method(&self, receiver: TypeId, name: &str) -> Option<&FfiSymbol<SyncFunction<A>>> {
+        self.methods.get(&receiver)?.get(name)
+    }
+
+    /// Resolve a call to the native function `name` with arguments of types `arg_types`, which
+    /// should be allocated in `self.tyck_info_pool()`. Arguments are checked against the
+    /// signature of the function, if there is one.
+    pub fn resolve_call(
+        &self,
+        name: &str,
+        arg_types: &[NonNull<TyckInfo>]
+    ) -> Result<ResolvedCall, LinkError> {
+        let (kind, signature): (FfiKind, &Option<Signature>) =
+            if let Some(symbol) = self.ffi_funcs.get(name) {
+                (FfiKind::Sync, &symbol.signature)
+            } else {
+                #[cfg(feature = "async")]
+                if let Some(symbol) = self.async_ffi_funcs.get(name) {
+                    (FfiKind::Async, &symbol.signature)
+                } else {
+                    return Err(LinkError::MissingSymbol {
+                        name: name.into(),
+                        kind: FfiKind::Sync
+                    });
+                }
+                #[cfg(not(feature = "async"))]
+                return Err(LinkError::MissingSymbol { name: name.into(), kind: FfiKind::Sync });
+            };
+
+        check_args(name, kind, signature, arg_types)
+    }
+
+    /// Resolve a call to the method `name` on a receiver of type `receiver`, with arguments of
+    /// types `arg_types` (not including the receiver). Types should be allocated in
+    /// `self.tyck_info_pool()`.
+    ///
+    /// If the receiver is known to be a host type, the call is resolved to the method of that
+    /// type, which should be imported with `FfiImport::method` and called with `FFICall`
+    /// instructions, passing the receiver as the first argument. Arguments are checked as in
+    /// `resolve_call`, with the receiver being argument #0. If the receiver has type `any`, the
+    /// call is resolved to a `FfiKind::Method` call dispatched at run time, which should be
+    /// imported with `FfiImport::dyn_method` and called with `CallMethod` instructions.
+    pub fn resolve_method_call(
+        &self,
+        receiver: NonNull<TyckInfo>,
+        name: &str,
+        arg_types: &[NonNull<TyckInfo>]
+    ) -> Result<ResolvedCall, LinkError> {
+        let type_id: TypeId = match unsafe { receiver.as_ref() } {
+            TyckInfo::Plain(type_id) => *type_id,
+            TyckInfo::Container(container) => container.type_id,
+            TyckInfo::AnyType if self.methods.values().any(|methods| methods.contains_key(name)) =>
+                return Ok(ResolvedCall { kind: FfiKind::Method, func_type: None }),
+            _ => return Err(LinkError::MissingMethod {
+                type_name: unsafe { receiver.as_ref() }.to_string(),
+                name: name.into()
+            })
+        };
+
+        let symbol: &FfiSymbol<SyncFunction<A>> = self.get_method(type_id, name)
+            .ok_or_else(|| LinkError::MissingMethod {
+                type_name: unsafe { receiver.as_ref() }.to_string(),
+                name: name.into()
+            })?;
+        let mut all_arg_types: Vec<NonNull<TyckInfo>> = Vec::with_capacity(arg_types.len() + 1);
+        all_arg_types.push(receiver);
+        all_arg_types.extend_from_slice(arg_types);
+        check_args(name, FfiKind::Sync, &symbol.signature, &all_arg_types)
+    }
+
+    /// Link `imports` against registered functions. Sync, async and dynamically dispatched method
+    /// imports are numbered separately in order of appearance, matching the indices used by
+    /// `FFICall`, `FFICallAsync` and `CallMethod` instructions. All missing or mismatched symbols
+    /// are reported.
+    pub fn link(
+        &self,
+        imports: &[FfiImport]
+    ) -> Result<LinkedFfi<A>, Vec<LinkError>> {
+        let mut errors: Vec<LinkError> = vec![];
+        let mut ffi_funcs: Vec<FFIHandle<SyncFunction<A>>> = vec![];
+        #[cfg(feature = "async")]
+        let mut async_ffi_funcs: Vec<FFIHandle<AsyncFunction<A>>> = vec![];
+        let mut method_tables: Vec<MethodTable<A>> = vec![];
+
+        for import in imports {
+            match import.kind {
+                FfiKind::Sync if import.receiver.is_some() => {
+                    let receiver: TypeId = import.receiver.unwrap();
+                    match self.get_method(receiver, &import.name) {
+                        Some(symbol) => {
+                            if let Err(e) = check_signature(import, &symbol.signature) {
+                                errors.push(e);
+                            }
+                            ffi_funcs.push(with_variadic_check(symbol));
+                        },
+                        None => errors.push(LinkError::MissingMethod {
+                            type_name: self.type_name(receiver),
+                            name: import.name.clone()
+                        })
+                    }
+                },
+                FfiKind::Sync => match self.ffi_funcs.get(&import.name) {
+                    Some(symbol) => {
+                        if let Err(e) = check_signature(import, &symbol.signature) {
+                            errors.push(e);
+                        }
+                        ffi_funcs.push(with_variadic_check(symbol));
+                    },
+                    None => errors.push(LinkError::MissingSymbol {
+                        name: import.name.clone(),
+                        kind: import.kind
+                    })
+                },
+                FfiKind::Method => {
+                    let mut method_table: MethodTable<A> = MethodTable::new(import.name.clone());
+                    for (receiver, methods) in self.methods.iter() {
+                        if let Some(symbol) = methods.get(&import.name) {
+                            method_table.impls.insert(*receiver, method_impl(symbol));
+                        }
+                    }
+                    if method_table.impls.is_empty() {
+                        errors.push(LinkError::MissingMethod {
+                            type_name: "any".into(),
+                            name: import.name.clone()
+                        });
+                    }
+                    method_tables.push(method_table);
+                },
+                #[cfg(feature = "async")]
+                FfiKind::Async => match self.async_ffi_funcs.get(&import.name) {
+                    Some(symbol) => {
+                        if let Err(e) = check_signature(import, &symbol.signature) {
+                            errors.push(e);
+                        }
+                        async_ffi_funcs.push(with_variadic_check(symbol));
+                    },
+                    None => errors.push(LinkError::MissingSymbol {
+                        name: import.name.clone(),
+                        kind: import.kind
+                    })
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(LinkedFfi {
+            ffi_funcs: ffi_funcs.into_boxed_slice(),
+            #[cfg(feature = "async")]
+            async_ffi_funcs: async_ffi_funcs.into_boxed_slice(),
+            method_tables: method_tables.into_boxed_slice()
+        })
+    }
+
+    /// Link `imports` and install the result into `program`
+    pub fn link_program(
+        &self,
+        program: &mut CompiledProgram<A>,
+        imports: &[FfiImport]
+    ) -> Result<(), Vec<LinkError>> {
+        let linked: LinkedFfi<A> = self.link(imports)?;
+        program.ffi_funcs = linked.ffi_funcs;
+        #[cfg(feature = "async")]
+        { program.async_ffi_funcs = linked.async_ffi_funcs; }
+        program.method_tables = linked.method_tables;
+        Ok(())
+    }
+
+    fn check_unused(&self, name: String) -> Result<String, LinkError> {
+        #[cfg(feature = "async")]
+        let used: bool = self.ffi_funcs.contains_key(&name)
+            || self.async_ffi_funcs.contains_key(&name);
+        #[cfg(not(feature = "async"))]
+        let used: bool = self.ffi_funcs.contains_key(&name);
+
+        if used {
+            Err(LinkError::DuplicateSymbol { name })
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn insert_method<T: 'static>(
+        &mut self,
+        name: String,
+        symbol: FfiSymbol<SyncFunction<A>>
+    ) -> Result<(), LinkError>
+        where Void: StaticBase<T>
+    {
+        let type_id: TypeId = <Void as StaticBase<T>>::type_id();
+        let methods: &mut HashMap<String, FfiSymbol<SyncFunction<A>>> =
+            self.methods.entry(type_id).or_default();
+        if methods.contains_key(&name) {
+            return Err(LinkError::DuplicateMethod {
+                type_name: <Void as StaticBase<T>>::type_name(),
+                name
+            });
+        }
+        methods.insert(name, symbol);
+        self.type_names.entry(type_id).or_insert_with(<Void as StaticBase<T>>::type_name);
+        Ok(())
+    }
+
+    fn type_name(&self, type_id: TypeId) -> String {
+        self.type_names.get(&type_id).cloned().unwrap_or_else(|| format!("{:?}", type_id))
+    }
+}
+
+impl<A: Alloc> Default for FfiRegistry<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Native functions linked by `FfiRegistry::link`, ready to be put into a `CompiledProgram`
+pub struct LinkedFfi<A: Alloc> {
+    pub ffi_funcs: Box<[FFIHandle<SyncFunction<A>>]>,
+    #[cfg(feature = "async")]
+    pub async_ffi_funcs: Box<[FFIHandle<AsyncFunction<A>>]>,
+    pub method_tables: Box<[MethodTable<A>]>
+}
+
+/// Handle of `symbol` for linking. Calls to variadic functions get their variadic arguments
+/// checked at run time, unless the element type is `any`.
+fn with_variadic_check<F: ?Sized>(symbol: &FfiSymbol<F>) -> FFIHandle<F> {
+    let func_type: &FunctionTyckInfo = if let Some(signature) = &symbol.signature {
+        unsafe { signature.func_type.as_ref().get_function_tyck_info_unchecked().as_ref() }
+    } else {
+        return symbol.func.clone();
+    };
+
+    match func_type.variadic {
+        Some(elem_type) if !matches!(unsafe { elem_type.as_ref() }, TyckInfo::AnyType) => {
+            let fixed_params: usize = unsafe { func_type.params.as_ref().len() };
+            symbol.func.clone().with_variadic_check(
+                VariadicCheck::new(fixed_params, unsafe { elem_type.as_ref() })
+            )
+        },
+        _ => symbol.func.clone()
+    }
+}
+
+fn method_impl<A: Alloc>(symbol: &FfiSymbol<SyncFunction<A>>) -> MethodImpl<A> {
+    let (counts, variadic, variadic_rets): (Option<(usize, usize)>, bool, bool) =
+        match &symbol.signature {
+            Some(signature) => {
+                let func_type: &FunctionTyckInfo = unsafe {
+                    signature.func_type.as_ref().get_function_tyck_info_unchecked().as_ref()
+                };
+                let counts: (usize, usize) = unsafe {
+                    (func_type.params.as_ref().len(), func_type.rets.as_ref().len())
+                };
+                (Some(counts), func_type.variadic.is_some(), func_type.variadic_rets.is_some())
+            },
+            None => (None, false, false)
+        };
+    MethodImpl { func: with_variadic_check(symbol), counts, variadic, variadic_rets }
+}
+
+/// Check `arg_types` against the signature of the native function `name`, if there is one
+fn check_args(
+    name: &str,
+    kind: FfiKind,
+    signature: &Option<Signature>,
+    arg_types: &[NonNull<TyckInfo>]
+) -> Result<ResolvedCall, LinkError> {
+    let signature: &Signature = if let Some(signature) = signature {
+        signature
+    } else {
+        return Ok(ResolvedCall { kind, func_type: None });
+    };
+
+    let func_type: &FunctionTyckInfo = unsafe {
+        signature.func_type.as_ref().get_function_tyck_info_unchecked().as_ref()
+    };
+    if !func_type.accepts_arg_count(arg_types.len()) {
+        return Err(LinkError::ArgCountMismatch {
+            name: name.into(),
+            min: func_type.required_params(),
+            max: func_type.variadic.map_or(
+                Some(unsafe { func_type.params.as_ref().len() }),
+                |_| None
+            ),
+            got: arg_types.len()
+        });
+    }
+    for (index, arg) in arg_types.iter().enumerate() {
+        let param: NonNull<TyckInfo> = func_type.param_type(index).unwrap();
+        if !unsafe { type_accepts(param, *arg) } {
+            return Err(LinkError::ArgTypeMismatch {
+                name: name.into(),
+                index,
+                expected: unsafe { param.as_ref() }.to_string(),
+                found: unsafe { arg.as_ref() }.to_string()
+            });
+        }
+    }
+    Ok(ResolvedCall { kind, func_type: Some(signature.func_type) })
+}
+
+/// Check whether a parameter of type `param` accepts an argument of type `arg`
+unsafe fn type_accepts(param: NonNull<TyckInfo>, arg: NonNull<TyckInfo>) -> bool {
+    if param == arg {
+        return true;
+    }
+    match param.as_ref() {
+        TyckInfo::AnyType => true,
+        TyckInfo::Nullable(base) => type_accepts(*base, arg),
+        _ => false
+    }
+}
+
+/// Check the function type `import` was compiled against against `signature`. Types are
+/// compared structurally, since the importing code may have been compiled with another pool.
+fn check_signature(import: &FfiImport, signature: &Option<Signature>) -> Result<(), LinkError> {
+    let display = |func_type: Option<NonNull<TyckInfo>>| match func_type {
+        Some(func_type) => unsafe { func_type.as_ref() }.to_string(),
+        None => "<untyped>".to_string()
+    };
+
+    let found: Option<NonNull<TyckInfo>> = signature.as_ref()
+        .map(|signature: &Signature| signature.func_type);
+    let matches: bool = match (import.func_type, found) {
+        (Some(expected), Some(found)) =>
+            unsafe { expected.as_ref().structural_eq(found.as_ref()) },
+        (None, None) => true,
+        _ => false
+    };
+    if matches {
+        Ok(())
+    } else {
+        Err(LinkError::SignatureMismatch {
+            name: import.name.clone(),
+            expected: display(import.func_type),
+            found: display(found)
+        })
+    }
+}
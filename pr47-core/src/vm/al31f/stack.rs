@@ -1,5 +1,7 @@
+use std::mem::replace;
 use std::ptr::NonNull;
 
+use smallvec::SmallVec;
 use unchecked_unwrap::UncheckedUnwrap;
 
 use crate::data::Value;
@@ -50,7 +52,9 @@ pub struct FrameInfo {
     pub ret_value_locs: NonNull<[usize]>,
     pub ret_addr: usize,
 
-    pub func_id: usize
+    pub func_id: usize,
+    /// Count of frames replaced by tail calls in this frame
+    pub elided_frames: usize
 }
 
 impl FrameInfo {
@@ -66,204 +70,215 @@ impl FrameInfo {
             frame_end,
             ret_value_locs,
             ret_addr,
-            func_id
+            func_id,
+            elided_frames: 0
         }
     }
 }
 
+/// Resource limits and growth strategy of a `Stack`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackConfig {
+    /// Maximum count of frames on the stack
+    pub max_frame_depth: usize,
+    /// Maximum count of value slots on the stack, summed over all frames
+    pub max_values: usize,
+    /// If set, value slots are allocated in segments of (at least) this many slots. When the
+    /// topmost segment is full a new one gets started, instead of reallocating and moving the
+    /// whole stack.
+    pub segment_size: Option<usize>
+}
+
+pub const DEFAULT_MAX_FRAME_DEPTH: usize = 65536;
+pub const DEFAULT_MAX_VALUES: usize = 1 << 22;
+
+impl Default for StackConfig {
+    fn default() -> Self {
+        Self {
+            max_frame_depth: DEFAULT_MAX_FRAME_DEPTH,
+            max_values: DEFAULT_MAX_VALUES,
+            segment_size: None
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+pub type StackValue = Option<Value>;
+
+#[cfg(not(debug_assertions))]
+pub type StackValue = Value;
+
 #[cfg(debug_assertions)]
+const EMPTY_STACK_VALUE: StackValue = None;
+
+#[cfg(not(debug_assertions))]
+const EMPTY_STACK_VALUE: StackValue = Value::new_null();
+
 pub struct Stack {
-    pub values: Vec<Option<Value>>,
-    pub frames: Vec<FrameInfo>
+    /// Value slots of the topmost segment
+    pub values: Vec<StackValue>,
+    pub frames: Vec<FrameInfo>,
+    pub config: StackConfig,
+    /// Index of the frame the innermost executor run started with. Returning from this frame
+    /// ends the run, and unwinding never goes below it.
+    pub entry_frame: usize,
+
+    /// Segments below `values`, only used in segmented mode
+    saved_segments: Vec<Vec<StackValue>>,
+    /// Index of the first frame of each segment except the bottom one
+    segment_bases: Vec<usize>,
+    /// Index of the first frame of the topmost segment
+    current_base: usize,
+    /// Total length of `saved_segments`
+    saved_len: usize,
+    /// The last segment left, kept for reuse so that calls around a segment boundary do not
+    /// allocate repeatedly
+    spare_segment: Option<Vec<StackValue>>
 }
 
 pub const EMPTY_RET_LOCS_SLICE: &'static [usize] = &[];
 
-#[cfg(debug_assertions)]
 impl Stack {
     pub fn new() -> Self {
-        Self {
-            values: Vec::with_capacity(64),
-            frames: Vec::with_capacity(4)
-        }
+        Self::with_config(StackConfig::default())
     }
 
-    pub unsafe fn ext_func_call_grow_stack(
-        &mut self,
-        func_id: usize,
-        frame_size: usize,
-        args: &[Value]
-    ) -> StackSlice {
-        assert_eq!(self.values.len(), 0);
-        assert_eq!(self.frames.len(), 0);
-
-        self.values.resize(frame_size, None);
-        for (i /*: usize*/, arg /*: &Value*/) in args.iter().enumerate() {
-            self.values[i].replace(*arg);
+    pub fn with_config(config: StackConfig) -> Self {
+        Self {
+            values: Vec::with_capacity(config.segment_size.unwrap_or(64)),
+            frames: Vec::with_capacity(4),
+            config,
+            entry_frame: 0,
+            saved_segments: Vec::new(),
+            segment_bases: Vec::new(),
+            current_base: 0,
+            saved_len: 0,
+            spare_segment: None
         }
-        self.frames.push(FrameInfo::new(
-            0, frame_size, NonNull::from(EMPTY_RET_LOCS_SLICE), 0, func_id
-        ));
-        StackSlice(&mut self.values[..] as *mut [Option<Value>])
     }
 
-    pub unsafe fn func_call_grow_stack(
-        &mut self,
-        func_id: usize,
-        frame_size: usize,
-        arg_locs: &[usize],
-        ret_value_locs: NonNull<[usize]>,
-        ret_addr: usize
-    ) -> StackSlice {
-        let this_frame: &FrameInfo = self.frames.last().unwrap();
-        let (this_frame_start, this_frame_end): (usize, usize)
-            = (this_frame.frame_start, this_frame.frame_end);
-
-        assert_eq!(this_frame_end, self.values.len());
-        let new_frame_end: usize = this_frame_end + frame_size;
-        self.values.resize(new_frame_end, None);
-        self.frames.push(
-            FrameInfo::new(this_frame_end, new_frame_end, ret_value_locs, ret_addr, func_id)
-        );
-        let old_slice: StackSlice =
-            StackSlice(&mut self.values[this_frame_start..this_frame_end] as *mut _);
-        let mut new_slice: StackSlice =
-            StackSlice(&mut self.values[this_frame_end..new_frame_end] as *mut _);
-        for (i /*: usize*/, arg_loc/*: &usize*/) in arg_locs.iter().enumerate() {
-            new_slice.set_value(i, old_slice.get_value(*arg_loc));
-        }
-        new_slice
+    /// Count of value slots in use, summed over all frames
+    pub fn value_count(&self) -> usize {
+        self.saved_len + self.values.len()
     }
 
-    pub unsafe fn closure_call_grow_stack(
-        &mut self,
-        func_id: usize,
-        frame_size: usize,
-        captures: &[Value],
-        arg_locs: &[usize],
-        ret_value_locs: NonNull<[usize]>,
-        ret_addr: usize
-    ) -> StackSlice {
-        let this_frame: &FrameInfo = self.frames.last().unwrap();
-        let (this_frame_start, this_frame_end): (usize, usize)
-            = (this_frame.frame_start, this_frame.frame_end);
-
-        assert_eq!(this_frame_end, self.values.len());
-        let new_frame_end: usize = this_frame_end + frame_size;
-        self.values.resize(new_frame_end, None);
-        self.frames.push(
-            FrameInfo::new(this_frame_end, new_frame_end, ret_value_locs, ret_addr, func_id)
-        );
-        let old_slice: StackSlice =
-            StackSlice(&mut self.values[this_frame_start..this_frame_end] as *mut _);
-        let mut new_slice: StackSlice =
-            StackSlice(&mut self.values[this_frame_end..new_frame_end] as *mut _);
-        for (i /*: usize*/, &capture /*: Value*/) in captures.iter().enumerate() {
-            new_slice.set_value(i, capture);
-        }
-        for (i /*: usize*/, arg_loc /*: &usize*/) in arg_locs.iter().enumerate() {
-            new_slice.set_value(i + captures.len(), old_slice.get_value(*arg_loc));
-        }
-        new_slice
+    /// Check if pushing a frame of `frame_size` slots would exceed the limits set by `config`
+    #[inline(always)] pub fn would_overflow(&self, frame_size: usize) -> bool {
+        self.frames.len() >= self.config.max_frame_depth
+            || self.value_count() + frame_size > self.config.max_values
     }
 
-    pub unsafe fn done_func_call_shrink_stack0(&mut self) -> Option<(StackSlice, usize)> {
-        self.done_func_call_shrink_stack(&[])
+    /// Check if resizing the topmost frame to `frame_size` slots for a tail call would exceed the
+    /// limits set by `config`
+    #[inline(always)] pub fn would_overflow_tail_call(&self, frame_size: usize) -> bool {
+        let frame: &FrameInfo = unsafe { self.frames.last().unchecked_unwrap() };
+        self.value_count() - (frame.frame_end - frame.frame_start) + frame_size
+            > self.config.max_values
     }
 
-    pub unsafe fn done_func_call_shrink_stack1(
-        &mut self,
-        ret_value_src: usize
-    ) -> Option<(StackSlice, usize)> {
-        self.done_func_call_shrink_stack(&[ret_value_src])
+    /// Iterate over all value slots, from the bottom of the stack to the top
+    pub fn values(&self) -> impl Iterator<Item=&StackValue> {
+        self.saved_segments.iter()
+            .flat_map(|segment: &Vec<StackValue>| segment.iter())
+            .chain(self.values.iter())
     }
 
-    pub unsafe fn done_func_call_shrink_stack(
-        &mut self,
-        ret_values: &[usize]
-    ) -> Option<(StackSlice, usize)> {
-        let frame_count: usize = self.frames.len();
-        if frame_count == 1 {
-            return None;
+    /// Get the segment holding frame `frame_idx`, which must be in either the topmost segment or
+    /// the one right below it
+    #[inline(always)] unsafe fn segment_of(&mut self, frame_idx: usize) -> &mut Vec<StackValue> {
+        if frame_idx >= self.current_base {
+            &mut self.values
+        } else {
+            self.saved_segments.last_mut().unchecked_unwrap()
         }
+    }
 
-        let this_frame: &FrameInfo = &self.frames[frame_count - 1];
-        let prev_frame: &FrameInfo = &self.frames[frame_count - 2];
-        assert_eq!(prev_frame.frame_end, this_frame.frame_start);
-        let this_slice =
-            StackSlice(&mut self.values[this_frame.frame_start..this_frame.frame_end] as *mut _);
-        let mut prev_slice =
-            StackSlice(&mut self.values[prev_frame.frame_start..prev_frame.frame_end] as *mut _);
+    #[inline(always)] unsafe fn frame_slice(&mut self, frame_idx: usize) -> StackSlice {
+        let frame: &FrameInfo = self.frames.get_unchecked(frame_idx);
+        let (frame_start, frame_end): (usize, usize) = (frame.frame_start, frame.frame_end);
+        let segment: &mut Vec<StackValue> = self.segment_of(frame_idx);
 
-        assert_eq!(ret_values.len(), this_frame.ret_value_locs.as_ref().len());
-        for (ret_value /*: &usize*/, ret_value_loc /*: &usize*/) in
-            ret_values.iter().zip(this_frame.ret_value_locs.as_ref().iter())
+        #[cfg(debug_assertions)]
         {
-            prev_slice.set_value(*ret_value_loc, this_slice.get_value(*ret_value))
+            StackSlice(&mut segment[frame_start..frame_end] as *mut _)
         }
 
-        let ret_addr: usize = this_frame.ret_addr;
-        self.values.truncate(prev_frame.frame_end);
-        self.frames.pop().unwrap();
-        Some((prev_slice, ret_addr))
+        #[cfg(not(debug_assertions))]
+        {
+            let _ = frame_end;
+            StackSlice(segment.as_mut_ptr().add(frame_start))
+        }
     }
 
-    pub unsafe fn last_frame_slice(&mut self) -> StackSlice {
-        let frame: &FrameInfo = self.frames.last().unchecked_unwrap();
-        StackSlice(&mut self.values[frame.frame_start..frame.frame_end] as *mut _)
+    /// Make room for a new frame of `frame_size` slots, starting a new segment in segmented mode
+    /// if the current one is full. Returns the start of the new frame in `values`.
+    #[inline(always)] fn reserve_frame(&mut self, frame_size: usize) -> usize {
+        let frame_start: usize = self.values.len();
+        if let Some(segment_size) = self.config.segment_size {
+            if frame_start + frame_size > self.values.capacity() {
+                self.start_segment(segment_size, frame_size);
+                return 0;
+            }
+        }
+        frame_start
     }
 
-    pub unsafe fn unwind_shrink_slice(&mut self) {
-        let frame: FrameInfo = self.frames.pop().unchecked_unwrap();
-        self.values.truncate(frame.frame_start);
+    /// Move the current segment to `saved_segments` and start a new one with room for at least
+    /// `frame_size` slots. The next frame pushed becomes the first frame of the new segment.
+    fn start_segment(&mut self, segment_size: usize, frame_size: usize) {
+        let segment: Vec<StackValue> = match self.spare_segment.take() {
+            Some(spare) if spare.capacity() >= frame_size => spare,
+            _ => Vec::with_capacity(usize::max(segment_size, frame_size))
+        };
+        let segment: Vec<StackValue> = replace(&mut self.values, segment);
+        self.saved_len += segment.len();
+        self.saved_segments.push(segment);
+        self.segment_bases.push(self.frames.len());
+        self.current_base = self.frames.len();
     }
-}
 
-#[cfg(any(feature = "bench", test))]
-impl Stack {
-    pub fn trace(&self) {
-        eprintln!("[STACK-TRACE] Begin stack tracing");
-        eprintln!("[STACK-TRACE] {{");
-        for (i, frame) /*: (usize, &FrameInfo)*/ in self.frames.iter().enumerate() {
-            eprintln!("[STACK-TRACE]     <frame {}: size = {}, ret_addr = {}, ret_val_locs = {:?}>",
-                      i,
-                      frame.frame_end - frame.frame_start,
-                      frame.ret_addr,
-                      unsafe { frame.ret_value_locs.as_ref() });
-            eprintln!("[STACK-TRACE]     [");
-            #[cfg(debug_assertions)]
-            for i /*: usize*/ in frame.frame_start..frame.frame_end {
-                if let Some(value /*: &Value*/) = &self.values[i] {
-                    eprintln!("[STACK-TRACE]         [{}] = {:?}", i - frame.frame_start, value);
-                } else {
-                    eprintln!("[STACK-TRACE]         [{}] = UNINIT", i - frame.frame_start);
-                }
-            }
-            #[cfg(not(debug_assertions))]
-            for i /*: usize*/ in frame.frame_start..frame.frame_end {
-                let value: &Value = &self.values[i];
-                eprintln!("[STACK-TRACE]         [{}] = {:?}", i - frame.frame_start, value);
-            }
-            eprintln!("[STACK-TRACE]     ]");
+    #[inline(always)] unsafe fn push_frame(
+        &mut self,
+        func_id: usize,
+        frame_size: usize,
+        ret_value_locs: NonNull<[usize]>,
+        ret_addr: usize
+    ) -> StackSlice {
+        #[cfg(debug_assertions)]
+        assert_eq!(self.frames.last().unwrap().frame_end, self.values.len());
+
+        let frame_start: usize = self.reserve_frame(frame_size);
+        let frame_end: usize = frame_start + frame_size;
+        self.values.resize(frame_end, EMPTY_STACK_VALUE);
+        self.frames.push(FrameInfo::new(frame_start, frame_end, ret_value_locs, ret_addr, func_id));
+
+        #[cfg(debug_assertions)]
+        {
+            StackSlice(&mut self.values[frame_start..frame_end] as *mut _)
         }
-        eprintln!("[STACK-TRACE] }}");
-        eprintln!("[STACK-TRACE] End stack tracing");
-    }
-}
 
-#[cfg(not(debug_assertions))]
-pub struct Stack {
-    pub values: Vec<Value>,
-    pub frames: Vec<FrameInfo>
-}
+        #[cfg(not(debug_assertions))]
+        {
+            StackSlice(self.values.as_mut_ptr().add(frame_start))
+        }
+    }
 
-#[cfg(not(debug_assertions))]
-impl Stack {
-    pub fn new() -> Self {
-        Self {
-            values: Vec::with_capacity(64),
-            frames: Vec::with_capacity(4)
+    /// Pop the topmost frame, going back to the segment below if the frame was the first one of
+    /// its segment
+    #[inline(always)] unsafe fn pop_frame(&mut self) -> FrameInfo {
+        let frame: FrameInfo = self.frames.pop().unchecked_unwrap();
+        if self.frames.len() == self.current_base && !self.segment_bases.is_empty() {
+            self.segment_bases.pop();
+            self.current_base = self.segment_bases.last().copied().unwrap_or(0);
+            let segment: Vec<StackValue> = self.saved_segments.pop().unchecked_unwrap();
+            self.saved_len -= segment.len();
+            let mut spare: Vec<StackValue> = replace(&mut self.values, segment);
+            spare.clear();
+            self.spare_segment = Some(spare);
+        } else {
+            self.values.truncate(frame.frame_start);
         }
+        frame
     }
 
     pub unsafe fn ext_func_call_grow_stack(
@@ -272,15 +287,80 @@ impl Stack {
         frame_size: usize,
         args: &[Value]
     ) -> StackSlice {
-        self.values.resize(frame_size, Value::new_null());
+        #[cfg(debug_assertions)]
+        {
+            assert_eq!(self.values.len(), 0);
+            assert_eq!(self.frames.len(), 0);
+        }
+
+        self.values.resize(frame_size, EMPTY_STACK_VALUE);
         for (i /*: usize*/, arg /*: &Value*/) in args.iter().enumerate() {
-            let dest: &mut Value = self.values.get_unchecked_mut(i);
-            *dest = *arg;
+            *self.values.get_unchecked_mut(i) = (*arg).into();
         }
         self.frames.push(FrameInfo::new(
-            0, frame_size, NonNull::from(EMPTY_RET_LOCS_SLICE), 0, func_id)
-        );
-        StackSlice(self.values.as_mut_ptr())
+            0, frame_size, NonNull::from(EMPTY_RET_LOCS_SLICE), 0, func_id
+        ));
+        self.frame_slice(0)
+    }
+
+    /// Pop the entry frame of a function started with `ext_func_call_grow_stack`, unless unwinding
+    /// already did, so that another function can be run on this stack.
+    ///
+    /// # Safety
+    /// Must be called after the function started with `ext_func_call_grow_stack` finishes.
+    pub unsafe fn ext_func_call_done(&mut self) {
+        if self.frames.len() > self.entry_frame {
+            self.pop_frame();
+        }
+    }
+
+    /// Push a frame for a call made from outside the executor while other frames are still
+    /// running, i.e. an FFI function calling back into script code. The frame always starts a new
+    /// segment, so the slots of the frames below never move and `StackSlice`s pointing to them
+    /// stay valid. The new frame becomes the entry frame, the previous entry frame is returned
+    /// and should be given back to `reentrant_call_done` later.
+    ///
+    /// # Safety
+    /// The stack must have at least one frame, and `captures.len() + args.len()` must not exceed
+    /// `frame_size`.
+    pub unsafe fn reentrant_call_grow_stack(
+        &mut self,
+        func_id: usize,
+        frame_size: usize,
+        captures: &[Value],
+        args: &[Value]
+    ) -> (StackSlice, usize) {
+        self.start_segment(self.config.segment_size.unwrap_or(64), frame_size);
+        self.values.resize(frame_size, EMPTY_STACK_VALUE);
+        self.frames.push(FrameInfo::new(
+            0, frame_size, NonNull::from(EMPTY_RET_LOCS_SLICE), 0, func_id
+        ));
+        let mut slice: StackSlice = self.frame_slice(self.frames.len() - 1);
+        let captures_len: usize = captures.len();
+        for i /*: usize*/ in 0..captures_len {
+            slice.set_value(i, *captures.get_unchecked(i));
+        }
+        for i /*: usize*/ in 0..args.len() {
+            slice.set_value(i + captures_len, *args.get_unchecked(i));
+        }
+        (slice, replace(&mut self.entry_frame, self.frames.len() - 1))
+    }
+
+    /// Whether the frames on top of the stack were started with `reentrant_call_grow_stack`
+    pub fn in_reentrant_call(&self) -> bool {
+        self.entry_frame != 0
+    }
+
+    /// Finish a call started with `reentrant_call_grow_stack`, popping its frame unless
+    /// unwinding already did, and restore `prev_entry_frame`.
+    ///
+    /// # Safety
+    /// Must be paired with the latest `reentrant_call_grow_stack` call not yet finished.
+    pub unsafe fn reentrant_call_done(&mut self, prev_entry_frame: usize) {
+        if self.frames.len() > self.entry_frame {
+            self.pop_frame();
+        }
+        self.entry_frame = prev_entry_frame;
     }
 
     pub unsafe fn func_call_grow_stack(
@@ -291,22 +371,15 @@ impl Stack {
         ret_value_locs: NonNull<[usize]>,
         ret_addr: usize
     ) -> StackSlice {
-        let this_frame: &FrameInfo = self.frames.last().unchecked_unwrap();
-        let (this_frame_start, this_frame_end): (usize, usize)
-            = (this_frame.frame_start, this_frame.frame_end);
-        let new_frame_end: usize = this_frame_end + frame_size;
-        self.values.resize(new_frame_end, Value::new_null());
-        self.frames.push(
-            FrameInfo::new(this_frame_end, new_frame_end, ret_value_locs, ret_addr, func_id)
-        );
-        let old_slice_ptr: *mut Value = self.values.as_mut_ptr().offset(this_frame_start as isize);
-        let new_slice_ptr: *mut Value = self.values.as_mut_ptr().offset(this_frame_end as isize);
+        let mut new_slice: StackSlice =
+            self.push_frame(func_id, frame_size, ret_value_locs, ret_addr);
+        let old_slice: StackSlice = self.frame_slice(self.frames.len() - 2);
 
         for i /*: usize*/ in 0..arg_locs.len() {
             let arg_loc: usize = *arg_locs.get_unchecked(i);
-            *new_slice_ptr.offset(i as isize) = *old_slice_ptr.offset(arg_loc as isize);
+            new_slice.set_value(i, *old_slice.get_value_mut_ref(arg_loc));
         }
-        StackSlice(new_slice_ptr)
+        new_slice
     }
 
     pub unsafe fn closure_call_grow_stack(
@@ -318,108 +391,166 @@ impl Stack {
         ret_value_locs: NonNull<[usize]>,
         ret_addr: usize
     ) -> StackSlice {
-        let this_frame: &FrameInfo = self.frames.last().unchecked_unwrap();
-        let (this_frame_start, this_frame_end): (usize, usize)
-            = (this_frame.frame_start, this_frame.frame_end);
-        let new_frame_end: usize = this_frame_end + frame_size;
-        self.values.resize(new_frame_end, Value::new_null());
-        self.frames.push(
-            FrameInfo::new(this_frame_end, new_frame_end, ret_value_locs, ret_addr, func_id)
-        );
-        let old_slice_ptr: *mut Value = self.values.as_mut_ptr().offset(this_frame_start as isize);
-        let new_slice_ptr: *mut Value = self.values.as_mut_ptr().offset(this_frame_end as isize);
+        let mut new_slice: StackSlice =
+            self.push_frame(func_id, frame_size, ret_value_locs, ret_addr);
+        let old_slice: StackSlice = self.frame_slice(self.frames.len() - 2);
 
         let captures_len: usize = captures.len();
-        for i in 0..captures_len {
-            *new_slice_ptr.offset(i as isize) = *captures.get_unchecked(i);
+        for i /*: usize*/ in 0..captures_len {
+            new_slice.set_value(i, *captures.get_unchecked(i));
         }
         for i /*: usize*/ in 0..arg_locs.len() {
             let arg_loc: usize = *arg_locs.get_unchecked(i);
-            *new_slice_ptr.offset((i + captures_len) as isize)
-                = *old_slice_ptr.offset(arg_loc as isize);
+            new_slice.set_value(i + captures_len, *old_slice.get_value_mut_ref(arg_loc));
+        }
+        new_slice
+    }
+
+    /// Reuse the topmost frame for a tail call to `func_id`. The frame is resized to `frame_size`
+    /// slots if it differs from the current size, while its `ret_value_locs` and `ret_addr` are
+    /// kept, so that the callee returns to the caller of current function directly.
+    ///
+    /// # Safety
+    /// The stack must have at least one frame, and `arg_locs` must be valid slots of it.
+    pub unsafe fn tail_call_reuse_stack(
+        &mut self,
+        func_id: usize,
+        frame_size: usize,
+        captures: &[Value],
+        arg_locs: &[usize]
+    ) -> StackSlice {
+        let frame_idx: usize = self.frames.len() - 1;
+        let old_slice: StackSlice = self.frame_slice(frame_idx);
+        let args: SmallVec<[Value; 8]> = arg_locs.iter()
+            .map(|arg_loc: &usize| *old_slice.get_value_mut_ref(*arg_loc))
+            .collect();
+
+        let frame: &mut FrameInfo = self.frames.get_unchecked_mut(frame_idx);
+        let frame_start: usize = frame.frame_start;
+        frame.frame_end = frame_start + frame_size;
+        frame.func_id = func_id;
+        frame.elided_frames += 1;
+
+        // the topmost frame always lives in the topmost segment
+        #[cfg(debug_assertions)]
+        self.values.truncate(frame_start);
+        self.values.resize(frame_start + frame_size, EMPTY_STACK_VALUE);
+
+        let mut new_slice: StackSlice = self.frame_slice(frame_idx);
+        let captures_len: usize = captures.len();
+        for i /*: usize*/ in 0..captures_len {
+            new_slice.set_value(i, *captures.get_unchecked(i));
+        }
+        for (i, arg) /*: (usize, Value)*/ in args.into_iter().enumerate() {
+            new_slice.set_value(i + captures_len, arg);
         }
-        StackSlice(new_slice_ptr)
+        new_slice
     }
 
     #[inline] pub unsafe fn done_func_call_shrink_stack0(&mut self) -> Option<(StackSlice, usize)> {
-        let frame_count = self.frames.len();
-        if frame_count == 1 {
+        let frame_count: usize = self.frames.len();
+        if frame_count == self.entry_frame + 1 {
             return None;
         }
 
-        let this_frame: &FrameInfo = self.frames.get_unchecked(frame_count - 1);
-        let prev_frame: &FrameInfo = self.frames.get_unchecked(frame_count - 2);
-        let prev_slice_ptr: *mut Value =
-            self.values.as_mut_ptr().offset(prev_frame.frame_start as isize);
-
-        let ret_addr: usize = this_frame.ret_addr;
-        self.values.truncate(prev_frame.frame_end);
-        self.frames.pop().unchecked_unwrap();
-        Some((StackSlice(prev_slice_ptr), ret_addr))
+        let prev_slice: StackSlice = self.frame_slice(frame_count - 2);
+        let ret_addr: usize = self.frames.get_unchecked(frame_count - 1).ret_addr;
+        self.pop_frame();
+        Some((prev_slice, ret_addr))
     }
 
     #[inline] pub unsafe fn done_func_call_shrink_stack1(
         &mut self,
         ret_value_src: usize
     ) -> Option<(StackSlice, usize)> {
-        let frame_count = self.frames.len();
-        if frame_count == 1 {
+        let frame_count: usize = self.frames.len();
+        if frame_count == self.entry_frame + 1 {
             return None;
         }
 
+        let this_slice: StackSlice = self.frame_slice(frame_count - 1);
+        let mut prev_slice: StackSlice = self.frame_slice(frame_count - 2);
         let this_frame: &FrameInfo = self.frames.get_unchecked(frame_count - 1);
-        let prev_frame: &FrameInfo = self.frames.get_unchecked(frame_count - 2);
 
-        let this_slice_ptr = self.values.as_ptr().offset(this_frame.frame_start as isize);
-        let prev_slice_ptr = self.values.as_mut_ptr().offset(prev_frame.frame_start as isize);
+        #[cfg(debug_assertions)]
+        assert_eq!(this_frame.ret_value_locs.as_ref().len(), 1);
 
         let ret_value_loc: usize = *this_frame.ret_value_locs.as_ref().get_unchecked(0);
-        *prev_slice_ptr.offset(ret_value_loc as isize)
-            = *this_slice_ptr.offset(ret_value_src as isize);
+        prev_slice.set_value(ret_value_loc, *this_slice.get_value_mut_ref(ret_value_src));
 
         let ret_addr: usize = this_frame.ret_addr;
-        self.values.truncate(prev_frame.frame_end);
-        self.frames.pop().unchecked_unwrap();
-        Some((StackSlice(prev_slice_ptr), ret_addr))
+        self.pop_frame();
+        Some((prev_slice, ret_addr))
     }
 
-    pub unsafe fn done_func_call_shrink_stack(
+    #[inline] pub unsafe fn done_func_call_shrink_stack(
         &mut self,
         ret_values: &[usize]
     ) -> Option<(StackSlice, usize)> {
-        let frame_count = self.frames.len();
-        if frame_count == 1 {
+        let frame_count: usize = self.frames.len();
+        if frame_count == self.entry_frame + 1 {
             return None;
         }
 
+        let this_slice: StackSlice = self.frame_slice(frame_count - 1);
+        let mut prev_slice: StackSlice = self.frame_slice(frame_count - 2);
         let this_frame: &FrameInfo = self.frames.get_unchecked(frame_count - 1);
-        let prev_frame: &FrameInfo = self.frames.get_unchecked(frame_count - 2);
-        let this_slice_ptr: *mut Value =
-            self.values.as_mut_ptr().offset(this_frame.frame_start as isize);
-        let prev_slice_ptr: *mut Value =
-            self.values.as_mut_ptr().offset(prev_frame.frame_start as isize);
-
-        let len: usize = ret_values.len();
-        for i /*: usize*/ in 0..len {
-            let ret_value_loc: usize = *this_frame.ret_value_locs.as_ref().get_unchecked(i);
+
+        #[cfg(debug_assertions)]
+        assert_eq!(ret_values.len(), this_frame.ret_value_locs.as_ref().len());
+
+        let ret_value_locs: &[usize] = this_frame.ret_value_locs.as_ref();
+        for i /*: usize*/ in 0..ret_values.len() {
+            let ret_value_loc: usize = *ret_value_locs.get_unchecked(i);
             let ret_value_src: usize = *ret_values.get_unchecked(i);
-            *prev_slice_ptr.offset(ret_value_loc as isize) =
-                *this_slice_ptr.offset(ret_value_src as isize);
+            prev_slice.set_value(ret_value_loc, *this_slice.get_value_mut_ref(ret_value_src));
         }
 
         let ret_addr: usize = this_frame.ret_addr;
-        self.values.truncate(prev_frame.frame_end);
-        self.frames.pop().unchecked_unwrap();
-        Some((StackSlice(prev_slice_ptr), ret_addr))
+        self.pop_frame();
+        Some((prev_slice, ret_addr))
     }
 
     #[inline] pub unsafe fn last_frame_slice(&mut self) -> StackSlice {
-        let frame: &FrameInfo = self.frames.last().unchecked_unwrap();
-        StackSlice(self.values.as_mut_ptr().offset(frame.frame_start as isize))
+        self.frame_slice(self.frames.len() - 1)
     }
 
     #[inline] pub unsafe fn unwind_shrink_slice(&mut self) {
-        let frame: FrameInfo = self.frames.pop().unchecked_unwrap();
-        self.values.truncate(frame.frame_start);
+        self.pop_frame();
+    }
+}
+
+#[cfg(any(feature = "bench", test))]
+impl Stack {
+    pub fn trace(&self) {
+        eprintln!("[STACK-TRACE] Begin stack tracing");
+        eprintln!("[STACK-TRACE] {{");
+        for (i, frame) /*: (usize, &FrameInfo)*/ in self.frames.iter().enumerate() {
+            let segment_idx: usize = self.segment_bases.partition_point(|base: &usize| *base <= i);
+            let segment: &Vec<StackValue> =
+                self.saved_segments.get(segment_idx).unwrap_or(&self.values);
+            eprintln!("[STACK-TRACE]     <frame {}: size = {}, ret_addr = {}, ret_val_locs = {:?}>",
+                      i,
+                      frame.frame_end - frame.frame_start,
+                      frame.ret_addr,
+                      unsafe { frame.ret_value_locs.as_ref() });
+            eprintln!("[STACK-TRACE]     [");
+            #[cfg(debug_assertions)]
+            for i /*: usize*/ in frame.frame_start..frame.frame_end {
+                if let Some(value /*: &Value*/) = &segment[i] {
+                    eprintln!("[STACK-TRACE]         [{}] = {:?}", i - frame.frame_start, value);
+                } else {
+                    eprintln!("[STACK-TRACE]         [{}] = UNINIT", i - frame.frame_start);
+                }
+            }
+            #[cfg(not(debug_assertions))]
+            for i /*: usize*/ in frame.frame_start..frame.frame_end {
+                let value: &Value = &segment[i];
+                eprintln!("[STACK-TRACE]         [{}] = {:?}", i - frame.frame_start, value);
+            }
+            eprintln!("[STACK-TRACE]     ]");
+        }
+        eprintln!("[STACK-TRACE] }}");
+        eprintln!("[STACK-TRACE] End stack tracing");
     }
 }
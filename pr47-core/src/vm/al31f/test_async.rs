@@ -1,18 +1,60 @@
 use std::any::TypeId;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use xjbutil::async_utils::block_on_future;
 use xjbutil::std_ext::ExpectSilentExt;
 use xjbutil::unchecked::UncheckedSendSync;
+use xjbutil::void::Void;
 
+use crate::builtins::closure::create_closure_vt;
 use crate::builtins::object::Object;
+#[cfg(feature = "al31f-builtin-ops")]
+use crate::builtins::weak::create_weak_vt;
 use crate::data::Value;
-use crate::data::exception::Exception;
-use crate::data::wrapper::DynBase;
+use crate::data::exception::{Exception, ExceptionInner, UncheckedException};
+use crate::data::generic::GenericTypeVT;
+use crate::data::traits::StaticBase;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
+use crate::data::wrapper::{DynBase, OwnershipInfo};
+use crate::ffi::{DataOption, Signature};
+use crate::ffi::convert::{CallError, ConversionError};
+use crate::ffi::sync_fn::{FunctionBase, VMContext};
 use crate::data::value_typed::{VALUE_TYPE_TAG_MASK, ValueTypeTag};
+use crate::vm::al31f::alloc::Alloc;
 use crate::vm::al31f::alloc::default_alloc::DefaultAlloc;
 use crate::vm::al31f::compiled::CompiledProgram;
-use crate::vm::al31f::executor::{VMThread, create_vm_main_thread, vm_thread_run_function};
-use crate::vm::al31f::test_program::{async_ffi_call_program, async_spawn_program, basic_fn_call_program, basic_program, exception_no_eh_program, exception_program, fibonacci_program, ffi_call_program, ffi_call_program2};
+use crate::vm::al31f::executor::{
+    VMThread,
+    create_vm_main_thread,
+    vm_call_function_sync,
+    vm_thread_run_function
+};
+use crate::vm::al31f::executor::unwinding::STACK_OVERFLOW_TRACE_DEPTH;
+use crate::vm::al31f::handle::RootedValue;
+use crate::vm::al31f::stack::{DEFAULT_MAX_FRAME_DEPTH, StackConfig};
+#[cfg(feature = "al31f-builtin-ops")] use crate::vm::al31f::Combustor;
+use crate::vm::al31f::test_program::{HostPoint, async_ffi_call_program, async_function_bind_program, async_spawn_program, basic_fn_call_program, basic_program, exception_no_eh_program, exception_program, fibonacci_program, ffi_call_program, ffi_call_program2, ffi_panic_program, method_call_program, overload_program, ownership_check_program, recursion_program, swap_program, tail_call_program, variadic_ffi_program};
+
+#[cfg(feature = "al31f-builtin-ops")] use crate::data::exception::{
+    FatalExceptionSet,
+    UncheckedExceptionKind
+};
+#[cfg(feature = "al31f-builtin-ops")] use crate::vm::al31f::test_program::{
+    closure_program,
+    finally_program,
+    Pr47Binder_checked_div,
+    Pr47Binder_lite_parse_int,
+    Pr47Binder_parse_int,
+    function_bind_program,
+    host_type_program,
+    lite_bind_program,
+    reentrant_program,
+    unchecked_exception_program,
+    unchecked_finally_program,
+    weak_ref_program
+};
 
 async fn basic_program_eval() {
     let program: CompiledProgram<DefaultAlloc> = basic_program::<>();
@@ -232,6 +274,269 @@ async fn async_spawn() {
     }
 }
 
+#[cfg(feature = "al31f-builtin-ops")]
+async fn weak_ref() {
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let object_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<Object>());
+    let weak_vt: GenericTypeVT = create_weak_vt(&mut tyck_info_pool, object_type);
+    let weak_ref_program: CompiledProgram<DefaultAlloc> =
+        weak_ref_program(NonNull::from(&weak_vt));
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &weak_ref_program).await;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 3);
+        unsafe {
+            assert!(!result[0].is_null());
+            assert_eq!(result[0].ptr_repr, result[1].ptr_repr);
+        }
+        assert!(result[2].is_null());
+    } else {
+        panic!()
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn finalizer() {
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let object_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<Object>());
+    let weak_vt: GenericTypeVT = create_weak_vt(&mut tyck_info_pool, object_type);
+    let weak_ref_program: CompiledProgram<DefaultAlloc> =
+        weak_ref_program(NonNull::from(&weak_vt));
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(alloc, &weak_ref_program).await;
+
+    let finalized: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    let finalized1: Arc<AtomicBool> = finalized.clone();
+    let garbage: Value = Value::new_owned::<String>("514".into());
+    let mut combustor: Combustor<DefaultAlloc> = Combustor::new(NonNull::from(&mut *vm_thread));
+    combustor.add_heap_managed(garbage);
+    combustor.add_finalizer(garbage, Box::new(move |context: &mut dyn VMContext, value: Value| {
+        // finalizers run with access to the VM, and may keep their objects alive
+        let rooted: RootedValue = context.root(value);
+        let value: &String = unsafe {
+            &*(rooted.value().get_as_mut_ptr::<String>() as *const String)
+        };
+        assert_eq!(value, "514");
+        finalized1.store(true, Ordering::SeqCst);
+    }));
+
+    // the program allocates enough objects to trigger collections
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    assert!(result.is_ok());
+    assert!(finalized.load(Ordering::SeqCst));
+}
+
+async fn overload_call() {
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let program: CompiledProgram<DefaultAlloc> = overload_program(&mut tyck_info_pool);
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 4);
+        unsafe {
+            assert_eq!(result[0].vt_data.inner.int_value, 2);
+            assert_eq!(result[1].vt_data.inner.int_value, 1);
+            assert_eq!(result[2].vt_data.inner.int_value, 2);
+            assert_eq!(result[3].vt_data.inner.int_value, 1);
+            // each call site caches the selection for its own argument types
+            for call_site /*: &OverloadCallSite*/ in program.overload_call_sites.iter() {
+                assert_eq!((*call_site.cache.get()).len(), 1);
+            }
+        }
+    } else {
+        panic!()
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn unchecked_exception_call() {
+    let program: CompiledProgram<DefaultAlloc> =
+        unchecked_exception_program(FatalExceptionSet::new());
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 1);
+        unsafe { assert_eq!(result[0].vt_data.inner.int_value, -1); }
+    } else {
+        panic!()
+    }
+
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value])
+        = (&mut vm_thread, 1, &[Value::new_float(5.14)]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 1);
+        unsafe { assert_eq!(result[0].vt_data.inner.float_value, 5.14); }
+    } else {
+        panic!()
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn unchecked_exception_fatal_call() {
+    let program: CompiledProgram<DefaultAlloc> = unchecked_exception_program(
+        FatalExceptionSet::new().with(UncheckedExceptionKind::DivideByZero)
+    );
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Err(e /*: Exception*/) = result {
+        assert!(matches!(e.inner, ExceptionInner::Unchecked(UncheckedException::DivideByZero)));
+        assert!(e.to_string().starts_with("division by zero"));
+    } else {
+        panic!()
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn finally_call() {
+    use crate::builtins::object::Object;
+    use crate::data::exception::StackTrace;
+
+    let program: CompiledProgram<DefaultAlloc> = finally_program();
+
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 1);
+        unsafe {
+            let object: &Object = &*(result[0].get_as_mut_ptr_norm() as *const _);
+            let finally: Value = object.fields.get().as_ref().unwrap()["finally"];
+            assert_eq!(finally.vt_data.inner.int_value, 1);
+        }
+    } else {
+        panic!()
+    }
+
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 1, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Err(e /*: Exception*/) = result {
+        let trace: Vec<(usize, usize)> = e.trace()
+            .iter()
+            .map(|trace: &StackTrace| (trace.func_id, trace.insc_ptr))
+            .collect();
+        assert_eq!(trace, vec![(3, 17), (2, 8), (1, 5)]);
+    } else {
+        panic!()
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn unchecked_finally_call() {
+    use crate::builtins::object::Object;
+    use crate::data::exception::StackTrace;
+
+    for fatal /*: bool*/ in [false, true] {
+        let fatal_exceptions: FatalExceptionSet = if fatal {
+            FatalExceptionSet::new()
+                .with(UncheckedExceptionKind::DivideByZero)
+                .with(UncheckedExceptionKind::MethodNotFound)
+        } else {
+            FatalExceptionSet::new()
+        };
+        let program: CompiledProgram<DefaultAlloc> = unchecked_finally_program(fatal_exceptions);
+
+        // `finally` runs for every exception, and the exception raised again is the original one
+        let object: Value = Value::new_owned(Object::new());
+        run_function_bind(&program, 0, &[object], |result: Result<Vec<Value>, Exception>| {
+            if let Err(e /*: Exception*/) = result {
+                assert!(matches!(
+                    e.inner,
+                    ExceptionInner::Unchecked(UncheckedException::DivideByZero)
+                ));
+                let trace: Vec<(usize, usize)> = e.trace()
+                    .iter()
+                    .map(|trace: &StackTrace| (trace.func_id, trace.insc_ptr))
+                    .collect();
+                assert_eq!(trace, vec![(0, 3)]);
+                unsafe {
+                    let object: &Object = &*(object.get_as_mut_ptr_norm() as *const _);
+                    let finally: Value = object.fields.get().as_ref().unwrap()["finally"];
+                    assert_eq!(finally.vt_data.inner.int_value, 1);
+                }
+            } else {
+                panic!()
+            }
+        }).await;
+
+        // catch-all handlers catch every exception, unless fatal
+        run_function_bind(&program, 1, &[Value::new_int(42)], |result| {
+            if fatal {
+                assert!(matches!(
+                    result,
+                    Err(Exception {
+                        inner: ExceptionInner::Unchecked(UncheckedException::MethodNotFound { .. }),
+                        ..
+                    })
+                ));
+            } else {
+                unsafe { assert_eq!(result.unwrap()[0].vt_data.inner.int_value, -1); }
+            }
+        }).await;
+    }
+}
+
 #[test] fn test_basic_program_eval() {
     block_on_future(basic_program_eval());
 }
@@ -252,6 +557,10 @@ async fn async_spawn() {
     block_on_future(exception_call());
 }
 
+#[test] fn test_overload_call() {
+    block_on_future(overload_call());
+}
+
 #[test] fn test_ffi_call() { block_on_future(ffi_call()); }
 
 #[test] fn test_ffi_call2() { block_on_future(ffi_call2()); }
@@ -266,3 +575,1253 @@ async fn async_spawn() {
     let fut = async_spawn();
     block_on_future(fut)
 }
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_weak_ref() {
+    block_on_future(weak_ref())
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_finalizer() {
+    block_on_future(finalizer())
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_unchecked_exception() {
+    block_on_future(unchecked_exception_call());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_unchecked_exception_fatal() {
+    block_on_future(unchecked_exception_fatal_call());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_finally() {
+    block_on_future(finally_call());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_unchecked_finally() {
+    block_on_future(unchecked_finally_call());
+}
+
+async fn ffi_panic() {
+    use crate::builtins::object::Object;
+
+    let program: CompiledProgram<DefaultAlloc> = ffi_panic_program();
+
+    let object: Value = Value::new_owned(Object::new());
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    vm_thread.vm.get_shared_data_mut().catch_foreign_panics = true;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 0, &[object]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Err(e /*: Exception*/) = result {
+        if let ExceptionInner::Unchecked(UncheckedException::ForeignPanic { message }) = &e.inner {
+            assert!(message.starts_with("cannot handle object"));
+        } else {
+            panic!()
+        }
+        assert_eq!(e.trace().len(), 1);
+        assert_eq!(e.trace()[0].func_id, 0);
+        assert_eq!(unsafe { object.ownership_info() }, OwnershipInfo::VMOwned);
+    } else {
+        panic!()
+    }
+
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, &program).await;
+    vm_thread.vm.get_shared_data_mut().catch_foreign_panics = true;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, 1, &[]);
+    let result: Result<Vec<Value>, Exception> = unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    };
+    if let Err(e /*: Exception*/) = result {
+        if let ExceptionInner::Unchecked(UncheckedException::ForeignPanic { message }) = &e.inner {
+            assert_eq!(message, "async panic");
+        } else {
+            panic!()
+        }
+        assert_eq!(e.trace()[0].func_id, 1);
+    } else {
+        panic!()
+    }
+}
+
+#[test] fn test_ffi_panic() {
+    block_on_future(ffi_panic());
+}
+
+async fn run_recursion(
+    program: &CompiledProgram<DefaultAlloc>,
+    stack_config: StackConfig,
+    func_id: usize,
+    args: &[Value]
+) -> Result<Vec<Value>, Exception> {
+    let alloc: DefaultAlloc = DefaultAlloc::new();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> = create_vm_main_thread(alloc, program).await;
+    vm_thread.stack.config = stack_config;
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, func_id, args);
+    unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    }
+}
+
+async fn stack_overflow() {
+    let program: CompiledProgram<DefaultAlloc> = recursion_program();
+
+    let result: Result<Vec<Value>, Exception> =
+        run_recursion(&program, StackConfig::default(), 0, &[]).await;
+    if let Err(e /*: Exception*/) = result {
+        if let ExceptionInner::Unchecked(
+            UncheckedException::StackOverflow { frame_depth, .. }
+        ) = &e.inner {
+            assert_eq!(*frame_depth, DEFAULT_MAX_FRAME_DEPTH);
+        } else {
+            panic!()
+        }
+        assert_eq!(e.trace().len(), STACK_OVERFLOW_TRACE_DEPTH);
+        assert_eq!(
+            e.trace().last().unwrap().elided_frames,
+            DEFAULT_MAX_FRAME_DEPTH - STACK_OVERFLOW_TRACE_DEPTH
+        );
+    } else {
+        panic!()
+    }
+
+    let small_stack: StackConfig = StackConfig {
+        max_values: 300,
+        ..StackConfig::default()
+    };
+    let result: Result<Vec<Value>, Exception> =
+        run_recursion(&program, small_stack, 1, &[Value::new_int(1000)]).await;
+    if let Err(e /*: Exception*/) = result {
+        if let ExceptionInner::Unchecked(
+            UncheckedException::StackOverflow { value_count, .. }
+        ) = &e.inner {
+            assert_eq!(*value_count, 300);
+        } else {
+            panic!()
+        }
+    } else {
+        panic!()
+    }
+}
+
+async fn segmented_stack() {
+    let program: CompiledProgram<DefaultAlloc> = recursion_program();
+
+    let segmented: StackConfig = StackConfig {
+        segment_size: Some(16),
+        ..StackConfig::default()
+    };
+    for _ in 0..2 {
+        let result: Result<Vec<Value>, Exception> =
+            run_recursion(&program, segmented, 1, &[Value::new_int(1000)]).await;
+        if let Ok(result /*: Vec<Value>*/) = result {
+            assert_eq!(result.len(), 1);
+            assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 1000);
+        } else {
+            panic!()
+        }
+    }
+
+    let result: Result<Vec<Value>, Exception> =
+        run_recursion(&program, segmented, 0, &[]).await;
+    assert!(result.is_err());
+}
+
+#[test] fn test_stack_overflow() {
+    block_on_future(stack_overflow());
+}
+
+#[test] fn test_segmented_stack() {
+    block_on_future(segmented_stack());
+}
+
+async fn tail_call() {
+    let program: CompiledProgram<DefaultAlloc> = tail_call_program();
+
+    // the frame of `sum` gets reused, so two frames are enough however deep it recurses
+    let two_frames: StackConfig = StackConfig {
+        max_frame_depth: 2,
+        ..StackConfig::default()
+    };
+    let result: Result<Vec<Value>, Exception> =
+        run_recursion(&program, two_frames, 1, &[Value::new_int(100_000)]).await;
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 1);
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 5_000_050_001);
+    } else {
+        panic!()
+    }
+
+    let one_frame: StackConfig = StackConfig {
+        max_frame_depth: 1,
+        segment_size: Some(4),
+        ..StackConfig::default()
+    };
+    let result: Result<Vec<Value>, Exception> =
+        run_recursion(&program, one_frame, 2, &[Value::new_int(1000)]).await;
+    if let Err(e /*: Exception*/) = result {
+        assert!(matches!(e.inner, ExceptionInner::Unchecked(UncheckedException::DivideByZero)));
+        assert_eq!(e.trace().len(), 1);
+        assert_eq!(e.trace()[0].func_id, 3);
+        assert_eq!(e.trace()[0].elided_frames, 1001);
+    } else {
+        panic!()
+    }
+}
+
+#[test] fn test_tail_call() {
+    block_on_future(tail_call());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn closure() {
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+    let func_type: NonNull<TyckInfo> = tyck_info_pool.create_function_type(&[], &[int_type], &[]);
+    let closure_vt: GenericTypeVT = create_closure_vt(&mut tyck_info_pool, func_type);
+    let program: CompiledProgram<DefaultAlloc> = closure_program(NonNull::from(&closure_vt));
+
+    let result: Result<Vec<Value>, Exception> =
+        run_recursion(&program, StackConfig::default(), 0, &[Value::new_int(10)]).await;
+    if let Ok(result /*: Vec<Value>*/) = result {
+        assert_eq!(result.len(), 3);
+        unsafe {
+            assert_eq!(result[0].vt_data.inner.int_value, 20);
+            assert_eq!(result[1].vt_data.inner.int_value, 20);
+            assert_eq!(result[2].vt_data.inner.int_value, 2);
+        }
+    } else {
+        panic!()
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_closure() {
+    block_on_future(closure());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn reentrant_call() {
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+    let func_type: NonNull<TyckInfo> =
+        tyck_info_pool.create_function_type(&[int_type], &[int_type], &[]);
+    let closure_vt: GenericTypeVT = create_closure_vt(&mut tyck_info_pool, func_type);
+    let program: CompiledProgram<DefaultAlloc> = reentrant_program(NonNull::from(&closure_vt));
+
+    // callbacks start new segments, which must not disturb frames in the segments below
+    let segmented: StackConfig = StackConfig {
+        segment_size: Some(4),
+        ..StackConfig::default()
+    };
+    for config /*: StackConfig*/ in [StackConfig::default(), segmented] {
+        let result: Result<Vec<Value>, Exception> =
+            run_recursion(&program, config, 0, &[Value::new_int(10)]).await;
+        if let Ok(result /*: Vec<Value>*/) = result {
+            assert_eq!(result.len(), 2);
+            unsafe {
+                assert_eq!(result[0].vt_data.inner.int_value, 16);
+                assert_eq!(result[1].vt_data.inner.int_value, 19);
+            }
+        } else {
+            panic!()
+        }
+
+        let result: Result<Vec<Value>, Exception> =
+            run_recursion(&program, config, 3, &[Value::new_int(1)]).await;
+        if let Err(e /*: Exception*/) = result {
+            assert!(matches!(e.inner, ExceptionInner::Unchecked(UncheckedException::DivideByZero)));
+            assert_eq!(e.trace().len(), 1);
+            assert_eq!(e.trace()[0].func_id, 3);
+        } else {
+            panic!()
+        }
+
+        let result: Result<Vec<Value>, Exception> =
+            run_recursion(&program, config, 4, &[Value::new_int(1)]).await;
+        if let Err(e /*: Exception*/) = result {
+            assert!(matches!(
+                e.inner,
+                ExceptionInner::Unchecked(UncheckedException::AwaitInCallback { .. })
+            ));
+        } else {
+            panic!()
+        }
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_reentrant_call() {
+    block_on_future(reentrant_call());
+}
+
+async fn run_function_bind(
+    program: &CompiledProgram<DefaultAlloc>,
+    func_id: usize,
+    args: &[Value],
+    check: impl FnOnce(Result<Vec<Value>, Exception>)
+) {
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), program).await;
+    for arg /*: &Value*/ in args.iter().filter(|arg: &&Value| arg.is_ref() && !arg.is_null()) {
+        unsafe { vm_thread.vm.get_shared_data_mut().alloc.add_managed(*arg); }
+    }
+    let arg_pack: (&mut VMThread<DefaultAlloc>, usize, &[Value]) = (&mut vm_thread, func_id, args);
+    // results and exceptions are only valid as long as the VM is alive
+    check(unsafe {
+        vm_thread_run_function::<_, false>(UncheckedSendSync::new(arg_pack))
+            .expect_silent("damn it")
+            .await
+            .into_inner()
+    });
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn function_bind() {
+    let program: CompiledProgram<DefaultAlloc> = function_bind_program();
+
+    let string: Value = Value::new_owned::<String>(" 42".into());
+    run_function_bind(&program, 0, &[string], |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 42);
+    }).await;
+
+    let string: Value = Value::new_owned::<String>("4x2".into());
+    run_function_bind(&program, 0, &[string], |result: Result<Vec<Value>, Exception>| {
+        if let Err(Exception { inner: ExceptionInner::Checked(checked), .. }) = result {
+            let type_id: TypeId = unsafe { (*checked.get_as_dyn_base()).dyn_type_id() };
+            assert_eq!(type_id, TypeId::of::<std::num::ParseIntError>());
+        } else {
+            panic!()
+        }
+    }).await;
+
+    run_function_bind(&program, 0, &[Value::new_null()], |result: Result<Vec<Value>, Exception>| {
+        assert!(matches!(
+            result.map_err(|e: Exception| e.inner),
+            Err(ExceptionInner::Unchecked(UncheckedException::UnexpectedNull { .. }))
+        ));
+    }).await;
+
+    let args: [Value; 2] = [Value::new_int(7), Value::new_int(2)];
+    run_function_bind(&program, 1, &args, |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 3);
+    }).await;
+
+    let args: [Value; 2] = [Value::new_int(7), Value::new_int(0)];
+    run_function_bind(&program, 1, &args, |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert!(result[0].is_null());
+    }).await;
+
+    run_function_bind(&program, 2, &[], |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 0);
+    }).await;
+
+    // passing the same object by reference and by value must not move it out while borrowed
+    let string: Value = Value::new_owned::<String>("ab".into());
+    run_function_bind(&program, 4, &[string], |result: Result<Vec<Value>, Exception>| {
+        assert!(matches!(
+            result.map_err(|e: Exception| e.inner),
+            Err(ExceptionInner::Unchecked(UncheckedException::OwnershipCheckFailure { .. }))
+        ));
+        unsafe {
+            assert_eq!(string.ownership_info_norm(), OwnershipInfo::VMOwned);
+            assert_eq!(&*(string.get_as_mut_ptr_norm() as *const String), "ab");
+        }
+    }).await;
+
+    // the string gets moved into Rust by the first call, so the second call fails
+    let args: [Value; 2] = [Value::new_owned::<String>("1".into()), Value::new_int(0)];
+    run_function_bind(&program, 3, &args, |result: Result<Vec<Value>, Exception>| {
+        assert!(matches!(
+            result.map_err(|e: Exception| e.inner),
+            Err(ExceptionInner::Unchecked(UncheckedException::OwnershipCheckFailure { .. }))
+        ));
+    }).await;
+
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let signature: Signature = Pr47Binder_checked_div::signature(&mut tyck_info_pool);
+    assert_eq!(signature.param_options.len(), 2);
+    assert!(matches!(signature.param_options[0], DataOption::Copy));
+    assert_eq!(signature.ret_option.len(), 1);
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_function_bind() {
+    block_on_future(function_bind());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn lite_bind() {
+    let program: CompiledProgram<DefaultAlloc> = lite_bind_program();
+
+    let string: Value = Value::new_owned::<String>(" 7".into());
+    run_function_bind(&program, 0, &[string], |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 7);
+    }).await;
+
+    let string: Value = Value::new_owned::<String>("x".into());
+    run_function_bind(&program, 0, &[string], |result: Result<Vec<Value>, Exception>| {
+        assert!(matches!(
+            result.map_err(|e: Exception| e.inner),
+            Err(ExceptionInner::Checked(_))
+        ));
+    }).await;
+
+    for (times, expected) in [(Value::new_int(3), "ababab"), (Value::new_null(), "ab")] {
+        let args: [Value; 2] = [Value::new_owned::<String>("ab".into()), times];
+        run_function_bind(&program, 1, &args, |result: Result<Vec<Value>, Exception>| {
+            let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+            let text: &String = unsafe { &*(result[0].get_as_mut_ptr_norm() as *const String) };
+            assert_eq!(text, expected);
+        }).await;
+    }
+
+    run_function_bind(&program, 2, &[], |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 0);
+    }).await;
+
+    // passing the same object by reference and by value must not move it out while borrowed
+    let string: Value = Value::new_owned::<String>("ab".into());
+    run_function_bind(&program, 3, &[string], |result: Result<Vec<Value>, Exception>| {
+        assert!(matches!(
+            result.map_err(|e: Exception| e.inner),
+            Err(ExceptionInner::Unchecked(UncheckedException::OwnershipCheckFailure { .. }))
+        ));
+        unsafe {
+            assert_eq!(string.ownership_info_norm(), OwnershipInfo::VMOwned);
+            assert_eq!(&*(string.get_as_mut_ptr_norm() as *const String), "ab");
+        }
+    }).await;
+
+    // the lite macro generates the same glue as the full one
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let lite: Signature = Pr47Binder_lite_parse_int::signature(&mut tyck_info_pool);
+    let full: Signature = Pr47Binder_parse_int::signature(&mut tyck_info_pool);
+    assert_eq!(lite.func_type, full.func_type);
+    assert!(matches!(lite.param_options[..], [DataOption::Move]));
+    assert!(matches!(lite.ret_option[..], [DataOption::Copy]));
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_lite_bind() {
+    block_on_future(lite_bind());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn host_type() {
+    let program: CompiledProgram<DefaultAlloc> = host_type_program();
+    let new_point = || Value::new_owned(HostPoint {
+        x: 3,
+        label: "point".into(),
+        payload: Value::new_null(),
+        history: vec![]
+    });
+
+    run_function_bind(&program, 0, &[new_point()], |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 3);
+        // `label` is not readable without allocation, thus only exposed by its getter
+        assert!(result[1].is_null());
+    }).await;
+
+    let args: [Value; 2] = [new_point(), Value::new_int(5)];
+    run_function_bind(&program, 1, &args, |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 5);
+    }).await;
+
+    run_function_bind(&program, 2, &[new_point()], |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        let label: &String = unsafe { &*(result[0].get_as_mut_ptr_norm() as *const String) };
+        assert_eq!(label, "point");
+    }).await;
+
+    assert_eq!(<Void as StaticBase<HostPoint>>::type_name(), "HostPoint");
+    let point: HostPoint = HostPoint {
+        x: 0,
+        label: String::new(),
+        payload: Value::new_int(1),
+        history: vec![Value::new_int(2), Value::new_int(3)]
+    };
+    let children: Vec<Value> = <Void as StaticBase<HostPoint>>::children(&point)
+        .unwrap()
+        .collect();
+    assert_eq!(children.len(), 3);
+
+    // fields of builtin container types are traced without `#[pr47(trace)]`
+    #[derive(pr47_codegen::Pr47Type)]
+    #[pr47(local)]
+    struct HostContainers {
+        vec: crate::builtins::vec::VMGenericVec,
+        closure: crate::builtins::closure::Closure
+    }
+    let containers: HostContainers = HostContainers {
+        vec: crate::builtins::vec::VMGenericVec {
+            inner: std::cell::UnsafeCell::new(vec![Value::new_int(1)])
+        },
+        closure: crate::builtins::closure::Closure::new(
+            smallvec::smallvec![Value::new_int(2), Value::new_int(3)],
+            0
+        )
+    };
+    let children: Vec<Value> = <Void as StaticBase<HostContainers>>::children(&containers)
+        .unwrap()
+        .collect();
+    assert_eq!(children.len(), 3);
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_host_type() {
+    block_on_future(host_type());
+}
+
+async fn async_function_bind() {
+    let program: CompiledProgram<DefaultAlloc> = async_function_bind_program();
+
+    let string: Value = Value::new_owned::<String>(" 42".into());
+    run_function_bind(&program, 0, &[string], |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 42);
+    }).await;
+
+    let string: Value = Value::new_owned::<String>("x".into());
+    run_function_bind(&program, 0, &[string], |result: Result<Vec<Value>, Exception>| {
+        if let Err(Exception { inner: ExceptionInner::Checked(checked), .. }) = result {
+            let type_id: TypeId = unsafe { (*checked.get_as_dyn_base()).dyn_type_id() };
+            assert_eq!(type_id, TypeId::of::<std::num::ParseIntError>());
+        } else {
+            panic!()
+        }
+    }).await;
+
+    run_function_bind(&program, 0, &[Value::new_null()], |result: Result<Vec<Value>, Exception>| {
+        assert!(matches!(
+            result.map_err(|e: Exception| e.inner),
+            Err(ExceptionInner::Unchecked(UncheckedException::UnexpectedNull { .. }))
+        ));
+    }).await;
+
+    // the string is mutably shared twice, which only works if the first share gets released
+    let args: [Value; 2] = [Value::new_owned::<String>("4".into()), Value::new_char('7')];
+    run_function_bind(&program, 1, &args, |result: Result<Vec<Value>, Exception>| {
+        let result: Vec<Value> = result.unwrap_or_else(|_| panic!());
+        assert_eq!(unsafe { result[0].vt_data.inner.int_value }, 477);
+    }).await;
+}
+
+#[test] fn test_async_function_bind() {
+    block_on_future(async_function_bind());
+}
+
+async fn typed_call() {
+    let program: CompiledProgram<DefaultAlloc> = swap_program();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+
+    let result: Result<(String, i64), CallError> = unsafe {
+        vm_thread.call::<(i64, String), (String, i64)>(0, (42, "answer".into())).await
+    };
+    assert_eq!(result.unwrap(), ("answer".to_string(), 42));
+
+    let result: Result<(Vec<Option<char>>, Option<f64>), CallError> = unsafe {
+        vm_thread.call::<(Option<f64>, Vec<Option<char>>), _>(0, (None, vec![Some('a'), None]))
+            .await
+    };
+    assert_eq!(result.unwrap(), (vec![Some('a'), None], None));
+
+    let result: Result<(HostPoint, Option<i64>), CallError> = unsafe {
+        vm_thread.call::<_, (HostPoint, Option<i64>)>(0, (
+            Some(13),
+            HostPoint { x: 7, label: "p".into(), payload: Value::new_null(), history: vec![] }
+        )).await
+    };
+    let (point, int): (HostPoint, Option<i64>) = result.unwrap();
+    assert_eq!((point.x, point.label.as_str(), int), (7, "p", Some(13)));
+
+    let result: Result<(bool, i64), CallError> = unsafe {
+        vm_thread.call::<(i64, i64), (bool, i64)>(0, (1, 2)).await
+    };
+    assert!(matches!(
+        result,
+        Err(CallError::Conversion(ConversionError::Element { index: 0, inner }))
+            if *inner == ConversionError::TypeMismatch { expected: "bool".into() }
+    ));
+
+    let result: Result<(Vec<i64>, i64), CallError> = unsafe {
+        vm_thread.call::<(i64, Vec<Option<i64>>), (Vec<i64>, i64)>(0, (1, vec![Some(1), None]))
+            .await
+    };
+    assert!(matches!(
+        result,
+        Err(CallError::Conversion(ConversionError::Element { index: 0, inner }))
+            if *inner == ConversionError::Element {
+                index: 1,
+                inner: Box::new(ConversionError::UnexpectedNull)
+            }
+    ));
+
+    let result: Result<(i64,), CallError> = unsafe {
+        vm_thread.call::<(i64, i64), (i64,)>(0, (1, 2)).await
+    };
+    assert!(matches!(
+        result,
+        Err(CallError::Conversion(ConversionError::CountMismatch { expected: 1, got: 2 }))
+    ));
+
+    let result: Result<(i64, i64), CallError> = unsafe {
+        vm_thread.call::<(i64,), (i64, i64)>(0, (1,)).await
+    };
+    assert!(matches!(
+        result.map_err(|e: CallError| if let CallError::Exception(e) = e { e.inner } else {
+            panic!()
+        }),
+        Err(ExceptionInner::Unchecked(UncheckedException::ArgCountMismatch { .. }))
+    ));
+}
+
+#[test] fn test_typed_call() {
+    block_on_future(typed_call());
+}
+
+#[test] fn test_typed_call_sync() {
+    let program: CompiledProgram<DefaultAlloc> = swap_program();
+    let result: Result<(String, Vec<String>), CallError> = unsafe {
+        vm_call_function_sync::<(Vec<String>, String), (String, Vec<String>), _>(
+            DefaultAlloc::new(), &program, 0, (vec!["a".into(), "b".into()], "c".into())
+        )
+    };
+    assert_eq!(result.unwrap(), ("c".to_string(), vec!["a".to_string(), "b".to_string()]));
+}
+
+#[test] fn test_conversion_gc() {
+    use smallvec::SmallVec;
+    use crate::builtins::closure::Closure;
+    use crate::builtins::vec::{VMGenericVec, create_vm_vec_vt, vm_vec_ctor};
+    use crate::ffi::convert::{FromValue, FromValues, IntoValues};
+
+    // converted values are not rooted, so no collection may happen until all of them get created
+    let mut alloc: DefaultAlloc = DefaultAlloc::new();
+    alloc.set_max_debt(1, 128);
+    alloc.set_gc_allowed(true);
+    let values: Vec<Value> = (
+        vec!["114".to_string(), "514".into(), "1919810".into()],
+        "893".to_string()
+    ).into_values(&mut alloc);
+    assert!(alloc.gc_allowed());
+    assert_eq!(alloc.stats().collections, 0);
+    assert_eq!(alloc.stats().managed_count, 5);
+    let (strings, string): (Vec<String>, String) = unsafe {
+        FromValues::from_values(&values).unwrap()
+    };
+    assert_eq!(strings, vec!["114", "514", "1919810"]);
+    assert_eq!(string, "893");
+
+    // vectors created with `CREATE-CONTAINER` are containers instead of plain objects
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+    let vec_vt: GenericTypeVT = create_vm_vec_vt(&mut tyck_info_pool, any_type);
+    let func_type: NonNull<TyckInfo> = tyck_info_pool.create_function_type(&[], &[], &[]);
+    let closure_vt: GenericTypeVT = create_closure_vt(&mut tyck_info_pool, func_type);
+    unsafe {
+        let vec: Value = Value::new_container(vm_vec_ctor(), &vec_vt);
+        alloc.add_managed(vec);
+        let inner: &VMGenericVec = &*(vec.get_as_mut_ptr() as *const VMGenericVec);
+        inner.inner.get().as_mut().unwrap().extend([Value::new_int(4), Value::new_int(2)]);
+        assert_eq!(Vec::<i64>::from_value(vec), Ok(vec![4, 2]));
+
+        let closure: Value = Closure::new(SmallVec::new(), 0).into_value(&closure_vt);
+        alloc.add_managed(closure);
+        assert_eq!(
+            Vec::<i64>::from_value(closure),
+            Err(ConversionError::TypeMismatch { expected: "vector".into() })
+        );
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn ffi_registry() {
+    use crate::vm::al31f::registry::{FfiImport, FfiKind, FfiRegistry, LinkError, ResolvedCall};
+    use crate::vm::al31f::test_program::{
+        PR47BINDER_ASYNC_PARSE_INT,
+        PR47BINDER_CHECKED_DIV,
+        PR47BINDER_FIELD_COUNT,
+        PR47BINDER_PARSE_INT
+    };
+
+    let mut registry: FfiRegistry<DefaultAlloc> = FfiRegistry::new();
+    registry.register("test.parse_int", PR47BINDER_PARSE_INT).unwrap();
+    registry.register("test.checked_div", PR47BINDER_CHECKED_DIV).unwrap();
+    registry.register("test.field_count", PR47BINDER_FIELD_COUNT).unwrap();
+    registry.register_async("test.async_parse_int", PR47BINDER_ASYNC_PARSE_INT).unwrap();
+    assert_eq!(
+        registry.register("test.async_parse_int", PR47BINDER_PARSE_INT).err(),
+        Some(LinkError::DuplicateSymbol { name: "test.async_parse_int".into() })
+    );
+
+    // resolving calls at compile time
+    let int_type: NonNull<TyckInfo> =
+        registry.tyck_info_pool().create_plain_type(TypeId::of::<i64>());
+    let string_type: NonNull<TyckInfo> = registry.tyck_info_pool().get_string_type();
+    let resolved: ResolvedCall =
+        registry.resolve_call("test.checked_div", &[int_type, int_type]).ok().unwrap();
+    assert_eq!(resolved.kind, FfiKind::Sync);
+    assert_eq!(
+        resolved.func_type,
+        registry.get("test.checked_div").unwrap().signature.as_ref().map(|s| s.func_type)
+    );
+    let resolved: ResolvedCall =
+        registry.resolve_call("test.async_parse_int", &[string_type]).ok().unwrap();
+    assert_eq!(resolved.kind, FfiKind::Async);
+    assert_eq!(
+        registry.resolve_call("test.checked_div", &[int_type]).err(),
+        Some(LinkError::ArgCountMismatch {
+            name: "test.checked_div".into(),
+            min: 2,
+            max: Some(2),
+            got: 1
+        })
+    );
+    assert_eq!(
+        registry.resolve_call("test.checked_div", &[string_type, int_type]).err(),
+        Some(LinkError::ArgTypeMismatch {
+            name: "test.checked_div".into(),
+            index: 0,
+            expected: "int".into(),
+            found: "string".into()
+        })
+    );
+    assert_eq!(
+        registry.resolve_call("test.missing", &[]).err(),
+        Some(LinkError::MissingSymbol { name: "test.missing".into(), kind: FfiKind::Sync })
+    );
+
+    // linking code compiled against another pool
+    let mut tyck_info_pool: TyckInfoPool = TyckInfoPool::new();
+    let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+    let nullable_int_type: NonNull<TyckInfo> = tyck_info_pool.create_nullable_type(int_type);
+    let div_type: NonNull<TyckInfo> =
+        tyck_info_pool.create_function_type(&[int_type, int_type], &[nullable_int_type], &[]);
+    let wrong_div_type: NonNull<TyckInfo> =
+        tyck_info_pool.create_function_type(&[int_type, int_type], &[int_type], &[]);
+    let parse_type: Option<NonNull<TyckInfo>> = registry.get("test.parse_int").unwrap()
+        .signature.as_ref().map(|s| s.func_type);
+    let count_type: Option<NonNull<TyckInfo>> = registry.get("test.field_count").unwrap()
+        .signature.as_ref().map(|s| s.func_type);
+
+    let errors: Vec<LinkError> = registry.link(&[
+        FfiImport::new("test.parse_int", FfiKind::Sync, parse_type),
+        FfiImport::new("test.checked_div", FfiKind::Sync, Some(wrong_div_type)),
+        FfiImport::new("test.async_parse_int", FfiKind::Sync, None)
+    ]).err().unwrap();
+    assert_eq!(errors, vec![
+        LinkError::SignatureMismatch {
+            name: "test.checked_div".into(),
+            expected: "fn(int, int) -> (int)".into(),
+            found: "fn(int, int) -> (int?)".into()
+        },
+        LinkError::MissingSymbol { name: "test.async_parse_int".into(), kind: FfiKind::Sync }
+    ]);
+
+    let mut program: CompiledProgram<DefaultAlloc> = function_bind_program();
+    program.ffi_funcs = Box::new([]);
+    registry.link_program(&mut program, &[
+        FfiImport::new("test.parse_int", FfiKind::Sync, parse_type),
+        FfiImport::new("test.checked_div", FfiKind::Sync, Some(div_type)),
+        FfiImport::new("test.field_count", FfiKind::Sync, count_type)
+    ]).unwrap();
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(String,), (i64,)>(0, (" 42".into(),)).await };
+    assert_eq!(result.unwrap(), (42,));
+    let result: Result<(Option<i64>,), CallError> =
+        unsafe { vm_thread.call::<(i64, i64), (Option<i64>,)>(1, (7, 0)).await };
+    assert_eq!(result.unwrap(), (None,));
+
+    #[cfg(feature = "std47")]
+    {
+        let mut registry: FfiRegistry<DefaultAlloc> = FfiRegistry::new();
+        crate::std47::register_std47(&mut registry).unwrap();
+        let int_type: NonNull<TyckInfo> =
+            registry.tyck_info_pool().create_plain_type(TypeId::of::<i64>());
+        assert!(registry.resolve_call("std.time.duration_for_millis", &[int_type]).is_ok());
+        assert_eq!(
+            registry.resolve_call("std.futures.sleep_ms", &[int_type]).ok().unwrap().kind,
+            FfiKind::Async
+        );
+        let string_type: NonNull<TyckInfo> = registry.tyck_info_pool().get_string_type();
+        assert!(registry.resolve_call("std.io.print", &[]).is_ok());
+        assert!(registry.resolve_call("std.io.print", &[int_type, string_type]).is_ok());
+        let promise_type: NonNull<TyckInfo> = registry.tyck_info_pool()
+            .create_plain_type(TypeId::of::<crate::ffi::async_fn::PromiseType>());
+        assert!(registry.resolve_call("std.futures.join", &[promise_type, promise_type]).is_ok());
+        assert_eq!(
+            registry.resolve_call("std.futures.select", &[promise_type, int_type]).err(),
+            Some(LinkError::ArgTypeMismatch {
+                name: "std.futures.select".into(),
+                index: 1,
+                expected: "promise".into(),
+                found: "int".into()
+            })
+        );
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_ffi_registry() {
+    block_on_future(ffi_registry());
+}
+
+async fn ffi_variadic() {
+    use crate::ffi::sync_fn::ClosureFunction;
+    use crate::vm::al31f::registry::{FfiImport, FfiKind, FfiRegistry, LinkError};
+
+    // sum(int, [int], ...int) -> (int)
+    let mut registry: FfiRegistry<DefaultAlloc> = FfiRegistry::new();
+    registry.register("test.sum", ClosureFunction::new(
+        |tyck_info_pool: &mut TyckInfoPool| {
+            let int_type: NonNull<TyckInfo> =
+                tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+            Signature {
+                func_type: tyck_info_pool.create_function_type_with_tail(
+                    &[int_type, int_type], 1, Some(int_type), &[int_type], &[]
+                ),
+                param_options: Box::new([DataOption::Copy, DataOption::Copy, DataOption::Copy]),
+                ret_option: Box::new([DataOption::Copy])
+            }
+        },
+        |_context, args: &[Value]| {
+            let sum: i64 = args.iter()
+                .map(|arg: &Value| unsafe { arg.vt_data.inner.int_value })
+                .sum();
+            Ok(vec![Value::new_int(sum)])
+        }
+    )).unwrap();
+
+    let int_type: NonNull<TyckInfo> =
+        registry.tyck_info_pool().create_plain_type(TypeId::of::<i64>());
+    let char_type: NonNull<TyckInfo> =
+        registry.tyck_info_pool().create_plain_type(TypeId::of::<char>());
+    for arg_count in 1..=4 {
+        assert!(registry.resolve_call("test.sum", &vec![int_type; arg_count]).is_ok());
+    }
+    assert_eq!(
+        registry.resolve_call("test.sum", &[]).err(),
+        Some(LinkError::ArgCountMismatch { name: "test.sum".into(), min: 1, max: None, got: 0 })
+    );
+    assert_eq!(
+        registry.resolve_call("test.sum", &[int_type, int_type, char_type]).err(),
+        Some(LinkError::ArgTypeMismatch {
+            name: "test.sum".into(),
+            index: 2,
+            expected: "int".into(),
+            found: "char".into()
+        })
+    );
+
+    let sum_type: Option<NonNull<TyckInfo>> = registry.get("test.sum").unwrap()
+        .signature.as_ref().map(|s| s.func_type);
+    assert_eq!(
+        unsafe { sum_type.unwrap().as_ref() }.to_string(),
+        "fn(int, [int], ...int) -> (int)"
+    );
+    let mut program: CompiledProgram<DefaultAlloc> = variadic_ffi_program();
+    registry.link_program(&mut program, &[FfiImport::new("test.sum", FfiKind::Sync, sum_type)])
+        .unwrap();
+    assert!(program.ffi_funcs[0].variadic_check().is_some());
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(i64, i64, i64), (i64,)>(0, (1, 2, 3)).await };
+    assert_eq!(result.unwrap(), (6,));
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(i64,), (i64,)>(1, (42,)).await };
+    assert_eq!(result.unwrap(), (42,));
+
+    // the variadic tail gets checked by `FFICallRtlc`
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(i64, i64, char), (i64,)>(0, (1, 2, 'c')).await };
+    assert!(matches!(
+        result,
+        Err(CallError::Exception(Exception {
+            inner: ExceptionInner::Unchecked(UncheckedException::TypeCheckFailure { .. }),
+            ..
+        }))
+    ));
+}
+
+#[test] fn test_ffi_variadic() {
+    block_on_future(ffi_variadic());
+}
+
+async fn ffi_method() {
+    use crate::ffi::sync_fn::ClosureFunction;
+    use crate::vm::al31f::registry::{FfiImport, FfiKind, FfiRegistry, LinkError, ResolvedCall};
+
+    // HostPoint.norm(HostPoint) -> (int), String.norm(<untyped>),
+    // ParseIntError.norm(ParseIntError, int) -> (int)
+    let mut registry: FfiRegistry<DefaultAlloc> = FfiRegistry::new();
+    let point_norm = || ClosureFunction::new(
+        |tyck_info_pool: &mut TyckInfoPool| {
+            let point_type: NonNull<TyckInfo> =
+                <Void as StaticBase<HostPoint>>::tyck_info(tyck_info_pool);
+            let int_type: NonNull<TyckInfo> =
+                tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+            Signature {
+                func_type: tyck_info_pool.create_function_type(&[point_type], &[int_type], &[]),
+                param_options: Box::new([DataOption::Share]),
+                ret_option: Box::new([DataOption::Copy])
+            }
+        },
+        |_context, args: &[Value]| {
+            let point: &HostPoint = unsafe { &*(args[0].get_as_mut_ptr_norm() as *const _) };
+            Ok(vec![Value::new_int(point.x * 2)])
+        }
+    );
+    registry.register_method::<HostPoint>("norm", point_norm()).unwrap();
+    registry.register_method_untyped::<String>("norm", ClosureFunction::untyped(
+        |_context, args: &[Value]| {
+            let string: &String = unsafe { &*(args[0].get_as_mut_ptr_norm() as *const _) };
+            Ok(vec![Value::new_int(string.len() as i64)])
+        }
+    )).unwrap();
+    registry.register_method::<std::num::ParseIntError>("norm", ClosureFunction::new(
+        |tyck_info_pool: &mut TyckInfoPool| {
+            let error_type: NonNull<TyckInfo> =
+                <Void as StaticBase<std::num::ParseIntError>>::tyck_info(tyck_info_pool);
+            let int_type: NonNull<TyckInfo> =
+                tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+            Signature {
+                func_type: tyck_info_pool.create_function_type(
+                    &[error_type, int_type],
+                    &[int_type],
+                    &[]
+                ),
+                param_options: Box::new([DataOption::Share, DataOption::Copy]),
+                ret_option: Box::new([DataOption::Copy])
+            }
+        },
+        |_context, args: &[Value]| Ok(vec![args[1]])
+    )).unwrap();
+    assert_eq!(
+        registry.register_method::<HostPoint>("norm", point_norm()).err(),
+        Some(LinkError::DuplicateMethod { type_name: "HostPoint".into(), name: "norm".into() })
+    );
+    // methods of different types and free functions live in different namespaces
+    registry.register_untyped("norm", ClosureFunction::untyped(|_context, _args| Ok(vec![])))
+        .unwrap();
+
+    let point_type: NonNull<TyckInfo> =
+        <Void as StaticBase<HostPoint>>::tyck_info(registry.tyck_info_pool());
+    let int_type: NonNull<TyckInfo> =
+        registry.tyck_info_pool().create_plain_type(TypeId::of::<i64>());
+    let any_type: NonNull<TyckInfo> = registry.tyck_info_pool().get_any_type();
+
+    let resolved: ResolvedCall = registry.resolve_method_call(point_type, "norm", &[])
+        .ok()
+        .unwrap();
+    assert_eq!(resolved.kind, FfiKind::Sync);
+    let norm_type: Option<NonNull<TyckInfo>> = resolved.func_type;
+    assert!(norm_type.is_some());
+    let resolved: ResolvedCall = registry.resolve_method_call(any_type, "norm", &[])
+        .ok()
+        .unwrap();
+    assert_eq!(resolved.kind, FfiKind::Method);
+    assert!(resolved.func_type.is_none());
+    assert_eq!(
+        registry.resolve_method_call(point_type, "norm", &[int_type]).err(),
+        Some(LinkError::ArgCountMismatch { name: "norm".into(), min: 1, max: Some(1), got: 2 })
+    );
+    assert_eq!(
+        registry.resolve_method_call(int_type, "norm", &[]).err(),
+        Some(LinkError::MissingMethod { type_name: "int".into(), name: "norm".into() })
+    );
+    assert_eq!(
+        registry.resolve_method_call(any_type, "cancel", &[]).err(),
+        Some(LinkError::MissingMethod { type_name: "any".into(), name: "cancel".into() })
+    );
+
+    let point_type_id: TypeId = <Void as StaticBase<HostPoint>>::type_id();
+    let mut program: CompiledProgram<DefaultAlloc> = method_call_program();
+    assert_eq!(
+        registry.link(&[FfiImport::method(point_type_id, "cancel", None)]).err(),
+        Some(vec![LinkError::MissingMethod { type_name: "HostPoint".into(), name: "cancel".into() }])
+    );
+    registry.link_program(&mut program, &[
+        FfiImport::method(point_type_id, "norm", norm_type),
+        FfiImport::dyn_method("norm")
+    ]).unwrap();
+    assert_eq!(program.method_tables[0].name, "norm");
+    assert_eq!(program.method_tables[0].impls.len(), 3);
+    drop(registry);
+
+    let new_point = || HostPoint {
+        x: 3,
+        label: "point".into(),
+        payload: Value::new_null(),
+        history: vec![]
+    };
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(HostPoint,), (i64,)>(1, (new_point(),)).await };
+    assert_eq!(result.unwrap(), (6,));
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(HostPoint,), (i64,)>(0, (new_point(),)).await };
+    assert_eq!(result.unwrap(), (6,));
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(String,), (i64,)>(0, ("hello".into(),)).await };
+    assert_eq!(result.unwrap(), (5,));
+
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(i64,), (i64,)>(0, (42,)).await };
+    if let Err(CallError::Exception(Exception {
+        inner: ExceptionInner::Unchecked(exception),
+        ..
+    })) = result {
+        assert_eq!(exception.to_string(), "no method `norm` for int 42");
+    } else {
+        panic!()
+    }
+    // the implementation for `ParseIntError` takes one more argument than the call site passes
+    let parse_error: std::num::ParseIntError = "x".parse::<i64>().unwrap_err();
+    let result: Result<(i64,), CallError> = unsafe {
+        vm_thread.call::<(std::num::ParseIntError,), (i64,)>(0, (parse_error,)).await
+    };
+    assert!(matches!(
+        result,
+        Err(CallError::Exception(Exception {
+            inner: ExceptionInner::Unchecked(UncheckedException::ArgCountMismatch {
+                func_id: 0,
+                expected: 2,
+                got: 1
+            }),
+            ..
+        }))
+    ));
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(Option<HostPoint>,), (i64,)>(0, (None,)).await };
+    assert!(matches!(
+        result,
+        Err(CallError::Exception(Exception {
+            inner: ExceptionInner::Unchecked(UncheckedException::UnexpectedNull { .. }),
+            ..
+        }))
+    ));
+}
+
+#[test] fn test_ffi_method() {
+    block_on_future(ffi_method());
+}
+
+async fn ownership_check() {
+    let program: CompiledProgram<DefaultAlloc> = ownership_check_program();
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+
+    let result: Result<(), CallError> =
+        unsafe { vm_thread.call::<(String,), ()>(0, ("owned".into(),)).await };
+    assert!(result.is_ok());
+    let result: Result<(), CallError> = unsafe { vm_thread.call::<(i64,), ()>(0, (42,)).await };
+    assert!(matches!(
+        result,
+        Err(CallError::Exception(Exception {
+            inner: ExceptionInner::Unchecked(UncheckedException::OwnershipCheckFailure { .. }),
+            ..
+        }))
+    ));
+    let result: Result<(), CallError> =
+        unsafe { vm_thread.call::<(Option<String>,), ()>(0, (None,)).await };
+    assert!(matches!(
+        result,
+        Err(CallError::Exception(Exception {
+            inner: ExceptionInner::Unchecked(UncheckedException::UnexpectedNull { .. }),
+            ..
+        }))
+    ));
+}
+
+#[test] fn test_ownership_check() {
+    block_on_future(ownership_check());
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+async fn ffi_closure_registry() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use crate::ffi::FFIException;
+    use crate::ffi::convert::FromValue;
+    use crate::ffi::async_fn::{AsyncClosureFunction, AsyncFunctionBase};
+    use crate::ffi::sync_fn::{ClosureFunction, Function, VMContext};
+    use crate::vm::al31f::AsyncCombustor;
+    use crate::vm::al31f::registry::{FfiImport, FfiKind, FfiRegistry, SyncFunction};
+    use crate::vm::al31f::test_program::{PR47BINDER_FIELD_COUNT, Pr47Binder_async_parse_int};
+
+    struct Divider {
+        calls: AtomicI64
+    }
+
+    impl<CTX: VMContext> Function<CTX> for Divider {
+        fn signature(&self, tyck_info_pool: &mut TyckInfoPool) -> Option<Signature> {
+            Some(<Pr47Binder_checked_div as FunctionBase>::signature(tyck_info_pool))
+        }
+
+        unsafe fn call_rtlc(
+            &self,
+            context: &mut CTX,
+            args: &[Value],
+            rets: &[*mut Value]
+        ) -> Result<(), FFIException> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            <Pr47Binder_checked_div as FunctionBase>::call_rtlc(context, args, rets)
+        }
+
+        unsafe fn call_unchecked(
+            &self,
+            context: &mut CTX,
+            args: &[Value],
+            rets: &[*mut Value]
+        ) -> Result<(), FFIException> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            <Pr47Binder_checked_div as FunctionBase>::call_unchecked(context, args, rets)
+        }
+    }
+
+    let parse_calls: Arc<AtomicI64> = Arc::new(AtomicI64::new(0));
+    let divider: Arc<Divider> = Arc::new(Divider { calls: AtomicI64::new(0) });
+
+    let mut registry: FfiRegistry<DefaultAlloc> = FfiRegistry::new();
+    let calls: Arc<AtomicI64> = parse_calls.clone();
+    // untyped closures registered by `register` have no signature either
+    registry.register("test.parse_int", ClosureFunction::untyped(
+        move |_context, args: &[Value]| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let text: String = unsafe { String::from_value(args[0]) }.unwrap();
+            Ok(vec![Value::new_int(text.trim().parse().unwrap())])
+        }
+    )).unwrap();
+    registry.register("test.checked_div", divider.clone() as Arc<SyncFunction<DefaultAlloc>>)
+        .unwrap();
+    registry.register("test.field_count", PR47BINDER_FIELD_COUNT).unwrap();
+    assert!(registry.get("test.parse_int").unwrap().signature.is_none());
+    assert!(registry.get("test.checked_div").unwrap().func.is_shared());
+    assert!(!registry.get("test.field_count").unwrap().func.is_shared());
+    assert_eq!(Arc::strong_count(&parse_calls), 2);
+    assert_eq!(Arc::strong_count(&divider), 2);
+
+    let div_type: Option<NonNull<TyckInfo>> = registry.get("test.checked_div").unwrap()
+        .signature.as_ref().map(|s| s.func_type);
+    let mut program: CompiledProgram<DefaultAlloc> = function_bind_program();
+    registry.link_program(&mut program, &[
+        FfiImport::new("test.parse_int", FfiKind::Sync, None),
+        FfiImport::new("test.checked_div", FfiKind::Sync, div_type)
+    ]).unwrap();
+    assert_eq!(Arc::strong_count(&divider), 3);
+
+    // the registry may go away once programs are linked
+    drop(registry);
+    assert_eq!(Arc::strong_count(&parse_calls), 2);
+    assert_eq!(Arc::strong_count(&divider), 2);
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+    for _ in 0..2 {
+        let result: Result<(i64,), CallError> =
+            unsafe { vm_thread.call::<(String,), (i64,)>(0, (" 42".into(),)).await };
+        assert_eq!(result.unwrap(), (42,));
+    }
+    let result: Result<(Option<i64>,), CallError> =
+        unsafe { vm_thread.call::<(i64, i64), (Option<i64>,)>(1, (7, 2)).await };
+    assert_eq!(result.unwrap(), (Some(3),));
+    assert_eq!(parse_calls.load(Ordering::SeqCst), 2);
+    assert_eq!(divider.calls.load(Ordering::SeqCst), 1);
+
+    drop(vm_thread);
+    drop(program);
+    assert_eq!(Arc::strong_count(&parse_calls), 1);
+    assert_eq!(Arc::strong_count(&divider), 1);
+
+    // async closures
+    let mut registry: FfiRegistry<DefaultAlloc> = FfiRegistry::new();
+    let calls: Arc<AtomicI64> = parse_calls.clone();
+    registry.register_async("test.async_parse_int", AsyncClosureFunction::new(
+        <Pr47Binder_async_parse_int as AsyncFunctionBase>::signature,
+        move |context: &AsyncCombustor<DefaultAlloc>, args: &[Value]| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            unsafe { <Pr47Binder_async_parse_int as AsyncFunctionBase>::call_rtlc(context, args) }
+        }
+    )).unwrap();
+
+    let parse_type: Option<NonNull<TyckInfo>> = registry.get_async("test.async_parse_int")
+        .unwrap()
+        .signature.as_ref().map(|s| s.func_type);
+    let mut program: CompiledProgram<DefaultAlloc> = async_function_bind_program();
+    registry.link_program(&mut program, &[
+        FfiImport::new("test.async_parse_int", FfiKind::Async, parse_type)
+    ]).unwrap();
+    drop(registry);
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(String,), (i64,)>(0, ("17".into(),)).await };
+    assert_eq!(result.unwrap(), (17,));
+    assert_eq!(parse_calls.load(Ordering::SeqCst), 3);
+
+    drop(vm_thread);
+    drop(program);
+    assert_eq!(Arc::strong_count(&parse_calls), 1);
+
+    // closures returning the wrong count of values fail the call instead of aborting the VM
+    let mut registry: FfiRegistry<DefaultAlloc> = FfiRegistry::new();
+    registry.register("test.parse_int", ClosureFunction::untyped(
+        |_context, _args: &[Value]| Ok(vec![Value::new_int(1), Value::new_int(2)])
+    )).unwrap();
+    let mut program: CompiledProgram<DefaultAlloc> = function_bind_program();
+    registry.link_program(&mut program, &[
+        FfiImport::new("test.parse_int", FfiKind::Sync, None)
+    ]).unwrap();
+    drop(registry);
+
+    let mut vm_thread: Box<VMThread<DefaultAlloc>> =
+        create_vm_main_thread(DefaultAlloc::new(), &program).await;
+    let result: Result<(i64,), CallError> =
+        unsafe { vm_thread.call::<(String,), (i64,)>(0, ("17".into(),)).await };
+    assert!(matches!(
+        result,
+        Err(CallError::Exception(Exception {
+            inner: ExceptionInner::Unchecked(UncheckedException::ForeignPanic { .. }),
+            ..
+        }))
+    ));
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+#[test] fn test_ffi_closure_registry() {
+    block_on_future(ffi_closure_registry());
+}
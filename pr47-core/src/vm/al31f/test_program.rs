@@ -1,16 +1,47 @@
+use std::any::TypeId;
+use std::num::ParseIntError;
+use std::ptr::NonNull;
+
+use pr47_codegen::{Pr47Type, pr47_function_bind};
+#[cfg(test)] use pr47_codegen_lite::pr47_function_bind as pr47_function_bind_lite;
 use xjbutil::boxed_slice;
 use xjbutil::slice_arena::SliceArena;
 use xjbutil::void::Void;
 
+use crate::builtins::closure::value_as_callable;
 use crate::builtins::object::Object;
+#[cfg(test)] use crate::builtins::object::ObjectRef;
+#[cfg(feature = "al31f-builtin-ops")]
+use crate::builtins::exception::{DivideByZeroError, TypeCheckError};
 use crate::data::Value;
-use crate::data::exception::ExceptionInner;
+use crate::data::exception::{
+    Exception,
+    ExceptionInner,
+    FatalExceptionSet,
+    UncheckedException
+};
+use crate::data::generic::GenericTypeVT;
 use crate::data::traits::StaticBase;
-use crate::data::tyck::TyckInfoPool;
+use crate::data::tyck::{TyckInfo, TyckInfoPool};
+use crate::data::wrapper::{OWN_INFO_READ_MASK, OWN_INFO_WRITE_MASK};
 use crate::ffi::{FFIException, Signature};
-use crate::ffi::sync_fn::{FunctionBase, OwnershipGuard, VMContext, value_into_ref};
+use crate::ffi::sync_fn::{
+    FunctionBase,
+    OwnershipGuard,
+    VMContext,
+    value_into_mut_ref,
+    value_into_ref
+};
 use crate::vm::al31f::alloc::Alloc;
-use crate::vm::al31f::compiled::{CompiledFunction, CompiledProgram, ExceptionHandlingBlock};
+use crate::vm::al31f::compiled::{
+    CompiledFunction,
+    CompiledProgram,
+    ExceptionHandlingBlock,
+    MethodTable,
+    OverloadCallSite,
+    OverloadTable
+};
+use crate::vm::al31f::handle::RootedValue;
 use crate::vm::al31f::insc::Insc;
 
 #[cfg(feature = "async")] use crate::ffi::async_fn::{
@@ -41,26 +72,1051 @@ pub fn basic_program<A: Alloc>() -> CompiledProgram<A> {
         functions: boxed_slice![
             CompiledFunction::new(0, 2, 1, 2, boxed_slice![])
         ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![],
+    }
+}
+
+pub fn swap_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                             // swap(%0, %1) -> (any, any)
+            /*00*/ Insc::Return(arena.unsafe_make(&[1, 0]))  // return [ %1, %0 ]
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 2, 2, 2, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![],
+    }
+}
+
+/// Calls the variadic native function @0, which should be linked before running
+pub fn variadic_ffi_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                             // call3(%0, %1, %2) -> (int)
+            /*00*/ Insc::FFICallRtlc(0,                      // %0 = ffi-call-rtlc @0(%0, %1, %2)
+                                     arena.unsafe_make(&[0, 1, 2]),
+                                     arena.unsafe_make(&[0])),
+            /*01*/ Insc::ReturnOne(0),                       // return %0
+
+                                                             // call1(%0) -> (int)
+            /*02*/ Insc::FFICallRtlc(0,                      // %0 = ffi-call-rtlc @0(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[0])),
+            /*03*/ Insc::ReturnOne(0)                        // return %0
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 3, 1, 3, boxed_slice![]),
+            CompiledFunction::new(2, 1, 1, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![],
+    }
+}
+
+pub fn basic_fn_call_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                             // application_start() -> (int)
+            /*00*/ Insc::MakeIntConst(1, 0),                 // %0 = $1
+            /*01*/ Insc::MakeIntConst(2, 1),                 // %1 = $2
+            /*02*/ Insc::Call(1, arena.unsafe_make(&[0, 1]), // [ %0 ] = call sum(%0, %1)
+                                 arena.unsafe_make(&[0])),
+            /*03*/ Insc::Return(arena.unsafe_make(&[0])),    // return [ %0 ]
+
+                                                             // sum(%0, %1) -> (int)
+            /*04*/ Insc::AddInt(0, 1, 0),                    // [ %0 ] = add int %0, %1
+            /*05*/ Insc::Return(arena.unsafe_make(&[0]))     // return [ %0 ]
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 0, 1, 2, boxed_slice![]), // application_start
+            CompiledFunction::new(4, 2, 1, 2, boxed_slice![]), // sum
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![],
+    }
+}
+
+pub fn fibonacci_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                           // fibonacci(%0) -> (int)
+            /*00*/ Insc::MakeIntConst(0, 1),               // %1 = $0
+            /*01*/ Insc::LeInt(0, 1, 2),                   // %2 = le int %0, %1
+            /*02*/ Insc::JumpIfTrue(2, 12),                // if %2 goto L.12
+            /*03*/ Insc::MakeIntConst(1, 1),               // %1 = $1
+            /*04*/ Insc::EqValue(0, 1, 2),                 // %2 = eq int %0, %1
+            /*05*/ Insc::JumpIfTrue(2, 12),                // if %2 goto L.12
+            /*06*/ Insc::SubInt(0, 1, 2),                  // %2 = sub int %0, %1
+            /*07*/ Insc::MakeIntConst(2, 1),               // %1 = $2
+            /*08*/ Insc::SubInt(0, 1, 3),                  // %3 = sub int %0, %1
+            /*09*/ Insc::Call(0, arena.unsafe_make(&[2]),
+                                 arena.unsafe_make(&[2])), // [ %2 ] = call fibonacci(%2)
+            /*10*/ Insc::Call(0, arena.unsafe_make(&[3]),
+                                 arena.unsafe_make(&[3])), // [ %3 ] = call fibonacci(%3)
+            /*11*/ Insc::AddInt(2, 3, 1),                  // %1 = add %2, %3
+            /*12*/ Insc::ReturnOne(1)                      // return %1
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 1, 1, 4, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![],
+    }
+}
+
+pub fn alloc_1m_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                      // alloc_1m()
+            /*00*/ Insc::MakeIntConst(0, 0),          // %0 = $0
+            /*01*/ Insc::MakeIntConst(1, 1),          // %1 = $1
+            /*02*/ Insc::MakeIntConst(10_000_000, 2), // %2 = $10_000_000
+            /*03*/ Insc::EqValue(0, 2, 3),            // %3 = eq value %0, %2
+            /*04*/ Insc::JumpIfTrue(3, 8),            // if %3 goto L.8
+            /*05*/ Insc::CreateObject(3),             // %3 = new object
+            /*06*/ Insc::SubInt(2, 1, 2),             // %2 = sub int %2, %1
+            /*07*/ Insc::Jump(3),                     // goto L.3
+            /*08*/ Insc::ReturnNothing                // return
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 0, 0, 4, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![],
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+pub fn weak_ref_program<A: Alloc>(weak_vt: NonNull<GenericTypeVT>) -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                        // weak_ref() -> (object, object?, object?)
+            /*00*/ Insc::CreateObject(0),               // %0 = new object
+            /*01*/ Insc::CreateWeak(0, weak_vt, 1),     // %1 = new weak %0
+            /*02*/ Insc::CreateObject(2),               // %2 = new object
+            /*03*/ Insc::CreateWeak(2, weak_vt, 3),     // %3 = new weak %2
+            /*04*/ Insc::MakeNull(2),                   // %2 = null
+            /*05*/ Insc::MakeIntConst(0, 4),            // %4 = $0
+            /*06*/ Insc::MakeIntConst(1, 5),            // %5 = $1
+            /*07*/ Insc::MakeIntConst(4096, 6),         // %6 = $4096
+            /*08*/ Insc::EqValue(4, 6, 7),              // %7 = eq value %4, %6
+            /*09*/ Insc::JumpIfTrue(7, 13),             // if %7 goto L.13
+            /*10*/ Insc::CreateObject(7),               // %7 = new object
+            /*11*/ Insc::AddInt(4, 5, 4),               // %4 = add int %4, %5
+            /*12*/ Insc::Jump(8),                       // goto L.8
+            /*13*/ Insc::WeakGet(1, 8),                 // %8 = weak get %1
+            /*14*/ Insc::WeakGet(3, 9),                 // %9 = weak get %3
+            /*15*/ Insc::Return(arena.unsafe_make(&[0, 8, 9])) // ret %0, %8, %9
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 0, 3, 10, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![],
     }
 }
 
-pub fn basic_fn_call_program<A: Alloc>() -> CompiledProgram<A> {
+/// Calls the overloaded function `describe`, whose parameter types are created in
+/// `tyck_info_pool`, from four call sites
+pub fn overload_program<A: Alloc>(tyck_info_pool: &mut TyckInfoPool) -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                          // overload() -> (int, int, int, int)
+            /*00*/ Insc::MakeIntConst(114, 0),            // %0 = $114
+            /*01*/ Insc::CallOverload(0, arena.unsafe_make(&[0]),
+                                      arena.unsafe_make(&[0])), // [ %0 ] = call overload.0(%0)
+            /*02*/ Insc::MakeFloatConst(5.14, 1),         // %1 = $5.14
+            /*03*/ Insc::CallOverload(1, arena.unsafe_make(&[1]),
+                                      arena.unsafe_make(&[1])), // [ %1 ] = call overload.0(%1)
+            /*04*/ Insc::MakeIntConst(1919, 2),           // %2 = $1919
+            /*05*/ Insc::CallOverload(2, arena.unsafe_make(&[2]),
+                                      arena.unsafe_make(&[2])), // [ %2 ] = call overload.0(%2)
+            /*06*/ Insc::MakeNull(3),                     // %3 = null
+            /*07*/ Insc::CallOverload(3, arena.unsafe_make(&[3]),
+                                      arena.unsafe_make(&[3])), // [ %3 ] = call overload.0(%3)
+            /*08*/ Insc::Return(arena.unsafe_make(&[0, 1, 2, 3])), // return [ %0, %1, %2, %3 ]
+
+                                                          // describe(%0: any) -> (int)
+            /*09*/ Insc::MakeIntConst(1, 0),              // %0 = $1
+            /*10*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // describe(%0: int) -> (int)
+            /*11*/ Insc::MakeIntConst(2, 0),              // %0 = $2
+            /*12*/ Insc::ReturnOne(0)                     // return %0
+        ];
+        (arena, code)
+    };
+
+    let any_type: NonNull<TyckInfo> = tyck_info_pool.get_any_type();
+    let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 0, 4, 4, boxed_slice![]),
+            CompiledFunction::new(9, 1, 1, 1, boxed_slice![Some(any_type)]),
+            CompiledFunction::new(11, 1, 1, 1, boxed_slice![Some(int_type)])
+        ],
+        overload_tables: boxed_slice![
+            OverloadTable::new(boxed_slice![1, 2])
+        ],
+        overload_call_sites: boxed_slice![
+            OverloadCallSite::new(0),
+            OverloadCallSite::new(0),
+            OverloadCallSite::new(0),
+            OverloadCallSite::new(0)
+        ],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+pub fn exception_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                          // foo() -> ()
+            /*00*/ Insc::MakeIntConst(12345, 0),          // %0 = $12345
+            /*01*/ Insc::Call(1, arena.unsafe_make(&[]),
+                              arena.unsafe_make(&[])),    // call bar()
+            /*02*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // foo:eh:Object
+            /*03*/ Insc::MakeIntConst(114514, 0),         // %0 = $114514
+            /*04*/ Insc::ReturnOne(0),                    // return %0^
+
+
+                                                          // bar() -> ()
+            /*05*/ Insc::Call(2, arena.unsafe_make(&[]),
+                                 arena.unsafe_make(&[])), // call baz()
+            /*06*/ Insc::ReturnNothing,                   // return
+
+                                                          // baz() -> !
+            /*07*/ Insc::CreateObject(0),                 // %0 = create-object
+            /*08*/ Insc::Raise(0)                         // raise %0
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new_with_exc(0, 0, 1, 1, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new(0, 2, <Void as StaticBase<Object>>::type_id(), 3)
+            ]),
+            CompiledFunction::new(5, 0, 0, 0, boxed_slice![]),
+            CompiledFunction::new(7, 0, 0, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+pub fn unchecked_exception_program<A: Alloc>(
+    fatal_exceptions: FatalExceptionSet
+) -> CompiledProgram<A> {
+    let tyck_info_pool: &mut TyckInfoPool = Box::leak(Box::new(TyckInfoPool::new()));
+    let int_type: NonNull<TyckInfo> = tyck_info_pool.create_plain_type(TypeId::of::<i64>());
+
+    let (slice_arena, code) = {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                          // div_zero() -> (int)
+            /*00*/ Insc::MakeIntConst(1, 0),              // %0 = $1
+            /*01*/ Insc::MakeIntConst(0, 1),              // %1 = $0
+            /*02*/ Insc::DivInt(0, 1, 0),                 // %0 = div int %0, %1
+            /*03*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // div_zero:eh:DivideByZeroError
+            /*04*/ Insc::MakeIntConst(-1, 0),             // %0 = $-1
+            /*05*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // type_check(%0) -> (any)
+            /*06*/ Insc::TypeCheck(0, int_type),          // tyck %0, int
+            /*07*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // type_check:eh:TypeCheckError
+            /*08*/ Insc::ExceptionValue(2, 1),            // %1 = exception value %2
+            /*09*/ Insc::ObjectGet(1, NonNull::from("object"), 0), // %0 = %1.object
+            /*10*/ Insc::ReturnOne(0)                     // return %0
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new_with_exc(0, 0, 1, 3, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new(0, 3, TypeId::of::<DivideByZeroError>(), 4)
+            ]),
+            CompiledFunction::new_with_exc(6, 1, 1, 3, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new(6, 7, TypeId::of::<TypeCheckError>(), 8)
+            ])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions,
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+pub fn finally_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                          // catch_main() -> (object)
+            /*00*/ Insc::CreateObject(0),                 // %0 = new object
+            /*01*/ Insc::Call(2, arena.unsafe_make(&[0]),
+                              arena.unsafe_make(&[])),    // call with_finally(%0)
+            /*02*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // catch_main:eh:string|object
+            /*03*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // uncaught_main() -> (object)
+            /*04*/ Insc::CreateObject(0),                 // %0 = new object
+            /*05*/ Insc::Call(2, arena.unsafe_make(&[0]),
+                              arena.unsafe_make(&[])),    // call with_finally(%0)
+            /*06*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // uncaught_main:eh:string
+            /*07*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // with_finally(%0) -> ()
+            /*08*/ Insc::Call(3, arena.unsafe_make(&[]),
+                              arena.unsafe_make(&[])),    // try { call raise_object() }
+            /*09*/ Insc::MakeIntConst(1, 1),              // finally { %1 = $1
+            /*10*/ Insc::ObjectPut(0, NonNull::from("finally"), 1), // %0.finally = %1 }
+            /*11*/ Insc::ReturnNothing,                   // return
+
+                                                          // with_finally:finally
+            /*12*/ Insc::MakeIntConst(1, 1),              // %1 = $1
+            /*13*/ Insc::ObjectPut(0, NonNull::from("finally"), 1), // %0.finally = %1
+            /*14*/ Insc::Rethrow(2),                      // rethrow %2
+
+                                                          // raise_object() -> !
+            /*15*/ Insc::CreateObject(0),                 // %0 = new object
+            /*16*/ Insc::Raise(0)                         // raise %0
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new_with_exc(0, 0, 1, 2, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new_multi(0, 2, boxed_slice![
+                    TypeId::of::<String>(),
+                    <Void as StaticBase<Object>>::type_id()
+                ], 3)
+            ]),
+            CompiledFunction::new_with_exc(4, 0, 1, 2, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new(4, 6, TypeId::of::<String>(), 7)
+            ]),
+            CompiledFunction::new_with_exc(8, 1, 0, 3, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new_finally(8, 8, 12)
+            ]),
+            CompiledFunction::new(15, 0, 0, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+/// Unchecked exceptions reaching `finally` and catch-all handlers
+#[cfg(feature = "al31f-builtin-ops")]
+pub fn unchecked_finally_program<A: Alloc>(
+    fatal_exceptions: FatalExceptionSet
+) -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                          // div_zero_finally(%0: object) -> ()
+            /*00*/ Insc::MakeIntConst(1, 1),              // %1 = $1
+            /*01*/ Insc::MakeIntConst(0, 2),              // %2 = $0
+            /*02*/ Insc::DivInt(1, 2, 1),                 // try { %1 = div int %1, %2 }
+            /*03*/ Insc::ReturnNothing,                   // return
+
+                                                          // div_zero_finally:finally
+            /*04*/ Insc::MakeIntConst(1, 1),              // %1 = $1
+            /*05*/ Insc::ObjectPut(0, NonNull::from("finally"), 1), // %0.finally = %1
+            /*06*/ Insc::Rethrow(3),                      // rethrow %3
+
+                                                          // no_method(%0: any) -> (int)
+            /*07*/ Insc::CallMethod(0,                    // try { %0 = call-method M.0(%0) }
+                                    arena.unsafe_make(&[0]),
+                                    arena.unsafe_make(&[0])),
+            /*08*/ Insc::ReturnOne(0),                    // return %0
+
+                                                          // no_method:eh:any
+            /*09*/ Insc::MakeIntConst(-1, 0),             // %0 = $-1
+            /*10*/ Insc::ReturnOne(0)                     // return %0
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new_with_exc(0, 1, 0, 4, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new_finally(0, 3, 4)
+            ]),
+            CompiledFunction::new_with_exc(7, 1, 1, 2, boxed_slice![], boxed_slice![
+                ExceptionHandlingBlock::new_catch_all(7, 8, 9)
+            ])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![MethodTable::new("missing")],
+        fatal_exceptions,
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+pub fn exception_no_eh_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                           // foo() -> (int)
+            /*00*/ Insc::Call(1, arena.unsafe_make(&[]),   // %0 = call bar
+                                 arena.unsafe_make(&[0])),
+            /*01*/ Insc::ReturnOne(0),                     // return %0
+
+                                                           // bar() -> !
+            /*02*/ Insc::CreateObject(0),                  // %0 = create-object
+            /*03*/ Insc::Raise(0),                         // raise %0
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 0, 1, 1, boxed_slice![]),
+            CompiledFunction::new(2, 0, 1, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+#[inline(never)] fn ffi_function(x: &Object, y: &Object, z: &Object) {
+    assert_eq!(x as *const Object as usize, y as *const Object as usize);
+    assert_eq!(y as *const Object as usize, z as *const Object as usize);
+}
+
+#[allow(non_camel_case_types)]
+struct Pr47Binder_ffi_function();
+
+impl FunctionBase for Pr47Binder_ffi_function {
+    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        todo!()
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        _context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        debug_assert_eq!(args.len(), 3);
+        debug_assert_eq!(rets.len(), 0);
+
+        let (a1, g1): (&Object, Option<OwnershipGuard>) = value_into_ref(*args.get_unchecked(0))?;
+        let (a2, g2): (&Object, Option<OwnershipGuard>) = value_into_ref(*args.get_unchecked(1))?;
+        let (a3, g3): (&Object, Option<OwnershipGuard>) = value_into_ref(*args.get_unchecked(2))?;
+
+        ffi_function(a1, a2, a3);
+
+        std::mem::drop(g3);
+        std::mem::drop(g2);
+        std::mem::drop(g1);
+
+        Ok(())
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        _context: &mut CTX,
+        _args: &[Value],
+        _rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        todo!()
+    }
+}
+
+const PR47BINDER_FFI_FUNCTION: &'static Pr47Binder_ffi_function = &Pr47Binder_ffi_function();
+
+pub fn ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                                    // main() -> ()
+            /*00*/ Insc::CreateObject(0),                           // %0 = create-object
+            /*01*/ Insc::FFICallRtlc(0,                             // ffi-call-rtlc @0(%0, %0, %0)
+                                     arena.unsafe_make(&[0, 0, 0]),
+                                     arena.unsafe_make(&[])),
+            /*02*/ Insc::ReturnNothing                              // return
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 0, 0, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PR47BINDER_FFI_FUNCTION.into()],
+        #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
+    }
+}
+
+pub fn recursion_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                           // runaway() -> ()
+            /*00*/ Insc::Call(0, arena.unsafe_make(&[]),   // call runaway()
+                                 arena.unsafe_make(&[])),
+            /*01*/ Insc::ReturnNothing,                    // return
+
+                                                           // count(%0: int) -> (int)
+            /*02*/ Insc::MakeIntConst(0, 1),               // %1 = $0
+            /*03*/ Insc::EqValue(0, 1, 2),                 // %2 = eq value %0, %1
+            /*04*/ Insc::JumpIfTrue(2, 9),                 // if %2 goto L.9
+            /*05*/ Insc::DecrInt(0),                       // %0 = decr %0
+            /*06*/ Insc::Call(1, arena.unsafe_make(&[0]),  // %1 = call count(%0)
+                                 arena.unsafe_make(&[1])),
+            /*07*/ Insc::IncrInt(1),                       // %1 = incr %1
+            /*08*/ Insc::ReturnOne(1),                     // return %1
+            /*09*/ Insc::ReturnOne(1)                      // L.9: return %1
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 0, 0, 1, boxed_slice![]),
+            CompiledFunction::new(2, 1, 1, 3, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+pub fn tail_call_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                           // sum(%0: int, %1: int) -> (int)
+            /*00*/ Insc::MakeIntConst(0, 2),               // %2 = $0
+            /*01*/ Insc::EqValue(0, 2, 2),                 // %2 = eq value %0, %2
+            /*02*/ Insc::JumpIfTrue(2, 6),                 // if %2 goto L.6
+            /*03*/ Insc::AddInt(1, 0, 1),                  // %1 = add int %1, %0
+            /*04*/ Insc::DecrInt(0),                       // %0 = decr %0
+            /*05*/ Insc::TailCall(0, arena.unsafe_make(&[0, 1])), // tail call sum(%0, %1)
+            /*06*/ Insc::ReturnOne(1),                     // L.6: return %1
+
+                                                           // sum_to(%0: int) -> (int)
+            /*07*/ Insc::MakeIntConst(0, 1),               // %1 = $0
+            /*08*/ Insc::Call(0, arena.unsafe_make(&[0, 1]), // %1 = call sum(%0, %1)
+                                 arena.unsafe_make(&[1])),
+            /*09*/ Insc::IncrInt(1),                       // %1 = incr %1
+            /*10*/ Insc::ReturnOne(1),                     // return %1
+
+                                                           // countdown(%0: int) -> ()
+            /*11*/ Insc::MakeIntConst(0, 1),               // %1 = $0
+            /*12*/ Insc::EqValue(0, 1, 1),                 // %1 = eq value %0, %1
+            /*13*/ Insc::JumpIfTrue(1, 17),                // if %1 goto L.17
+            /*14*/ Insc::DecrInt(0),                       // %0 = decr %0
+            /*15*/ Insc::MakeIntConst(2, 2),               // %2 = $2 (countdown)
+            /*16*/ Insc::TailCallPtr(2, arena.unsafe_make(&[0])), // tail call %2(%0)
+            /*17*/ Insc::TailCall(3, arena.unsafe_make(&[0])),    // L.17: tail call fail(%0)
+
+                                                           // fail(%0: int) -> ()
+            /*18*/ Insc::DivInt(0, 0, 4),                  // %4 = div int %0, %0
+            /*19*/ Insc::ReturnNothing                     // return
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 2, 1, 3, boxed_slice![]),
+            CompiledFunction::new(7, 1, 1, 2, boxed_slice![]),
+            CompiledFunction::new(11, 1, 0, 3, boxed_slice![]),
+            CompiledFunction::new(18, 1, 0, 5, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+#[allow(non_camel_case_types)]
+struct Pr47Binder_capture_count();
+
+impl FunctionBase for Pr47Binder_capture_count {
+    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        unimplemented!()
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        _context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        let callback: Value = *args.get_unchecked(0);
+        let (_, captures): (usize, &[Value]) = value_as_callable(callback).ok_or(
+            ExceptionInner::Unchecked(UncheckedException::InvalidCastOp {
+                dest_type: "closure",
+                src: callback
+            })
+        )?;
+        **rets.get_unchecked(0) = Value::new_int(captures.len() as i64);
+        Ok(())
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        Self::call_rtlc(context, args, rets)
+    }
+}
+
+const PR47BINDER_CAPTURE_COUNT: &'static Pr47Binder_capture_count = &Pr47Binder_capture_count();
+
+#[cfg(feature = "al31f-builtin-ops")]
+pub fn closure_program<A: Alloc>(closure_vt: NonNull<GenericTypeVT>) -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                           // main(%0: int) -> (int, int, int)
+            /*00*/ Insc::MakeCell(0, 1),                   // %1 = new cell %0
+            /*01*/ Insc::MakeIntConst(5, 2),               // %2 = $5
+            /*02*/ Insc::MakeClosure(1,                    // %3 = closure F.1 %1, %2
+                                     arena.unsafe_make(&[1, 2]),
+                                     closure_vt,
+                                     3),
+            /*03*/ Insc::CallPtr(3, arena.unsafe_make(&[]),   // %4 = call %3()
+                                    arena.unsafe_make(&[4])),
+            /*04*/ Insc::CallPtr(3, arena.unsafe_make(&[]),   // %4 = call %3()
+                                    arena.unsafe_make(&[4])),
+            /*05*/ Insc::CellGet(1, 5),                    // %5 = cell get %1
+            /*06*/ Insc::FFICallRtlc(0,                    // %2 = ffi-call-rtlc @0(%3)
+                                     arena.unsafe_make(&[3]),
+                                     arena.unsafe_make(&[2])),
+            /*07*/ Insc::Return(arena.unsafe_make(&[4, 5, 2])), // return %4, %5, %2
+
+                                                           // add_k(%0: cell, %1: int) -> (int)
+            /*08*/ Insc::CellGet(0, 2),                    // %2 = cell get %0
+            /*09*/ Insc::AddInt(2, 1, 2),                  // %2 = add int %2, %1
+            /*10*/ Insc::CellSet(0, 2),                    // cell set %0, %2
+            /*11*/ Insc::ReturnOne(2)                      // return %2
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 1, 3, 6, boxed_slice![]),
+            CompiledFunction::new(8, 2, 1, 3, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PR47BINDER_CAPTURE_COUNT.into()],
+        #[cfg(feature = "async")]
+        async_ffi_funcs: boxed_slice![]
+    }
+}
+
+#[allow(non_camel_case_types)]
+struct Pr47Binder_apply_twice();
+
+impl FunctionBase for Pr47Binder_apply_twice {
+    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        unimplemented!()
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        let callback: Value = *args.get_unchecked(0);
+        let once: Vec<RootedValue> = context.call_value(callback, &args[1..2])
+            .map_err(|e: Exception| e.inner)?;
+        let twice: Vec<RootedValue> = context.call_value(callback, &[once[0].value()])
+            .map_err(|e: Exception| e.inner)?;
+        **rets.get_unchecked(0) = twice[0].value();
+        Ok(())
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        Self::call_rtlc(context, args, rets)
+    }
+}
+
+const PR47BINDER_APPLY_TWICE: &'static Pr47Binder_apply_twice = &Pr47Binder_apply_twice();
+
+#[cfg(feature = "async")]
+#[allow(non_camel_case_types)]
+struct Pr47Binder_apply_async();
+
+#[cfg(feature = "async")]
+impl AsyncFunctionBase for Pr47Binder_apply_async {
+    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        unimplemented!()
+    }
+
+    unsafe fn call_rtlc<A: Alloc, VD: VMDataTrait<Alloc= A>, ACTX: AsyncVMContext<VMData = VD>> (
+        context: &ACTX,
+        args: &[Value]
+    ) -> Result<Promise<A>, FFIException> {
+        struct AsyncRet {
+            r: Result<Vec<RootedValue>, Exception>
+        }
+
+        impl<A: Alloc> AsyncReturnType<A> for AsyncRet {
+            fn is_err(&self) -> bool {
+                self.r.is_err()
+            }
+
+            fn resolve(self: Box<Self>, _alloc: &mut A, dests: &[*mut Value]) -> Result<usize, ExceptionInner> {
+                let values: Vec<RootedValue> = self.r.map_err(|e: Exception| e.inner)?;
+                unsafe { **dests.get_unchecked(0) = values[0].value(); }
+                Ok(1)
+            }
+        }
+
+        unsafe impl Send for AsyncRet {}
+        unsafe impl Sync for AsyncRet {}
+
+        let fut = context.call_value(*args.get_unchecked(0), args[1..].to_vec());
+        let fut = async move {
+            Box::new(AsyncRet { r: fut.await }) as Box<dyn AsyncReturnType<A>>
+        };
+
+        Ok(Promise(Box::pin(fut)))
+    }
+}
+
+#[cfg(feature = "async")]
+const PR47BINDER_APPLY_ASYNC: &'static Pr47Binder_apply_async = &Pr47Binder_apply_async();
+
+#[cfg(all(feature = "async", feature = "al31f-builtin-ops"))]
+pub fn reentrant_program<A: Alloc>(closure_vt: NonNull<GenericTypeVT>) -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                           // main(%0: int) -> (int, int)
+            /*00*/ Insc::MakeIntConst(3, 1),               // %1 = $3
+            /*01*/ Insc::MakeClosure(1,                    // %2 = closure F.1 %1
+                                     arena.unsafe_make(&[1]),
+                                     closure_vt,
+                                     2),
+            /*02*/ Insc::FFICallRtlc(0,                    // %1 = ffi-call-rtlc @0(%2, %0)
+                                     arena.unsafe_make(&[2, 0]),
+                                     arena.unsafe_make(&[1])),
+            /*03*/ Insc::FFICallAsync(0,                   // %3 = ffi-call-async @0(%2, %1)
+                                      arena.unsafe_make(&[2, 1]), 3),
+            /*04*/ Insc::Await(3, arena.unsafe_make(&[3])), // %3 = await %3
+            /*05*/ Insc::Return(arena.unsafe_make(&[1, 3])), // return %1, %3
+
+                                                           // add_k(%0: int, %1: int) -> (int)
+            /*06*/ Insc::AddInt(0, 1, 1),                  // %1 = add int %0, %1
+            /*07*/ Insc::ReturnOne(1),                     // return %1
+
+                                                           // div_zero(%0: int) -> (int)
+            /*08*/ Insc::MakeIntConst(0, 1),               // %1 = $0
+            /*09*/ Insc::DivInt(0, 1, 1),                  // %1 = div int %0, %1
+            /*10*/ Insc::ReturnOne(1),                     // return %1
+
+                                                           // fail(%0: int) -> (int)
+            /*11*/ Insc::MakeIntConst(2, 1),               // %1 = $2
+            /*12*/ Insc::FFICallRtlc(0,                    // %0 = ffi-call-rtlc @0(%1, %0)
+                                     arena.unsafe_make(&[1, 0]),
+                                     arena.unsafe_make(&[0])),
+            /*13*/ Insc::ReturnOne(0),                     // return %0
+
+                                                           // await_twice(%0: int) -> (int)
+            /*14*/ Insc::MakeIntConst(1, 1),               // %1 = $1
+            /*15*/ Insc::MakeClosure(5,                    // %2 = closure F.5 %1
+                                     arena.unsafe_make(&[1]),
+                                     closure_vt,
+                                     2),
+            /*16*/ Insc::FFICallRtlc(0,                    // %0 = ffi-call-rtlc @0(%2, %0)
+                                     arena.unsafe_make(&[2, 0]),
+                                     arena.unsafe_make(&[0])),
+            /*17*/ Insc::ReturnOne(0),                     // return %0
+
+                                                           // add_k_async(%0: int, %1: int) -> (int)
+            /*18*/ Insc::MakeClosure(1,                    // %2 = closure F.1 %0
+                                     arena.unsafe_make(&[0]),
+                                     closure_vt,
+                                     2),
+            /*19*/ Insc::FFICallAsync(0,                   // %3 = ffi-call-async @0(%2, %1)
+                                      arena.unsafe_make(&[2, 1]), 3),
+            /*20*/ Insc::Await(3, arena.unsafe_make(&[3])), // %3 = await %3
+            /*21*/ Insc::ReturnOne(3)                      // return %3
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 1, 2, 4, boxed_slice![]),
+            CompiledFunction::new(6, 2, 1, 2, boxed_slice![]),
+            CompiledFunction::new(8, 1, 1, 2, boxed_slice![]),
+            CompiledFunction::new(11, 1, 1, 2, boxed_slice![]),
+            CompiledFunction::new(14, 1, 1, 3, boxed_slice![]),
+            CompiledFunction::new(18, 2, 1, 4, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PR47BINDER_APPLY_TWICE.into()],
+        async_ffi_funcs: boxed_slice![PR47BINDER_APPLY_ASYNC.into()]
+    }
+}
+
+impl StaticBase<ParseIntError> for Void {}
+
+#[pr47_function_bind(local)]
+pub fn parse_int(text: String) -> Result<i64, ParseIntError> {
+    text.trim().parse()
+}
+
+#[pr47_function_bind(local)]
+pub fn checked_div(x: i64, y: i64) -> Option<i64> {
+    x.checked_div(y)
+}
+
+#[pr47_function_bind(local)]
+pub fn field_count(object: &Object) -> i64 {
+    unsafe { (*object.fields.get()).len() as i64 }
+}
+
+#[pr47_function_bind(local)]
+pub fn concat_consume(prefix: &String, text: String) -> String {
+    format!("{}{}", prefix, text)
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+pub fn function_bind_program<A: Alloc>() -> CompiledProgram<A> {
     let (slice_arena, code) = unsafe {
         let arena: SliceArena<8192, 8> = SliceArena::new();
         let code: Box<[Insc]> = boxed_slice![
-                                                             // application_start() -> (int)
-            /*00*/ Insc::MakeIntConst(1, 0),                 // %0 = $1
-            /*01*/ Insc::MakeIntConst(2, 1),                 // %1 = $2
-            /*02*/ Insc::Call(1, arena.unsafe_make(&[0, 1]), // [ %0 ] = call sum(%0, %1)
+                                                           // parse(%0: string) -> (int)
+            /*00*/ Insc::FFICallRtlc(0,                    // %0 = ffi-call-rtlc @0(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[0])),
+            /*01*/ Insc::ReturnOne(0),                     // return %0
+
+                                                           // div(%0: int, %1: int) -> (int?)
+            /*02*/ Insc::FFICall(1,                        // %0 = ffi-call @1(%0, %1)
+                                 arena.unsafe_make(&[0, 1]),
                                  arena.unsafe_make(&[0])),
-            /*03*/ Insc::Return(arena.unsafe_make(&[0])),    // return [ %0 ]
+            /*03*/ Insc::ReturnOne(0),                     // return %0
 
-                                                             // sum(%0, %1) -> (int)
-            /*04*/ Insc::AddInt(0, 1, 0),                    // [ %0 ] = add int %0, %1
-            /*05*/ Insc::Return(arena.unsafe_make(&[0]))     // return [ %0 ]
+                                                           // count() -> (int)
+            /*04*/ Insc::CreateObject(0),                  // %0 = new object
+            /*05*/ Insc::FFICallRtlc(2,                    // %0 = ffi-call-rtlc @2(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[0])),
+            /*06*/ Insc::ReturnOne(0),                     // return %0
+
+                                                           // parse_twice(%0: string, %1: int)
+                                                           //   -> (int)
+            /*07*/ Insc::FFICallRtlc(0,                    // %1 = ffi-call-rtlc @0(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[1])),
+            /*08*/ Insc::FFICallRtlc(0,                    // %1 = ffi-call-rtlc @0(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[1])),
+            /*09*/ Insc::ReturnOne(1),                     // return %1
+
+                                                           // concat_self(%0: string)
+                                                           //   -> (string)
+            /*10*/ Insc::FFICallRtlc(3,                    // %0 = ffi-call-rtlc @3(%0, %0)
+                                     arena.unsafe_make(&[0, 0]),
+                                     arena.unsafe_make(&[0])),
+            /*11*/ Insc::ReturnOne(0)                      // return %0
         ];
         (arena, code)
     };
@@ -71,35 +1127,82 @@ pub fn basic_fn_call_program<A: Alloc>() -> CompiledProgram<A> {
         const_pool: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
-            CompiledFunction::new(0, 0, 1, 2, boxed_slice![]), // application_start
-            CompiledFunction::new(4, 2, 1, 2, boxed_slice![]), // sum
+            CompiledFunction::new(0, 1, 1, 1, boxed_slice![]),
+            CompiledFunction::new(2, 2, 1, 2, boxed_slice![]),
+            CompiledFunction::new(4, 0, 1, 1, boxed_slice![]),
+            CompiledFunction::new(7, 2, 1, 2, boxed_slice![]),
+            CompiledFunction::new(10, 1, 1, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![
+            PR47BINDER_PARSE_INT.into(),
+            PR47BINDER_CHECKED_DIV.into(),
+            PR47BINDER_FIELD_COUNT.into(),
+            PR47BINDER_CONCAT_CONSUME.into()
         ],
-        ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
-        async_ffi_funcs: boxed_slice![],
+        async_ffi_funcs: boxed_slice![]
     }
 }
 
-pub fn fibonacci_program<A: Alloc>() -> CompiledProgram<A> {
+#[cfg(test)]
+#[pr47_function_bind_lite(local)]
+pub fn lite_parse_int(text: String) -> Result<i64, ParseIntError> {
+    text.trim().parse()
+}
+
+#[cfg(test)]
+#[pr47_function_bind_lite(local)]
+pub fn lite_repeat(text: &String, times: Option<i64>) -> String {
+    text.repeat(times.unwrap_or(1) as usize)
+}
+
+#[cfg(test)]
+#[pr47_function_bind_lite(local)]
+pub fn lite_field_count(object: ObjectRef) -> i64 {
+    unsafe { (*object.ptr.as_ref().fields.get()).len() as i64 }
+}
+
+#[cfg(test)]
+#[pr47_function_bind_lite(local)]
+pub fn lite_concat_consume(prefix: &String, text: String) -> String {
+    format!("{}{}", prefix, text)
+}
+
+#[cfg(all(test, feature = "al31f-builtin-ops"))]
+pub fn lite_bind_program<A: Alloc>() -> CompiledProgram<A> {
     let (slice_arena, code) = unsafe {
         let arena: SliceArena<8192, 8> = SliceArena::new();
         let code: Box<[Insc]> = boxed_slice![
-                                                           // fibonacci(%0) -> (int)
-            /*00*/ Insc::MakeIntConst(0, 1),               // %1 = $0
-            /*01*/ Insc::LeInt(0, 1, 2),                   // %2 = le int %0, %1
-            /*02*/ Insc::JumpIfTrue(2, 12),                // if %2 goto L.12
-            /*03*/ Insc::MakeIntConst(1, 1),               // %1 = $1
-            /*04*/ Insc::EqValue(0, 1, 2),                 // %2 = eq int %0, %1
-            /*05*/ Insc::JumpIfTrue(2, 12),                // if %2 goto L.12
-            /*06*/ Insc::SubInt(0, 1, 2),                  // %2 = sub int %0, %1
-            /*07*/ Insc::MakeIntConst(2, 1),               // %1 = $2
-            /*08*/ Insc::SubInt(0, 1, 3),                  // %3 = sub int %0, %1
-            /*09*/ Insc::Call(0, arena.unsafe_make(&[2]),
-                                 arena.unsafe_make(&[2])), // [ %2 ] = call fibonacci(%2)
-            /*10*/ Insc::Call(0, arena.unsafe_make(&[3]),
-                                 arena.unsafe_make(&[3])), // [ %3 ] = call fibonacci(%3)
-            /*11*/ Insc::AddInt(2, 3, 1),                  // %1 = add %2, %3
-            /*12*/ Insc::ReturnOne(1)                      // return %1
+                                                           // parse(%0: string) -> (int)
+            /*00*/ Insc::FFICallRtlc(0,                    // %0 = ffi-call-rtlc @0(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[0])),
+            /*01*/ Insc::ReturnOne(0),                     // return %0
+
+                                                           // repeat(%0: string, %1: int?)
+                                                           //   -> (string)
+            /*02*/ Insc::FFICall(1,                        // %1 = ffi-call @1(%0, %1)
+                                 arena.unsafe_make(&[0, 1]),
+                                 arena.unsafe_make(&[1])),
+            /*03*/ Insc::ReturnOne(1),                     // return %1
+
+                                                           // count() -> (int)
+            /*04*/ Insc::CreateObject(0),                  // %0 = new object
+            /*05*/ Insc::FFICallRtlc(2,                    // %0 = ffi-call-rtlc @2(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[0])),
+            /*06*/ Insc::ReturnOne(0),                     // return %0
+
+                                                           // concat_self(%0: string)
+                                                           //   -> (string)
+            /*07*/ Insc::FFICallRtlc(3,                    // %0 = ffi-call-rtlc @3(%0, %0)
+                                     arena.unsafe_make(&[0, 0]),
+                                     arena.unsafe_make(&[0])),
+            /*08*/ Insc::ReturnOne(0)                      // return %0
         ];
         (arena, code)
     };
@@ -110,28 +1213,42 @@ pub fn fibonacci_program<A: Alloc>() -> CompiledProgram<A> {
         const_pool: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
-            CompiledFunction::new(0, 1, 1, 4, boxed_slice![])
+            CompiledFunction::new(0, 1, 1, 1, boxed_slice![]),
+            CompiledFunction::new(2, 2, 1, 2, boxed_slice![]),
+            CompiledFunction::new(4, 0, 1, 1, boxed_slice![]),
+            CompiledFunction::new(7, 1, 1, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![
+            PR47BINDER_LITE_PARSE_INT.into(),
+            PR47BINDER_LITE_REPEAT.into(),
+            PR47BINDER_LITE_FIELD_COUNT.into(),
+            PR47BINDER_LITE_CONCAT_CONSUME.into()
         ],
-        ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
-        async_ffi_funcs: boxed_slice![],
+        async_ffi_funcs: boxed_slice![]
     }
 }
 
-pub fn alloc_1m_program<A: Alloc>() -> CompiledProgram<A> {
-    let (slice_arena, code) = {
+/// Calls the method table @0 and the native function @0, which should be linked before running
+pub fn method_call_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
         let arena: SliceArena<8192, 8> = SliceArena::new();
         let code: Box<[Insc]> = boxed_slice![
-                                                      // alloc_1m()
-            /*00*/ Insc::MakeIntConst(0, 0),          // %0 = $0
-            /*01*/ Insc::MakeIntConst(1, 1),          // %1 = $1
-            /*02*/ Insc::MakeIntConst(10_000_000, 2), // %2 = $10_000_000
-            /*03*/ Insc::EqValue(0, 2, 3),            // %3 = eq value %0, %2
-            /*04*/ Insc::JumpIfTrue(3, 8),            // if %3 goto L.8
-            /*05*/ Insc::CreateObject(3),             // %3 = new object
-            /*06*/ Insc::SubInt(2, 1, 2),             // %2 = sub int %2, %1
-            /*07*/ Insc::Jump(3),                     // goto L.3
-            /*08*/ Insc::ReturnNothing                // return
+                                                             // call_dyn(%0) -> (any)
+            /*00*/ Insc::CallMethod(0,                       // %0 = call-method M.0(%0)
+                                    arena.unsafe_make(&[0]),
+                                    arena.unsafe_make(&[0])),
+            /*01*/ Insc::ReturnOne(0),                       // return %0
+
+                                                             // call_static(%0) -> (int)
+            /*02*/ Insc::FFICallRtlc(0,                      // %0 = ffi-call-rtlc @0(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[0])),
+            /*03*/ Insc::ReturnOne(0)                        // return %0
         ];
         (arena, code)
     };
@@ -142,37 +1259,28 @@ pub fn alloc_1m_program<A: Alloc>() -> CompiledProgram<A> {
         const_pool: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
-            CompiledFunction::new(0, 0, 0, 4, boxed_slice![])
+            CompiledFunction::new(0, 1, 1, 1, boxed_slice![]),
+            CompiledFunction::new(2, 1, 1, 1, boxed_slice![])
         ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![],
     }
 }
 
-pub fn exception_program<A: Alloc>() -> CompiledProgram<A> {
-    let (slice_arena, code) = unsafe {
+/// Checks that the argument is readable and writable
+pub fn ownership_check_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = {
         let arena: SliceArena<8192, 8> = SliceArena::new();
         let code: Box<[Insc]> = boxed_slice![
-                                                          // foo() -> ()
-            /*00*/ Insc::MakeIntConst(12345, 0),          // %0 = $12345
-            /*01*/ Insc::Call(1, arena.unsafe_make(&[]),
-                              arena.unsafe_make(&[])),    // call bar()
-            /*02*/ Insc::ReturnOne(0),                    // return %0
-
-                                                          // foo:eh:Object
-            /*03*/ Insc::MakeIntConst(114514, 0),         // %0 = $114514
-            /*04*/ Insc::ReturnOne(0),                    // return %0^
-
-
-                                                          // bar() -> ()
-            /*05*/ Insc::Call(2, arena.unsafe_make(&[]),
-                                 arena.unsafe_make(&[])), // call baz()
-            /*06*/ Insc::ReturnNothing,                   // return
-
-                                                          // baz() -> !
-            /*07*/ Insc::CreateObject(0),                 // %0 = create-object
-            /*08*/ Insc::Raise(0)                         // raise %0
+                                                             // check(%0) -> ()
+            /*00*/ Insc::OwnershipInfoCheck(0,               // ownership-check %0, RW
+                                            OWN_INFO_READ_MASK | OWN_INFO_WRITE_MASK),
+            /*01*/ Insc::ReturnNothing                       // return
         ];
         (arena, code)
     };
@@ -183,30 +1291,50 @@ pub fn exception_program<A: Alloc>() -> CompiledProgram<A> {
         const_pool: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
-            CompiledFunction::new_with_exc(0, 0, 1, 1, boxed_slice![], boxed_slice![
-                ExceptionHandlingBlock::new(0, 2, <Void as StaticBase<Object>>::type_id(), 3)
-            ]),
-            CompiledFunction::new(5, 0, 0, 0, boxed_slice![]),
-            CompiledFunction::new(7, 0, 0, 1, boxed_slice![])
+            CompiledFunction::new(0, 1, 0, 1, boxed_slice![])
         ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
         ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
-        async_ffi_funcs: boxed_slice![]
+        async_ffi_funcs: boxed_slice![],
     }
 }
 
-pub fn exception_no_eh_program<A: Alloc>() -> CompiledProgram<A> {
+#[derive(Clone, Pr47Type)]
+#[pr47(local, name = "HostPoint")]
+pub struct HostPoint {
+    #[pr47(get, set)] pub x: i64,
+    #[pr47(get)] pub label: String,
+    #[pr47(get, set)] pub payload: Value,
+    pub history: Vec<Value>
+}
+
+#[cfg(feature = "al31f-builtin-ops")]
+pub fn host_type_program<A: Alloc>() -> CompiledProgram<A> {
     let (slice_arena, code) = unsafe {
         let arena: SliceArena<8192, 8> = SliceArena::new();
         let code: Box<[Insc]> = boxed_slice![
-                                                           // foo() -> (int)
-            /*00*/ Insc::Call(1, arena.unsafe_make(&[]),   // %0 = call bar
-                                 arena.unsafe_make(&[0])),
-            /*01*/ Insc::ReturnOne(0),                     // return %0
+                                                           // get(%0: HostPoint) -> (int, any)
+            /*00*/ Insc::HostFieldGet(0, NonNull::from("x"), 1), // %1 = %0.x
+            /*01*/ Insc::HostFieldGet(0, NonNull::from("label"), 2), // %2 = %0.label
+            /*02*/ Insc::Return(arena.unsafe_make(&[1, 2])), // return %1, %2
 
-                                                           // bar() -> !
-            /*02*/ Insc::CreateObject(0),                  // %0 = create-object
-            /*03*/ Insc::Raise(0),                         // raise %0
+                                                           // set(%0: HostPoint, %1: int) -> (int)
+            /*03*/ Insc::FFICallRtlc(0,                    // ffi-call-rtlc @0(%0, %1)
+                                     arena.unsafe_make(&[0, 1]),
+                                     arena.unsafe_make(&[])),
+            /*04*/ Insc::HostFieldGet(0, NonNull::from("x"), 2), // %2 = %0.x
+            /*05*/ Insc::ReturnOne(2),                     // return %2
+
+                                                           // label(%0: HostPoint) -> (string)
+            /*06*/ Insc::MakeIntConst(0, 1),               // %1 = $0
+            /*07*/ Insc::FFICallRtlc(1,                    // %1 = ffi-call-rtlc @1(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[1])),
+            /*08*/ Insc::ReturnOne(1)                      // return %1
         ];
         (arena, code)
     };
@@ -217,70 +1345,58 @@ pub fn exception_no_eh_program<A: Alloc>() -> CompiledProgram<A> {
         const_pool: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
-            CompiledFunction::new(0, 0, 1, 1, boxed_slice![]),
-            CompiledFunction::new(2, 0, 1, 1, boxed_slice![])
+            CompiledFunction::new(0, 1, 2, 3, boxed_slice![]),
+            CompiledFunction::new(3, 2, 1, 3, boxed_slice![]),
+            CompiledFunction::new(6, 1, 1, 2, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![
+            PR47BINDER_HOSTPOINT_SET_X.into(),
+            PR47BINDER_HOSTPOINT_GET_LABEL.into()
         ],
-        ffi_funcs: boxed_slice![],
         #[cfg(feature = "async")]
         async_ffi_funcs: boxed_slice![]
     }
 }
 
-#[inline(never)] fn ffi_function(x: &Object, y: &Object, z: &Object) {
-    assert_eq!(x as *const Object as usize, y as *const Object as usize);
-    assert_eq!(y as *const Object as usize, z as *const Object as usize);
+#[cfg(feature = "async")]
+#[pr47_function_bind(local)]
+pub async fn async_parse_int(text: &String) -> Result<i64, ParseIntError> {
+    text.trim().parse()
 }
 
-#[allow(non_camel_case_types)]
-struct Pr47Binder_ffi_function();
-
-impl FunctionBase for Pr47Binder_ffi_function {
-    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
-        todo!()
-    }
-
-    unsafe fn call_rtlc<CTX: VMContext>(
-        _context: &mut CTX,
-        args: &[Value],
-        rets: &[*mut Value]
-    ) -> Result<(), FFIException> {
-        debug_assert_eq!(args.len(), 3);
-        debug_assert_eq!(rets.len(), 0);
-
-        let (a1, g1): (&Object, Option<OwnershipGuard>) = value_into_ref(*args.get_unchecked(0))?;
-        let (a2, g2): (&Object, Option<OwnershipGuard>) = value_into_ref(*args.get_unchecked(1))?;
-        let (a3, g3): (&Object, Option<OwnershipGuard>) = value_into_ref(*args.get_unchecked(2))?;
-
-        ffi_function(a1, a2, a3);
-
-        std::mem::drop(g3);
-        std::mem::drop(g2);
-        std::mem::drop(g1);
-
-        Ok(())
-    }
-
-    unsafe fn call_unchecked<CTX: VMContext>(
-        _context: &mut CTX,
-        _args: &[Value],
-        _rets: &[*mut Value]
-    ) -> Result<(), FFIException> {
-        todo!()
-    }
+#[cfg(feature = "async")]
+#[pr47_function_bind(local)]
+pub async fn async_push_char(text: &mut String, ch: char) {
+    text.push(ch)
 }
 
-const PR47BINDER_FFI_FUNCTION: &'static Pr47Binder_ffi_function = &Pr47Binder_ffi_function();
-
-pub fn ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
+#[cfg(feature = "async")]
+pub fn async_function_bind_program<A: Alloc>() -> CompiledProgram<A> {
     let (slice_arena, code) = unsafe {
         let arena: SliceArena<8192, 8> = SliceArena::new();
         let code: Box<[Insc]> = boxed_slice![
-                                                                    // main() -> ()
-            /*00*/ Insc::CreateObject(0),                           // %0 = create-object
-            /*01*/ Insc::FFICallRtlc(0,                             // ffi-call-rtlc @0(%0, %0, %0)
-                                     arena.unsafe_make(&[0, 0, 0]),
-                                     arena.unsafe_make(&[])),
-            /*02*/ Insc::ReturnNothing                              // return
+                                                           // parse(%0: string) -> (int)
+            /*00*/ Insc::FFICallAsync(0,                   // %1 = ffi-call-async @0(%0)
+                                      arena.unsafe_make(&[0]), 1),
+            /*01*/ Insc::Await(1, arena.unsafe_make(&[1])), // %1 = await %1
+            /*02*/ Insc::ReturnOne(1),                     // return %1
+
+                                                           // push_twice(%0: string, %1: char)
+                                                           //   -> (int)
+            /*03*/ Insc::FFICallAsync(1,                   // %2 = ffi-call-async @1(%0, %1)
+                                      arena.unsafe_make(&[0, 1]), 2),
+            /*04*/ Insc::Await(2, arena.unsafe_make(&[])), // await %2
+            /*05*/ Insc::FFICallAsync(1,                   // %2 = ffi-call-async @1(%0, %1)
+                                      arena.unsafe_make(&[0, 1]), 2),
+            /*06*/ Insc::Await(2, arena.unsafe_make(&[])), // await %2
+            /*07*/ Insc::FFICallAsync(0,                   // %2 = ffi-call-async @0(%0)
+                                      arena.unsafe_make(&[0]), 2),
+            /*08*/ Insc::Await(2, arena.unsafe_make(&[2])), // %2 = await %2
+            /*09*/ Insc::ReturnOne(2)                      // return %2
         ];
         (arena, code)
     };
@@ -291,10 +1407,18 @@ pub fn ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
         const_pool: boxed_slice![],
         init_proc: 0,
         functions: boxed_slice![
-            CompiledFunction::new(0, 0, 0, 1, boxed_slice![])
+            CompiledFunction::new(0, 1, 1, 2, boxed_slice![]),
+            CompiledFunction::new(3, 2, 1, 3, boxed_slice![])
         ],
-        ffi_funcs: boxed_slice![PR47BINDER_FFI_FUNCTION as _],
-        #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![],
+        async_ffi_funcs: boxed_slice![
+            PR47BINDER_ASYNC_PARSE_INT.into(),
+            PR47BINDER_ASYNC_PUSH_CHAR.into()
+        ]
     }
 }
 
@@ -322,6 +1446,10 @@ pub fn bench_raw_iter_program<A: Alloc>() -> CompiledProgram<A> {
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 5, boxed_slice![])
         ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
         ffi_funcs: boxed_slice![],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
@@ -356,7 +1484,11 @@ pub fn bench_ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 5, boxed_slice![])
         ],
-        ffi_funcs: boxed_slice![PR47BINDER_FFI_FUNCTION as _],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PR47BINDER_FFI_FUNCTION.into()],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
 }
@@ -422,7 +1554,11 @@ pub fn ffi_call_program2<A: Alloc>() -> CompiledProgram<A> {
         functions: boxed_slice![
             CompiledFunction::new(0, 2, 1, 2, boxed_slice![])
         ],
-        ffi_funcs: boxed_slice![PR47_BINDER_FFI_FUNCTION2 as _],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PR47_BINDER_FFI_FUNCTION2.into()],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
 }
@@ -462,7 +1598,11 @@ pub fn bench_ffi_call_program2<A: Alloc>() -> CompiledProgram<A> {
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 0, 6, boxed_slice![])
         ],
-        ffi_funcs: boxed_slice![PR47_BINDER_FFI_FUNCTION2 as _],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PR47_BINDER_FFI_FUNCTION2.into()],
         #[cfg(feature="async")] async_ffi_funcs: boxed_slice![]
     }
 }
@@ -548,8 +1688,109 @@ pub fn async_ffi_call_program<A: Alloc>() -> CompiledProgram<A> {
         functions: boxed_slice![
             CompiledFunction::new(0, 0, 1, 1, boxed_slice![])
         ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
         ffi_funcs: boxed_slice![],
-        async_ffi_funcs: boxed_slice![PR47BINDER_ASYNC_FFI_FUNCTION as _]
+        async_ffi_funcs: boxed_slice![PR47BINDER_ASYNC_FFI_FUNCTION.into()]
+    }
+}
+
+#[allow(non_camel_case_types)]
+struct Pr47Binder_panicking_ffi_function();
+
+impl FunctionBase for Pr47Binder_panicking_ffi_function {
+    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        unimplemented!()
+    }
+
+    unsafe fn call_rtlc<CTX: VMContext>(
+        _context: &mut CTX,
+        args: &[Value],
+        _rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        let (object, guard): (&mut Object, OwnershipGuard) =
+            value_into_mut_ref(*args.get_unchecked(0))?;
+        // simulate a binding which loses its guard before panicking
+        std::mem::forget(guard);
+        panic!("cannot handle object at {:p}", object)
+    }
+
+    unsafe fn call_unchecked<CTX: VMContext>(
+        context: &mut CTX,
+        args: &[Value],
+        rets: &[*mut Value]
+    ) -> Result<(), FFIException> {
+        Self::call_rtlc(context, args, rets)
+    }
+}
+
+const PR47BINDER_PANICKING_FFI_FUNCTION: &'static Pr47Binder_panicking_ffi_function
+    = &Pr47Binder_panicking_ffi_function();
+
+#[cfg(feature = "async")]
+#[allow(non_camel_case_types)]
+struct Pr47Binder_panicking_async_ffi_function();
+
+#[cfg(feature = "async")]
+impl AsyncFunctionBase for Pr47Binder_panicking_async_ffi_function {
+    fn signature(_tyck_info_pool: &mut TyckInfoPool) -> Signature {
+        unimplemented!()
+    }
+
+    unsafe fn call_rtlc<A: Alloc, VD: VMDataTrait<Alloc= A>, ACTX: AsyncVMContext<VMData = VD>> (
+        _context: &ACTX,
+        _args: &[Value]
+    ) -> Result<Promise<A>, FFIException> {
+        let fut = async move {
+            tokio::task::yield_now().await;
+            panic!("async panic")
+        };
+
+        Ok(Promise(Box::pin(fut)))
+    }
+}
+
+#[cfg(feature = "async")]
+const PR47BINDER_PANICKING_ASYNC_FFI_FUNCTION: &'static Pr47Binder_panicking_async_ffi_function
+    = &Pr47Binder_panicking_async_ffi_function();
+
+#[cfg(feature = "async")]
+pub fn ffi_panic_program<A: Alloc>() -> CompiledProgram<A> {
+    let (slice_arena, code) = unsafe {
+        let arena: SliceArena<8192, 8> = SliceArena::new();
+        let code: Box<[Insc]> = boxed_slice![
+                                                                  // sync_panic(%0: object) -> ()
+            /*00*/ Insc::FFICallRtlc(0,                           // ffi-call-rtlc @0(%0)
+                                     arena.unsafe_make(&[0]),
+                                     arena.unsafe_make(&[])),
+            /*01*/ Insc::ReturnNothing,                           // return
+
+                                                                  // async_panic() -> ()
+            /*02*/ Insc::FFICallAsync(0,                          // %0 = ffi-call-async @0()
+                                      arena.unsafe_make(&[]), 0),
+            /*03*/ Insc::Await(0, arena.unsafe_make(&[])),        // await %0
+            /*04*/ Insc::ReturnNothing                            // return
+        ];
+        (arena, code)
+    };
+
+    CompiledProgram {
+        slice_arena,
+        code,
+        const_pool: boxed_slice![],
+        init_proc: 0,
+        functions: boxed_slice![
+            CompiledFunction::new(0, 1, 0, 1, boxed_slice![]),
+            CompiledFunction::new(2, 0, 0, 1, boxed_slice![])
+        ],
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PR47BINDER_PANICKING_FFI_FUNCTION.into()],
+        async_ffi_funcs: boxed_slice![PR47BINDER_PANICKING_ASYNC_FFI_FUNCTION.into()]
     }
 }
 
@@ -618,7 +1859,11 @@ pub fn async_spawn_program<A: Alloc>() -> CompiledProgram<A> {
             CompiledFunction::new(0, 0, 0, 2, boxed_slice![]),
             CompiledFunction::new(12, 0, 0, 1, boxed_slice![])
         ],
-        ffi_funcs: boxed_slice![PRINT_BIND as _],
-        async_ffi_funcs: boxed_slice![SLEEP_MS_BIND as _]
+        overload_tables: boxed_slice![],
+        overload_call_sites: boxed_slice![],
+        method_tables: boxed_slice![],
+        fatal_exceptions: FatalExceptionSet::new(),
+        ffi_funcs: boxed_slice![PRINT_BIND.into()],
+        async_ffi_funcs: boxed_slice![SLEEP_MS_BIND.into()]
     }
 }